@@ -0,0 +1,54 @@
+use std::{fmt::Write, hint::black_box};
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use ebnf_toolkit::{
+    Grammar,
+    benchmarking::{parse, simplify, tokenize},
+};
+
+const SYNTHETIC_RULES: usize = 5000;
+
+/// A grammar with thousands of rules that refer to each other, using every kind of node
+fn synthetic_grammar() -> String {
+    let mut src = String::new();
+    for n in 0..SYNTHETIC_RULES {
+        let next = (n + 1) % SYNTHETIC_RULES;
+        let other = (n * 7 + 3) % SYNTHETIC_RULES;
+        writeln!(
+            src,
+            "rule_{n} = ('k{n}' rule_{next}) | {{#'[a-z]+' rule_{other}}} [',' rule_{other}?] ('x' | 'y')*;"
+        )
+        .unwrap();
+    }
+    src
+}
+
+fn phases(c: &mut Criterion) {
+    let synthetic = synthetic_grammar();
+    let inputs = [
+        ("irc", include_str!("grammars/irc.ebnf")),
+        ("json", include_str!("grammars/json.ebnf")),
+        ("synthetic", &synthetic),
+    ];
+
+    for (name, input) in inputs {
+        let mut group = c.benchmark_group(name);
+        let tokens = tokenize(input);
+        let grammar = parse(input, &tokens);
+
+        group.bench_function("tokenize", |b| b.iter(|| tokenize(black_box(input))));
+        group.bench_function("parse", |b| b.iter(|| parse(input, black_box(&tokens))));
+        group.bench_function("simplify", |b| {
+            b.iter_batched_ref(|| grammar.clone(), simplify, BatchSize::LargeInput);
+        });
+        group.bench_function("total", |b| b.iter(|| Grammar::new(black_box(input))));
+        // The cost that `SharedGrammar` avoids in pipelines that keep copies of a grammar
+        group.bench_function("clone", |b| b.iter(|| black_box(&grammar).clone()));
+        let shared = grammar.clone().share();
+        group.bench_function("clone shared", |b| b.iter(|| black_box(&shared).clone()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, phases);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Macros for embedding grammars for [`ebnf_toolkit`] in a program, checked while it is compiled rather than when it runs.
+//!
+//! The code these generate refers to `ebnf_toolkit`, so a crate using them needs to depend on it as well, at the same version as this crate does.
+#![forbid(unsafe_code)]
+#![warn(clippy::pedantic)]
+#![warn(missing_docs)]
+
+use ebnf_toolkit::{Grammar, ReportConfig};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{LitByteStr, LitStr, parse_macro_input};
+
+/// Builds a [`Grammar`] from a string literal, which is parsed while compiling, so that a grammar that doesn't parse is a compile error carrying the usual report of what went wrong, rather than a failure at runtime.
+///
+/// A `Grammar` can't be built in a constant, so the expression decodes the grammar from the form written by [`Grammar::to_bytes`], which is much faster than parsing it again and certain to succeed. The result borrows from the encoded bytes, so is a `Grammar<'static>` that can be kept in a `static` with [`LazyLock`](std::sync::LazyLock).
+/// ```rust
+/// use std::sync::LazyLock;
+///
+/// use ebnf_toolkit::Grammar;
+/// use ebnf_toolkit_macros::grammar;
+///
+/// static LIST: LazyLock<Grammar<'static>> = LazyLock::new(|| {
+///     grammar!(r"
+///         list = item % ',';
+///         item = #'[a-z]+';
+///     ")
+/// });
+/// assert!(LIST.get("item").is_some());
+/// ```
+/// A grammar that doesn't parse is rejected:
+/// ```rust,compile_fail
+/// # use ebnf_toolkit_macros::grammar;
+/// let g = grammar!("list = (item;");
+/// ```
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let source = literal.value();
+    let grammar = match Grammar::new(&source) {
+        Ok(grammar) => grammar,
+        Err(error) => {
+            // A literal can't be pointed into on stable, so the report says where in it the problem is
+            let report = error.render(ReportConfig::default().with_color(false));
+            return syn::Error::new(literal.span(), report)
+                .to_compile_error()
+                .into();
+        }
+    };
+    let bytes = LitByteStr::new(&grammar.to_bytes(), literal.span());
+    quote! {
+        ::ebnf_toolkit::Grammar::from_bytes(#bytes).expect("The grammar was encoded when it was compiled")
+    }
+    .into()
+}
@@ -0,0 +1,13 @@
+use ebnf_toolkit::Grammar;
+use ebnf_toolkit_macros::grammar;
+
+#[test]
+fn same_as_runtime() {
+    let src = "sum = term ('+' term)*; term = #'[0-9]+' | ('(' sum ')');";
+    let g = grammar!("sum = term ('+' term)*; term = #'[0-9]+' | ('(' sum ')');");
+    assert_eq!(g, Grammar::new(src).unwrap());
+
+    // Escapes in the literal are undone before parsing, as at runtime
+    let escaped = grammar!("quote = \"\\\"\" 'x';\n");
+    assert_eq!(escaped["quote"].body.len(), 2);
+}
@@ -0,0 +1,206 @@
+use alloc::{string::String, vec::Vec};
+
+use crate::{ChoiceSemantics, Expr, Rule, Span, reorder::literal_prefix};
+
+/// An alternative of a [`Choice`](Expr::Choice) that an earlier alternative of the same `Choice` makes redundant, from [`Grammar::alternative_issues`](crate::Grammar::alternative_issues)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternativeIssue<'a> {
+    /// The name of the rule containing the [`Choice`](Expr::Choice)
+    pub rule: &'a str,
+    /// The alternative that comes first
+    pub earlier: &'a Expr<'a>,
+    /// The alternative made redundant by `earlier`
+    pub later: &'a Expr<'a>,
+    /// How `earlier` makes `later` redundant
+    pub kind: AlternativeIssueKind,
+}
+
+/// How the earlier alternative of an [`AlternativeIssue`] makes the later one redundant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlternativeIssueKind {
+    /// The two alternatives are the same apart from their spans, which often comes from merging duplicate definitions of a rule. [`Grammar::remove_duplicate_alternatives`](crate::Grammar::remove_duplicate_alternatives) removes the later one.
+    Duplicate,
+    /// The earlier alternative is only literal text, and a strict prefix of the literal text the later one begins with. Under [ordered](ChoiceSemantics::Ordered) choice, the earlier one succeeds first and the later one is never tried, so this is only reported for choices written with `/` or in grammars with ordered choice. [`Grammar::reorder_choices`](crate::Grammar::reorder_choices) can fix this.
+    Prefix,
+}
+
+/// A rule that separates the alternatives of some [`Choice`s](Expr::Choice) with `/` and others with `|`, from [`Grammar::mixed_choices`](crate::Grammar::mixed_choices). This is allowed, but as PEG tools only read `/` and other EBNF tools only `|`, it is usually a mistake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixedChoice<'a> {
+    /// The name of the rule
+    pub rule: &'a str,
+    /// The first choice in the rule written with `/`
+    pub ordered: &'a Expr<'a>,
+    /// The first choice in the rule written with `|`
+    pub unordered: &'a Expr<'a>,
+}
+
+pub(crate) fn rule_mixed_choice<'a>(rule: &'a Rule<'_>) -> Option<MixedChoice<'a>> {
+    let mut first = [None, None];
+    let mut stack: Vec<_> = rule.body.iter().rev().collect();
+    while let Some(node) = stack.pop() {
+        if let Expr::Choice { ordered, .. } = node {
+            first[usize::from(*ordered)].get_or_insert(node);
+        }
+        stack.extend(node.children().iter().rev());
+    }
+    let [Some(unordered), Some(ordered)] = first else {
+        return None;
+    };
+    Some(MixedChoice {
+        rule: &rule.name,
+        ordered,
+        unordered,
+    })
+}
+
+pub(crate) fn rule_alternative_issues<'a>(
+    rule: &'a Rule<'_>,
+    semantics: ChoiceSemantics,
+) -> Vec<AlternativeIssue<'a>> {
+    let mut issues = Vec::new();
+    let mut stack: Vec<_> = rule.body.iter().collect();
+    while let Some(node) = stack.pop() {
+        if let Expr::Choice { body, ordered, .. } = node {
+            let ordered = *ordered || semantics == ChoiceSemantics::Ordered;
+            let prefixes: Vec<_> = body
+                .iter()
+                .map(|alternative| {
+                    let mut prefix = String::new();
+                    let complete = literal_prefix(alternative, &mut prefix);
+                    (prefix, complete)
+                })
+                .collect();
+            for (index, later) in body.iter().enumerate() {
+                let (later_prefix, _) = &prefixes[index];
+                let found = body[..index].iter().zip(&prefixes).find_map(
+                    |(earlier, (earlier_prefix, complete))| {
+                        if earlier.eq_ignoring_spans(later) {
+                            Some((earlier, AlternativeIssueKind::Duplicate))
+                        } else if ordered
+                            && *complete
+                            && earlier_prefix.len() < later_prefix.len()
+                            && later_prefix.starts_with(earlier_prefix.as_str())
+                        {
+                            Some((earlier, AlternativeIssueKind::Prefix))
+                        } else {
+                            None
+                        }
+                    },
+                );
+                if let Some((earlier, kind)) = found {
+                    issues.push(AlternativeIssue {
+                        rule: &rule.name,
+                        earlier,
+                        later,
+                        kind,
+                    });
+                }
+            }
+        }
+        stack.extend(node.children());
+    }
+    issues
+}
+
+/// Removes every alternative that is the same as an earlier one in its [`Choice`](Expr::Choice), replacing a `Choice` left with a single alternative by that alternative. Returns the spans of the removed alternatives.
+pub(crate) fn remove_rule_duplicates(rule: &mut Rule<'_>, removed: &mut Vec<Span>) {
+    let mut stack: Vec<_> = rule.body.iter_mut().collect();
+    while let Some(node) = stack.pop() {
+        if let Expr::Choice { body, .. } = node {
+            let mut kept: Vec<Expr<'_>> = Vec::with_capacity(body.len());
+            for alternative in body.drain(..) {
+                if kept.iter().any(|k| k.eq_ignoring_spans(&alternative)) {
+                    removed.push(alternative.span());
+                } else {
+                    kept.push(alternative);
+                }
+            }
+            *body = kept;
+            if let [_] = &body[..] {
+                *node = body.pop().unwrap();
+            }
+        }
+        stack.extend(node.children_mut());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{AlternativeIssueKind, ChoiceSemantics, Grammar};
+
+    #[test]
+    fn duplicates_and_prefixes() {
+        let src = "A = 'x' | B | 'x' | ('xy' Z); A = B | ('x' C); B = 'b' | 'b';";
+        let mut g = Grammar::new(src).unwrap();
+        g.set_choice_semantics(ChoiceSemantics::Ordered);
+
+        let issues: Vec<_> = g
+            .alternative_issues()
+            .iter()
+            .map(|i| {
+                (
+                    i.rule,
+                    &src[i.earlier.span().range()],
+                    &src[i.later.span().range()],
+                    i.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                ("A", "'x'", "'x'", AlternativeIssueKind::Duplicate),
+                ("A", "'x'", "'xy' Z", AlternativeIssueKind::Prefix),
+                ("A", "B", "B", AlternativeIssueKind::Duplicate),
+                ("B", "'b'", "'b'", AlternativeIssueKind::Duplicate),
+            ]
+        );
+
+        let removed: Vec<_> = g
+            .remove_duplicate_alternatives()
+            .iter()
+            .map(|s| &src[s.range()])
+            .collect();
+        assert_eq!(removed, ["'x'", "B", "'b'"]);
+        assert_eq!(g["A"].body[0].children().len(), 4);
+        assert_eq!(g["B"].body[0].to_string(), r#""b""#);
+        assert!(
+            g.alternative_issues()
+                .iter()
+                .all(|i| i.kind == AlternativeIssueKind::Prefix)
+        );
+        g.set_choice_semantics(ChoiceSemantics::Unordered);
+        assert!(g.alternative_issues().is_empty());
+
+        let g = Grammar::new("S = 'x' / 'xy';").unwrap();
+        assert_eq!(g.alternative_issues()[0].kind, AlternativeIssueKind::Prefix);
+    }
+
+    #[test]
+    fn mixed_choices() {
+        let src = "A = (B | C) D; B = 'b' / 'c' | E; C = [F / 'x'] ('y' | 'z') (G / H);";
+        let g = Grammar::new(src).unwrap();
+
+        let mixed: Vec<_> = g
+            .mixed_choices()
+            .iter()
+            .map(|m| {
+                (
+                    m.rule,
+                    &src[m.ordered.span().range()],
+                    &src[m.unordered.span().range()],
+                )
+            })
+            .collect();
+        assert_eq!(
+            mixed,
+            [
+                ("B", "'b' / 'c'", "'b' / 'c' | E"),
+                ("C", "F / 'x'", "'y' | 'z'")
+            ]
+        );
+    }
+}
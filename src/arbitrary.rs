@@ -0,0 +1,183 @@
+//! [proptest](https://docs.rs/proptest) strategies for generating syntax trees and whole grammars, for fuzzing tools that consume EBNF. Only available with the `proptest` feature.
+//!
+//! Every span in the generated values is [`Span::default`], and every name and terminal is one of `string0000` to `string0127`. Generated grammars never refer to a rule they don't define.
+//! ```rust
+//! # use ebnf_toolkit::{Grammar, arbitrary};
+//! use proptest::{prelude::*, strategy::ValueTree, test_runner::TestRunner};
+//!
+//! let mut runner = TestRunner::default();
+//! let source = arbitrary::grammar_source().new_tree(&mut runner).unwrap().current();
+//! let grammar = Grammar::new(&source).unwrap();
+//! assert_eq!(grammar.first_dangling_reference(), None);
+//! ```
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+use proptest::{arbitrary::Arbitrary, prelude::*, strategy::BoxedStrategy};
+
+use crate::{Expr, Grammar, Rule, Span, SpanSet, simplification::simplify_node};
+
+const NUM_NAMES: usize = 128;
+
+/// `string0000` to `string0127`, used for the names of nonterminals and the contents of terminals
+#[expect(clippy::cast_possible_truncation, reason = "The digit count is tiny")]
+pub(crate) const NAMES: [&str; NUM_NAMES] = const {
+    const NUM_LENGTH: usize = 4;
+    const DIGITS: [u8; 10] = [b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9'];
+    const PREFIX: &str = "string";
+
+    const BYTE_BLOCKS: [[u8; PREFIX.len() + NUM_LENGTH]; 128] = {
+        let mut orig = [[0; PREFIX.len() + NUM_LENGTH]; 128];
+        let mut name_idx = 0;
+        while name_idx < orig.len() {
+            let leading = orig[name_idx]
+                .first_chunk_mut::<{ PREFIX.len() }>()
+                .unwrap();
+            leading.copy_from_slice(PREFIX.as_bytes());
+            let mut digit = NUM_LENGTH;
+            while digit > 0 {
+                let shift = name_idx / (10usize.pow((digit - 1) as u32));
+
+                orig[name_idx][PREFIX.len() + NUM_LENGTH - digit] = DIGITS[shift % DIGITS.len()];
+
+                digit -= 1;
+            }
+            name_idx += 1;
+        }
+        orig
+    };
+
+    let mut output = [""; NUM_NAMES];
+    let mut a = 0;
+    while a < output.len() {
+        output[a] = match str::from_utf8(&BYTE_BLOCKS[a]) {
+            Ok(s) => s,
+            Err(_) => unreachable!(),
+        };
+        a += 1;
+    }
+
+    output
+};
+
+/// Nodes whose nonterminals are all drawn from `names`, with every span set to `span`
+pub(crate) fn expr_strategy(
+    span: Span,
+    names: &'static [&'static str],
+) -> impl Strategy<Value = Expr<'static>> + Clone {
+    let nonterminal = (0..names.len()).prop_map(move |n| Expr::Nonterminal {
+        span,
+        name: names[n],
+    });
+    let terminal = (0..NAMES.len() * 2).prop_map(move |n| {
+        let typ = n % 2;
+        let n = n / 2;
+        match typ {
+            0 => Expr::Literal {
+                span,
+                str: NAMES[n],
+            },
+            1 => Expr::Regex {
+                span,
+                pattern: NAMES[n],
+            },
+            _ => unreachable!(),
+        }
+    });
+    let leaf = prop_oneof![nonterminal, terminal, Just(Expr::Empty { span })];
+
+    leaf.prop_recursive(2, 10, 2, move |inner| {
+        prop_oneof![
+            (prop::collection::vec(inner.clone(), 2), any::<bool>()).prop_map(
+                move |(body, ordered)| Expr::Choice {
+                    span,
+                    body,
+                    ordered,
+                }
+            ),
+            prop::collection::vec(inner.clone(), 2)
+                .prop_map(move |body| Expr::Optional { span, body }),
+            (
+                prop::collection::vec(inner.clone(), 2),
+                any::<bool>(),
+                any::<bool>()
+            )
+                .prop_map(move |(body, one_needed, separated)| Expr::Repetition {
+                    span,
+                    body,
+                    one_needed,
+                    separated,
+                }),
+            prop::collection::vec(inner.clone(), 2)
+                .prop_map(move |body| Expr::Group { span, body }),
+            prop::collection::vec(inner.clone(), 2).prop_map(move |body| Expr::And { span, body }),
+            prop::collection::vec(inner.clone(), 2).prop_map(move |body| Expr::Not { span, body }),
+            prop::collection::vec(inner.clone(), 2)
+                .prop_map(move |body| Expr::Hidden { span, body }),
+            (0..names.len(), prop::collection::vec(inner.clone(), 2)).prop_map(move |(n, body)| {
+                Expr::Labeled {
+                    span,
+                    label: names[n],
+                    body,
+                }
+            }),
+        ]
+    })
+}
+
+/// Any node that can be written as EBNF, as [`Expr::new`] would parse it
+pub fn expr() -> impl Strategy<Value = Expr<'static>> {
+    expr_strategy(Span::default(), &NAMES).prop_map(|mut expr| {
+        simplify_node(&mut expr);
+        expr
+    })
+}
+
+impl Arbitrary for Expr<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+        expr().boxed()
+    }
+}
+
+/// Grammars of between one and `NUM_RULES` rules, each with a body of up to four nodes, that only refer to rules they define
+pub fn grammar() -> impl Strategy<Value = Grammar<'static>> {
+    const NUM_RULES: usize = 8;
+    (1..=NUM_RULES).prop_flat_map(|len| {
+        let names = &NAMES[..len];
+        let body = prop::collection::vec(expr_strategy(Span::default(), names), 1..=4);
+        prop::collection::vec(body, len).prop_map(move |bodies| {
+            names
+                .iter()
+                .zip(bodies)
+                .map(|(name, mut body)| {
+                    body.iter_mut().for_each(simplify_node);
+                    Rule {
+                        name: (*name).into(),
+                        body,
+                        definitions: SpanSet::default(),
+                        hidden: false,
+                        annotations: Vec::new(),
+                    }
+                })
+                .collect()
+        })
+    })
+}
+
+/// The source text of grammars as generated by [`grammar`], one rule per line
+pub fn grammar_source() -> impl Strategy<Value = String> {
+    grammar().prop_map(|grammar| {
+        let mut source = String::new();
+        for rule in grammar.rules.values() {
+            write!(source, "{} =", rule.name).unwrap();
+            for node in &rule.body {
+                write!(source, " {node}").unwrap();
+            }
+            source.push_str(";\n");
+        }
+        source
+    })
+}
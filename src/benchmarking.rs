@@ -0,0 +1,46 @@
+//! Entry points to each phase of parsing, so that the benchmarks in `benches/` can time them separately. Only available with the `bench` feature, and not part of the crate's supported API.
+
+use alloc::vec::Vec;
+
+use crate::{
+    Grammar, ParseOptions, parse_rules_from_tokens,
+    simplification::simplify_node,
+    token_data::{Token, tokenize_with_imports},
+};
+
+/// The tokens of an input, as produced by [`tokenize`]
+#[derive(Debug, Clone)]
+pub struct Tokens<'a>(Vec<Token<'a>>);
+
+/// Splits `input` into tokens, the first phase of [`Grammar::new`]
+///
+/// # Panics
+/// If the input can't be tokenized
+pub fn tokenize(input: &str) -> Tokens<'_> {
+    let (tokens, _) = tokenize_with_imports(input).unwrap();
+    Tokens(tokens)
+}
+
+/// Builds a grammar from the tokens of `input`, which includes simplifying each rule as it is completed
+///
+/// # Panics
+/// If the tokens don't form a valid grammar
+pub fn parse<'a>(input: &'a str, tokens: &Tokens<'a>) -> Grammar<'a> {
+    let rules = parse_rules_from_tokens(
+        input,
+        input.len(),
+        &mut &tokens.0[..],
+        ParseOptions::default(),
+    )
+    .unwrap();
+    rules.into_iter().collect()
+}
+
+/// Runs the simplification pass over every rule of an already parsed grammar. This finds nothing left to change, but still does all the work of looking.
+pub fn simplify(grammar: &mut Grammar<'_>) {
+    for rule in grammar.rules.values_mut() {
+        for node in &mut rule.body {
+            simplify_node(node);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! The `ebnf` command-line tool, built with the `cli` feature, which checks, formats, graphs, lints, converts and generates sentences from grammar files
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use ebnf_toolkit::{
+    ConvertError, Dialect, GenerateOptions, Grammar, GrammarSet, LoadError, ReportConfig, convert,
+    lint::{LintConfig, LintLevel},
+};
+
+#[derive(Parser)]
+#[command(name = "ebnf", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Parses a grammar and the files it imports, and checks that every rule referred to is defined
+    Check { file: PathBuf },
+    /// Writes a grammar out in normalized notation. Comments are not kept.
+    Fmt {
+        file: PathBuf,
+        /// Replace the file's contents rather than printing them
+        #[arg(long, conflicts_with = "check")]
+        write: bool,
+        /// Print nothing, and fail if the file isn't already formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Prints which rules each rule refers to, including those of imported files
+    Graph {
+        file: PathBuf,
+        /// Print a Graphviz digraph rather than a line for each rule
+        #[arg(long)]
+        dot: bool,
+    },
+    /// Runs the built-in lints over a grammar, failing if any reports an error
+    Lint { file: PathBuf },
+    /// Prints random sentences matched by a rule, one per line. Alternatives of an ordered choice that an earlier alternative would match instead are avoided, but only by looking at the alternative's own text, so a sentence may still not match.
+    Gen {
+        file: PathBuf,
+        /// The rule to start from, which is the first one in the file by default
+        #[arg(long)]
+        start: Option<String>,
+        /// How many sentences to print
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+        #[arg(long, default_value_t = GenerateOptions::default().seed)]
+        seed: u64,
+        /// How many rules deep sentences are expanded before taking the quickest way to finish
+        #[arg(long, default_value_t = GenerateOptions::default().max_depth)]
+        max_depth: usize,
+        /// The most times a repetition is repeated beyond the fewest it needs
+        #[arg(long, default_value_t = GenerateOptions::default().max_repetitions)]
+        max_repetitions: usize,
+        /// How many parts of the grammar a sentence expands before taking the quickest way to finish
+        #[arg(long, default_value_t = GenerateOptions::default().max_nodes)]
+        max_nodes: usize,
+    },
+    /// Converts a grammar to another notation, listing anything that couldn't be carried over exactly. Imports are not followed.
+    Convert {
+        file: PathBuf,
+        /// The notation the file is written in
+        #[arg(long, value_enum, default_value_t = Notation::Ebnf)]
+        from: Notation,
+        /// The notation to write the grammar in
+        #[arg(long, value_enum)]
+        to: Notation,
+    },
+}
+
+/// The notations `convert` knows, as named on the command line
+#[derive(Clone, Copy, ValueEnum)]
+enum Notation {
+    Ebnf,
+    Pest,
+    Yacc,
+    Lalrpop,
+    TreeSitter,
+}
+
+impl From<Notation> for Dialect {
+    fn from(notation: Notation) -> Self {
+        match notation {
+            Notation::Ebnf => Dialect::Ebnf,
+            Notation::Pest => Dialect::Pest,
+            Notation::Yacc => Dialect::Yacc,
+            Notation::Lalrpop => Dialect::Lalrpop,
+            Notation::TreeSitter => Dialect::TreeSitter,
+        }
+    }
+}
+
+/// A grammar file loaded along with everything it imports. Files are read once and live until the tool exits, so their text is leaked to let the grammars borrow from it.
+struct Loaded {
+    root: &'static str,
+    set: GrammarSet<'static>,
+    sources: BTreeMap<&'static str, &'static str>,
+}
+
+impl Loaded {
+    fn grammar(&self) -> &Grammar<'static> {
+        let (_, grammar) = self
+            .set
+            .files()
+            .find(|(name, _)| *name == self.root)
+            .unwrap();
+        grammar
+    }
+}
+
+fn leak(s: String) -> &'static str {
+    Box::leak(s.into_boxed_str())
+}
+
+fn report_config(name: &str) -> ReportConfig<'_> {
+    ReportConfig::default()
+        .with_color(io::stderr().is_terminal())
+        .with_source_name(name)
+}
+
+fn load(file: &Path) -> Result<Loaded, ExitCode> {
+    let root = leak(file.display().to_string());
+    let input = fs::read_to_string(file).map_err(|error| {
+        eprintln!("Could not read {root}: {error}");
+        ExitCode::FAILURE
+    })?;
+    let input = leak(input);
+
+    let mut sources = BTreeMap::from([(root, input)]);
+    // Imports are found relative to the file importing them
+    let mut resolver = |importer: &str, path: &'static str| {
+        let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+        let name = leak(dir.join(path).display().to_string());
+        let text = leak(fs::read_to_string(name)?);
+        sources.insert(name, text);
+        Ok::<_, io::Error>((name, text))
+    };
+    match GrammarSet::load(root, input, &mut resolver) {
+        Ok(set) => Ok(Loaded { root, set, sources }),
+        Err(LoadError::Parse(error)) => {
+            let _ = error.write_report(io::stderr(), report_config(error.source_name));
+            Err(ExitCode::FAILURE)
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn check(loaded: &Loaded) -> ExitCode {
+    let errors = loaded.set.undefined_references();
+    for (file, error) in &errors {
+        eprint!(
+            "{}",
+            error.render(loaded.sources[file], report_config(file))
+        );
+    }
+    if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn fmt(loaded: &Loaded, write: bool, check: bool) -> ExitCode {
+    let formatted = loaded.grammar().to_string();
+    if check {
+        if formatted == loaded.sources[loaded.root] {
+            return ExitCode::SUCCESS;
+        }
+        eprintln!("{} is not formatted", loaded.root);
+        return ExitCode::FAILURE;
+    }
+    if write {
+        if let Err(error) = fs::write(loaded.root, formatted) {
+            eprintln!("Could not write {}: {error}", loaded.root);
+            return ExitCode::FAILURE;
+        }
+    } else {
+        print!("{formatted}");
+    }
+    ExitCode::SUCCESS
+}
+
+fn graph(loaded: &Loaded, dot: bool) -> ExitCode {
+    let frozen = loaded.set.to_grammar().freeze();
+    let mut out = io::stdout().lock();
+    let result = (|| {
+        if dot {
+            writeln!(out, "digraph grammar {{")?;
+        }
+        for id in frozen.ids() {
+            let name = &frozen.rule(id).name;
+            let dependencies = frozen
+                .dependencies(id)
+                .iter()
+                .map(|&d| &frozen.rule(d).name);
+            if dot {
+                writeln!(out, "    {name:?};")?;
+                for dependency in dependencies {
+                    writeln!(out, "    {name:?} -> {dependency:?};")?;
+                }
+            } else {
+                write!(out, "{name}:")?;
+                for dependency in dependencies {
+                    write!(out, " {dependency}")?;
+                }
+                writeln!(out)?;
+            }
+        }
+        if dot {
+            writeln!(out, "}}")?;
+        }
+        Ok::<_, io::Error>(())
+    })();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn lint(loaded: &Loaded) -> ExitCode {
+    let diagnostics = loaded.grammar().lint(&LintConfig::default());
+    for diagnostic in &diagnostics {
+        println!("{}: {diagnostic}", loaded.root);
+    }
+    if diagnostics.iter().any(|d| d.level == LintLevel::Error) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn generate(
+    loaded: &Loaded,
+    start: Option<&str>,
+    count: usize,
+    options: GenerateOptions,
+) -> ExitCode {
+    let grammar = loaded.set.to_grammar();
+    let first = loaded
+        .grammar()
+        .rules()
+        .min_by_key(|rule| rule.definitions.hull().map(|span| span.start()));
+    let Some(start) = start.or(first.map(|rule| &*rule.name)) else {
+        eprintln!("{} has no rules", loaded.root);
+        return ExitCode::FAILURE;
+    };
+
+    let mut generator = grammar.generator(options);
+    for _ in 0..count {
+        match generator.generate(start) {
+            Ok(sentence) => println!("{sentence}"),
+            Err(error) => {
+                eprintln!("{error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn convert_file(file: &Path, from: Notation, to: Notation) -> ExitCode {
+    let name = file.display().to_string();
+    let input = match fs::read_to_string(file) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("Could not read {name}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match convert(&input, from.into(), to.into()) {
+        Ok(conversion) => {
+            print!("{}", conversion.output);
+            for approximation in &conversion.approximations {
+                eprintln!("{name}: {approximation}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(ConvertError::Ebnf(error)) => {
+            let _ = error.write_report(io::stderr(), report_config(&name));
+            ExitCode::FAILURE
+        }
+        Err(error) => {
+            eprintln!("{name}: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let file = match &cli.command {
+        // Other notations can't be imported from, so the file is read on its own
+        Command::Convert { file, from, to } => return convert_file(file, *from, *to),
+        Command::Check { file }
+        | Command::Fmt { file, .. }
+        | Command::Graph { file, .. }
+        | Command::Lint { file }
+        | Command::Gen { file, .. } => file,
+    };
+    let loaded = match load(file) {
+        Ok(loaded) => loaded,
+        Err(code) => return code,
+    };
+
+    match cli.command {
+        Command::Check { .. } => check(&loaded),
+        Command::Fmt { write, check, .. } => fmt(&loaded, write, check),
+        Command::Graph { dot, .. } => graph(&loaded, dot),
+        Command::Lint { .. } => lint(&loaded),
+        Command::Gen {
+            start,
+            count,
+            seed,
+            max_depth,
+            max_repetitions,
+            max_nodes,
+            ..
+        } => generate(
+            &loaded,
+            start.as_deref(),
+            count,
+            GenerateOptions {
+                seed,
+                max_depth,
+                max_repetitions,
+                max_nodes,
+            },
+        ),
+        Command::Convert { .. } => unreachable!("conversions return before loading"),
+    }
+}
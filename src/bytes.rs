@@ -0,0 +1,468 @@
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+use core::fmt::Display;
+
+use crate::{
+    Annotation, ChoiceSemantics, Expr, Grammar, Import, NodeKind, ParseOptions, Rule, Span,
+    SpanSet, expr::Operator,
+};
+
+/// Written at the start of every encoding, followed by [`VERSION`]
+const MAGIC: &[u8; 4] = b"EBNF";
+/// Raised whenever the layout changes in a way older readers can't follow
+const VERSION: u8 = 1;
+
+/// The ways [`Grammar::from_bytes`] can fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BytesError {
+    /// The data doesn't start the way [`Grammar::to_bytes`] starts its output
+    NotAGrammar,
+    /// The data was written in a layout this version of the crate doesn't know
+    UnsupportedVersion(u8),
+    /// The data ends part way through the grammar
+    Truncated,
+    /// Something at this offset into the data can't be part of a grammar, such as a code that stands for no kind of node, text that isn't UTF-8, a second rule with the same name, or a node nested more than [`ParseOptions::DEFAULT_MAX_DEPTH`](crate::ParseOptions::DEFAULT_MAX_DEPTH) deep
+    Invalid(usize),
+    /// The grammar ended before the data did, at this offset
+    TrailingData(usize),
+}
+
+impl Display for BytesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BytesError::NotAGrammar => write!(f, "The data is not an encoded grammar"),
+            BytesError::UnsupportedVersion(version) => {
+                write!(f, "The grammar was encoded in unknown version {version}")
+            }
+            BytesError::Truncated => write!(f, "The data ends part way through the grammar"),
+            BytesError::Invalid(offset) => write!(f, "The data is not valid at byte {offset}"),
+            BytesError::TrailingData(offset) => {
+                write!(f, "The grammar ends at byte {offset}, before the data does")
+            }
+        }
+    }
+}
+
+pub(crate) fn to_bytes(grammar: &Grammar<'_>) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    out.push(VERSION);
+    out.push(
+        u8::from(grammar.choice_semantics == ChoiceSemantics::Ordered)
+            | u8::from(grammar.name_order) << 1,
+    );
+    write_len(&mut out, grammar.imports.len());
+    for import in &grammar.imports {
+        write_str(&mut out, import.path);
+        write_span(&mut out, import.span);
+    }
+    write_len(&mut out, grammar.rules.len());
+    for rule in grammar.rules.values() {
+        write_rule(&mut out, rule);
+    }
+    out
+}
+
+// Every number is written as a LEB128 varint, so small ones take a single byte
+fn write_len(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        #[expect(clippy::cast_possible_truncation, reason = "Masked to seven bits")]
+        out.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    #[expect(clippy::cast_possible_truncation, reason = "Less than 0x80")]
+    out.push(n as u8);
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_len(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_span(out: &mut Vec<u8>, span: Span) {
+    let (start_line, start_column) = span.start_line();
+    let (end_line, end_column) = span.end_line();
+    for n in [
+        span.start(),
+        span.end(),
+        start_line,
+        start_column,
+        end_line,
+        end_column,
+    ] {
+        write_len(out, n);
+    }
+}
+
+fn write_rule(out: &mut Vec<u8>, rule: &Rule<'_>) {
+    write_str(out, &rule.name);
+    out.push(u8::from(rule.hidden));
+    write_len(out, rule.annotations.len());
+    for annotation in &rule.annotations {
+        write_str(out, annotation.name);
+        write_span(out, annotation.span);
+    }
+    write_len(out, rule.definitions.len());
+    for span in &rule.definitions {
+        write_span(out, span);
+    }
+    write_nodes(out, &rule.body);
+}
+
+fn write_nodes(out: &mut Vec<u8>, nodes: &[Expr<'_>]) {
+    write_len(out, nodes.len());
+    for node in nodes {
+        write_node(out, node);
+    }
+}
+
+fn write_node(out: &mut Vec<u8>, node: &Expr<'_>) {
+    out.push(NodeKind::from(node).stable_code());
+    write_span(out, node.span());
+    match node {
+        Expr::Literal { str: text, .. }
+        | Expr::Nonterminal { name: text, .. }
+        | Expr::Regex { pattern: text, .. } => write_str(out, text),
+        Expr::Choice { body, ordered, .. } => {
+            out.push(u8::from(*ordered));
+            write_nodes(out, body);
+        }
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated,
+            ..
+        } => {
+            out.push(u8::from(*one_needed) | u8::from(*separated) << 1);
+            write_nodes(out, body);
+        }
+        Expr::Labeled { label, body, .. } => {
+            write_str(out, label);
+            write_nodes(out, body);
+        }
+        Expr::Optional { body, .. }
+        | Expr::Group { body, .. }
+        | Expr::And { body, .. }
+        | Expr::Not { body, .. }
+        | Expr::Hidden { body, .. } => write_nodes(out, body),
+        Expr::UnparsedOperator { op, .. } => out.push(op.stable_code()),
+        Expr::Rule { rule, .. } => write_rule(out, rule),
+        Expr::Empty { .. } => {}
+    }
+}
+
+pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Grammar<'_>, BytesError> {
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        return Err(BytesError::NotAGrammar);
+    };
+    let mut reader = Reader {
+        bytes,
+        offset: bytes.len() - rest.len(),
+        depth: 0,
+    };
+    match reader.byte()? {
+        VERSION => {}
+        version => return Err(BytesError::UnsupportedVersion(version)),
+    }
+    let offset = reader.offset;
+    let flags = reader.byte()?;
+    if flags > 0b11 {
+        return Err(BytesError::Invalid(offset));
+    }
+    let choice_semantics = if flags & 1 != 0 {
+        ChoiceSemantics::Ordered
+    } else {
+        ChoiceSemantics::Unordered
+    };
+    let imports = (0..reader.len()?)
+        .map(|_| {
+            Ok(Import {
+                path: reader.str()?,
+                span: reader.span()?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let mut rules = BTreeMap::new();
+    for _ in 0..reader.len()? {
+        let offset = reader.offset;
+        let rule = reader.rule()?;
+        if rules.insert(rule.name.clone(), rule).is_some() {
+            return Err(BytesError::Invalid(offset));
+        }
+    }
+    if reader.offset < bytes.len() {
+        return Err(BytesError::TrailingData(reader.offset));
+    }
+    Ok(Grammar {
+        rules,
+        imports,
+        choice_semantics,
+        name_order: flags & 0b10 != 0,
+    })
+}
+
+/// Reads back what the functions above write, borrowing the text from `bytes`
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    /// How many nodes enclose the one being read. Decoding recurses once per level, and the tree it builds has to be safe to clone and drop, so this is held to [`ParseOptions::DEFAULT_MAX_DEPTH`] like parsed text is.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, BytesError> {
+        let byte = *self.bytes.get(self.offset).ok_or(BytesError::Truncated)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn flag(&mut self) -> Result<bool, BytesError> {
+        let offset = self.offset;
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(BytesError::Invalid(offset)),
+        }
+    }
+
+    fn len(&mut self) -> Result<usize, BytesError> {
+        let offset = self.offset;
+        let mut n = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            let bits = usize::from(byte & 0x7f);
+            if bits << shift >> shift != bits {
+                return Err(BytesError::Invalid(offset));
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(BytesError::Invalid(offset))
+    }
+
+    fn str(&mut self) -> Result<&'a str, BytesError> {
+        let len = self.len()?;
+        let offset = self.offset;
+        let end = offset.checked_add(len).ok_or(BytesError::Truncated)?;
+        let bytes = self.bytes.get(offset..end).ok_or(BytesError::Truncated)?;
+        self.offset = end;
+        core::str::from_utf8(bytes).map_err(|_| BytesError::Invalid(offset))
+    }
+
+    fn span(&mut self) -> Result<Span, BytesError> {
+        let start = self.len()?;
+        let end = self.len()?;
+        let start_line = (self.len()?, self.len()?);
+        let end_line = (self.len()?, self.len()?);
+        Ok(Span::new(start..end, start_line, end_line))
+    }
+
+    fn rule(&mut self) -> Result<Rule<'a>, BytesError> {
+        let name = Cow::Borrowed(self.str()?);
+        let hidden = self.flag()?;
+        let annotations = (0..self.len()?)
+            .map(|_| {
+                Ok(Annotation {
+                    name: self.str()?,
+                    span: self.span()?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let mut definitions = SpanSet::default();
+        for _ in 0..self.len()? {
+            definitions.insert(self.span()?);
+        }
+        Ok(Rule {
+            name,
+            body: self.nodes()?,
+            definitions,
+            hidden,
+            annotations,
+        })
+    }
+
+    fn nodes(&mut self) -> Result<Vec<Expr<'a>>, BytesError> {
+        (0..self.len()?).map(|_| self.node()).collect()
+    }
+
+    fn node(&mut self) -> Result<Expr<'a>, BytesError> {
+        let offset = self.offset;
+        if self.depth == ParseOptions::DEFAULT_MAX_DEPTH {
+            return Err(BytesError::Invalid(offset));
+        }
+        self.depth += 1;
+        let node = self.node_contents(offset);
+        self.depth -= 1;
+        node
+    }
+
+    fn node_contents(&mut self, offset: usize) -> Result<Expr<'a>, BytesError> {
+        let kind = NodeKind::from_stable_code(self.byte()?).ok_or(BytesError::Invalid(offset))?;
+        let span = self.span()?;
+        Ok(match kind {
+            NodeKind::Literal => Expr::Literal {
+                span,
+                str: self.str()?,
+            },
+            NodeKind::Nonterminal => Expr::Nonterminal {
+                span,
+                name: self.str()?,
+            },
+            NodeKind::Regex => Expr::Regex {
+                span,
+                pattern: self.str()?,
+            },
+            NodeKind::Choice => Expr::Choice {
+                span,
+                ordered: self.flag()?,
+                body: self.nodes()?,
+            },
+            NodeKind::Repetition => {
+                let offset = self.offset;
+                let flags = self.byte()?;
+                if flags > 0b11 {
+                    return Err(BytesError::Invalid(offset));
+                }
+                Expr::Repetition {
+                    span,
+                    one_needed: flags & 1 != 0,
+                    separated: flags & 0b10 != 0,
+                    body: self.nodes()?,
+                }
+            }
+            NodeKind::Labeled => Expr::Labeled {
+                span,
+                label: self.str()?,
+                body: self.nodes()?,
+            },
+            NodeKind::Optional => Expr::Optional {
+                span,
+                body: self.nodes()?,
+            },
+            NodeKind::Group => Expr::Group {
+                span,
+                body: self.nodes()?,
+            },
+            NodeKind::And => Expr::And {
+                span,
+                body: self.nodes()?,
+            },
+            NodeKind::Not => Expr::Not {
+                span,
+                body: self.nodes()?,
+            },
+            NodeKind::Hidden => Expr::Hidden {
+                span,
+                body: self.nodes()?,
+            },
+            NodeKind::UnparsedOperator => {
+                let offset = self.offset;
+                let op =
+                    Operator::from_stable_code(self.byte()?).ok_or(BytesError::Invalid(offset))?;
+                Expr::UnparsedOperator { span, op }
+            }
+            NodeKind::Rule => Expr::Rule {
+                span,
+                rule: self.rule()?,
+            },
+            NodeKind::Empty => Expr::Empty { span },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{vec, vec::Vec};
+
+    use crate::{BytesError, ChoiceSemantics, Grammar, NodeKind, ParseOptions};
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut g = Grammar::new(
+            "%import 'lexer.ebnf';\n@token\n<list> = item % ',' | &'x' !'y' [label:item <'z'>]; \
+             item = #'[a-z]+' | ('(' list ')') | ε | item+; item = 'extra';",
+        )
+        .unwrap();
+        g.set_choice_semantics(ChoiceSemantics::Ordered);
+        let bytes = g.to_bytes();
+        assert_eq!(Grammar::from_bytes(&bytes), Ok(g));
+
+        assert_eq!(Grammar::from_bytes(b"EBNG"), Err(BytesError::NotAGrammar));
+        assert_eq!(
+            Grammar::from_bytes(b"EBNF\x09"),
+            Err(BytesError::UnsupportedVersion(9))
+        );
+        for end in 5..bytes.len() {
+            assert_eq!(
+                Grammar::from_bytes(&bytes[..end]),
+                Err(BytesError::Truncated)
+            );
+        }
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(
+            Grammar::from_bytes(&extra),
+            Err(BytesError::TrailingData(bytes.len()))
+        );
+        let mut invalid = vec![];
+        invalid.extend_from_slice(b"EBNF\x01\x04");
+        assert_eq!(Grammar::from_bytes(&invalid), Err(BytesError::Invalid(5)));
+    }
+
+    #[test]
+    fn bytes_rejected() {
+        let header = |rules: u8| {
+            let mut bytes = b"EBNF\x01\x00\x00".to_vec();
+            bytes.push(rules);
+            bytes
+        };
+        let rule = |bytes: &mut Vec<u8>, name: u8| {
+            bytes.extend_from_slice(&[1, name, 0, 0, 0, 1]);
+        };
+        let group = |bytes: &mut Vec<u8>| {
+            bytes.push(NodeKind::Group.stable_code());
+            bytes.extend_from_slice(&[0; 6]);
+            bytes.push(1);
+        };
+        let empty = |bytes: &mut Vec<u8>| {
+            bytes.push(NodeKind::Empty.stable_code());
+            bytes.extend_from_slice(&[0; 6]);
+        };
+
+        let nested = |levels: usize| {
+            let mut bytes = header(1);
+            rule(&mut bytes, b'A');
+            for _ in 0..levels {
+                group(&mut bytes);
+            }
+            empty(&mut bytes);
+            bytes
+        };
+        let deepest = nested(ParseOptions::DEFAULT_MAX_DEPTH - 1);
+        let g = Grammar::from_bytes(&deepest).unwrap();
+        assert_eq!(g["A"].body[0].depth(), ParseOptions::DEFAULT_MAX_DEPTH);
+
+        let too_deep = nested(ParseOptions::DEFAULT_MAX_DEPTH);
+        let offset = too_deep.len() - 7;
+        assert_eq!(
+            Grammar::from_bytes(&too_deep),
+            Err(BytesError::Invalid(offset))
+        );
+        assert!(matches!(
+            Grammar::from_bytes(&nested(100_000)),
+            Err(BytesError::Invalid(_))
+        ));
+
+        let mut duplicate = header(2);
+        rule(&mut duplicate, b'A');
+        empty(&mut duplicate);
+        let offset = duplicate.len();
+        rule(&mut duplicate, b'A');
+        empty(&mut duplicate);
+        assert_eq!(
+            Grammar::from_bytes(&duplicate),
+            Err(BytesError::Invalid(offset))
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+
+use crate::Grammar;
+
+/// Whether a rule belongs in the lexer or the parser of a generated parser, from [`Grammar::classify_rules`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleClass {
+    /// The rule only matches literals and regexes, directly or through other lexical rules, and never refers back to itself, so it describes a token
+    Lexical,
+    /// The rule refers to itself, directly or through other rules, or to a rule that is syntactic or undefined
+    Syntactic,
+}
+
+pub(crate) fn classify_rules<'a>(grammar: &'a Grammar<'_>) -> Vec<(&'a str, RuleClass)> {
+    let references: Vec<_> = grammar
+        .rules
+        .values()
+        .map(|rule| (&*rule.name, rule.nonterminals()))
+        .collect();
+
+    // A rule in a cycle can never have all of its references marked lexical first, so recursive rules are never added
+    let mut lexical = BTreeSet::new();
+    loop {
+        let before = lexical.len();
+        for (name, names) in &references {
+            if !lexical.contains(name) && names.iter().all(|n| lexical.contains(n)) {
+                lexical.insert(*name);
+            }
+        }
+        if lexical.len() == before {
+            break;
+        }
+    }
+
+    references
+        .into_iter()
+        .map(|(name, _)| {
+            let class = if lexical.contains(name) {
+                RuleClass::Lexical
+            } else {
+                RuleClass::Syntactic
+            };
+            (name, class)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{Grammar, RuleClass};
+
+    #[test]
+    fn classify() {
+        let src = "expr = term (op term)*; term = number | '(' expr ')'; op = '+' | MINUS; \
+                   MINUS = '-'; number = digit+ ('.' digit+)?; digit = #'[0-9]'; \
+                   ws = ws ' ' | ' '; call = ident args; ident = #'[a-z]+'; args = missing;";
+        let g = Grammar::new(src).unwrap();
+
+        let classes = g.classify_rules();
+        assert_eq!(classes.len(), 10);
+        let lexical: Vec<_> = classes
+            .iter()
+            .filter(|(_, class)| *class == RuleClass::Lexical)
+            .map(|(name, _)| *name)
+            .collect();
+        assert_eq!(lexical, ["MINUS", "digit", "ident", "number", "op"]);
+
+        let syntactic: Vec<_> = g.syntactic_rules().iter().map(|r| &*r.name).collect();
+        assert_eq!(syntactic, ["args", "call", "expr", "term", "ws"]);
+        assert_eq!(g.lexical_rules().len(), lexical.len());
+    }
+}
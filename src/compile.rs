@@ -0,0 +1,215 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt::Display;
+
+use regex::Regex;
+
+use crate::{Expr, Grammar, Rule, Span};
+
+/// The ways [`Rule::compile_to_regex`] can fail
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CompileError<'a> {
+    /// The rule refers back to the rule called `name` while compiling it, directly or through other rules, so what it matches may not be regular
+    Recursive {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+        /// Where the reference that closes the cycle appears
+        span: Span,
+    },
+    /// The rule refers to a nonterminal `name` that the grammar doesn't define
+    Undefined {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+        /// Where the reference appears
+        span: Span,
+    },
+    /// The rule contains an [`And`](Expr::And) or [`Not`](Expr::Not) lookahead, which the `regex` crate has no equivalent for, or an [`UnparsedOperator`](Expr::UnparsedOperator)
+    Unsupported {
+        /// Where the node appears
+        span: Span,
+    },
+    /// The combined pattern was rejected, either because a regex in the grammar is invalid or because the result is too large
+    Regex(regex::Error),
+}
+
+impl Display for CompileError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CompileError::Recursive { name, span } => {
+                write!(f, "Rule `{name}` is used recursively at {span}")
+            }
+            CompileError::Undefined { name, span } => {
+                write!(f, "`{name}` at {span} is not defined")
+            }
+            CompileError::Unsupported { span } => {
+                write!(f, "The node at {span} can't be written as a regex")
+            }
+            CompileError::Regex(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+pub(crate) fn compile_rule<'a>(
+    rule: &Rule<'a>,
+    grammar: &Grammar<'a>,
+) -> Result<Regex, CompileError<'a>> {
+    let pattern = rule_pattern(rule, grammar)?;
+    Regex::new(&format!("^(?:{pattern})$")).map_err(CompileError::Regex)
+}
+
+/// The pattern [`compile_rule`] builds for `rule`, before it's anchored or checked
+pub(crate) fn rule_pattern<'a>(
+    rule: &Rule<'a>,
+    grammar: &Grammar<'a>,
+) -> Result<String, CompileError<'a>> {
+    let mut pattern = String::new();
+    let mut path = vec![&*rule.name];
+    write_sequence(&rule.body, grammar, &mut path, &mut pattern)?;
+    Ok(pattern)
+}
+
+fn write_sequence<'g, 'a>(
+    nodes: &'g [Expr<'a>],
+    grammar: &'g Grammar<'a>,
+    path: &mut Vec<&'g str>,
+    out: &mut String,
+) -> Result<(), CompileError<'a>> {
+    for node in nodes {
+        write_node(node, grammar, path, out)?;
+    }
+    Ok(())
+}
+
+/// Appends a pattern for `node` to `out`, inlining the rules its nonterminals name. `path` holds the rules being inlined further out, which can't be inlined again.
+fn write_node<'g, 'a>(
+    node: &'g Expr<'a>,
+    grammar: &'g Grammar<'a>,
+    path: &mut Vec<&'g str>,
+    out: &mut String,
+) -> Result<(), CompileError<'a>> {
+    match node {
+        Expr::Literal { str, .. } => out.push_str(&regex_syntax::escape(str)),
+        Expr::Regex { pattern, .. } => {
+            out.push_str("(?:");
+            out.push_str(pattern);
+            out.push(')');
+        }
+        Expr::Empty { .. } => {}
+        &Expr::Nonterminal { span, name } => {
+            if path.contains(&name) {
+                return Err(CompileError::Recursive { name, span });
+            }
+            let rule = grammar
+                .rules
+                .get(name)
+                .ok_or(CompileError::Undefined { name, span })?;
+            path.push(name);
+            out.push_str("(?:");
+            write_sequence(&rule.body, grammar, path, out)?;
+            out.push(')');
+            path.pop();
+        }
+        Expr::Choice { body, .. } => {
+            out.push_str("(?:");
+            for (n, alternative) in body.iter().enumerate() {
+                if n > 0 {
+                    out.push('|');
+                }
+                write_node(alternative, grammar, path, out)?;
+            }
+            out.push(')');
+        }
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated: true,
+            ..
+        } if body.len() > 1 => {
+            let (item, separator) = body.split_at(body.len() - 1);
+            out.push_str("(?:");
+            write_sequence(item, grammar, path, out)?;
+            out.push_str("(?:");
+            write_sequence(separator, grammar, path, out)?;
+            write_sequence(item, grammar, path, out)?;
+            out.push_str(")*)");
+            if !one_needed {
+                out.push('?');
+            }
+        }
+        Expr::Optional { body, .. } | Expr::Repetition { body, .. } => {
+            out.push_str("(?:");
+            write_sequence(body, grammar, path, out)?;
+            out.push(')');
+            out.push(match node {
+                Expr::Repetition {
+                    one_needed: true, ..
+                } => '+',
+                Expr::Repetition { .. } => '*',
+                _ => '?',
+            });
+        }
+        // Hiding and labelling only change the tree a parser builds, not what is matched
+        Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+            out.push_str("(?:");
+            write_sequence(body, grammar, path, out)?;
+            out.push(')');
+        }
+        Expr::Rule { rule, .. } => {
+            out.push_str("(?:");
+            write_sequence(&rule.body, grammar, path, out)?;
+            out.push(')');
+        }
+        Expr::And { span, .. } | Expr::Not { span, .. } | Expr::UnparsedOperator { span, .. } => {
+            return Err(CompileError::Unsupported { span: *span });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CompileError, Grammar};
+
+    #[test]
+    fn compile_to_regex() {
+        let src = "number = sign? digits ('.' digits)?; sign = '+' | '-'; <digits> = #'[0-9]'+; \
+                   list = '(' list ')' | number; bad = number !'.'; dangling = missing;";
+        let g = Grammar::new(src).unwrap();
+
+        let number = g["number"].compile_to_regex(&g).unwrap();
+        for matching in ["1", "-12", "+3.25"] {
+            assert!(number.is_match(matching), "{matching}");
+        }
+        for other in ["", "1.", ".5", "1+2", "x1"] {
+            assert!(!number.is_match(other), "{other}");
+        }
+
+        let separated = Grammar::new("args = #'[a-z]'* % ',';").unwrap();
+        let args = separated["args"].compile_to_regex(&separated).unwrap();
+        for matching in ["", "a", "a,b,c"] {
+            assert!(args.is_match(matching), "{matching}");
+        }
+        for other in [",", "a,", "ab", ",a"] {
+            assert!(!args.is_match(other), "{other}");
+        }
+
+        let Err(CompileError::Recursive { name: "list", span }) = g["list"].compile_to_regex(&g)
+        else {
+            panic!()
+        };
+        assert_eq!(&src[span.range()], "list");
+
+        let Err(CompileError::Unsupported { span }) = g["bad"].compile_to_regex(&g) else {
+            panic!()
+        };
+        assert!(src[span.range()].ends_with("'.'"));
+
+        assert!(matches!(
+            g["dangling"].compile_to_regex(&g),
+            Err(CompileError::Undefined {
+                name: "missing",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::{borrow::Cow, collections::BTreeMap, string::String, vec::Vec};
+
+use crate::{Expr, Grammar, Rule, ValidationError};
+
+pub(crate) fn compose<'a>(
+    grammar: &mut Grammar<'a>,
+    prefix: &str,
+    other: &Grammar<'a>,
+    names: &'a mut String,
+) -> Result<(), Vec<ValidationError<'a>>> {
+    // Every new name is written out before any are borrowed, as `names` can't grow once it has been
+    let mut ranges = Vec::with_capacity(other.rules.len());
+    for name in other.rules.keys() {
+        let start = names.len();
+        names.push_str(prefix);
+        names.push_str(name);
+        ranges.push(start..names.len());
+    }
+    let names: &'a str = names;
+    let renamed: BTreeMap<&str, &'a str> = other
+        .rules
+        .keys()
+        .map(|name| &**name)
+        .zip(ranges.into_iter().map(|range| &names[range]))
+        .collect();
+
+    let errors: Vec<_> = renamed
+        .values()
+        .filter_map(|name| grammar.rules.get(*name))
+        .map(|existing| ValidationError::DuplicateRule {
+            name: existing.name.clone(),
+            definitions: existing.definitions.clone(),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    for rule in other.rules.values() {
+        let mut rule = rule.clone();
+        rule.name = Cow::Borrowed(renamed[&*rule.name]);
+        rename_references(&mut rule, &renamed);
+        grammar.rules.insert(rule.name.clone(), rule);
+    }
+    Ok(())
+}
+
+fn rename_references<'a>(rule: &mut Rule<'a>, renamed: &BTreeMap<&str, &'a str>) {
+    let mut stack: Vec<_> = rule.body.iter_mut().collect();
+    while let Some(node) = stack.pop() {
+        match node {
+            Expr::Nonterminal { name, .. } => {
+                if let Some(new) = renamed.get(name) {
+                    *name = new;
+                }
+            }
+            other => stack.extend(other.children_mut()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use crate::{Grammar, ValidationError};
+
+    #[test]
+    fn compose() {
+        let numbers =
+            Grammar::new("number = digit+ ('.' digit+)?; digit = #'[0-9]'; spaced = ws number ws;")
+                .unwrap();
+
+        let mut names = String::new();
+        let mut g = Grammar::new("price = '$' num_number; ws = ' ';").unwrap();
+        g.compose("num_", &numbers, &mut names).unwrap();
+        assert_eq!(g["num_number"].nonterminals(), ["num_digit", "num_digit"]);
+        // `ws` isn't defined by `numbers`, so it refers to the rule of the composed grammar
+        assert_eq!(g["num_spaced"].nonterminals(), ["ws", "num_number", "ws"]);
+        assert!(g.first_dangling_reference().is_none());
+
+        let mut names = String::new();
+        let mut clash = Grammar::new("num_digit = 'x';").unwrap();
+        let errors = clash.compose("num_", &numbers, &mut names).unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ValidationError::DuplicateRule { name, .. }] if name == "num_digit"
+        ));
+        assert_eq!(clash.rules.len(), 1);
+    }
+
+    #[test]
+    fn compose_twice() {
+        let digits = Grammar::new("digit = #'[0-9]';").unwrap();
+        let (mut names, mut more_names, mut again) = (String::new(), String::new(), String::new());
+        let mut numbers = Grammar::new("number = d_digit+;").unwrap();
+        numbers.compose("d_", &digits, &mut names).unwrap();
+
+        let mut g = Grammar::new("range = from_number '..' to_number;").unwrap();
+        g.compose("from_", &numbers, &mut more_names).unwrap();
+        g.compose("to_", &numbers, &mut again).unwrap();
+        let mut composed: Vec<_> = g.rules().map(|rule| &*rule.name).collect();
+        composed.sort_unstable();
+        assert_eq!(
+            composed,
+            [
+                "from_d_digit",
+                "from_number",
+                "range",
+                "to_d_digit",
+                "to_number"
+            ]
+        );
+        assert_eq!(g["to_number"].nonterminals(), ["to_d_digit"]);
+        assert!(g.first_dangling_reference().is_none());
+        assert!(Grammar::new(&g.to_string()).unwrap().eq_ignoring_spans(&g));
+
+        let mut names = String::new();
+        let errors = g.compose("to_", &numbers, &mut names).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,229 @@
+use alloc::{vec, vec::Vec};
+use core::fmt::Display;
+
+use crate::{Expr, Rule, Span};
+
+/// Why a character found by [`Grammar::suspicious_characters`](crate::Grammar::suspicious_characters) is likely to be a mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CharacterIssue {
+    /// The character takes up no space when displayed, such as a zero-width space or a byte order mark
+    Invisible,
+    /// The character looks like an ordinary space but is a different one, such as a non-breaking space
+    UnusualSpace,
+    /// The character looks like a different, usually ASCII, one, such as the Cyrillic `а` in place of the Latin `a`. These are only reported in text that also contains ASCII letters, so that a grammar written in another script isn't flagged throughout.
+    Confusable,
+}
+
+/// A character in a literal, regex or name that is easily mistaken for another or can't be seen at all, which usually arrives by copying text from a document or web page.
+/// ```rust
+/// # use ebnf_toolkit::{CharacterIssue, Grammar};
+/// let src = "greeting = 'hello\u{a0}world';";
+/// let g = Grammar::new(src).unwrap();
+/// let [found] = &g.suspicious_characters()[..] else { panic!() };
+/// assert_eq!(found.issue, CharacterIssue::UnusualSpace);
+/// assert_eq!(found.replacement, Some(' '));
+/// assert_eq!(&src[found.span.range()], "\u{a0}");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspiciousCharacter<'a> {
+    /// The name of the rule the character appears in
+    pub rule: &'a str,
+    /// Where the character itself is in the input
+    pub span: Span,
+    /// The character that was found
+    pub found: char,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub issue: CharacterIssue,
+    /// The character that was most likely intended, or `None` if it should just be removed
+    pub replacement: Option<char>,
+}
+
+impl Display for SuspiciousCharacter<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let description = match self.issue {
+            CharacterIssue::Invisible => "an invisible character",
+            CharacterIssue::UnusualSpace => "an unusual space",
+            CharacterIssue::Confusable => "a confusable character",
+        };
+        write!(
+            f,
+            "`{}` contains {description} (U+{:04X}) at {}",
+            self.rule,
+            u32::from(self.found),
+            self.span
+        )?;
+        match self.replacement {
+            Some(c) => write!(f, ", which could be replaced by {c:?}"),
+            None => write!(f, ", which could be removed"),
+        }
+    }
+}
+
+/// Characters that are shown identically, or nearly so, to an ASCII one in most fonts
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('е', 'e'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('ѕ', 's'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ԁ', 'd'),
+    ('ԛ', 'q'),
+    ('ԝ', 'w'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Ѕ', 'S'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    // Greek
+    ('ο', 'o'),
+    ('ν', 'v'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    // Punctuation
+    ('\u{2018}', '\''),
+    ('\u{2019}', '\''),
+    ('\u{201C}', '"'),
+    ('\u{201D}', '"'),
+    ('\u{2010}', '-'),
+    ('\u{2011}', '-'),
+    ('\u{2012}', '-'),
+    ('\u{2013}', '-'),
+    ('\u{2212}', '-'),
+];
+
+fn classify(c: char) -> Option<(CharacterIssue, Option<char>)> {
+    match c {
+        '\u{200B}'..='\u{200F}'
+        | '\u{00AD}'
+        | '\u{2060}'
+        | '\u{FEFF}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2066}'..='\u{2069}' => Some((CharacterIssue::Invisible, None)),
+        '\u{00A0}'
+        | '\u{1680}'
+        | '\u{2000}'..='\u{200A}'
+        | '\u{202F}'
+        | '\u{205F}'
+        | '\u{3000}' => Some((CharacterIssue::UnusualSpace, Some(' '))),
+        // The fullwidth forms of every printable ASCII character, as used in CJK text
+        '\u{FF01}'..='\u{FF5E}' => Some((
+            CharacterIssue::Confusable,
+            char::from_u32(u32::from(c) - 0xFEE0),
+        )),
+        _ => CONFUSABLES
+            .iter()
+            .find(|(confusable, _)| *confusable == c)
+            .map(|(_, ascii)| (CharacterIssue::Confusable, Some(*ascii))),
+    }
+}
+
+pub(crate) fn rule_suspicious_characters<'a>(rule: &'a Rule<'_>) -> Vec<SuspiciousCharacter<'a>> {
+    let mut found = vec![];
+    for definition in &rule.definitions {
+        check_text(&rule.name, definition, 0, &rule.name, &mut found);
+    }
+
+    let mut stack: Vec<_> = rule.body.iter().collect();
+    while let Some(node) = stack.pop() {
+        match node {
+            Expr::Literal { span, str } => check_text(&rule.name, *span, 1, str, &mut found),
+            Expr::Regex { span, pattern } => {
+                check_text(&rule.name, *span, 2, pattern, &mut found);
+            }
+            Expr::Nonterminal { span, name } => check_text(&rule.name, *span, 0, name, &mut found),
+            other => stack.extend(other.children()),
+        }
+    }
+    found
+}
+
+/// Checks `text`, which begins `prefix` bytes into `span` (after any quotes or `#`)
+fn check_text<'a>(
+    rule: &'a str,
+    span: Span,
+    prefix: usize,
+    text: &str,
+    found: &mut Vec<SuspiciousCharacter<'a>>,
+) {
+    let has_ascii_letters = text.chars().any(|c| c.is_ascii_alphabetic());
+    for (offset, c) in text.char_indices() {
+        let Some((issue, replacement)) = classify(c) else {
+            continue;
+        };
+        if issue == CharacterIssue::Confusable && !has_ascii_letters {
+            continue;
+        }
+        found.push(SuspiciousCharacter {
+            rule,
+            span: span.subspan(prefix, &text[..offset], c.len_utf8()),
+            found: c,
+            issue,
+            replacement,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{CharacterIssue, Grammar};
+
+    #[test]
+    fn characters() {
+        let src = "nаme = 'x\u{200B}y' #'[0-9]\u{3000}' 'ｆoo';\n\
+                   дом = 'дом' | 'a\n\u{a0}b';";
+        let g = Grammar::new(src).unwrap();
+        let found: Vec<_> = g
+            .suspicious_characters()
+            .into_iter()
+            .map(|s| {
+                (
+                    &src[s.span.range()],
+                    s.span.start_line(),
+                    s.issue,
+                    s.replacement,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                ("а", (1, 1), CharacterIssue::Confusable, Some('a')),
+                ("\u{200B}", (1, 10), CharacterIssue::Invisible, None),
+                ("\u{3000}", (1, 23), CharacterIssue::UnusualSpace, Some(' ')),
+                ("ｆ", (1, 29), CharacterIssue::Confusable, Some('f')),
+                ("\u{a0}", (3, 0), CharacterIssue::UnusualSpace, Some(' ')),
+            ]
+        );
+    }
+}
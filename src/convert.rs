@@ -0,0 +1,316 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use crate::{
+    ChoiceSemantics, Dialect, EbnfError, ExportError, ExportOptions, Expr, Grammar, PestError,
+    Span, YaccError, pest::read_pest, yacc::read_yacc,
+};
+
+/// The result of [`convert`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The grammar in the notation converted to
+    pub output: String,
+    /// What couldn't be carried over exactly, in the order each was first found
+    pub approximations: Vec<Approximation>,
+}
+
+/// A construct that [`convert`] couldn't carry over exactly, and what it did instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approximation {
+    /// What was approximated, such as `"an action"`
+    pub construct: &'static str,
+    /// What was done with it, such as `"left out"`
+    pub treatment: &'static str,
+    /// Where it appears in the input, if anywhere in particular
+    pub spans: Vec<Span>,
+}
+
+impl Display for Approximation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut construct = self.construct.chars();
+        if let Some(first) = construct.next() {
+            write!(f, "{}{}", first.to_uppercase(), construct.as_str())?;
+        }
+        write!(f, " was {}", self.treatment)?;
+        for (n, span) in self.spans.iter().enumerate() {
+            let separator = if n == 0 { ", at" } else { "," };
+            write!(f, "{separator} {span}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`Approximation`]s found so far, with one entry for each kind of construct
+#[derive(Debug, Default)]
+pub(crate) struct Approximations(Vec<Approximation>);
+
+impl Approximations {
+    /// Records that `construct` was given `treatment`, at `span` if it has one
+    pub(crate) fn add(
+        &mut self,
+        construct: &'static str,
+        treatment: &'static str,
+        span: Option<Span>,
+    ) {
+        if let Some(approximation) = self.0.iter_mut().find(|a| a.construct == construct) {
+            approximation.spans.extend(span);
+        } else {
+            self.0.push(Approximation {
+                construct,
+                treatment,
+                spans: span.into_iter().collect(),
+            });
+        }
+    }
+}
+
+/// The ways [`convert`] can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConvertError<'a> {
+    /// The input isn't valid EBNF
+    Ebnf(EbnfError<'a>),
+    /// The input isn't a valid pest grammar
+    Pest(PestError),
+    /// The input isn't a valid Yacc grammar
+    Yacc(YaccError),
+    /// The grammar can't be written in the notation converted to, for the reason given by `message`
+    Export {
+        /// Where the construct that can't be written appears in the input
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        message: String,
+    },
+    /// Grammars can only be written in `dialect`, not read from it
+    Unreadable {
+        #[expect(missing_docs, reason = "Obvious")]
+        dialect: Dialect,
+    },
+}
+
+impl Display for ConvertError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConvertError::Ebnf(error) => write!(f, "{error}"),
+            ConvertError::Pest(error) => write!(f, "{error}"),
+            ConvertError::Yacc(error) => write!(f, "{error}"),
+            ConvertError::Export { message, .. } => write!(f, "{message}"),
+            ConvertError::Unreadable { dialect } => {
+                write!(
+                    f,
+                    "Grammars can be written in {dialect}, but not read from it"
+                )
+            }
+        }
+    }
+}
+
+/// Converts a grammar from one notation to another, reading it as [`Grammar::new`], [`Grammar::from_pest`] or [`Grammar::from_yacc`] do and writing it with the grammar's [`Display`] or one of its exporters, such as [`Grammar::to_pest`]. Exporters that need the grammar [lowered](ExportOptions::lower) to BNF have it lowered, and [`Dialect::Yacc`] gives the grammar file of [`Grammar::to_grmtools`], without its lexer.
+///
+/// Anything that had to be left out or written differently along the way, such as a Yacc action or an unordered choice written in pest, is listed in [`Conversion::approximations`], so that the output is never silently different from the input.
+/// ```rust
+/// # use ebnf_toolkit::{convert, Dialect};
+/// let conversion = convert("list = item (',' item)*; item = 'a' | 'b';", Dialect::Ebnf, Dialect::Pest).unwrap();
+/// assert_eq!(conversion.output, "\
+/// list = { item ~ (\",\" ~ item)* }
+/// item = { (\"a\" | \"b\") }
+/// ");
+/// assert_eq!(
+///     conversion.approximations[0].to_string(),
+///     "An unordered choice was written as an ordered one, which takes the first alternative that matches, at [1:32..1:41]",
+/// );
+/// ```
+///
+/// # Errors
+/// If the input can't be read in the notation `from`, or `from` is one that can only be written, such as [`Dialect::TreeSitter`]. Also if the grammar has something with no equivalent in the notation `to`, such as a lookahead in Yacc.
+pub fn convert(input: &str, from: Dialect, to: Dialect) -> Result<Conversion, ConvertError<'_>> {
+    match from {
+        Dialect::Ebnf => {
+            let grammar = Grammar::new(input).map_err(ConvertError::Ebnf)?;
+            write(&grammar, to, Approximations::default())
+        }
+        Dialect::Pest => {
+            let (structured, approximations) = read_pest(input).map_err(ConvertError::Pest)?;
+            write(&Grammar::from_structured(&structured), to, approximations)
+        }
+        Dialect::Yacc => {
+            let (grammar, approximations) = read_yacc(input).map_err(ConvertError::Yacc)?;
+            write(&grammar, to, approximations)
+        }
+        Dialect::Lalrpop | Dialect::TreeSitter => Err(ConvertError::Unreadable { dialect: from }),
+    }
+}
+
+/// Writes `grammar` in the notation `to`, adding what it approximates to `approximations`
+fn write(
+    grammar: &Grammar<'_>,
+    to: Dialect,
+    mut approximations: Approximations,
+) -> Result<Conversion, ConvertError<'static>> {
+    let lower = ExportOptions { lower: true };
+    let output = match to {
+        Dialect::Ebnf => Ok(grammar.to_string()),
+        Dialect::Pest => grammar.to_pest(),
+        Dialect::Yacc => grammar.to_grmtools(lower).map(|files| files.grammar),
+        Dialect::Lalrpop => grammar.to_lalrpop(lower),
+        Dialect::TreeSitter => Ok(grammar.to_tree_sitter()),
+    }
+    .map_err(|error| {
+        let span = match error {
+            ExportError::Unsupported { span, .. }
+            | ExportError::NeedsLowering { span, .. }
+            | ExportError::InvalidName { span, .. }
+            | ExportError::Import { span, .. } => span,
+        };
+        ConvertError::Export {
+            span,
+            message: error.to_string(),
+        }
+    })?;
+
+    let ordered = grammar.choice_semantics == ChoiceSemantics::Ordered;
+    for rule in grammar.rules_in_definition_order() {
+        if rule.hidden && matches!(to, Dialect::Yacc | Dialect::Lalrpop) {
+            approximations.add(
+                "a hidden rule",
+                "written as an ordinary rule",
+                rule.definitions.hull(),
+            );
+        }
+        for node in &rule.body {
+            find_approximations(node, to, ordered, &mut approximations);
+        }
+    }
+    if to == Dialect::Yacc {
+        approximations.add(
+            "the definitions of tokens",
+            "left out, as Yacc needs a separate lexer",
+            None,
+        );
+    }
+    Ok(Conversion {
+        output,
+        approximations: approximations.0,
+    })
+}
+
+/// Adds what writing `node` in the notation `to` approximates to `approximations`, where `ordered` is whether choices are ordered in the grammar being written
+fn find_approximations(
+    node: &Expr<'_>,
+    to: Dialect,
+    ordered: bool,
+    approximations: &mut Approximations,
+) {
+    let lr = matches!(to, Dialect::Yacc | Dialect::Lalrpop);
+    let span = Some(node.span());
+    match node {
+        Expr::Choice { .. } if ordered && (lr || to == Dialect::TreeSitter) => approximations.add(
+            "an ordered choice",
+            "written as an unordered one, which can match any alternative",
+            span,
+        ),
+        Expr::Choice { .. } if !ordered && to == Dialect::Pest => approximations.add(
+            "an unordered choice",
+            "written as an ordered one, which takes the first alternative that matches",
+            span,
+        ),
+        Expr::Hidden { .. } if lr || to == Dialect::TreeSitter => {
+            approximations.add("a hidden part", "written without being hidden", span);
+        }
+        Expr::Labeled { .. } if lr => approximations.add("a label", "left out", span),
+        Expr::And { .. } | Expr::Not { .. } if to == Dialect::TreeSitter => {
+            approximations.add("a lookahead", "written as `blank()`", span);
+        }
+        Expr::Regex { .. } if to == Dialect::TreeSitter => approximations.add(
+            "a regex",
+            "copied without translating its syntax to JavaScript's",
+            span,
+        ),
+        _ => {}
+    }
+    for child in node.children() {
+        find_approximations(child, to, ordered, approximations);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::ToString, vec::Vec};
+
+    use crate::{ConvertError, Dialect, convert};
+
+    #[test]
+    fn convert_between_dialects() {
+        let yacc = r"
+%token NUM
+%left '+'
+%%
+expr: expr '+' expr { $$ = $1 + $3; }
+    | NUM %prec '+'
+    ;
+";
+        let conversion = convert(yacc, Dialect::Yacc, Dialect::Ebnf).unwrap();
+        assert_eq!(
+            conversion.output,
+            "expr = ((((expr) (\"+\") (expr)))|(NUM));\nNUM = \"NUM\";\n"
+        );
+        let report: Vec<_> = conversion
+            .approximations
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        insta::assert_debug_snapshot!(report, @r#"
+        [
+            "A precedence declaration was left out, so the conflicts it settles are left ambiguous, at [3:0..3:5]",
+            "An action was left out, at [5:20..5:37]",
+            "A `%prec` marker was left out, at [6:10..6:19]",
+            "A token was made a rule matching a literal of its name, or of its alias, at [6:6..6:9]",
+        ]
+        "#);
+
+        let pest = "list = @{ SOI ~ ASCII_DIGIT+ ~ EOI }";
+        let conversion = convert(pest, Dialect::Pest, Dialect::TreeSitter).unwrap();
+        let report: Vec<_> = conversion
+            .approximations
+            .iter()
+            .map(|a| (a.construct, a.spans.len()))
+            .collect();
+        insta::assert_debug_snapshot!(report, @r#"
+        [
+            (
+                "a rule modifier",
+                1,
+            ),
+            (
+                "`SOI`",
+                1,
+            ),
+            (
+                "a regex",
+                2,
+            ),
+            (
+                "a lookahead",
+                1,
+            ),
+        ]
+        "#);
+
+        assert_eq!(
+            convert("a = &'x' 'x';", Dialect::Ebnf, Dialect::Lalrpop)
+                .unwrap_err()
+                .to_string(),
+            "Rule `a` uses a lookahead at [1:5..1:8], which can't be exported"
+        );
+        assert_eq!(
+            convert("", Dialect::TreeSitter, Dialect::Ebnf),
+            Err(ConvertError::Unreadable {
+                dialect: Dialect::TreeSitter
+            })
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ptr;
+
+use crate::{
+    Expr, Grammar, NodeId, RuleId, Span,
+    matcher::{Used, match_str_tracing},
+    node_ids::preorder,
+};
+
+/// Which rules and alternatives of a [`Grammar`] a corpus of inputs used, from [`Grammar::coverage`], for finding the branches of a grammar that a test suite never exercises
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage<'g> {
+    /// How many of the inputs matched in full
+    pub matched: usize,
+    /// The position in the corpus of each input that didn't match in full, which isn't counted towards anything else
+    pub unmatched: Vec<usize>,
+    /// Every rule, by name, with how many of the matched inputs used it
+    pub rules: BTreeMap<&'g str, usize>,
+    /// Every alternative of every [`Choice`](Expr::Choice), in order of [`NodeId`], with how many of the matched inputs took it
+    pub alternatives: Vec<AlternativeCoverage<'g>>,
+}
+
+/// An alternative of a [`Choice`](Expr::Choice), as listed by [`Coverage::alternatives`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternativeCoverage<'g> {
+    /// The name of the rule containing the choice
+    pub rule: &'g str,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub id: NodeId,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub span: Span,
+    /// How many of the matched inputs took this alternative at least once
+    pub inputs: usize,
+}
+
+impl<'g> Coverage<'g> {
+    /// The rules that none of the matched inputs used, in order of name
+    pub fn unused_rules(&self) -> impl Iterator<Item = &'g str> + '_ {
+        self.rules
+            .iter()
+            .filter(|&(_, &inputs)| inputs == 0)
+            .map(|(&name, _)| name)
+    }
+
+    /// The alternatives that none of the matched inputs took
+    pub fn unused_alternatives(&self) -> impl Iterator<Item = &AlternativeCoverage<'g>> {
+        self.alternatives.iter().filter(|a| a.inputs == 0)
+    }
+}
+
+pub(crate) fn coverage<'g, 'i>(
+    grammar: &'g Grammar<'_>,
+    inputs: impl Iterator<Item = &'i str>,
+    start: &str,
+) -> Option<Coverage<'g>> {
+    let start = grammar.rules.get(start)?;
+    let mut rules: BTreeMap<&'g str, usize> =
+        grammar.rules.keys().map(|name| (&**name, 0)).collect();
+
+    // Alternatives are told apart by where they are in memory, as two can be written the same way
+    let mut alternatives = Vec::new();
+    let mut by_address = BTreeMap::new();
+    for (rule_id, rule) in grammar.rules.values().enumerate() {
+        let mut index = 0;
+        let mut ids = BTreeMap::new();
+        preorder(&rule.body, &mut |node| {
+            ids.insert(ptr::from_ref(node).addr(), index);
+            index += 1;
+        });
+        preorder(&rule.body, &mut |node| {
+            if let Expr::Choice { body, .. } = node {
+                for alternative in body {
+                    let address = ptr::from_ref(alternative).addr();
+                    by_address.insert(address, alternatives.len());
+                    alternatives.push(AlternativeCoverage {
+                        rule: &rule.name,
+                        id: NodeId {
+                            rule: RuleId(rule_id),
+                            index: ids[&address],
+                        },
+                        span: alternative.span(),
+                        inputs: 0,
+                    });
+                }
+            }
+        });
+    }
+
+    let mut matched = 0;
+    let mut unmatched = Vec::new();
+    for (position, input) in inputs.enumerate() {
+        let used = match match_str_tracing(start, grammar, input) {
+            Some((end, used)) if end == input.len() => used,
+            _ => {
+                unmatched.push(position);
+                continue;
+            }
+        };
+        matched += 1;
+        let mut used_rules = Vec::new();
+        let mut used_alternatives = Vec::new();
+        for used in used {
+            match used {
+                Used::Rule(name) => used_rules.push(name),
+                Used::Alternative(node) => {
+                    used_alternatives.push(by_address[&ptr::from_ref(node).addr()]);
+                }
+            }
+        }
+        used_rules.sort_unstable();
+        used_rules.dedup();
+        for name in used_rules {
+            *rules.get_mut(name).unwrap() += 1;
+        }
+        used_alternatives.sort_unstable();
+        used_alternatives.dedup();
+        for alternative in used_alternatives {
+            alternatives[alternative].inputs += 1;
+        }
+    }
+    alternatives.sort_by_key(|a| a.id);
+    Some(Coverage {
+        matched,
+        unmatched,
+        rules,
+        alternatives,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::Grammar;
+
+    #[test]
+    fn counts_only_the_match() {
+        let g = Grammar::new(
+            "stmt = (kw ('x' | 'y')) | (kw 'z'); kw = 'if' | 'iffy'; \
+             list = item (',' item)* [';' 'end']; item = 'a' | ('a' 'b');",
+        )
+        .unwrap();
+
+        // `kw` takes the longest keyword, and the first alternative of `stmt` fails after it, so only the second counts
+        let coverage = g.coverage(["iffyz"].into_iter(), "stmt").unwrap();
+        let taken: Vec<_> = coverage
+            .alternatives
+            .iter()
+            .filter(|a| a.inputs > 0)
+            .map(|a| g.node(a.id).unwrap().to_string())
+            .collect();
+        assert_eq!(taken, ["\"iffy\"", "((kw) (\"z\"))"]);
+
+        let coverage = g.coverage(["a,ab", "a;", "a"].into_iter(), "list").unwrap();
+        assert_eq!(coverage.matched, 2);
+        assert_eq!(coverage.unmatched, [1]);
+        assert_eq!(coverage.rules["item"], 2);
+        assert_eq!(coverage.unused_rules().collect::<Vec<_>>(), ["kw", "stmt"]);
+        let inputs: Vec<_> = coverage
+            .alternatives
+            .iter()
+            .filter(|a| a.rule == "item")
+            .map(|a| a.inputs)
+            .collect();
+        assert_eq!(inputs, [2, 1]);
+
+        assert!(g.coverage([].into_iter(), "missing").is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Expr, Rule, expr::ExprKind};
+use crate::{Expr, Grammar, Rule, expr::NodeKind};
 use display_tree::{AsTree, DisplayTree, Style};
 use std::{
     fmt::{Formatter, Write},
@@ -12,8 +12,24 @@ impl DisplayTree for Expr<'_> {
         let indentation = style.indentation as usize - 1;
         let horizontal_bar = format!("{:indentation$}", style.char_set.horizontal);
 
-        if ExprKind::from(self) != ExprKind::Rule {
-            let name: &str = ExprKind::from(self).into();
+        if let Expr::Labeled { label, .. } = self {
+            let name: &str = NodeKind::Labeled.into();
+            writeln!(
+                f,
+                "{} {} {label}",
+                style.leaf_style.apply(name),
+                self.span()
+            )?;
+        } else if let Expr::Choice { ordered: true, .. } = self {
+            let name: &str = NodeKind::Choice.into();
+            writeln!(
+                f,
+                "{} {} ordered",
+                style.leaf_style.apply(name),
+                self.span()
+            )?;
+        } else if NodeKind::from(self) != NodeKind::Rule {
+            let name: &str = NodeKind::from(self).into();
             writeln!(f, "{} {}", style.leaf_style.apply(name), self.span())?;
         }
 
@@ -35,6 +51,7 @@ impl DisplayTree for Expr<'_> {
                     style.char_set.end_connector, s
                 ))
             )?,
+            Expr::Empty { .. } => {}
             Expr::UnparsedOperator { op, .. } => {
                 let op: &str = op.into();
                 write!(
@@ -50,7 +67,11 @@ impl DisplayTree for Expr<'_> {
             Expr::Choice { body, .. }
             | Expr::Optional { body, .. }
             | Expr::Repetition { body, .. }
-            | Expr::Group { body, .. } => {
+            | Expr::Group { body, .. }
+            | Expr::And { body, .. }
+            | Expr::Not { body, .. }
+            | Expr::Hidden { body, .. }
+            | Expr::Labeled { body, .. } => {
                 print_vec_tree(f, style, body)?;
             }
             Expr::Rule { rule, .. } => write!(f, "{}", AsTree::new(rule))?,
@@ -59,10 +80,10 @@ impl DisplayTree for Expr<'_> {
     }
 }
 
-pub(crate) fn print_vec_tree(
+pub(crate) fn print_vec_tree<T: DisplayTree>(
     f: &mut impl Write,
     style: Style,
-    body: &[Expr<'_>],
+    body: &[T],
 ) -> Result<(), std::fmt::Error> {
     let indentation = style.indentation as usize - 1;
     let spacer = format!(" {EMPTY_STRING:indentation$}");
@@ -72,7 +93,7 @@ pub(crate) fn print_vec_tree(
     for (block_no, block) in vec_output.into_iter().enumerate() {
         for (n, line) in block.lines().enumerate() {
             if n == 0 && block_no == 0 {
-                write!(f, "{}{horizontal_bar}", style.char_set.end_connector,)?;
+                write!(f, "{}{horizontal_bar}", style.char_set.end_connector)?;
             } else {
                 write!(f, "{spacer}")?;
             }
@@ -98,9 +119,38 @@ impl DisplayTree for Rule<'_> {
     }
 }
 
+// Lets a list of borrowed rules be printed without cloning them
+impl DisplayTree for &Rule<'_> {
+    fn fmt(&self, f: &mut Formatter, style: Style) -> std::fmt::Result {
+        DisplayTree::fmt(*self, f, style)
+    }
+}
+
+/// Lists the imports, then the rules in the order they were first defined, as for `Display`
+impl DisplayTree for Grammar<'_> {
+    fn fmt(&self, f: &mut Formatter, style: Style) -> std::fmt::Result {
+        let indentation = style.indentation as usize - 1;
+        let horizontal_bar = format!("{:indentation$}", style.char_set.horizontal);
+        writeln!(f, "{}", style.leaf_style.apply("Grammar"))?;
+        for import in &self.imports {
+            writeln!(
+                f,
+                "{1}{horizontal_bar}import: {0}",
+                import.path, style.char_set.connector
+            )?;
+        }
+
+        let rules = self.rules_in_definition_order();
+        if !rules.is_empty() {
+            print_vec_tree(f, style, &rules)?;
+        }
+        Ok(())
+    }
+}
+
 pub(crate) fn fmt_vec<T: DisplayTree>(v: &[T], style: Style) -> impl Iterator<Item = String> + '_ {
     let max_index = v.len() - 1;
-    let num_width = format!("{max_index}",).len();
+    let num_width = format!("{max_index}").len();
 
     let vertical = style
         .branch_style
@@ -134,7 +184,30 @@ mod test {
     use std::borrow::Cow;
 
     use super::*;
-    use crate::{expr::Operator, token_data::DUMMY_SPAN};
+    use crate::{SpanSet, expr::Operator, token_data::DUMMY_SPAN};
+
+    #[test]
+    fn grammar_test() {
+        let g = Grammar::new("%import 'lex';\nB = 'b';\nA = B | 'a';").unwrap();
+        let tree = AsTree::new(&g);
+
+        insta::assert_snapshot!(tree, @r"
+        Grammar
+        ├─import: lex
+        └─0: Rule
+          │  ├─name: B
+          │  └─0: Literal [2:4..2:7]
+          │       └─ 'b'
+          1: Rule
+             ├─name: A
+             └─0: Choice [3:4..3:11]
+                  └─0: Nonterminal [3:4..3:5]
+                    │  └─ B
+                    1: Literal [3:8..3:11]
+                       └─ 'a'
+        ");
+    }
+
     #[test]
     fn one_level_test() {
         let span = DUMMY_SPAN;
@@ -160,8 +233,10 @@ mod test {
                         span,
                         body: vec![Expr::Regex { span, pattern: "a" }],
                         one_needed: true,
+                        separated: false,
                     },
                 ],
+                ordered: false,
             },
         ];
         let n = Expr::Rule {
@@ -169,6 +244,9 @@ mod test {
             rule: Rule {
                 name: Cow::Borrowed("name"),
                 body,
+                definitions: SpanSet::default(),
+                hidden: false,
+                annotations: vec![],
             },
         };
         let tree = AsTree::new(&n);
@@ -0,0 +1,102 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use crate::{Expr, Grammar};
+
+pub(crate) fn count_derivations(
+    grammar: &Grammar<'_>,
+    start: &str,
+    max_depth: usize,
+) -> Option<u128> {
+    let index = grammar.rules.keys().position(|name| name == start)?;
+    // Counts for each rule when expanded at most `depth` levels deep, built up a level at a time
+    let mut counts = vec![0; grammar.rules.len()];
+    for depth in 1..=max_depth {
+        let below: BTreeMap<&str, u128> = grammar
+            .rules
+            .keys()
+            .map(|name| &**name)
+            .zip(counts.iter().copied())
+            .collect();
+        counts = grammar
+            .rules
+            .values()
+            .map(|rule| sequence(&rule.body, &below, depth))
+            .collect::<Vec<_>>();
+    }
+    Some(counts[index])
+}
+
+/// How many ways there are to derive `nodes` in order, given the counts of the rules they refer to in `below`. Repetitions are counted up to `depth` times.
+fn sequence(nodes: &[Expr<'_>], below: &BTreeMap<&str, u128>, depth: usize) -> u128 {
+    nodes.iter().fold(1, |total, node| {
+        total.saturating_mul(node_count(node, below, depth))
+    })
+}
+
+fn node_count(node: &Expr<'_>, below: &BTreeMap<&str, u128>, depth: usize) -> u128 {
+    match node {
+        // A reference to an undefined rule is taken to be a single token, as are literals and regexes
+        Expr::Nonterminal { name, .. } => below.get(name).copied().unwrap_or(1),
+        Expr::Literal { .. }
+        | Expr::Regex { .. }
+        | Expr::Empty { .. }
+        | Expr::And { .. }
+        | Expr::Not { .. }
+        | Expr::UnparsedOperator { .. } => 1,
+        Expr::Choice { body, .. } => body.iter().fold(0, |total, alternative| {
+            total.saturating_add(node_count(alternative, below, depth))
+        }),
+        Expr::Optional { body, .. } => sequence(body, below, depth).saturating_add(1),
+        Expr::Repetition { one_needed, .. } => {
+            let (body, separator) = node.repetition_parts().unwrap();
+            let once = sequence(body, below, depth);
+            // Every match after the first also derives the separator
+            let again = separator.map_or(once, |separator| {
+                once.saturating_mul(node_count(separator, below, depth))
+            });
+            let mut total = u128::from(!one_needed);
+            let mut repeated = once;
+            for _ in 0..depth {
+                total = total.saturating_add(repeated);
+                repeated = repeated.saturating_mul(again);
+            }
+            total
+        }
+        Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+            sequence(body, below, depth)
+        }
+        Expr::Rule { rule, .. } => sequence(&rule.body, below, depth),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::Grammar;
+
+    #[test]
+    fn count_derivations() {
+        let g = Grammar::new(
+            "pair = bit bit; bit = '0' | '1'; maybe = bit?; list = bit*; some = bit+; \
+             tree = 'leaf' | ('(' tree tree ')'); token = ident | #'[0-9]+'; huge = tree tree tree tree tree tree tree tree;",
+        )
+        .unwrap();
+        assert_eq!(g.count_derivations("pair", 2), Some(4));
+        assert_eq!(g.count_derivations("pair", 1), Some(0));
+        assert_eq!(g.count_derivations("pair", 0), Some(0));
+        assert_eq!(g.count_derivations("maybe", 2), Some(3));
+        // Repetitions are bounded by the depth: 1 + 2 + 4 + 8 sequences of up to three bits
+        assert_eq!(g.count_derivations("list", 3), Some(15));
+        assert_eq!(g.count_derivations("some", 3), Some(14));
+        // t(1) = 1, t(d) = 1 + t(d - 1)^2
+        let trees: Vec<_> = (1..=5)
+            .map(|d| g.count_derivations("tree", d).unwrap())
+            .collect();
+        assert_eq!(trees, [1, 2, 5, 26, 677]);
+        // An undefined rule counts as a single token
+        assert_eq!(g.count_derivations("token", 1), Some(2));
+        assert_eq!(g.count_derivations("missing", 3), None);
+        assert_eq!(g.count_derivations("huge", 12), Some(u128::MAX));
+    }
+}
@@ -0,0 +1,281 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt::Write, ops::Range};
+
+use strum::EnumProperty;
+
+use crate::{
+    EbnfError, Expr, FailureReason,
+    error::{
+        check_missing_terminator, empty_brackets, expected_closing_offset, limit_name,
+        non_identifier_rule_name, unclosed_brackets,
+    },
+    expr::{NodeKind, Operator},
+    json::{write_json_str, write_nodes},
+};
+
+/// What a report of an [`EbnfError`] says, apart from the drawing of the parse stack, shared by the ariadne report and [`EbnfError::to_json`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Diagnostic {
+    /// The headline, if the labels don't say it all
+    pub(crate) message: Option<&'static str>,
+    pub(crate) labels: Vec<DiagnosticLabel>,
+    pub(crate) notes: Vec<String>,
+    pub(crate) helps: Vec<String>,
+}
+
+/// A message attached to a byte range of the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiagnosticLabel {
+    pub(crate) range: Range<usize>,
+    pub(crate) message: String,
+    /// Which of the report's colors the label is drawn in, counting from 0 in the order they are generated
+    pub(crate) color: usize,
+}
+
+impl Diagnostic {
+    fn label(&mut self, range: Range<usize>, message: impl Into<String>, color: usize) {
+        self.labels.push(DiagnosticLabel {
+            range,
+            message: message.into(),
+            color,
+        });
+    }
+}
+
+pub(crate) fn diagnostic(error: &EbnfError<'_>) -> Diagnostic {
+    let mut diagnostic = Diagnostic {
+        message: None,
+        labels: vec![],
+        notes: vec![],
+        helps: vec![],
+    };
+    match error {
+        &EbnfError::LexError { input, offset } => {
+            let len = input[offset..].chars().next().map_or(1, char::len_utf8);
+            diagnostic.message = Some("Tokenization error");
+            diagnostic.label(
+                offset..offset + len,
+                "This was not recognised as the start of a valid token",
+                0,
+            );
+            match input.as_bytes()[offset] {
+                b'\'' | b'"' => diagnostic
+                    .notes
+                    .push("Is this the beginning of an unclosed string?".to_string()),
+                b'\\' => diagnostic.notes.push(
+                    "Backslash escapes are only valid inside quoted literals or regexes"
+                        .to_string(),
+                ),
+                _ => {}
+            }
+        }
+        EbnfError::EmptyInput => diagnostic.message = Some("Input string was empty"),
+        &EbnfError::LimitExceeded {
+            input,
+            offset,
+            limit,
+            max,
+        } => {
+            let len = input[offset..].chars().next().map_or(1, char::len_utf8);
+            diagnostic.message = Some("Input is too large to parse");
+            diagnostic.label(
+                offset..offset + len,
+                format!("The {} goes over {max} here", limit_name(limit)),
+                0,
+            );
+            diagnostic
+                .notes
+                .push("The limits can be raised with ParseOptions".to_string());
+        }
+        EbnfError::ParseError { offset, reason, .. } => {
+            if let Some(reason) = reason {
+                parse_error_labels(&mut diagnostic, *offset, reason);
+            }
+            diagnostic.helps = error
+                .bracket_balance()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+        }
+    }
+    diagnostic
+}
+
+fn parse_error_labels(diagnostic: &mut Diagnostic, offset: usize, reason: &FailureReason<'_>) {
+    // The label on the `;` takes the first color, and the others follow in order
+    let mut colors = 1..;
+
+    let stack = reason.stack();
+    let unclosed = unclosed_brackets(stack);
+    let empty = empty_brackets(stack);
+    for span in &empty {
+        diagnostic.label(
+            span.range(),
+            "Empty brackets are not allowed",
+            colors.next().unwrap(),
+        );
+    }
+    for n in stack {
+        if let Expr::UnparsedOperator { span, op } = n
+            && *op != Operator::Equals
+            && *op != Operator::Terminator
+            && !empty
+                .iter()
+                .any(|e| e.start() <= span.start() && span.end() <= e.end())
+        {
+            let message = match *op {
+                _ if unclosed.iter().any(|(s, _)| s == span) => "This bracket is never closed",
+                Operator::OpenedGroup
+                | Operator::OpenedSquare
+                | Operator::OpenedBrace
+                | Operator::OpenedAngle
+                | Operator::ClosedGroup
+                | Operator::ClosedSquare
+                | Operator::ClosedBrace
+                | Operator::ClosedAngle => "Contents of these brackets did not parse",
+                Operator::Kleene | Operator::Optional | Operator::Repeat => {
+                    "Could not apply to preceding term"
+                }
+                Operator::And | Operator::Not => "Could not apply to following term",
+                _ => "Operator not understood",
+            };
+            diagnostic.label(span.range(), message, colors.next().unwrap());
+        }
+    }
+
+    if let Some(offset) = expected_closing_offset(stack)
+        && !unclosed.is_empty()
+    {
+        // Innermost first, in the order they need writing
+        let closing: String = unclosed
+            .iter()
+            .rev()
+            .filter_map(|(_, op)| op.closing_bracket()?.get_str("repr"))
+            .collect();
+        diagnostic.label(
+            offset..offset,
+            format!("Expected `{closing}` here"),
+            colors.next().unwrap(),
+        );
+    }
+
+    match reason {
+        FailureReason::ExhaustedInput(nodes) => {
+            let message = if check_missing_terminator(nodes) {
+                "Missing semicolon here".to_string()
+            } else {
+                format!("Unexpected end of input at index {offset}")
+            };
+            diagnostic.label(offset..offset, message, colors.next().unwrap());
+        }
+        FailureReason::TerminatorNotEndingRule(nodes) => {
+            if let Some(not_identifier) = non_identifier_rule_name(nodes) {
+                diagnostic.label(
+                    not_identifier.span().range(),
+                    format!(
+                        "Expected identifier, found {:?}",
+                        NodeKind::from(not_identifier)
+                    ),
+                    colors.next().unwrap(),
+                );
+            }
+            diagnostic.label(
+                offset..offset + 1,
+                "Rule ending here did not parse successfully",
+                0,
+            );
+        }
+    }
+}
+
+/// Writes `error` as the JSON object documented on [`EbnfError::to_json`]
+pub(crate) fn write_json(out: &mut String, error: &EbnfError<'_>) {
+    let diagnostic = diagnostic(error);
+    write!(out, r#"{{"code":"{}","message":"#, error.code()).unwrap();
+    match diagnostic.message {
+        Some(message) => write_json_str(out, message),
+        None => out.push_str("null"),
+    }
+    match error.offset() {
+        Some(offset) => write!(out, r#","offset":{offset}"#).unwrap(),
+        None => out.push_str(r#","offset":null"#),
+    }
+
+    out.push_str(r#","labels":["#);
+    for (n, label) in diagnostic.labels.iter().enumerate() {
+        if n > 0 {
+            out.push(',');
+        }
+        write!(
+            out,
+            r#"{{"span":[{},{}],"message":"#,
+            label.range.start, label.range.end
+        )
+        .unwrap();
+        write_json_str(out, &label.message);
+        out.push('}');
+    }
+    out.push(']');
+
+    for (key, messages) in [("notes", &diagnostic.notes), ("helps", &diagnostic.helps)] {
+        write!(out, r#","{key}":["#).unwrap();
+        for (n, message) in messages.iter().enumerate() {
+            if n > 0 {
+                out.push(',');
+            }
+            write_json_str(out, message);
+        }
+        out.push(']');
+    }
+
+    out.push_str(r#","stack":"#);
+    match error {
+        EbnfError::ParseError {
+            reason: Some(reason),
+            ..
+        } => write_nodes(out, reason.stack()),
+        _ => out.push_str("null"),
+    }
+    out.push('}');
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Grammar, ReportConfig, diagnostic::diagnostic};
+
+    // Everything in the JSON should be in the report as well
+    #[cfg(feature = "report")]
+    #[test]
+    fn matches_report() {
+        for src in [
+            "A = (B;",
+            "A = 'B",
+            "A = B",
+            "'A' = B;",
+            "A = [B {C};",
+            "A = () B;",
+            "",
+        ] {
+            let error = Grammar::new(src).unwrap_err();
+            let report = error.render(ReportConfig::default().with_color(false));
+            let diagnostic = diagnostic(&error);
+            let messages = diagnostic.labels.iter().map(|l| &*l.message);
+            for message in messages
+                .chain(diagnostic.message)
+                .chain(diagnostic.notes.iter().map(|n| &**n))
+                .chain(diagnostic.helps.iter().map(|h| &**h))
+            {
+                assert!(report.contains(message), "{src:?}: {message}");
+            }
+        }
+        assert_eq!(
+            Grammar::new("").unwrap_err().to_json(),
+            r#"{"code":"E009","message":"Input string was empty","offset":null,"labels":[],"notes":[],"helps":[],"stack":null}"#
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use core::fmt::Display;
+
+use crate::NodeKind;
+
+/// A notation for writing grammars, with tables describing its syntax so that editors, highlighters and formatters can configure themselves from the same definitions the parser follows rather than keeping their own copies.
+///
+/// The parser reads [`Ebnf`](Dialect::Ebnf), described in [the crate documentation](crate#syntax). The others are notations of parser generators that [`convert`](crate::convert) can read or write. None of them write an escaped character such as `\n` outside quotes as a terminal of its own, so the parser reports one as [`ErrorCode::StrayBackslash`](crate::ErrorCode::StrayBackslash).
+/// ```rust
+/// # use ebnf_toolkit::{Dialect, OperatorRole};
+/// let alternation = Dialect::Ebnf
+///     .operators()
+///     .iter()
+///     .find(|o| o.role == OperatorRole::Alternation)
+///     .unwrap();
+/// assert_eq!(alternation.spellings, ["|"]);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// The notation of [`Grammar::new`](crate::Grammar::new), following instaparse
+    #[default]
+    Ebnf,
+    /// The PEG notation of [pest](https://pest.rs), as read by [`Grammar::from_pest`](crate::Grammar::from_pest)
+    Pest,
+    /// The rules of a Yacc or Bison grammar, as read by [`Grammar::from_yacc`](crate::Grammar::from_yacc)
+    Yacc,
+    /// The notation of [LALRPOP](https://github.com/lalrpop/lalrpop), as written by [`Grammar::to_lalrpop`](crate::Grammar::to_lalrpop)
+    Lalrpop,
+    /// A tree-sitter `grammar.js`, as written by [`Grammar::to_tree_sitter`](crate::Grammar::to_tree_sitter)
+    TreeSitter,
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Dialect::Ebnf => "EBNF",
+            Dialect::Pest => "pest",
+            Dialect::Yacc => "Yacc",
+            Dialect::Lalrpop => "LALRPOP",
+            Dialect::TreeSitter => "tree-sitter",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What an operator does, as listed by [`Dialect::operators`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OperatorRole {
+    /// Separates the name of a rule from its body
+    Definition,
+    /// Ends a rule
+    Terminator,
+    /// Separates the alternatives of a [`Choice`](crate::Expr::Choice)
+    Alternation,
+    /// Separates the alternatives of an ordered [`Choice`](crate::Expr::Choice), as in PEG
+    OrderedAlternation,
+    /// Separates the items of a sequence. Writing them one after another with only whitespace between also works.
+    Concatenation,
+    /// Follows a term to make it [`Optional`](crate::Expr::Optional)
+    Optional,
+    /// Follows a term to make it a [`Repetition`](crate::Expr::Repetition) of zero or more
+    ZeroOrMore,
+    /// Follows a term to make it a [`Repetition`](crate::Expr::Repetition) of one or more
+    OneOrMore,
+    /// Precedes a term to make it an [`And`](crate::Expr::And) lookahead
+    And,
+    /// Precedes a term to make it a [`Not`](crate::Expr::Not) lookahead
+    Not,
+    /// Separates a term from the separator matched between its repetitions, as in `item % ','`
+    Separator,
+}
+
+/// The ways of writing one operator in a [`Dialect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OperatorSpelling {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub role: OperatorRole,
+    /// Every accepted spelling, with the one used when writing a grammar out first
+    pub spellings: &'static [&'static str],
+}
+
+/// A kind of comment accepted by a [`Dialect`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CommentStyle {
+    /// A comment starting with the given text and running to the end of the line
+    Line(&'static str),
+    /// A comment between the given opening and closing text, which can span several lines
+    Block(&'static str, &'static str),
+}
+
+/// A pair of brackets in a [`Dialect`], and the kind of node the bracketed terms form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BracketPair {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub open: &'static str,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub close: &'static str,
+    /// The kind of node the brackets produce. `{ }` produces a [`Repetition`](crate::Expr::Repetition) of zero or more.
+    pub kind: NodeKind,
+}
+
+/// [`Dialect::operators`] for pest, which has the PEG operators but no terminator
+const PEST_OPERATORS: &[OperatorSpelling] = &[
+    OperatorSpelling {
+        role: OperatorRole::Definition,
+        spellings: &["="],
+    },
+    OperatorSpelling {
+        role: OperatorRole::OrderedAlternation,
+        spellings: &["|"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Concatenation,
+        spellings: &["~"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Optional,
+        spellings: &["?"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::ZeroOrMore,
+        spellings: &["*"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::OneOrMore,
+        spellings: &["+"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::And,
+        spellings: &["&"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Not,
+        spellings: &["!"],
+    },
+];
+
+/// [`Dialect::operators`] for Yacc, where a rule is a choice of sequences and nothing more
+const YACC_OPERATORS: &[OperatorSpelling] = &[
+    OperatorSpelling {
+        role: OperatorRole::Definition,
+        spellings: &[":"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Terminator,
+        spellings: &[";"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Alternation,
+        spellings: &["|"],
+    },
+];
+
+/// [`Dialect::operators`] for LALRPOP, whose alternatives are listed in braces and separated by commas
+const LALRPOP_OPERATORS: &[OperatorSpelling] = &[
+    OperatorSpelling {
+        role: OperatorRole::Definition,
+        spellings: &["="],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Terminator,
+        spellings: &[";"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Alternation,
+        spellings: &[","],
+    },
+    OperatorSpelling {
+        role: OperatorRole::Optional,
+        spellings: &["?"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::ZeroOrMore,
+        spellings: &["*"],
+    },
+    OperatorSpelling {
+        role: OperatorRole::OneOrMore,
+        spellings: &["+"],
+    },
+];
+
+impl Dialect {
+    /// Every operator of the dialect and how it can be written
+    pub fn operators(self) -> &'static [OperatorSpelling] {
+        match self {
+            Dialect::Ebnf => &[
+                OperatorSpelling {
+                    role: OperatorRole::Definition,
+                    spellings: &["=", "::="],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Terminator,
+                    spellings: &[";"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Alternation,
+                    spellings: &["|"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::OrderedAlternation,
+                    spellings: &["/"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Concatenation,
+                    spellings: &[","],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Optional,
+                    spellings: &["?"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::ZeroOrMore,
+                    spellings: &["*"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::OneOrMore,
+                    spellings: &["+"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::And,
+                    spellings: &["&"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Not,
+                    spellings: &["!"],
+                },
+                OperatorSpelling {
+                    role: OperatorRole::Separator,
+                    spellings: &["%"],
+                },
+            ],
+            Dialect::Pest => PEST_OPERATORS,
+            Dialect::Yacc => YACC_OPERATORS,
+            Dialect::Lalrpop => LALRPOP_OPERATORS,
+            // Everything is a function call, such as `choice(..)` or `optional(..)`
+            Dialect::TreeSitter => &[],
+        }
+    }
+
+    /// The kinds of comment the dialect accepts between terms
+    pub fn comment_styles(self) -> &'static [CommentStyle] {
+        match self {
+            Dialect::Ebnf | Dialect::Lalrpop => &[CommentStyle::Line("//")],
+            Dialect::Pest | Dialect::Yacc | Dialect::TreeSitter => {
+                &[CommentStyle::Line("//"), CommentStyle::Block("/*", "*/")]
+            }
+        }
+    }
+
+    /// The brackets the dialect uses to enclose terms
+    pub fn bracket_pairs(self) -> &'static [BracketPair] {
+        match self {
+            Dialect::Ebnf => &[
+                BracketPair {
+                    open: "(",
+                    close: ")",
+                    kind: NodeKind::Group,
+                },
+                BracketPair {
+                    open: "[",
+                    close: "]",
+                    kind: NodeKind::Optional,
+                },
+                BracketPair {
+                    open: "{",
+                    close: "}",
+                    kind: NodeKind::Repetition,
+                },
+                BracketPair {
+                    open: "<",
+                    close: ">",
+                    kind: NodeKind::Hidden,
+                },
+            ],
+            Dialect::Pest | Dialect::Lalrpop => &[BracketPair {
+                open: "(",
+                close: ")",
+                kind: NodeKind::Group,
+            }],
+            Dialect::Yacc | Dialect::TreeSitter => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+
+    use crate::{CommentStyle, Dialect, Expr, NodeKind, OperatorRole, Rule, token_data::tokenize};
+
+    // The lexer's token definitions can't refer to the tables, so these check that the two agree
+    #[test]
+    fn tables_match_parser() {
+        let dialect = Dialect::Ebnf;
+        for operator in dialect.operators() {
+            for spelling in operator.spellings {
+                let parsed = match operator.role {
+                    OperatorRole::Definition => Rule::new(&format!("A {spelling} 'a';")).is_ok(),
+                    OperatorRole::Terminator => Rule::new(&format!("A = 'a'{spelling}")).is_ok(),
+                    OperatorRole::Alternation => Expr::new(&format!("'a' {spelling} 'b'"))
+                        .is_ok_and(|e| matches!(e, Expr::Choice { ordered: false, .. })),
+                    OperatorRole::OrderedAlternation => Expr::new(&format!("'a' {spelling} 'b'"))
+                        .is_ok_and(|e| matches!(e, Expr::Choice { ordered: true, .. })),
+                    OperatorRole::Concatenation => Expr::new(&format!("('a' {spelling} 'b')"))
+                        .is_ok_and(|e| e.kind() == NodeKind::Group),
+                    OperatorRole::Optional => Expr::new(&format!("'a'{spelling}"))
+                        .is_ok_and(|e| e.kind() == NodeKind::Optional),
+                    OperatorRole::ZeroOrMore => {
+                        Expr::new(&format!("'a'{spelling}")).is_ok_and(|e| {
+                            matches!(
+                                e,
+                                Expr::Repetition {
+                                    one_needed: false,
+                                    ..
+                                }
+                            )
+                        })
+                    }
+                    OperatorRole::And => Expr::new(&format!("{spelling}'a'"))
+                        .is_ok_and(|e| e.kind() == NodeKind::And),
+                    OperatorRole::Not => Expr::new(&format!("{spelling}'a'"))
+                        .is_ok_and(|e| e.kind() == NodeKind::Not),
+                    OperatorRole::OneOrMore => {
+                        Expr::new(&format!("'a'{spelling}")).is_ok_and(|e| {
+                            matches!(
+                                e,
+                                Expr::Repetition {
+                                    one_needed: true,
+                                    ..
+                                }
+                            )
+                        })
+                    }
+                    OperatorRole::Separator => {
+                        Expr::new(&format!("'a' {spelling} 'b'")).is_ok_and(|e| {
+                            matches!(
+                                e,
+                                Expr::Repetition {
+                                    separated: true,
+                                    ..
+                                }
+                            )
+                        })
+                    }
+                };
+                assert!(parsed, "{:?} {spelling}", operator.role);
+            }
+        }
+
+        for style in dialect.comment_styles() {
+            let input = match style {
+                CommentStyle::Line(start) => format!("'a' {start} comment\n"),
+                CommentStyle::Block(open, close) => format!("'a' {open} comment {close}"),
+            };
+            assert_eq!(tokenize(&input).unwrap().len(), 1, "{style:?}");
+        }
+
+        // Braces only take a single term, so the contents are grouped
+        for pair in dialect.bracket_pairs() {
+            let input = format!("{}('a' 'b'){}", pair.open, pair.close);
+            let expr = Expr::new(&input).unwrap();
+            assert_eq!(expr.kind(), pair.kind, "{input}");
+        }
+    }
+}
@@ -1,20 +1,14 @@
-#![allow(clippy::range_plus_one)]
-
-use std::{fmt::Display, ops::Range};
-
-use ariadne::{ColorGenerator, Label, ReportBuilder};
-use display_tree::Style;
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::fmt::Display;
 
 use crate::{
-    Expr, Span,
-    debug::print_vec_tree,
-    expr::{ExprKind, Operator},
+    Expr, ParseLimit, Span, SpanSet,
+    expr::{NodeKind, Operator},
+    json::write_nodes,
     parser::LrStack,
-    token_data::{Token, TokenPayload},
+    token_data::{Token, TokenPayload, tokenize_with_imports},
 };
 
-type ReportType<'a> = ReportBuilder<'a, (&'static str, Range<usize>)>;
-
 /// A struct describing possible errors that mean an input could not be successfully parsed into the requested type
 #[derive(Debug, Clone)]
 #[non_exhaustive]
@@ -38,25 +32,155 @@ pub enum EbnfError<'a> {
     },
     /// There was nothing to parse
     EmptyInput,
+    /// The input went over one of the limits of [`ParseOptions`](crate::ParseOptions), so parsing was abandoned
+    LimitExceeded {
+        #[expect(missing_docs)]
+        input: &'a str,
+        /// Where the input went over the limit
+        offset: usize,
+        /// Which limit it was
+        limit: ParseLimit,
+        /// The value of the limit
+        max: usize,
+    },
 }
 
 impl EbnfError<'_> {
     /// The input string that was being parsed
     pub fn input(&self) -> &str {
         match self {
-            EbnfError::LexError { input, .. } | EbnfError::ParseError { input, .. } => input,
+            EbnfError::LexError { input, .. }
+            | EbnfError::ParseError { input, .. }
+            | EbnfError::LimitExceeded { input, .. } => input,
             EbnfError::EmptyInput => "",
         }
     }
     /// the offset within the overall string where the problem occured
     pub fn offset(&self) -> Option<usize> {
         match self {
-            EbnfError::LexError { offset, .. } | EbnfError::ParseError { offset, .. } => {
-                Some(*offset)
+            EbnfError::LexError { offset, .. }
+            | EbnfError::ParseError { offset, .. }
+            | EbnfError::LimitExceeded { offset, .. } => Some(*offset),
+            EbnfError::EmptyInput => None,
+        }
+    }
+
+    /// The [`ErrorCode`] for the kind of problem this is, which is shown in the header of the report. For a [`ParseError`](EbnfError::ParseError), this comes from the same guesswork as [`FailureReason`], and may change between versions for the same input.
+    /// ```rust
+    /// # use ebnf_toolkit::{ErrorCode, Grammar};
+    /// let err = Grammar::new("A = (B C;").unwrap_err();
+    /// assert_eq!(err.code(), ErrorCode::UnclosedBracket);
+    /// assert_eq!(err.code().as_str(), "E001");
+    /// assert_eq!(Grammar::new("A = B").unwrap_err().code(), ErrorCode::MissingSemicolon);
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            EbnfError::LexError { input, offset } => match input.as_bytes().get(*offset) {
+                Some(b'\'' | b'"') => ErrorCode::UnclosedString,
+                Some(b'\\') => ErrorCode::StrayBackslash,
+                _ => ErrorCode::UnrecognizedToken,
+            },
+            EbnfError::ParseError {
+                reason: Some(reason),
+                ..
+            } => {
+                if !empty_brackets(reason.stack()).is_empty() {
+                    return ErrorCode::EmptyBrackets;
+                }
+                if !unclosed_brackets(reason.stack()).is_empty() {
+                    return ErrorCode::UnclosedBracket;
+                }
+                match reason {
+                    FailureReason::ExhaustedInput(nodes) if check_missing_terminator(nodes) => {
+                        ErrorCode::MissingSemicolon
+                    }
+                    FailureReason::ExhaustedInput(_) => ErrorCode::UnexpectedEnd,
+                    FailureReason::TerminatorNotEndingRule(nodes)
+                        if non_identifier_rule_name(nodes).is_some() =>
+                    {
+                        ErrorCode::InvalidRuleName
+                    }
+                    FailureReason::TerminatorNotEndingRule(_) => ErrorCode::InvalidRule,
+                }
             }
-            _ => None,
+            EbnfError::ParseError { reason: None, .. } => ErrorCode::InvalidRule,
+            EbnfError::EmptyInput => ErrorCode::EmptyInput,
+            EbnfError::LimitExceeded { .. } => ErrorCode::LimitExceeded,
+        }
+    }
+
+    /// Counts the brackets of each kind in the rule where parsing failed, from the end of the rule before it up to where the error was found, and describes each kind that was opened more or fewer times than it was closed, such as `3 ( but only 2 )`. Reports show these as hints alongside their labels. Returns nothing for errors other than a [`ParseError`](EbnfError::ParseError), or if every kind of bracket is balanced.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "list = '[' items ']';\nitems = (item (',' (item))*;";
+    /// let err = Grammar::new(src).unwrap_err();
+    /// let balance = err.bracket_balance();
+    /// assert_eq!(balance[0].to_string(), "3 `(` but only 2 `)` before the end of the rule");
+    /// let unmatched: Vec<_> = balance[0].unmatched.iter().map(|s| s.start_line()).collect();
+    /// assert_eq!(unmatched, [(2, 8)]);
+    /// ```
+    pub fn bracket_balance(&self) -> Vec<BracketImbalance> {
+        match self {
+            EbnfError::ParseError { input, offset, .. } => bracket_balance(input, *offset),
+            _ => Vec::new(),
         }
     }
+
+    /// What the report of this error says, as JSON, for a web frontend or editor to draw its own diagnostics without parsing the report's text. Needs no features, so it's available where the `report` feature is too heavy. The object has the form
+    /// ```json
+    /// {
+    ///     "code": "E001",
+    ///     "message": null,
+    ///     "offset": 6,
+    ///     "labels": [{ "span": [4, 5], "message": "This bracket is never closed" }],
+    ///     "notes": ["..."],
+    ///     "helps": ["1 `(` but no `)` before the end of the rule"],
+    ///     "stack": [...]
+    /// }
+    /// ```
+    /// where `code` is the [`ErrorCode`], `message` is the report's headline, or `null` if its labels say it all, and `offset` is where the error was found, or `null` for [`EmptyInput`](EbnfError::EmptyInput). Spans are byte offsets into the input, in the order the report adds their labels, and may be empty to point between two characters. The `helps` are the [`bracket_balance`](EbnfError::bracket_balance) hints. For a [`ParseError`](EbnfError::ParseError) that says why it failed, `stack` is the [`FailureReason::stack`], most recent last, with nodes in the format of [`FailureReason::to_json`], and it's `null` otherwise.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let err = Grammar::new("A = (B;").unwrap_err();
+    /// let json = err.to_json();
+    /// assert!(json.starts_with(r#"{"code":"E001","message":null,"offset":6,"labels":[{"span":[4,5],"message":"This bracket is never closed"}"#));
+    /// assert!(json.contains(r#""helps":["1 `(` but no `)` before the end of the rule"],"stack":[{"kind":"Nonterminal""#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        crate::diagnostic::write_json(&mut out, self);
+        out
+    }
+
+    /// Renders the same report as the `Display` implementation, but laid out according to `config`
+    #[cfg(feature = "report")]
+    pub fn render(&self, config: crate::ReportConfig<'_>) -> String {
+        let mut output = String::new();
+        // Writing to a String can't fail
+        let _ = crate::report::write_report(self, &mut output, config);
+        output
+    }
+
+    /// Writes the report that [`EbnfError::render`] would build straight to `w`, such as a locked `stderr`, without collecting it into a `String` first
+    /// ```rust
+    /// # use ebnf_toolkit::{ReportConfig, Rule};
+    /// let err = Rule::new("A = (B;").unwrap_err();
+    /// let config = ReportConfig::default().with_color(false);
+    /// let mut output = Vec::new();
+    /// err.write_report(&mut output, config).unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), err.render(config));
+    /// ```
+    ///
+    /// # Errors
+    /// Any error from writing to `w`
+    #[cfg(feature = "report")]
+    pub fn write_report(
+        &self,
+        mut w: impl std::io::Write,
+        config: crate::ReportConfig<'_>,
+    ) -> std::io::Result<()> {
+        crate::report::stream_report(self, &mut w, config)
+    }
 }
 
 impl PartialEq for EbnfError<'_> {
@@ -65,6 +189,20 @@ impl PartialEq for EbnfError<'_> {
         use EbnfError::*;
         match (self, other) {
             (EmptyInput, EmptyInput) => true,
+            (
+                LimitExceeded {
+                    input,
+                    offset,
+                    limit,
+                    max,
+                },
+                LimitExceeded {
+                    input: other_input,
+                    offset: other_offset,
+                    limit: other_limit,
+                    max: other_max,
+                },
+            ) => (input, offset, limit, max) == (other_input, other_offset, other_limit, other_max),
             (this @ LexError { .. }, other @ LexError { .. })
             | (this @ ParseError { .. }, other @ ParseError { .. }) => this
                 .input()
@@ -79,6 +217,50 @@ impl PartialEq for EbnfError<'_> {
 
 impl Eq for EbnfError<'_> {}
 
+/// An [`EbnfError`] from an input that was given a name, such as the path of the file it was read from. The name is used in place of `<input>` when the error is displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedError<'a> {
+    /// The name the input was given
+    pub source_name: &'a str,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub error: EbnfError<'a>,
+}
+
+impl NamedError<'_> {
+    /// Renders the report for the underlying error according to `config`, with the source name replaced by this error's `source_name`
+    #[cfg(feature = "report")]
+    pub fn render(&self, config: crate::ReportConfig<'_>) -> String {
+        self.error.render(config.with_source_name(self.source_name))
+    }
+
+    /// Writes the report for the underlying error to `w` as [`EbnfError::write_report`] does, with the source name replaced by this error's `source_name`
+    ///
+    /// # Errors
+    /// Any error from writing to `w`
+    #[cfg(feature = "report")]
+    pub fn write_report(
+        &self,
+        w: impl std::io::Write,
+        config: crate::ReportConfig<'_>,
+    ) -> std::io::Result<()> {
+        self.error
+            .write_report(w, config.with_source_name(self.source_name))
+    }
+}
+
+impl Display for NamedError<'_> {
+    #[cfg(feature = "report")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let config = crate::ReportConfig::default().with_source_name(self.source_name);
+        crate::report::write_report(&self.error, f, config)
+    }
+
+    #[cfg(not(feature = "report"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.source_name, self.error)
+    }
+}
+
 /// More detail about the potential cause of a [`ParseError`](`EbnfError::ParseError`).
 ///
 /// These values are provisional, and generated on a best-effort basis. While Semver applies to the enum structure itself (no variants will disappear in a patch release, *etc*) the exact logic for which `FailureReason` (if any) is returned from any particular invalid input are **not stable and may change in a point release** - tests, *etc*, should check that an invalid input produces a `ParseError` without examining any further. That said, a false positive or incorrect suggestion for why a parse error has occured is still considered a bug; filing an issue describing incorrect reports or suggestions for improving the messages would be appreciated.
@@ -91,141 +273,223 @@ pub enum FailureReason<'a> {
     ExhaustedInput(Vec<Expr<'a>>),
 }
 
-impl Display for EbnfError<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use ariadne::{ColorGenerator, Label, Report, ReportKind, Source};
-        let input = match self {
-            EbnfError::LexError { input, .. } | EbnfError::ParseError { input, .. } => *input,
-            EbnfError::EmptyInput => return write!(f, "Input string was empty"),
-        };
-
-        let s = Source::from(input);
-
-        let mut report = Report::build(ReportKind::Error, ("<input>", 0..input.len()));
+/// How a [`ParseLimit`] is described in reports
+pub(crate) fn limit_name(limit: ParseLimit) -> &'static str {
+    match limit {
+        ParseLimit::NestingDepth => "nesting depth",
+        ParseLimit::Tokens => "number of tokens",
+        ParseLimit::Rules => "number of rules",
+        ParseLimit::LiteralLength => "length of a literal",
+        ParseLimit::RegexLength => "length of a regex",
+    }
+}
 
+impl<'a> FailureReason<'a> {
+    /// The nodes left on the parse stack when parsing failed, with the most recent last. This is the same stack that reports draw in their note, where it is shown the other way up.
+    /// ```rust
+    /// # use ebnf_toolkit::{EbnfError, Expr, Grammar};
+    /// let Err(EbnfError::ParseError { reason: Some(reason), .. }) = Grammar::new("A = (B") else { panic!() };
+    /// assert!(matches!(reason.stack(), [.., Expr::UnparsedOperator { .. }, Expr::Nonterminal { name: "B", .. }]));
+    /// ```
+    pub fn stack(&self) -> &[Expr<'a>] {
         match self {
-            &EbnfError::LexError { offset, .. } => {
-                let mut colors = ColorGenerator::new();
-
-                let col = colors.next();
-                report = report.with_message("Tokenization error").with_label(
-                    Label::new(("<input>", offset..offset + 1))
-                        .with_message("This was not recognised as the start of a valid token")
-                        .with_color(col),
-                );
-                if input.as_bytes()[offset] == b'\'' || input.as_bytes()[offset] == b'"' {
-                    report = report.with_note("Is this the beginning of an unclosed string?");
-                }
-            }
-            EbnfError::EmptyInput => {
-                report = report.with_message("Input was empty");
-            }
-            EbnfError::ParseError {
-                input: _,
-                offset: start,
-                reason,
-            } => {
-                report = handle_parse_error(report, *start, reason.as_ref());
-            }
+            FailureReason::ExhaustedInput(nodes)
+            | FailureReason::TerminatorNotEndingRule(nodes) => nodes,
         }
+    }
 
-        let r = report.finish();
-
-        let mut output = vec![];
-
-        r.write(("<input>", s), &mut output).unwrap();
-        write!(f, "{}", String::from_utf8(output).unwrap())?;
+    /// Where each opening bracket left on the [`stack`](FailureReason::stack) without a closing bracket after it is, outermost first. The closing brackets were expected after the last node on the stack before the `;`, if there is one, which reports label along with the brackets themselves.
+    /// ```rust
+    /// # use ebnf_toolkit::{EbnfError, Rule};
+    /// let src = "A = (B [C;";
+    /// let Err(EbnfError::ParseError { reason: Some(reason), .. }) = Rule::new(src) else { panic!() };
+    /// let unclosed: Vec<_> = reason.unclosed_brackets().iter().map(|s| &src[s.range()]).collect();
+    /// assert_eq!(unclosed, ["(", "["]);
+    /// ```
+    pub fn unclosed_brackets(&self) -> Vec<Span> {
+        unclosed_brackets(self.stack())
+            .into_iter()
+            .map(|(span, _)| span)
+            .collect()
+    }
 
-        Ok(())
+    /// The name of the variant and the [`stack`](FailureReason::stack) as JSON, for editors and other tools to show without parsing the text of a report. Nodes are written as objects in the format of the `wasm` module's `parseGrammar`, with operators as nodes of kind `UnparsedOperator` carrying their `text`.
+    /// ```rust
+    /// # use ebnf_toolkit::{EbnfError, Grammar};
+    /// let Err(EbnfError::ParseError { reason: Some(reason), .. }) = Grammar::new("A = B &;") else { panic!() };
+    /// assert!(reason.to_json().starts_with(r#"{"reason":"TerminatorNotEndingRule","stack":[{"kind":"Nonterminal""#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let name = match self {
+            FailureReason::TerminatorNotEndingRule(_) => "TerminatorNotEndingRule",
+            FailureReason::ExhaustedInput(_) => "ExhaustedInput",
+        };
+        let mut out = String::new();
+        out.push_str(r#"{"reason":""#);
+        out.push_str(name);
+        out.push_str(r#"","stack":"#);
+        write_nodes(&mut out, self.stack());
+        out.push('}');
+        out
     }
 }
 
-fn handle_parse_error<'a>(
-    mut report: ReportType<'a>,
-    offset: usize,
-    reason: Option<&FailureReason<'_>>,
-) -> ReportType<'a> {
-    let mut colors = ColorGenerator::new();
-
-    let col = colors.next();
+/// A kind of bracket that's opened more or fewer times than it's closed in the rule where parsing failed, from [`EbnfError::bracket_balance`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BracketImbalance {
+    /// The opening bracket, such as `"("`
+    pub open: &'static str,
+    /// The closing bracket, such as `")"`
+    pub close: &'static str,
+    /// How many opening brackets the rule has
+    pub opened: usize,
+    /// How many closing brackets the rule has
+    pub closed: usize,
+    /// The brackets of this kind with no partner, in order: opening brackets that are never closed, and closing brackets with nothing open to close
+    pub unmatched: Vec<Span>,
+}
 
-    let nodes = match reason.as_ref().unwrap() {
-        FailureReason::ExhaustedInput(nodes) | FailureReason::TerminatorNotEndingRule(nodes) => {
-            nodes
+impl Display for BracketImbalance {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (more, more_count, fewer, fewer_count) = if self.opened > self.closed {
+            (self.open, self.opened, self.close, self.closed)
+        } else {
+            (self.close, self.closed, self.open, self.opened)
+        };
+        if fewer_count == 0 {
+            write!(
+                f,
+                "{more_count} `{more}` but no `{fewer}` before the end of the rule"
+            )
+        } else {
+            write!(
+                f,
+                "{more_count} `{more}` but only {fewer_count} `{fewer}` before the end of the rule"
+            )
         }
+    }
+}
+
+/// The brackets of the rule in `input` that was being parsed at `offset`, for [`EbnfError::bracket_balance`]
+pub(crate) fn bracket_balance(input: &str, offset: usize) -> Vec<BracketImbalance> {
+    const PAIRS: [(TokenPayload<'_>, TokenPayload<'_>, &str, &str); 4] = [
+        (
+            TokenPayload::OpeningGroup,
+            TokenPayload::ClosingGroup,
+            "(",
+            ")",
+        ),
+        (
+            TokenPayload::OpeningSquare,
+            TokenPayload::ClosingSquare,
+            "[",
+            "]",
+        ),
+        (
+            TokenPayload::OpeningBrace,
+            TokenPayload::ClosingBrace,
+            "{",
+            "}",
+        ),
+        (
+            TokenPayload::OpeningAngle,
+            TokenPayload::ClosingAngle,
+            "<",
+            ">",
+        ),
+    ];
+    let Ok((tokens, _)) = tokenize_with_imports(input) else {
+        return Vec::new();
     };
+    let before: Vec<_> = tokens.iter().filter(|t| t.span.start() < offset).collect();
+    let rule_start = before
+        .iter()
+        .rposition(|t| t.payload == TokenPayload::Termination)
+        .map_or(0, |i| i + 1);
+    let rule = &before[rule_start..];
 
-    for n in nodes {
-        if let Expr::UnparsedOperator { span, op } = n
-            && *op != Operator::Equals
-            && *op != Operator::Terminator
-        {
-            let message = match *op {
-                Operator::OpenedGroup | Operator::OpenedSquare => "Possible unclosed bracket",
-                Operator::Kleene | Operator::Optional | Operator::Repeat => {
-                    "Could not apply to preceding term"
+    let mut imbalances = Vec::new();
+    for (opening, closing, open, close) in PAIRS {
+        let (mut opened, mut closed) = (0, 0);
+        let mut open_spans = Vec::new();
+        let mut unmatched = Vec::new();
+        for token in rule {
+            if token.payload == opening {
+                opened += 1;
+                open_spans.push(token.span);
+            } else if token.payload == closing {
+                closed += 1;
+                if open_spans.pop().is_none() {
+                    unmatched.push(token.span);
                 }
-                _ => "Operator not understood",
-            };
-            report = report.with_label(
-                Label::new(("<input>", span.start()..span.end()))
-                    .with_color(colors.next())
-                    .with_message(message),
-            );
-        }
-    }
-
-    match reason.as_ref().unwrap() {
-        FailureReason::ExhaustedInput(nodes) => {
-            if check_missing_terminator(nodes) {
-                report = report.with_label(
-                    Label::new(("<input>", offset..offset))
-                        .with_message("Missing semicolon here")
-                        .with_color(colors.next()),
-                );
-            } else {
-                report = report.with_label(
-                    Label::new(("<input>", offset..offset))
-                        .with_message(format!("Unexpected end of input at index {offset}"))
-                        .with_color(colors.next()),
-                );
             }
-            attach_stack_to_report(report, nodes)
         }
+        if opened != closed {
+            unmatched.extend(open_spans);
+            unmatched.sort_by_key(Span::start);
+            imbalances.push(BracketImbalance {
+                open,
+                close,
+                opened,
+                closed,
+                unmatched,
+            });
+        }
+    }
+    imbalances
+}
 
-        FailureReason::TerminatorNotEndingRule(nodes) => {
-            if let Some(equals) = nodes.iter().position(|n| {
-                matches!(
-                    n,
-                    Expr::UnparsedOperator {
-                        op: Operator::Equals,
-                        ..
-                    }
-                )
-            }) && let Some(not_identifier) = nodes.get(equals - 1)
-                && ExprKind::from(not_identifier) != ExprKind::Nonterminal
+/// The opening brackets in `nodes` with no matching closing bracket after them, outermost first
+pub(crate) fn unclosed_brackets(nodes: &[Expr<'_>]) -> Vec<(Span, Operator)> {
+    let mut open: Vec<(Span, Operator)> = Vec::new();
+    for node in nodes {
+        if let Expr::UnparsedOperator { span, op } = node {
+            if op.closing_bracket().is_some() {
+                open.push((*span, *op));
+            } else if let Some(position) = open
+                .iter()
+                .rposition(|(_, opener)| opener.closing_bracket() == Some(*op))
             {
-                let Range { start, end } = not_identifier.span().range();
-                report = report.with_label(
-                    Label::new(("<input>", start..end))
-                        .with_message(format!(
-                            "Expected identifier, found {:?}",
-                            ExprKind::from(not_identifier)
-                        ))
-                        .with_color(colors.next()),
-                );
+                open.truncate(position);
             }
-            report = report.with_label(
-                Label::new(("<input>", offset..(offset + 1)))
-                    .with_message("Rule ending here did not parse successfully".to_string())
-                    .with_color(col),
-            );
-            attach_stack_to_report(report, nodes)
         }
     }
+    open
+}
+
+/// The spans of the pairs of brackets in `nodes` with nothing between them, each covering both brackets
+pub(crate) fn empty_brackets(nodes: &[Expr<'_>]) -> Vec<Span> {
+    nodes
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [
+                Expr::UnparsedOperator { span: a, op: open },
+                Expr::UnparsedOperator { span: b, op: close },
+            ] if open.closing_bracket() == Some(*close) => Some(a.join(*b)),
+            _ => None,
+        })
+        .collect()
 }
 
-fn check_missing_terminator(nodes: &[Expr<'_>]) -> bool {
+/// Where the closing brackets for [`unclosed_brackets`] were expected: just after the last node of `nodes` that isn't the `;` ending the rule
+pub(crate) fn expected_closing_offset(nodes: &[Expr<'_>]) -> Option<usize> {
+    nodes
+        .iter()
+        .rev()
+        .find(|n| {
+            !matches!(
+                n,
+                Expr::UnparsedOperator {
+                    op: Operator::Terminator,
+                    ..
+                }
+            )
+        })
+        .map(|n| n.span().end())
+}
+
+/// Whether `nodes` would have made a rule if the input had a `;` after them
+pub(crate) fn check_missing_terminator(nodes: &[Expr<'_>]) -> bool {
     let mut stack = LrStack::new();
     for n in nodes {
         stack.push_node(n.clone());
@@ -239,12 +503,228 @@ fn check_missing_terminator(nodes: &[Expr<'_>]) -> bool {
     matches!(stack.pop_node(), Some(Expr::Rule { .. }))
 }
 
-fn attach_stack_to_report<'a>(report: ReportType<'a>, nodes: &[Expr<'_>]) -> ReportType<'a> {
-    let mut nodes = nodes.to_vec();
-    nodes.reverse();
-    let mut tree_output = String::new();
-    print_vec_tree(&mut tree_output, Style::default(), &nodes).unwrap();
-    report.with_note(format!(
-        "The parse stack looked like this (most recent on top):\n{tree_output}",
-    ))
+/// The node in front of the `=` in `nodes`, if it is something other than the name a rule needs
+pub(crate) fn non_identifier_rule_name<'n, 'a>(nodes: &'n [Expr<'a>]) -> Option<&'n Expr<'a>> {
+    let equals = nodes.iter().position(|n| {
+        matches!(
+            n,
+            Expr::UnparsedOperator {
+                op: Operator::Equals,
+                ..
+            }
+        )
+    })?;
+    nodes
+        .get(equals.checked_sub(1)?)
+        .filter(|name| NodeKind::from(*name) != NodeKind::Nonterminal)
+}
+
+/// A stable identifier for a kind of problem with a grammar, from [`EbnfError::code`] or [`ValidationError::code`]. Codes never change meaning or get reused between versions of this crate, so tools can link them to an explanation or pick out the kinds they want to ignore.
+///
+/// Problems with the syntax have codes starting at `E001`, and problems found when checking a set of rules have codes starting at `E101`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// `E001` - a `(`, `[`, `{` or `<` has no matching closing bracket
+    UnclosedBracket,
+    /// `E002` - the input ran out where a rule could have ended, but without the `;` that ends it
+    MissingSemicolon,
+    /// `E003` - the input ran out part way through a rule
+    UnexpectedEnd,
+    /// `E004` - a rule ended with a `;`, but what came before it was not a valid rule
+    InvalidRule,
+    /// `E005` - something other than a name came before the `=` of a rule
+    InvalidRuleName,
+    /// `E006` - part of the input was not the start of any token
+    UnrecognizedToken,
+    /// `E007` - a quote starts a literal that is never closed
+    UnclosedString,
+    /// `E008` - a backslash appears outside a quoted literal or regex
+    StrayBackslash,
+    /// `E009` - there was nothing to parse
+    EmptyInput,
+    /// `E010` - the input was too large or too deeply nested, see [`EbnfError::LimitExceeded`]
+    LimitExceeded,
+    /// `E011` - a pair of brackets has nothing between them, like `()` or `{}`
+    EmptyBrackets,
+    /// `E101` - see [`ValidationError::DuplicateRule`]
+    DuplicateRule,
+    /// `E102` - see [`ValidationError::DanglingReference`]
+    DanglingReference,
+    /// `E103` - see [`ValidationError::DefinedInSeveralInputs`]
+    DefinedInSeveralInputs,
+}
+
+impl ErrorCode {
+    /// The code as it is written in reports, such as `E001`
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnclosedBracket => "E001",
+            ErrorCode::MissingSemicolon => "E002",
+            ErrorCode::UnexpectedEnd => "E003",
+            ErrorCode::InvalidRule => "E004",
+            ErrorCode::InvalidRuleName => "E005",
+            ErrorCode::UnrecognizedToken => "E006",
+            ErrorCode::UnclosedString => "E007",
+            ErrorCode::StrayBackslash => "E008",
+            ErrorCode::EmptyInput => "E009",
+            ErrorCode::LimitExceeded => "E010",
+            ErrorCode::EmptyBrackets => "E011",
+            ErrorCode::DuplicateRule => "E101",
+            ErrorCode::DanglingReference => "E102",
+            ErrorCode::DefinedInSeveralInputs => "E103",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem with a set of rules found by [`Grammar::from_rules`](`crate::Grammar::from_rules`) or [`GrammarSet::load`](`crate::GrammarSet::load`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError<'a> {
+    /// More than one rule was given this name
+    DuplicateRule {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: Cow<'a, str>,
+        /// Every definition of the rule, if the rules being checked recorded where they came from
+        definitions: SpanSet,
+    },
+    /// The rule called `rule` refers to a nonterminal `name` that has no definition
+    DanglingReference {
+        #[expect(missing_docs, reason = "Obvious")]
+        rule: Cow<'a, str>,
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+        /// Where the reference to `name` appears
+        span: Span,
+        /// A defined rule whose name is only a few characters away from `name`, which it may have been a typo for
+        suggestion: Option<Cow<'a, str>>,
+    },
+    /// More than one input of a [`GrammarSet`](crate::GrammarSet) defines a rule with this name
+    DefinedInSeveralInputs {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: Cow<'a, str>,
+        /// The name of the first input to define the rule, and where it does so
+        first: (&'a str, SpanSet),
+        /// The name of a later input that defines the rule again, and where it does so
+        second: (&'a str, SpanSet),
+    },
+}
+
+impl ValidationError<'_> {
+    /// The [`ErrorCode`] for the kind of problem this is
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ValidationError::DuplicateRule { .. } => ErrorCode::DuplicateRule,
+            ValidationError::DanglingReference { .. } => ErrorCode::DanglingReference,
+            ValidationError::DefinedInSeveralInputs { .. } => ErrorCode::DefinedInSeveralInputs,
+        }
+    }
+
+    /// Renders an ariadne report of the problem, like the one for an [`EbnfError`], with its spans labelled in `input`, which should be the text the rules were parsed from. A [`DanglingReference`](ValidationError::DanglingReference) with a suggestion says what the name might have been meant to be.
+    ///
+    /// The spans of a [`DefinedInSeveralInputs`](ValidationError::DefinedInSeveralInputs) are in two different inputs, so they aren't labelled.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, ReportConfig, Rule, ValidationLevel};
+    /// let src = "message = clinet_prefix ' ' command;";
+    /// let rules = [
+    ///     Rule::new(src).unwrap(),
+    ///     Rule::new("client_prefix = ':' #'[a-z]+';").unwrap(),
+    ///     Rule::new("command = #'[A-Z]+';").unwrap(),
+    /// ];
+    /// let errors = Grammar::from_rules(rules, ValidationLevel::Strict).unwrap_err();
+    /// let report = errors[0].render(src, ReportConfig::default().with_color(false));
+    /// assert!(report.contains("Did you mean `client_prefix`?"));
+    /// ```
+    #[cfg(feature = "report")]
+    pub fn render(&self, input: &str, config: crate::ReportConfig<'_>) -> String {
+        let mut output = String::new();
+        // Writing to a String can't fail
+        let _ = crate::report::write_validation_report(self, input, &mut output, config);
+        output
+    }
+}
+
+impl Display for ValidationError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ValidationError::DuplicateRule { name, definitions } => {
+                write!(f, "Rule `{name}` is defined more than once")?;
+                if !definitions.is_empty() {
+                    write!(f, ", at {definitions}")?;
+                }
+                Ok(())
+            }
+            ValidationError::DanglingReference {
+                rule,
+                name,
+                span,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "Rule `{rule}` refers to `{name}` at {span}, which is not defined"
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " (did you mean `{suggestion}`?)")?;
+                }
+                Ok(())
+            }
+            ValidationError::DefinedInSeveralInputs {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Rule `{name}` is defined in `{}` at {}, and again in `{}` at {}",
+                first.0, first.1, second.0, second.1
+            ),
+        }
+    }
+}
+
+impl Display for EbnfError<'_> {
+    #[cfg(feature = "report")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        crate::report::write_report(self, f, crate::ReportConfig::default())
+    }
+
+    #[cfg(not(feature = "report"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "[{}] ", self.code())?;
+        match self {
+            EbnfError::LexError { input, offset } => {
+                write!(
+                    f,
+                    "Tokenization error: input at index {offset} was not recognised as the start of a valid token"
+                )?;
+                if input.as_bytes()[*offset] == b'\\' {
+                    write!(
+                        f,
+                        " (backslash escapes are only valid inside quoted literals or regexes)"
+                    )?;
+                }
+                Ok(())
+            }
+            EbnfError::ParseError { offset, .. } => {
+                write!(
+                    f,
+                    "Parse error: input could not be parsed at index {offset}"
+                )
+            }
+            EbnfError::EmptyInput => write!(f, "Input string was empty"),
+            EbnfError::LimitExceeded {
+                offset, limit, max, ..
+            } => write!(
+                f,
+                "Limit exceeded: the {} at index {offset} is over the limit of {max}",
+                limit_name(*limit)
+            ),
+        }
+    }
 }
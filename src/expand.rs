@@ -0,0 +1,138 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Expr, Grammar, Rule, Span, simplification::simplify_node};
+
+pub(crate) fn expand_rule<'g, 'a>(
+    rule: &'g Rule<'a>,
+    grammar: &'g Grammar<'a>,
+    depth: usize,
+) -> Expr<'a> {
+    let mut path = vec![&*rule.name];
+    let body = expand_all(&rule.body, grammar, depth, &mut path);
+    if body.is_empty() {
+        return Expr::Group {
+            span: rule.definitions.hull().unwrap_or_default(),
+            body,
+        };
+    }
+    let mut expr = Expr::Group {
+        span: Span::union(body.iter()),
+        body,
+    };
+    simplify_node(&mut expr);
+    expr
+}
+
+fn expand_all<'g, 'a>(
+    nodes: &'g [Expr<'a>],
+    grammar: &'g Grammar<'a>,
+    depth: usize,
+    path: &mut Vec<&'g str>,
+) -> Vec<Expr<'a>> {
+    nodes
+        .iter()
+        .map(|n| expand(n, grammar, depth, path))
+        .collect()
+}
+
+/// Copies `node`, replacing each nonterminal with the body of the rule it names, unless that rule is one of those in `path` that are already being expanded
+fn expand<'g, 'a>(
+    node: &'g Expr<'a>,
+    grammar: &'g Grammar<'a>,
+    depth: usize,
+    path: &mut Vec<&'g str>,
+) -> Expr<'a> {
+    match node {
+        Expr::Nonterminal { span, name } => match grammar.rules.get(*name) {
+            Some(rule) if depth > 0 && !rule.body.is_empty() && !path.contains(name) => {
+                path.push(name);
+                let body = expand_all(&rule.body, grammar, depth - 1, path);
+                path.pop();
+                Expr::Group { span: *span, body }
+            }
+            _ => node.clone(),
+        },
+        Expr::Choice {
+            span,
+            body,
+            ordered,
+        } => Expr::Choice {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+            ordered: *ordered,
+        },
+        Expr::Optional { span, body } => Expr::Optional {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::Repetition {
+            span,
+            body,
+            one_needed,
+            separated,
+        } => Expr::Repetition {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+            one_needed: *one_needed,
+            separated: *separated,
+        },
+        Expr::Group { span, body } => Expr::Group {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::And { span, body } => Expr::And {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::Not { span, body } => Expr::Not {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::Hidden { span, body } => Expr::Hidden {
+            span: *span,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::Labeled { span, label, body } => Expr::Labeled {
+            span: *span,
+            label,
+            body: expand_all(body, grammar, depth, path),
+        },
+        Expr::Rule { span, rule } => Expr::Rule {
+            span: *span,
+            rule: Rule {
+                name: rule.name.clone(),
+                body: expand_all(&rule.body, grammar, depth, path),
+                definitions: rule.definitions.clone(),
+                hidden: rule.hidden,
+                annotations: rule.annotations.clone(),
+            },
+        },
+        Expr::Literal { .. }
+        | Expr::Regex { .. }
+        | Expr::Empty { .. }
+        | Expr::UnparsedOperator { .. } => node.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Expr, Grammar};
+
+    #[test]
+    fn expand() {
+        let g = Grammar::new("S = A B; A = 'a' | B; B = 'b' S?; C = D 'c';").unwrap();
+
+        let shallow = g["S"].expand(&g, 0);
+        assert!(shallow.eq_ignoring_spans(&Expr::new("(A B)").unwrap()));
+
+        let deep = g["S"].expand(&g, 2);
+        let expected = Expr::new("(('a' | ('b' S?)) 'b' S?)").unwrap();
+        assert!(deep.eq_ignoring_spans(&expected), "{deep:?}");
+
+        // Each rule is expanded once along any path, so the recursion through S stops on its own
+        assert!(g["S"].expand(&g, 100).eq_ignoring_spans(&deep));
+
+        let undefined = g["C"].expand(&g, 5);
+        assert!(undefined.eq_ignoring_spans(&Expr::new("(D 'c')").unwrap()));
+    }
+}
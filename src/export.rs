@@ -0,0 +1,1217 @@
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::{Display, Write},
+    slice,
+};
+
+use regex_syntax::hir::{Class, ClassUnicode, Hir, HirKind, Look};
+
+use crate::{Expr, Grammar, Span};
+
+/// The ways writing a grammar in another parser generator's notation, as with [`Grammar::to_pest`], can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExportError<'a> {
+    /// The rule called `rule` has a node at `span` that the notation has no equivalent for
+    Unsupported {
+        #[expect(missing_docs, reason = "Obvious")]
+        rule: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What it is about the node that can't be written, such as `"lazy repetition"`
+        reason: &'static str,
+    },
+    /// The rule called `rule` has a node at `span` that the notation only has an equivalent for once it's lowered to plain BNF, which [`ExportOptions::lower`] does
+    NeedsLowering {
+        #[expect(missing_docs, reason = "Obvious")]
+        rule: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What the node is, such as `"a repetition"`
+        reason: &'static str,
+    },
+    /// The rule or label `name` at `span` isn't a valid name in the notation, or is one it reserves for itself
+    InvalidName {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+    },
+    /// The grammar imports the file at `path`, which the notation has no way to do. Rules from imported files can be brought into the grammar first with [`GrammarSet::to_grammar`](crate::GrammarSet::to_grammar).
+    Import {
+        #[expect(missing_docs, reason = "Obvious")]
+        path: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+    },
+}
+
+impl Display for ExportError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExportError::Unsupported { rule, span, reason } => {
+                write!(
+                    f,
+                    "Rule `{rule}` uses {reason} at {span}, which can't be exported"
+                )
+            }
+            ExportError::NeedsLowering { rule, span, reason } => {
+                write!(
+                    f,
+                    "Rule `{rule}` uses {reason} at {span}, which has to be lowered to BNF to be exported"
+                )
+            }
+            ExportError::InvalidName { name, span } => {
+                write!(f, "`{name}` at {span} can't be used as a name")
+            }
+            ExportError::Import { path, span } => {
+                write!(f, "The import of `{path}` at {span} can't be exported")
+            }
+        }
+    }
+}
+
+/// Names that pest gives a meaning of its own
+const PEST_BUILTINS: &[&str] = &[
+    "ANY",
+    "SOI",
+    "EOI",
+    "PUSH",
+    "POP",
+    "POP_ALL",
+    "PEEK",
+    "PEEK_ALL",
+    "DROP",
+    "NEWLINE",
+    "ASCII",
+    "ASCII_DIGIT",
+    "ASCII_NONZERO_DIGIT",
+    "ASCII_BIN_DIGIT",
+    "ASCII_OCT_DIGIT",
+    "ASCII_HEX_DIGIT",
+    "ASCII_ALPHA",
+    "ASCII_ALPHA_LOWER",
+    "ASCII_ALPHA_UPPER",
+    "ASCII_ALPHANUMERIC",
+];
+
+pub(crate) fn to_pest<'a>(grammar: &'a Grammar<'_>) -> Result<String, ExportError<'a>> {
+    if let Some(import) = grammar.imports.first() {
+        return Err(ExportError::Import {
+            path: import.path,
+            span: import.span,
+        });
+    }
+    let mut out = String::new();
+    for rule in grammar.rules_in_definition_order() {
+        let span = rule.definitions.hull().unwrap_or_default();
+        let writer = PestWriter { rule: &rule.name };
+        check_pest_name(&rule.name, span)?;
+        let silent = if rule.hidden { "_" } else { "" };
+        write!(out, "{} = {silent}{{ ", rule.name).unwrap();
+        writer.sequence(&rule.body, &mut out)?;
+        out.push_str(" }\n");
+    }
+    Ok(out)
+}
+
+fn check_pest_name(name: &str, span: Span) -> Result<(), ExportError<'_>> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !PEST_BUILTINS.contains(&name);
+    if valid {
+        Ok(())
+    } else {
+        Err(ExportError::InvalidName { name, span })
+    }
+}
+
+/// Writes the nodes of the rule called `rule` in pest's notation
+struct PestWriter<'a> {
+    rule: &'a str,
+}
+
+impl<'a> PestWriter<'a> {
+    fn unsupported(&self, span: Span, reason: &'static str) -> ExportError<'a> {
+        ExportError::Unsupported {
+            rule: self.rule,
+            span,
+            reason,
+        }
+    }
+
+    fn sequence(&self, nodes: &'a [Expr<'_>], out: &mut String) -> Result<(), ExportError<'a>> {
+        if nodes.is_empty() {
+            out.push_str("\"\"");
+        }
+        for (n, node) in nodes.iter().enumerate() {
+            if n > 0 {
+                out.push_str(" ~ ");
+            }
+            self.node(node, out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `nodes` in parentheses, followed by `suffix`
+    fn group(
+        &self,
+        prefix: &str,
+        nodes: &'a [Expr<'_>],
+        suffix: &str,
+        out: &mut String,
+    ) -> Result<(), ExportError<'a>> {
+        out.push_str(prefix);
+        out.push('(');
+        self.sequence(nodes, out)?;
+        out.push(')');
+        out.push_str(suffix);
+        Ok(())
+    }
+
+    fn node(&self, node: &'a Expr<'_>, out: &mut String) -> Result<(), ExportError<'a>> {
+        match node {
+            // Quotes are the only characters escaped in a literal
+            Expr::Literal { str, .. } => {
+                write_string(&str.replace(r"\'", "'").replace(r#"\""#, "\""), out);
+            }
+            &Expr::Nonterminal { name, span } => {
+                check_pest_name(name, span)?;
+                out.push_str(name);
+            }
+            &Expr::Regex { pattern, span } => {
+                let hir = regex_syntax::parse(pattern)
+                    .map_err(|_| self.unsupported(span, "an invalid regex"))?;
+                out.push('(');
+                write_regex(&hir, out).map_err(|reason| self.unsupported(span, reason))?;
+                out.push(')');
+            }
+            Expr::Empty { .. } => out.push_str("\"\""),
+            Expr::Choice { body, .. } => {
+                out.push('(');
+                for (n, alternative) in body.iter().enumerate() {
+                    if n > 0 {
+                        out.push_str(" | ");
+                    }
+                    self.node(alternative, out)?;
+                }
+                out.push(')');
+            }
+            Expr::Optional { body, .. } => self.group("", body, "?", out)?,
+            Expr::Repetition { one_needed, .. } => match node.repetition_parts().unwrap() {
+                (repeated, None) => {
+                    self.group("", repeated, if *one_needed { "+" } else { "*" }, out)?;
+                }
+                // `x % s` is written `(x ~ (s ~ x)*)`
+                (repeated, Some(separator)) => {
+                    out.push('(');
+                    self.sequence(repeated, out)?;
+                    out.push_str(" ~ (");
+                    self.node(separator, out)?;
+                    out.push_str(" ~ ");
+                    self.sequence(repeated, out)?;
+                    out.push_str(")*)");
+                    if !one_needed {
+                        out.push('?');
+                    }
+                }
+            },
+            Expr::Group { body, .. } => self.group("", body, "", out)?,
+            Expr::And { body, .. } => self.group("&", body, "", out)?,
+            Expr::Not { body, .. } => self.group("!", body, "", out)?,
+            Expr::Labeled {
+                label, body, span, ..
+            } => {
+                check_pest_name(label, *span)?;
+                self.group(&format!("#{label} = "), body, "", out)?;
+            }
+            Expr::Hidden { span, .. } => {
+                return Err(self.unsupported(*span, "hiding part of a rule"));
+            }
+            Expr::UnparsedOperator { span, .. } | Expr::Rule { span, .. } => {
+                return Err(self.unsupported(*span, "an unparsed node"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes an expression matching what `hir` does, or says why it can't
+fn write_regex(hir: &Hir, out: &mut String) -> Result<(), &'static str> {
+    match hir.kind() {
+        HirKind::Empty => out.push_str("\"\""),
+        HirKind::Literal(literal) => {
+            let text = core::str::from_utf8(&literal.0).map_err(|_| "non-UTF-8 bytes")?;
+            write_string(text, out);
+        }
+        HirKind::Class(Class::Unicode(class)) => {
+            let mut complement = class.clone();
+            complement.negate();
+            if class.ranges().is_empty() {
+                return Err("a class that matches nothing");
+            } else if complement.ranges().is_empty() {
+                out.push_str("ANY");
+            } else if complement.ranges().len() < class.ranges().len() {
+                out.push_str("(!");
+                write_class(&complement, out);
+                out.push_str(" ~ ANY)");
+            } else {
+                write_class(class, out);
+            }
+        }
+        HirKind::Class(Class::Bytes(_)) => return Err("a class of bytes"),
+        HirKind::Look(Look::Start) => out.push_str("SOI"),
+        HirKind::Look(Look::End) => out.push_str("EOI"),
+        HirKind::Look(_) => return Err("a regex assertion"),
+        HirKind::Repetition(repetition) => {
+            if !repetition.greedy {
+                return Err("lazy repetition");
+            }
+            out.push('(');
+            write_regex(&repetition.sub, out)?;
+            out.push(')');
+            match (repetition.min, repetition.max) {
+                (0, None) => out.push('*'),
+                (1, None) => out.push('+'),
+                (0, Some(1)) => out.push('?'),
+                (min, None) => write!(out, "{{{min},}}").unwrap(),
+                (min, Some(max)) if min == max => write!(out, "{{{min}}}").unwrap(),
+                (min, Some(max)) => write!(out, "{{{min}, {max}}}").unwrap(),
+            }
+        }
+        HirKind::Capture(capture) => write_regex(&capture.sub, out)?,
+        HirKind::Concat(subs) | HirKind::Alternation(subs) => {
+            let separator = if matches!(hir.kind(), HirKind::Concat(_)) {
+                " ~ "
+            } else {
+                " | "
+            };
+            out.push('(');
+            for (n, sub) in subs.iter().enumerate() {
+                if n > 0 {
+                    out.push_str(separator);
+                }
+                write_regex(sub, out)?;
+            }
+            out.push(')');
+        }
+    }
+    Ok(())
+}
+
+/// Writes the ranges of `class` as a choice between character ranges
+fn write_class(class: &ClassUnicode, out: &mut String) {
+    let ranges: Vec<_> = class.ranges().iter().collect();
+    if ranges.len() > 1 {
+        out.push('(');
+    }
+    for (n, range) in ranges.iter().enumerate() {
+        if n > 0 {
+            out.push_str(" | ");
+        }
+        write_char(range.start(), out);
+        if range.end() != range.start() {
+            out.push_str("..");
+            write_char(range.end(), out);
+        }
+    }
+    if ranges.len() > 1 {
+        out.push(')');
+    }
+}
+
+fn write_char(c: char, out: &mut String) {
+    out.push('\'');
+    match c {
+        '\'' => out.push_str(r"\'"),
+        '\\' => out.push_str(r"\\"),
+        c if c.is_control() || u32::from(c) > 0xFFFF => {
+            write!(out, r"\u{{{:04X}}}", u32::from(c)).unwrap();
+        }
+        c => out.push(c),
+    }
+    out.push('\'');
+}
+
+/// Writes `s` as a quoted pest string
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            c if c.is_control() => write!(out, r"\u{{{:04X}}}", u32::from(c)).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How [`Grammar::to_lalrpop`] and [`Grammar::to_grmtools`] treat the parts of EBNF that the notation lacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ExportOptions {
+    /// Whether to lower those parts to plain BNF first, as extra rules named after the rule they come from, rather than failing with [`ExportError::NeedsLowering`]. LALRPOP lacks choices inside a sequence, and grmtools also lacks optional parts and repetitions.
+    pub lower: bool,
+}
+
+/// The two files written by [`Grammar::to_grmtools`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GrmtoolsGrammar {
+    /// The `.y` file for `lrpar`, with a production for each alternative and no actions
+    pub grammar: String,
+    /// The `.l` file for `lrlex`, with a token for each distinct literal and regex
+    pub lexer: String,
+}
+
+/// Words LALRPOP doesn't allow as a name
+const LALRPOP_KEYWORDS: &[&str] = &[
+    "else", "enum", "extern", "for", "grammar", "if", "match", "mut", "pub", "type", "use", "where",
+];
+
+/// A symbol of a production once the EBNF around it is lowered
+#[derive(Debug, Clone)]
+enum Symbol<'a> {
+    /// The text of a literal, with its quotes unescaped
+    Literal(String),
+    Regex(&'a str),
+    /// A rule of the grammar, or one made while lowering it
+    Rule(String),
+    /// `symbols` followed by `?`, `*` or `+`, kept for notations that have them
+    Suffixed(Vec<Symbol<'a>>, char),
+}
+
+/// A rule of a BNF grammar, with each alternative as a sequence of symbols
+#[derive(Debug)]
+struct Production<'a> {
+    name: String,
+    alternatives: Vec<Vec<Symbol<'a>>>,
+}
+
+/// Turns each rule of `grammar` into productions, in the order the rules are defined, with any rules made while lowering it right after it. Optional parts and repetitions are kept as [`Symbol::Suffixed`] if `suffixes` is set, and every other name is checked with `valid_name`.
+fn lower<'a>(
+    grammar: &'a Grammar<'_>,
+    options: ExportOptions,
+    suffixes: bool,
+    valid_name: fn(&str) -> bool,
+) -> Result<Vec<Production<'a>>, ExportError<'a>> {
+    if let Some(import) = grammar.imports.first() {
+        return Err(ExportError::Import {
+            path: import.path,
+            span: import.span,
+        });
+    }
+    let mut productions = Vec::new();
+    for rule in grammar.rules_in_definition_order() {
+        if !valid_name(&rule.name) {
+            return Err(ExportError::InvalidName {
+                name: &rule.name,
+                span: rule.definitions.hull().unwrap_or_default(),
+            });
+        }
+        let mut lowerer = Lowerer {
+            grammar,
+            rule: &rule.name,
+            options,
+            suffixes,
+            valid_name,
+            helpers: Vec::new(),
+            count: 0,
+        };
+        let alternatives = match &rule.body[..] {
+            [Expr::Choice { body, .. }] => body
+                .iter()
+                .map(|alternative| lowerer.sequence(slice::from_ref(alternative)))
+                .collect::<Result<_, _>>()?,
+            body => vec![lowerer.sequence(body)?],
+        };
+        productions.push(Production {
+            name: rule.name.to_string(),
+            alternatives,
+        });
+        productions.append(&mut lowerer.helpers);
+    }
+    Ok(productions)
+}
+
+/// Lowers the nodes of the rule called `rule`, collecting the rules it makes in `helpers`
+struct Lowerer<'a> {
+    grammar: &'a Grammar<'a>,
+    rule: &'a str,
+    options: ExportOptions,
+    suffixes: bool,
+    valid_name: fn(&str) -> bool,
+    helpers: Vec<Production<'a>>,
+    count: usize,
+}
+
+impl<'a> Lowerer<'a> {
+    fn sequence(&mut self, nodes: &'a [Expr<'_>]) -> Result<Vec<Symbol<'a>>, ExportError<'a>> {
+        let mut symbols = Vec::new();
+        for node in nodes {
+            self.node(node, &mut symbols)?;
+        }
+        Ok(symbols)
+    }
+
+    fn node(
+        &mut self,
+        node: &'a Expr<'_>,
+        out: &mut Vec<Symbol<'a>>,
+    ) -> Result<(), ExportError<'a>> {
+        match node {
+            // An empty literal matches no text, which a lexer can't produce a token for
+            Expr::Literal { str: "", .. } | Expr::Empty { .. } => {}
+            Expr::Literal { str, .. } => {
+                out.push(Symbol::Literal(
+                    str.replace(r"\'", "'").replace(r#"\""#, "\""),
+                ));
+            }
+            &Expr::Regex { pattern, .. } => out.push(Symbol::Regex(pattern)),
+            &Expr::Nonterminal { name, span } => {
+                if !(self.valid_name)(name) {
+                    return Err(ExportError::InvalidName { name, span });
+                }
+                out.push(Symbol::Rule(name.to_string()));
+            }
+            // Neither notation builds a tree, so there's nothing to hide or label
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                for node in body {
+                    self.node(node, out)?;
+                }
+            }
+            Expr::Optional { body, span } => self.suffixed(body, '?', *span, out)?,
+            &Expr::Repetition {
+                one_needed, span, ..
+            } => {
+                let op = if one_needed { '+' } else { '*' };
+                match node.repetition_parts().unwrap() {
+                    (repeated, None) => self.suffixed(repeated, op, span, out)?,
+                    (repeated, Some(separator)) => {
+                        self.separated(repeated, separator, one_needed, span, out)?;
+                    }
+                }
+            }
+            Expr::Choice { body, span, .. } => {
+                if !self.options.lower {
+                    return Err(self.needs_lowering(*span, "a choice inside a sequence"));
+                }
+                let alternatives = body
+                    .iter()
+                    .map(|alternative| self.sequence(slice::from_ref(alternative)))
+                    .collect::<Result<_, _>>()?;
+                out.push(self.helper("alt", alternatives));
+            }
+            Expr::And { span, .. } | Expr::Not { span, .. } => {
+                return Err(ExportError::Unsupported {
+                    rule: self.rule,
+                    span: *span,
+                    reason: "a lookahead",
+                });
+            }
+            Expr::UnparsedOperator { span, .. } | Expr::Rule { span, .. } => {
+                return Err(ExportError::Unsupported {
+                    rule: self.rule,
+                    span: *span,
+                    reason: "an unparsed node",
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds `body` followed by `op`, or a rule that matches the same, to `out`. Repetitions become left-recursive rules, which LR parsers handle in constant stack space.
+    fn suffixed(
+        &mut self,
+        body: &'a [Expr<'_>],
+        op: char,
+        span: Span,
+        out: &mut Vec<Symbol<'a>>,
+    ) -> Result<(), ExportError<'a>> {
+        if !self.suffixes && !self.options.lower {
+            let reason = if op == '?' {
+                "an optional part"
+            } else {
+                "a repetition"
+            };
+            return Err(self.needs_lowering(span, reason));
+        }
+        let symbols = self.sequence(body)?;
+        self.suffix_symbols(symbols, op, out);
+        Ok(())
+    }
+
+    /// Adds `repeated` with `separator` between each repetition, as `repeated (separator repeated)*`, to `out`, inside an optional part unless `one_needed` is set
+    fn separated(
+        &mut self,
+        repeated: &'a [Expr<'_>],
+        separator: &'a Expr<'_>,
+        one_needed: bool,
+        span: Span,
+        out: &mut Vec<Symbol<'a>>,
+    ) -> Result<(), ExportError<'a>> {
+        if !self.suffixes && !self.options.lower {
+            return Err(self.needs_lowering(span, "a repetition"));
+        }
+        let item = self.sequence(repeated)?;
+        let mut rest = self.sequence(slice::from_ref(separator))?;
+        rest.extend(item.iter().cloned());
+        let mut list = item;
+        self.suffix_symbols(rest, '*', &mut list);
+        if one_needed {
+            out.extend(list);
+        } else {
+            self.suffix_symbols(list, '?', out);
+        }
+        Ok(())
+    }
+
+    /// Adds `symbols` followed by `op` to `out`, as [`Symbol::Suffixed`] or a rule that matches the same
+    fn suffix_symbols(&mut self, symbols: Vec<Symbol<'a>>, op: char, out: &mut Vec<Symbol<'a>>) {
+        if symbols.is_empty() {
+            return;
+        }
+        if self.suffixes {
+            out.push(Symbol::Suffixed(symbols, op));
+            return;
+        }
+        let (kind, first) = match op {
+            '?' => ("opt", Vec::new()),
+            '*' => ("rep", Vec::new()),
+            _ => ("rep", symbols.clone()),
+        };
+        let name = self.fresh_name(kind);
+        let mut rest = if op == '?' {
+            Vec::new()
+        } else {
+            vec![Symbol::Rule(name.clone())]
+        };
+        rest.extend(symbols);
+        self.helpers.push(Production {
+            name: name.clone(),
+            alternatives: vec![first, rest],
+        });
+        out.push(Symbol::Rule(name));
+    }
+
+    /// Makes a rule with `alternatives`, returning a reference to it
+    fn helper(&mut self, kind: &str, alternatives: Vec<Vec<Symbol<'a>>>) -> Symbol<'a> {
+        let name = self.fresh_name(kind);
+        self.helpers.push(Production {
+            name: name.clone(),
+            alternatives,
+        });
+        Symbol::Rule(name)
+    }
+
+    fn fresh_name(&mut self, kind: &str) -> String {
+        loop {
+            self.count += 1;
+            let name = format!("{}_{kind}{}", self.rule, self.count);
+            if !self.grammar.rules.contains_key(name.as_str()) {
+                return name;
+            }
+        }
+    }
+
+    fn needs_lowering(&self, span: Span, reason: &'static str) -> ExportError<'a> {
+        ExportError::NeedsLowering {
+            rule: self.rule,
+            span,
+            reason,
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) fn to_lalrpop<'a>(
+    grammar: &'a Grammar<'_>,
+    options: ExportOptions,
+) -> Result<String, ExportError<'a>> {
+    let productions = lower(grammar, options, true, |name| {
+        is_identifier(name) && !LALRPOP_KEYWORDS.contains(&name)
+    })?;
+    let mut regexes = Vec::new();
+    for production in &productions {
+        for alternative in &production.alternatives {
+            collect_regexes(alternative, &mut regexes);
+        }
+    }
+    let mut out = String::from("grammar;\n");
+    // LALRPOP refuses two regexes that can match the same text unless they're given priorities
+    if regexes.len() > 1 {
+        out.push_str("\nmatch {\n");
+        for pattern in regexes {
+            out.push_str("    ");
+            write_raw_string(pattern, &mut out);
+            out.push_str(",\n} else {\n");
+        }
+        out.push_str("    _\n}\n");
+    }
+    for (n, production) in productions.iter().enumerate() {
+        let visibility = if n == 0 { "pub " } else { "" };
+        writeln!(out, "\n{visibility}{}: () = {{", production.name).unwrap();
+        for alternative in &production.alternatives {
+            out.push_str("    ");
+            write_lalrpop_symbols(alternative, &mut out);
+            if !alternative.is_empty() {
+                out.push(' ');
+            }
+            out.push_str("=> (),\n");
+        }
+        out.push_str("};\n");
+    }
+    Ok(out)
+}
+
+/// Adds each regex in `symbols` that isn't already in `regexes`
+fn collect_regexes<'a>(symbols: &[Symbol<'a>], regexes: &mut Vec<&'a str>) {
+    for symbol in symbols {
+        match symbol {
+            Symbol::Regex(pattern) if !regexes.contains(pattern) => regexes.push(pattern),
+            Symbol::Suffixed(symbols, _) => collect_regexes(symbols, regexes),
+            _ => {}
+        }
+    }
+}
+
+fn write_lalrpop_symbols(symbols: &[Symbol<'_>], out: &mut String) {
+    for (n, symbol) in symbols.iter().enumerate() {
+        if n > 0 {
+            out.push(' ');
+        }
+        match symbol {
+            Symbol::Literal(text) => {
+                out.push('"');
+                for c in text.chars() {
+                    if matches!(c, '"' | '\\') {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+                out.push('"');
+            }
+            Symbol::Regex(pattern) => write_raw_string(pattern, out),
+            Symbol::Rule(name) => out.push_str(name),
+            Symbol::Suffixed(symbols, op) => {
+                if let [symbol] = &symbols[..]
+                    && !matches!(symbol, Symbol::Suffixed(..))
+                {
+                    write_lalrpop_symbols(symbols, out);
+                } else {
+                    out.push('(');
+                    write_lalrpop_symbols(symbols, out);
+                    out.push(')');
+                }
+                out.push(*op);
+            }
+        }
+    }
+}
+
+/// Writes `s` as a raw string, with as many `#`s as it needs
+fn write_raw_string(s: &str, out: &mut String) {
+    let mut hashes = String::new();
+    while s.contains(&format!("\"{hashes}")) {
+        hashes.push('#');
+    }
+    write!(out, "r{hashes}\"{s}\"{hashes}").unwrap();
+}
+
+pub(crate) fn to_grmtools<'a>(
+    grammar: &'a Grammar<'_>,
+    options: ExportOptions,
+) -> Result<GrmtoolsGrammar, ExportError<'a>> {
+    let productions = lower(grammar, options, false, |name| {
+        is_identifier(name) && name != "error"
+    })?;
+    let mut tokens = Tokens::default();
+    // Literals are named by their own text where they can be, so they're taken first, then regexes that make up a whole rule are named after it
+    for production in &productions {
+        for symbol in production.alternatives.iter().flatten() {
+            if let Symbol::Literal(text) = symbol {
+                tokens.literal(text);
+            }
+        }
+    }
+    for production in &productions {
+        if let [alternative] = &production.alternatives[..]
+            && let [Symbol::Regex(pattern)] = &alternative[..]
+        {
+            tokens.regex(pattern, Some(&production.name));
+        }
+    }
+    let start = productions
+        .first()
+        .map_or("", |production| &production.name);
+    let mut grammar = format!("%start {start}\n%%\n");
+    for production in &productions {
+        write!(grammar, "\n{}\n", production.name).unwrap();
+        for (n, alternative) in production.alternatives.iter().enumerate() {
+            grammar.push_str(if n == 0 { "    :" } else { "    |" });
+            for symbol in alternative {
+                let name = match symbol {
+                    Symbol::Literal(text) => tokens.literal(text),
+                    Symbol::Regex(pattern) => tokens.regex(pattern, None),
+                    Symbol::Rule(name) => {
+                        write!(grammar, " {name}").unwrap();
+                        continue;
+                    }
+                    Symbol::Suffixed(..) => unreachable!("only kept when `suffixes` is set"),
+                };
+                write!(grammar, " '{name}'").unwrap();
+            }
+            grammar.push('\n');
+        }
+        grammar.push_str("    ;\n");
+    }
+    // lrlex takes the longest match, and the first rule among those as long, so literals come before regexes
+    let mut lexer = String::from("%%\n");
+    for (text, name) in &tokens.literals {
+        writeln!(lexer, "{} \"{name}\"", regex_syntax::escape(text)).unwrap();
+    }
+    for (pattern, name) in &tokens.regexes {
+        writeln!(lexer, "{pattern} \"{name}\"").unwrap();
+    }
+    Ok(GrmtoolsGrammar { grammar, lexer })
+}
+
+/// The names given to the distinct literals and regexes of a grammar
+#[derive(Default)]
+struct Tokens<'a> {
+    names: BTreeSet<String>,
+    literals: Vec<(String, String)>,
+    regexes: Vec<(&'a str, String)>,
+}
+
+impl<'a> Tokens<'a> {
+    /// The name of the token for the literal `text`, which is the text itself unless it has quotes, backslashes or whitespace
+    fn literal(&mut self, text: &str) -> String {
+        if let Some((_, name)) = self.literals.iter().find(|(t, _)| t == text) {
+            return name.clone();
+        }
+        let plain = !text.contains(|c: char| matches!(c, '\'' | '"' | '\\') || c.is_whitespace());
+        let name = self.fresh_name(plain.then_some(text), "literal");
+        self.literals.push((text.to_string(), name.clone()));
+        name
+    }
+
+    /// The name of the token for the regex `pattern`, which is `preferred` if it hasn't been named yet
+    fn regex(&mut self, pattern: &'a str, preferred: Option<&str>) -> String {
+        if let Some((_, name)) = self.regexes.iter().find(|(p, _)| *p == pattern) {
+            return name.clone();
+        }
+        let name = self.fresh_name(preferred, "regex");
+        self.regexes.push((pattern, name.clone()));
+        name
+    }
+
+    fn fresh_name(&mut self, preferred: Option<&str>, kind: &str) -> String {
+        let mut name = preferred.map(ToString::to_string);
+        let mut count = 0;
+        while name.as_ref().is_none_or(|name| self.names.contains(name)) {
+            count += 1;
+            name = Some(format!("{kind}{count}"));
+        }
+        let name = name.unwrap();
+        self.names.insert(name.clone());
+        name
+    }
+}
+
+pub(crate) fn to_tree_sitter(grammar: &Grammar<'_>) -> String {
+    let mut out = String::new();
+    for import in &grammar.imports {
+        writeln!(
+            out,
+            "// Rules imported from `{}` aren't included",
+            import.path
+        )
+        .unwrap();
+    }
+    out.push_str("module.exports = grammar({\n  name: 'grammar',\n\n  rules: {\n");
+    let writer = TreeSitterWriter { grammar };
+    for rule in grammar.rules_in_definition_order() {
+        out.push_str("    ");
+        writer.name(&rule.name, "", &mut out);
+        out.push_str(": $ => ");
+        writer.sequence(&rule.body, &mut out);
+        out.push_str(",\n");
+    }
+    out.push_str("  },\n});\n");
+    out
+}
+
+/// Writes nodes as calls to tree-sitter's DSL, looking up which rules are hidden in `grammar`
+struct TreeSitterWriter<'g> {
+    grammar: &'g Grammar<'g>,
+}
+
+impl TreeSitterWriter<'_> {
+    /// Writes the name of a rule after `prefix`, with the `_` that hides a hidden rule in tree-sitter
+    fn name(&self, name: &str, prefix: &str, out: &mut String) {
+        let hidden = if self.grammar.rules.get(name).is_some_and(|rule| rule.hidden) {
+            "_"
+        } else {
+            ""
+        };
+        if is_identifier(name) {
+            write!(out, "{prefix}{hidden}{name}").unwrap();
+        } else {
+            // Tree-sitter itself only accepts identifiers, but this keeps the output valid JavaScript
+            out.push_str(prefix.trim_end_matches('.'));
+            if !prefix.is_empty() {
+                out.push('[');
+            }
+            write_js_string(&format!("{hidden}{name}"), out);
+            if !prefix.is_empty() {
+                out.push(']');
+            }
+        }
+    }
+
+    fn sequence(&self, nodes: &[Expr<'_>], out: &mut String) {
+        match nodes {
+            [] => out.push_str("blank()"),
+            [node] => self.node(node, out),
+            nodes => self.call("seq", nodes, out),
+        }
+    }
+
+    /// Writes a call to `function` with each of `nodes` as an argument
+    fn call(&self, function: &str, nodes: &[Expr<'_>], out: &mut String) {
+        write!(out, "{function}(").unwrap();
+        for (n, node) in nodes.iter().enumerate() {
+            if n > 0 {
+                out.push_str(", ");
+            }
+            self.node(node, out);
+        }
+        out.push(')');
+    }
+
+    /// Writes a call to `function` with the sequence `nodes` as its argument
+    fn wrap(&self, function: &str, nodes: &[Expr<'_>], out: &mut String) {
+        write!(out, "{function}(").unwrap();
+        self.sequence(nodes, out);
+        out.push(')');
+    }
+
+    fn node(&self, node: &Expr<'_>, out: &mut String) {
+        match node {
+            // Quotes are the only characters escaped in a literal
+            Expr::Literal { str, .. } => {
+                write_js_string(&str.replace(r"\'", "'").replace(r#"\""#, "\""), out);
+            }
+            Expr::Regex { pattern, .. } => {
+                out.push_str("token(/");
+                let mut escaped = false;
+                for c in pattern.chars() {
+                    if c == '/' && !escaped {
+                        out.push('\\');
+                    }
+                    escaped = c == '\\' && !escaped;
+                    out.push(c);
+                }
+                out.push_str("/)");
+            }
+            Expr::Nonterminal { name, .. } => self.name(name, "$.", out),
+            Expr::Empty { .. } => out.push_str("blank()"),
+            Expr::Choice { body, .. } => self.call("choice", body, out),
+            Expr::Optional { body, .. } => self.wrap("optional", body, out),
+            Expr::Repetition { one_needed, .. } => match node.repetition_parts().unwrap() {
+                (repeated, None) => {
+                    self.wrap(
+                        if *one_needed { "repeat1" } else { "repeat" },
+                        repeated,
+                        out,
+                    );
+                }
+                // `x % s` is written `seq(x, repeat(seq(s, x)))`
+                (repeated, Some(separator)) => {
+                    if !one_needed {
+                        out.push_str("optional(");
+                    }
+                    out.push_str("seq(");
+                    self.sequence(repeated, out);
+                    out.push_str(", repeat(seq(");
+                    self.node(separator, out);
+                    out.push_str(", ");
+                    self.sequence(repeated, out);
+                    out.push_str(")))");
+                    if !one_needed {
+                        out.push(')');
+                    }
+                }
+            },
+            // Tree-sitter can only hide whole rules, and the parts of a rule are anonymous anyway
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } => self.sequence(body, out),
+            Expr::Labeled { label, body, .. } => {
+                out.push_str("field(");
+                write_js_string(label, out);
+                out.push_str(", ");
+                self.sequence(body, out);
+                out.push(')');
+            }
+            Expr::And { .. } | Expr::Not { .. } => out.push_str("/* lookahead */ blank()"),
+            Expr::UnparsedOperator { .. } | Expr::Rule { .. } => {
+                out.push_str("/* unparsed */ blank()");
+            }
+        }
+    }
+}
+
+/// Writes `s` as a single-quoted JavaScript string
+fn write_js_string(s: &str, out: &mut String) {
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str(r"\'"),
+            '\\' => out.push_str(r"\\"),
+            c if c.is_control() => write!(out, r"\u{{{:X}}}", u32::from(c)).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ExportError, ExportOptions, Grammar};
+
+    #[test]
+    fn to_pest() {
+        let g = Grammar::new(
+            r#"
+            list = '[' [item (',' item)*] ']';
+            item = !'-' value:(number | string);
+            <number> = #'-?[0-9]+(\.[0-9]{1,3})?';
+            string = '"' #'[^"\\]*' '"' | "it's";
+            "#,
+        )
+        .unwrap();
+        insta::assert_snapshot!(g.to_pest().unwrap(), @r#"
+        list = { "[" ~ (item ~ ("," ~ item)*)? ~ "]" }
+        item = { !("-") ~ #value = ((number | string)) }
+        number = _{ ((("-")? ~ ('0'..'9')+ ~ (("." ~ ('0'..'9'){1, 3}))?)) }
+        string = { "\"" ~ (((!('"' | '\\') ~ ANY))*) ~ ("\"" | "it's") }
+        "#);
+
+        let separated = Grammar::new("args = '(' arg* % ',' ')'; arg = #'[a-z]+' % '.';").unwrap();
+        insta::assert_snapshot!(separated.to_pest().unwrap(), @r#"
+        args = { "(" ~ (arg ~ ("," ~ arg)*)? ~ ")" }
+        arg = { ((('a'..'z')+) ~ ("." ~ (('a'..'z')+))*) }
+        "#);
+
+        let hidden = Grammar::new("a = 'x' <'y'>;").unwrap();
+        assert!(matches!(
+            hidden.to_pest(),
+            Err(ExportError::Unsupported {
+                rule: "a",
+                reason: "hiding part of a rule",
+                ..
+            })
+        ));
+        let boundary = Grammar::new(r"a = #'\bx+?';").unwrap();
+        assert!(matches!(
+            boundary.to_pest(),
+            Err(ExportError::Unsupported {
+                reason: "a regex assertion",
+                ..
+            })
+        ));
+        let builtin = Grammar::new("a = ANY;").unwrap();
+        assert!(matches!(
+            builtin.to_pest(),
+            Err(ExportError::InvalidName { name: "ANY", .. })
+        ));
+        let import = Grammar::new("%import 'base.ebnf'; a = b;").unwrap();
+        assert!(matches!(
+            import.to_pest(),
+            Err(ExportError::Import {
+                path: "base.ebnf",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn to_lalrpop() {
+        let lower = ExportOptions { lower: true };
+        let g = Grammar::new(
+            r#"
+            expr = term (('+' | '-') term)*;
+            term = #'[0-9]+' | #'[a-z]+' | ('(' expr ')') | '"';
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            g.to_lalrpop(ExportOptions::default()),
+            Err(ExportError::NeedsLowering {
+                rule: "expr",
+                reason: "a choice inside a sequence",
+                ..
+            })
+        ));
+        insta::assert_snapshot!(g.to_lalrpop(lower).unwrap(), @r##"
+        grammar;
+
+        match {
+            r"[0-9]+",
+        } else {
+            r"[a-z]+",
+        } else {
+            _
+        }
+
+        pub expr: () = {
+            term (expr_alt1 term)* => (),
+        };
+
+        expr_alt1: () = {
+            "+" => (),
+            "-" => (),
+        };
+
+        term: () = {
+            r"[0-9]+" => (),
+            r"[a-z]+" => (),
+            "(" expr ")" => (),
+            "\"" => (),
+        };
+        "##);
+
+        let keyword = Grammar::new("match = 'x';").unwrap();
+        assert!(matches!(
+            keyword.to_lalrpop(lower),
+            Err(ExportError::InvalidName { name: "match", .. })
+        ));
+        let lookahead = Grammar::new("a = !'x' #'.';").unwrap();
+        assert!(matches!(
+            lookahead.to_lalrpop(lower),
+            Err(ExportError::Unsupported {
+                reason: "a lookahead",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn to_grmtools() {
+        let g = Grammar::new(
+            r#"
+            list = '[' [item (',' item)+] ']';
+            item = num | "it's" | "a b" | #'[a-z]+';
+            num = #'[0-9]+';
+            extra = 'literal1';
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            g.to_grmtools(ExportOptions::default()),
+            Err(ExportError::NeedsLowering {
+                rule: "list",
+                reason: "an optional part",
+                ..
+            })
+        ));
+        let files = g.to_grmtools(ExportOptions { lower: true }).unwrap();
+        insta::assert_snapshot!(files.grammar, @r"
+        %start list
+        %%
+
+        list
+            : '[' list_opt2 ']'
+            ;
+
+        list_rep1
+            : ',' item
+            | list_rep1 ',' item
+            ;
+
+        list_opt2
+            :
+            | item list_rep1
+            ;
+
+        item
+            : num
+            | 'literal1'
+            | 'literal2'
+            | 'regex1'
+            ;
+
+        num
+            : 'num'
+            ;
+
+        extra
+            : 'literal3'
+            ;
+        ");
+        insta::assert_snapshot!(files.lexer, @r#"
+        %%
+        \[ "["
+        \] "]"
+        , ","
+        it's "literal1"
+        a b "literal2"
+        literal1 "literal3"
+        [0-9]+ "num"
+        [a-z]+ "regex1"
+        "#);
+    }
+
+    #[test]
+    fn to_tree_sitter() {
+        let g = Grammar::new(
+            r#"
+            %import 'base.ebnf';
+            list = '[' [item (',' item)*] ']';
+            item = value:(number | string) | (&'-' number);
+            <number> = #'-?[0-9]+(/[0-9]+)?';
+            string = ("'" #'[a-z\\]*' "'") | '';
+            "#,
+        )
+        .unwrap();
+        insta::assert_snapshot!(g.to_tree_sitter(), @r"
+        // Rules imported from `base.ebnf` aren't included
+        module.exports = grammar({
+          name: 'grammar',
+
+          rules: {
+            list: $ => seq('[', optional(seq($.item, repeat(seq(',', $.item)))), ']'),
+            item: $ => choice(field('value', choice($._number, $.string)), seq(/* lookahead */ blank(), $._number)),
+            _number: $ => token(/-?[0-9]+(\/[0-9]+)?/),
+            string: $ => choice(seq('\'', token(/[a-z\\]*/), '\''), blank()),
+          },
+        });
+        ");
+
+        let separated = Grammar::new("args = '(' arg* % ',' ')'; arg = #'[a-z]+' % '.';").unwrap();
+        let js = separated.to_tree_sitter();
+        assert!(
+            js.contains("args: $ => seq('(', optional(seq($.arg, repeat(seq(',', $.arg)))), ')'),"),
+            "{js}"
+        );
+        assert!(
+            js.contains("arg: $ => seq(token(/[a-z]+/), repeat(seq('.', token(/[a-z]+/)))),"),
+            "{js}"
+        );
+    }
+}
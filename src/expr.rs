@@ -1,15 +1,16 @@
 use crate::{
-    EbnfError, FailureReason, Rule,
-    parser::LrStack,
+    EbnfError, FailureReason, Grammar, ParseOptions, Rule,
+    limits::check_token_limits,
+    parser::{HIDDEN_NAME, LrStack, PREDICATE_NAME},
     simplification::simplify_node,
-    token_data::{Span, tokenize},
+    token_data::{Span, TokenPayload, tokenize},
 };
-use std::fmt::Display;
-use strum::{EnumDiscriminants, EnumProperty, IntoStaticStr, VariantNames};
+use alloc::{vec, vec::Vec};
+use core::fmt::Display;
+use strum::{EnumProperty, IntoStaticStr, VariantNames};
 
 /// A node in the syntax tree of a EBNF rule
-#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumDiscriminants)]
-#[strum_discriminants(name(ExprKind), derive(VariantNames, IntoStaticStr))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Expr<'a> {
     /// A terminal - a string, a whole string and nothing but the string
@@ -32,6 +33,8 @@ pub enum Expr<'a> {
         span: Span,
         #[expect(missing_docs, reason = "Obvious")]
         body: Vec<Expr<'a>>,
+        /// Whether the alternatives were separated by `/` rather than `|`, which in PEG-style grammars means the first alternative that matches is taken, rather than any of them. See [`ChoiceSemantics`](crate::ChoiceSemantics).
+        ordered: bool,
     },
     /// Either the entire sequence of child nodes in order, or nothing
     Optional {
@@ -41,6 +44,8 @@ pub enum Expr<'a> {
         body: Vec<Expr<'a>>,
     },
     /// The child nodes, in sequence, repeated any number of times, possibly including zero.
+    ///
+    /// If `separated` is set, the last child node is instead a separator, matched between each repetition of the others but not before the first or after the last, as in `item* % ','`. See [`Expr::repetition_parts`].
     Repetition {
         #[expect(missing_docs, reason = "Obvious")]
         span: Span,
@@ -48,6 +53,8 @@ pub enum Expr<'a> {
         body: Vec<Expr<'a>>,
         /// If at least one repetition is needed or none
         one_needed: bool,
+        /// Whether the last node of `body` is a separator rather than part of what is repeated
+        separated: bool,
     },
     /// A regular expression on the input string.
     ///
@@ -65,6 +72,41 @@ pub enum Expr<'a> {
         #[expect(missing_docs, reason = "Obvious")]
         body: Vec<Expr<'a>>,
     },
+    /// Succeeds without consuming any input if the sequence of child nodes would match at this point, and fails otherwise. Written `&x`, as in instaparse.
+    And {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        body: Vec<Expr<'a>>,
+    },
+    /// Succeeds without consuming any input if the sequence of child nodes would *not* match at this point, and fails otherwise. Written `!x`, as in instaparse.
+    Not {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        body: Vec<Expr<'a>>,
+    },
+    /// The child nodes, in order, exactly once, but left out of the tree produced by parsing input against the grammar. Written `<x>`, as in instaparse.
+    Hidden {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        body: Vec<Expr<'a>>,
+    },
+    /// The child nodes, in order, exactly once, given a name for code generators to use for the field of the AST that holds what they match. Written `name:x`, where the label applies to a single term along with any postfix operator after it, so `items:item*` labels the whole repetition.
+    Labeled {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// The name given to the child nodes, which follows the same rules as the name of a nonterminal
+        label: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        body: Vec<Expr<'a>>,
+    },
+    /// Matches the empty string without consuming any input. Written `ε`, `''` or `""`, or by leaving an alternative out entirely, as in `A = B | ;`.
+    Empty {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+    },
     #[doc(hidden)]
     UnparsedOperator { span: Span, op: Operator },
     /// An entire EBNF rule. This is not the same type as [`Rule`] and user code should use the latter, but it's included as part of `Expr` for internal reasons.
@@ -82,7 +124,40 @@ impl<'a> Expr<'a> {
     /// # Errors
     /// If the input string is ill-formed, an [`EbnfError`] is returned. See that type for possible reasons why.
     pub fn new(input: &'a str) -> Result<Self, EbnfError<'a>> {
+        Expr::parse(input, true)
+    }
+
+    /// Parses a string like [`Expr::new`], but without any of the [reductions](crate#reductions), so every pair of brackets is kept as a [`Group`](Expr::Group) and a run of choices such as `a | b | c` is kept as a chain of two-way [`Choice`](Expr::Choice)s, as the parser built them. This is for tools that care how the input was written, such as ones looking for redundant brackets.
+    ///
+    /// # Errors
+    /// As for [`Expr::new`]. Since each choice in a run is kept inside the next, a run of more than [`ParseOptions::DEFAULT_MAX_DEPTH`] alternatives goes over the [nesting limit](ParseOptions::max_depth).
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let raw = Expr::new_raw("(a | b) | ((c))").unwrap();
+    /// assert_eq!(raw.node_count(), 8);
+    /// assert_eq!(Expr::new("(a | b) | ((c))").unwrap().node_count(), 4);
+    /// ```
+    pub fn new_raw(input: &'a str) -> Result<Self, EbnfError<'a>> {
+        Expr::parse(input, false)
+    }
+
+    fn parse(input: &'a str, simplify: bool) -> Result<Self, EbnfError<'a>> {
         let tokens = tokenize(input)?;
+        // Only `Rule::new` and `Grammar::new` have anywhere to put an annotation
+        if let Some(annotation) = tokens
+            .iter()
+            .find(|t| matches!(t.payload, TokenPayload::Annotation(_)))
+        {
+            return Err(EbnfError::LexError {
+                input,
+                offset: annotation.span.start(),
+            });
+        }
+        let options = ParseOptions {
+            simplify,
+            ..ParseOptions::default()
+        };
+        check_token_limits(input, &tokens, options)?;
         let mut stack = LrStack::new();
         for token in tokens {
             stack.push_token(token);
@@ -90,13 +165,15 @@ impl<'a> Expr<'a> {
         }
         let token_stack = stack.into_parse_stack();
         if token_stack.len() == 1
-            && token_stack.first().map(ExprKind::from) != Some(ExprKind::UnparsedOperator)
+            && token_stack.first().map(NodeKind::from) != Some(NodeKind::UnparsedOperator)
         {
             let mut expr = token_stack
                 .into_iter()
                 .next()
                 .unwrap_or_else(|| unreachable!());
-            simplify_node(&mut expr);
+            if simplify {
+                simplify_node(&mut expr);
+            }
             Ok(expr)
         } else {
             Err(EbnfError::ParseError {
@@ -107,6 +184,11 @@ impl<'a> Expr<'a> {
         }
     }
 
+    /// What kind of node this is, without any of its contents
+    pub fn kind(&self) -> NodeKind {
+        NodeKind::from(self)
+    }
+
     /// The [`Span`] of the input this node and all of its children represent
     pub fn span(&self) -> Span {
         match self {
@@ -117,11 +199,63 @@ impl<'a> Expr<'a> {
             | Expr::Repetition { span, .. }
             | Expr::Regex { span, .. }
             | Expr::Group { span, .. }
+            | Expr::And { span, .. }
+            | Expr::Not { span, .. }
+            | Expr::Hidden { span, .. }
+            | Expr::Labeled { span, .. }
+            | Expr::Empty { span }
             | Expr::UnparsedOperator { span, .. }
             | Expr::Rule { span, .. } => *span,
         }
     }
 
+    /// Replaces the span of this node and every node within it with the result of calling `f` on it. This can be used to move the spans to match an edited input, for instance.
+    /// ```rust
+    /// # use ebnf_toolkit::{Expr, Span};
+    /// let mut expr = Expr::new("('a' | b)").unwrap();
+    /// expr.map_spans(|s| {
+    ///     let ((start_line, start_col), (end_line, end_col)) = (s.start_line(), s.end_line());
+    ///     Span::new(s.start() + 10..s.end() + 10, (start_line + 1, start_col), (end_line + 1, end_col))
+    /// });
+    /// assert_eq!(expr.span().range(), 11..18);
+    /// assert_eq!(expr.span().start_line(), (2, 1));
+    /// ```
+    pub fn map_spans(&mut self, mut f: impl FnMut(Span) -> Span) {
+        self.map_spans_with(&mut f);
+    }
+
+    pub(crate) fn map_spans_with(&mut self, f: &mut impl FnMut(Span) -> Span) {
+        match self {
+            Expr::Literal { span, .. }
+            | Expr::Nonterminal { span, .. }
+            | Expr::Regex { span, .. }
+            | Expr::Empty { span }
+            | Expr::UnparsedOperator { span, .. } => *span = f(*span),
+            Expr::Choice { span, body, .. }
+            | Expr::Optional { span, body }
+            | Expr::Repetition { span, body, .. }
+            | Expr::Group { span, body }
+            | Expr::And { span, body }
+            | Expr::Not { span, body }
+            | Expr::Hidden { span, body }
+            | Expr::Labeled { span, body, .. } => {
+                *span = f(*span);
+                for node in body {
+                    node.map_spans_with(f);
+                }
+            }
+            Expr::Rule { span, rule } => {
+                *span = f(*span);
+                rule.map_spans_with(f);
+            }
+        }
+    }
+
+    /// Sets the span of this node and every node within it to [`Span::default`], so that nodes built from different inputs compare equal if they have the same structure. This is handy for snapshot tests, while [`IgnoringSpans`](crate::IgnoringSpans) can compare nodes without changing them.
+    pub fn strip_spans(&mut self) {
+        self.map_spans(|_| Span::default());
+    }
+
     #[must_use]
     /// Substitutes the body of the given rule into this expression whenever its name appears.
     /// The substitution is only performed once, so a recursive rule will not cause infinite loops.
@@ -140,15 +274,15 @@ impl<'a> Expr<'a> {
     pub fn substitute(&self, rule: &'a Rule<'a>) -> Expr<'a> {
         let mut new = self.clone();
         new.apply_replacement(&mut |n| {
-            if let &Expr::Nonterminal { name, .. } = n
+            if let Expr::Nonterminal { name, .. } = n
                 && name == rule.name
             {
-                Some(Expr::Group {
+                Expr::Group {
                     body: rule.body.clone(),
                     span: Span::union(rule.body.iter()),
-                })
+                }
             } else {
-                None
+                n
             }
         });
         simplify_node(&mut new);
@@ -158,63 +292,262 @@ impl<'a> Expr<'a> {
     pub(crate) fn contains_nonterminal(&self) -> bool {
         match self {
             Expr::Nonterminal { .. } => true,
-            Expr::Literal { .. } | Expr::Regex { .. } | Expr::UnparsedOperator { .. } => false,
-            Expr::Choice { body, .. }
-            | Expr::Optional { body, .. }
-            | Expr::Repetition { body, .. }
-            | Expr::Group { body, .. } => body.iter().any(Expr::contains_nonterminal),
-            Expr::Rule { rule, .. } => rule.body.iter().any(Expr::contains_nonterminal),
+            _ => self.children().iter().any(Expr::contains_nonterminal),
         }
     }
 
     pub(crate) fn node_pattern_code(&self) -> &'static str {
-        if let Expr::UnparsedOperator { op, .. } = self {
-            op.get_str("repr").unwrap()
-        } else {
-            let name: &str = ExprKind::from(self).into();
-            &name[..1]
+        match self {
+            Expr::UnparsedOperator { op, .. } => op.get_str("repr").unwrap(),
+            Expr::Hidden { body, .. } if matches!(body[..], [Expr::Nonterminal { .. }]) => {
+                HIDDEN_NAME
+            }
+            Expr::And { body, .. } | Expr::Not { body, .. }
+                if matches!(body[..], [Expr::Nonterminal { .. }]) =>
+            {
+                PREDICATE_NAME
+            }
+            _ => NodeKind::from(self).pattern_code(),
         }
     }
 
-    pub(crate) fn apply_replacement(
-        &mut self,
-        func: &mut impl for<'b> FnMut(&Expr<'a>) -> Option<Expr<'a>>,
-    ) {
+    /// The nodes directly inside this one, in order, which for a [`Rule`](Expr::Rule) is the rule's body. A leaf has none, but so can other nodes built by hand.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let expr = Expr::new("('a' | b)*").unwrap();
+    /// let [choice] = expr.children() else { panic!() };
+    /// assert_eq!(choice.children().len(), 2);
+    /// assert!(choice.children().iter().all(Expr::is_leaf));
+    /// ```
+    pub fn children(&self) -> &[Expr<'a>] {
+        match self {
+            Expr::Rule {
+                rule: Rule { body, .. },
+                ..
+            }
+            | Expr::Choice { body, .. }
+            | Expr::Optional { body, .. }
+            | Expr::Repetition { body, .. }
+            | Expr::Group { body, .. }
+            | Expr::And { body, .. }
+            | Expr::Not { body, .. }
+            | Expr::Hidden { body, .. }
+            | Expr::Labeled { body, .. } => body,
+            Expr::Regex { .. }
+            | Expr::Empty { .. }
+            | Expr::UnparsedOperator { .. }
+            | Expr::Literal { .. }
+            | Expr::Nonterminal { .. } => &[],
+        }
+    }
+
+    /// The nodes directly inside this one, as for [`Expr::children`], for changing in place. The node's own span is left as it is.
+    pub fn children_mut(&mut self) -> &mut [Expr<'a>] {
+        self.span_and_body_mut().map_or(&mut [], |(_, body)| body)
+    }
+
+    /// Whether this is a kind of node that never has children: a literal, regex, nonterminal or [`Empty`](Expr::Empty)
+    pub fn is_leaf(&self) -> bool {
+        matches!(
+            self,
+            Expr::Literal { .. }
+                | Expr::Nonterminal { .. }
+                | Expr::Regex { .. }
+                | Expr::Empty { .. }
+                | Expr::UnparsedOperator { .. }
+        )
+    }
+
+    /// The span and body of a node that can have children
+    pub(crate) fn span_and_body_mut(&mut self) -> Option<(&mut Span, &mut Vec<Expr<'a>>)> {
         match self {
             Expr::Rule {
                 span,
                 rule: Rule { body, .. },
             }
-            | Expr::Choice { span, body }
+            | Expr::Choice { span, body, .. }
             | Expr::Optional { span, body }
             | Expr::Repetition { span, body, .. }
-            | Expr::Group { span, body } => {
-                for n in body.iter_mut() {
-                    if let Some(new) = func(n) {
-                        *n = new;
-                    }
-                    n.apply_replacement(func);
-                }
-                *span = Span::union(body.iter());
-            }
-
+            | Expr::Group { span, body }
+            | Expr::And { span, body }
+            | Expr::Not { span, body }
+            | Expr::Hidden { span, body }
+            | Expr::Labeled { span, body, .. } => Some((span, body)),
             Expr::Regex { .. }
+            | Expr::Empty { .. }
             | Expr::UnparsedOperator { .. }
             | Expr::Literal { .. }
-            | Expr::Nonterminal { .. } => { /* no children, do nothing */ }
+            | Expr::Nonterminal { .. } => None,
         }
-        let res = func(self);
-        if let Some(res) = res {
-            *self = res;
+    }
+
+    /// For a [`Repetition`](Expr::Repetition), the nodes that are repeated, and the separator matched between repetitions if it has one. Returns `None` for any other kind of node.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let list = Expr::new("(item ':' value)* % ','").unwrap();
+    /// let (repeated, separator) = list.repetition_parts().unwrap();
+    /// assert_eq!(repeated.len(), 3);
+    /// assert!(matches!(separator, Some(Expr::Literal { str: ",", .. })));
+    /// assert_eq!(Expr::new("item+").unwrap().repetition_parts().unwrap().1, None);
+    /// ```
+    pub fn repetition_parts(&self) -> Option<(&[Expr<'a>], Option<&Expr<'a>>)> {
+        match self {
+            Expr::Repetition {
+                body,
+                separated: true,
+                ..
+            } if body.len() > 1 => {
+                let (separator, repeated) = body.split_last()?;
+                Some((repeated, Some(separator)))
+            }
+            Expr::Repetition { body, .. } => Some((body, None)),
+            _ => None,
+        }
+    }
+
+    /// Writes a [`Repetition`](Expr::Repetition) with a separator in plain EBNF, for tools that don't understand `%`: `x % s` becomes `(x (s x)*)` and `x* % s` becomes `[x (s x)*]`. Returns `None` for any other node. The copies of `x` and `s` keep their spans, and the nodes added take the span of the whole repetition.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let list = Expr::new("#'[0-9]+'* % ','").unwrap();
+    /// let lowered = list.lower_separator().unwrap();
+    /// assert!(lowered.eq_ignoring_spans(&Expr::new("[#'[0-9]+' (',' #'[0-9]+')*]").unwrap()));
+    /// assert!(Expr::new("x*").unwrap().lower_separator().is_none());
+    /// ```
+    pub fn lower_separator(&self) -> Option<Expr<'a>> {
+        let &Expr::Repetition {
+            span, one_needed, ..
+        } = self
+        else {
+            return None;
+        };
+        let (repeated, separator) = self.repetition_parts()?;
+        let separator = separator?;
+        let mut again = vec![separator.clone()];
+        again.extend_from_slice(repeated);
+        let mut body = repeated.to_vec();
+        body.push(Expr::Repetition {
+            span,
+            body: again,
+            one_needed: false,
+            separated: false,
+        });
+        Some(if one_needed {
+            Expr::Group { span, body }
+        } else {
+            Expr::Optional { span, body }
+        })
+    }
+
+    /// How many levels of nodes this one contains, counting itself, so a leaf has a depth of 1. This is found without recursion, so it is safe to call on any tree, but displaying, cloning, comparing or dropping a tree still recurse once per level, so a tree much deeper than [`ParseOptions::DEFAULT_MAX_DEPTH`] may overflow the stack during them.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// assert_eq!(Expr::new("'a'").unwrap().depth(), 1);
+    /// assert_eq!(Expr::new("('a' | !b)*").unwrap().depth(), 4);
+    /// ```
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            stack.extend(node.children().iter().map(|child| (child, depth + 1)));
+        }
+        deepest
+    }
+
+    /// How many nodes this one contains, counting itself, so a leaf has a count of 1. Unlike [`Expr::depth`], this recurses once per level rather than keeping a stack of its own, so it never allocates but is only as safe on very deep trees as dropping them is.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// assert_eq!(Expr::new("'a'").unwrap().node_count(), 1);
+    /// assert_eq!(Expr::new("('a' | !b)*").unwrap().node_count(), 5);
+    /// ```
+    pub fn node_count(&self) -> usize {
+        1 + self.children().iter().map(Expr::node_count).sum::<usize>()
+    }
+
+    /// How many points within this node a parser has to decide between ways to go on: one for each alternative of a [`Choice`](Expr::Choice) beyond the first, and one for each [`Optional`](Expr::Optional) or [`Repetition`](Expr::Repetition). Recurses like [`Expr::node_count`], without allocating.
+    pub(crate) fn decision_count(&self) -> usize {
+        let own = match self {
+            Expr::Choice { body, .. } => body.len().saturating_sub(1),
+            Expr::Optional { .. } | Expr::Repetition { .. } => 1,
+            _ => 0,
+        };
+        own + self
+            .children()
+            .iter()
+            .map(Expr::decision_count)
+            .sum::<usize>()
+    }
+
+    /// Rebuilds the tree from the bottom up, passing each node through `func` once its children have been. Each child is also passed through `func` before its own children are visited, so a replacement can change what gets visited below it. Spans of nodes with children are recalculated from the new children.
+    ///
+    /// This keeps its own stack of the nodes being visited rather than recursing, so it doesn't overflow on deeply nested trees.
+    pub(crate) fn apply_replacement(&mut self, func: &mut impl FnMut(Expr<'a>) -> Expr<'a>) {
+        struct Frame<'a> {
+            node: Expr<'a>,
+            remaining: vec::IntoIter<Expr<'a>>,
+            done: Vec<Expr<'a>>,
+        }
+        fn enter(mut node: Expr<'_>) -> Frame<'_> {
+            let children = node
+                .span_and_body_mut()
+                .map(|(_, body)| core::mem::take(body))
+                .unwrap_or_default();
+            Frame {
+                node,
+                done: Vec::with_capacity(children.len()),
+                remaining: children.into_iter(),
+            }
+        }
+
+        let placeholder = Expr::Literal {
+            span: Span::default(),
+            str: "",
+        };
+        let mut stack = vec![enter(core::mem::replace(self, placeholder))];
+        while let Some(frame) = stack.last_mut() {
+            if let Some(child) = frame.remaining.next() {
+                stack.push(enter(func(child)));
+                continue;
+            }
+            let Frame { mut node, done, .. } = stack.pop().unwrap();
+            if let Some((span, body)) = node.span_and_body_mut() {
+                *body = done;
+                *span = Span::union(body.iter());
+            }
+            let node = func(node);
+            match stack.last_mut() {
+                Some(parent) => parent.done.push(node),
+                None => *self = node,
+            }
         }
     }
 }
 
+pub(crate) fn lower_separated_repetitions(grammar: &mut Grammar<'_>) -> Vec<Span> {
+    fn lower(node: &mut Expr<'_>, lowered: &mut Vec<Span>) {
+        for child in node.children_mut() {
+            lower(child, lowered);
+        }
+        if let Some(replacement) = node.lower_separator() {
+            lowered.push(node.span());
+            *node = replacement;
+        }
+    }
+    let mut lowered = Vec::new();
+    for rule in grammar.rules.values_mut() {
+        for node in &mut rule.body {
+            lower(node, &mut lowered);
+        }
+    }
+    lowered.sort_unstable();
+    lowered
+}
+
 fn write_slice(
-    f: &mut std::fmt::Formatter<'_>,
+    f: &mut core::fmt::Formatter<'_>,
     slice: &[Expr<'_>],
     sep: &'static str,
-) -> std::fmt::Result {
+) -> core::fmt::Result {
     write!(f, "(")?;
     for (ind, child) in slice.iter().enumerate() {
         if ind > 0 {
@@ -225,24 +558,138 @@ fn write_slice(
     write!(f, ")")
 }
 
+/// The kind of an [`Expr`] node, from [`Expr::kind`], for classifying or counting nodes without matching on every variant of `Expr`.
+///
+/// The name of a kind is available through `&'static str: From<NodeKind>`, and its number through [`NodeKind::stable_code`].
+/// ```rust
+/// # use std::collections::BTreeMap;
+/// # use ebnf_toolkit::{Expr, NodeKind};
+/// let expr = Expr::new("('a' | b | c)*").unwrap();
+/// let Expr::Repetition { body, .. } = &expr else { panic!() };
+/// let Expr::Choice { body, .. } = &body[0] else { panic!() };
+///
+/// let mut counts = BTreeMap::new();
+/// for node in body {
+///     *counts.entry(node.kind()).or_insert(0) += 1;
+/// }
+/// assert_eq!(counts[&NodeKind::Nonterminal], 2);
+/// assert_eq!(<&str>::from(expr.kind()), "Repetition");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, VariantNames, IntoStaticStr)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum NodeKind {
+    /// [`Expr::Literal`]
+    Literal = 0,
+    /// [`Expr::Nonterminal`]
+    Nonterminal = 1,
+    /// [`Expr::Choice`]
+    Choice = 2,
+    /// [`Expr::Optional`]
+    Optional = 3,
+    /// [`Expr::Repetition`]
+    Repetition = 4,
+    /// [`Expr::Regex`]
+    Regex = 5,
+    /// [`Expr::Group`]
+    Group = 6,
+    #[doc(hidden)]
+    UnparsedOperator = 7,
+    /// [`Expr::Rule`]
+    Rule = 8,
+    /// [`Expr::And`]
+    And = 9,
+    /// [`Expr::Not`]
+    Not = 10,
+    /// [`Expr::Hidden`]
+    Hidden = 11,
+    /// [`Expr::Labeled`]
+    Labeled = 12,
+    /// [`Expr::Empty`]
+    Empty = 13,
+}
+
+impl From<&Expr<'_>> for NodeKind {
+    fn from(node: &Expr<'_>) -> Self {
+        match node {
+            Expr::Literal { .. } => NodeKind::Literal,
+            Expr::Nonterminal { .. } => NodeKind::Nonterminal,
+            Expr::Choice { .. } => NodeKind::Choice,
+            Expr::Optional { .. } => NodeKind::Optional,
+            Expr::Repetition { .. } => NodeKind::Repetition,
+            Expr::Regex { .. } => NodeKind::Regex,
+            Expr::Group { .. } => NodeKind::Group,
+            Expr::UnparsedOperator { .. } => NodeKind::UnparsedOperator,
+            Expr::Rule { .. } => NodeKind::Rule,
+            Expr::And { .. } => NodeKind::And,
+            Expr::Not { .. } => NodeKind::Not,
+            Expr::Hidden { .. } => NodeKind::Hidden,
+            Expr::Labeled { .. } => NodeKind::Labeled,
+            Expr::Empty { .. } => NodeKind::Empty,
+        }
+    }
+}
+
+impl NodeKind {
+    /// The character standing for this kind of node in the parser's reduction patterns
+    pub(crate) fn pattern_code(self) -> &'static str {
+        // The rule pattern needs nonterminals to be told apart from everything else, and labels from literals
+        match self {
+            NodeKind::Not => return "n",
+            NodeKind::Labeled => return "l",
+            _ => {}
+        }
+        let name: &str = self.into();
+        &name[..1]
+    }
+
+    /// The number standing for this kind of node, which is also its discriminant. Unlike the order of the variants, these are never reassigned, so that data written by one version of the crate can be read by later ones.
+    pub const fn stable_code(self) -> u8 {
+        self as u8
+    }
+
+    /// The inverse of [`NodeKind::stable_code`], or `None` if `code` doesn't stand for any kind of node
+    pub const fn from_stable_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(NodeKind::Literal),
+            1 => Some(NodeKind::Nonterminal),
+            2 => Some(NodeKind::Choice),
+            3 => Some(NodeKind::Optional),
+            4 => Some(NodeKind::Repetition),
+            5 => Some(NodeKind::Regex),
+            6 => Some(NodeKind::Group),
+            7 => Some(NodeKind::UnparsedOperator),
+            8 => Some(NodeKind::Rule),
+            9 => Some(NodeKind::And),
+            10 => Some(NodeKind::Not),
+            11 => Some(NodeKind::Hidden),
+            12 => Some(NodeKind::Labeled),
+            13 => Some(NodeKind::Empty),
+            _ => None,
+        }
+    }
+}
+
 impl Display for Expr<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Expr::Nonterminal { name: str, .. } => write!(f, "{str}")?,
             Expr::Literal { str, .. } => write!(f, "\"{str}\"")?,
 
-            Expr::Repetition {
-                body, one_needed, ..
-            } => {
+            Expr::Repetition { one_needed, .. } => {
+                let (repeated, separator) = self.repetition_parts().unwrap();
                 if *one_needed {
                     write!(f, "{{")?;
-                    write_slice(f, body, " ")?;
+                    write_slice(f, repeated, " ")?;
                     write!(f, "}}")?;
                 } else {
                     write!(f, "(")?;
-                    write_slice(f, body, " ")?;
+                    write_slice(f, repeated, " ")?;
                     write!(f, ")*")?;
                 }
+                if let Some(separator) = separator {
+                    write!(f, " % ({separator})")?;
+                }
             }
             Expr::Optional { body, .. } => {
                 write!(f, "[")?;
@@ -251,21 +698,30 @@ impl Display for Expr<'_> {
             }
 
             Expr::Regex { pattern, .. } => write!(f, "#'{pattern}'")?,
+            Expr::Empty { .. } => write!(f, "ε")?,
             Expr::Group { body, .. } => write_slice(f, body, " ")?,
-            Expr::Choice { body, .. } => {
-                write_slice(f, body, "|")?;
+            Expr::And { body, .. } => {
+                write!(f, "&")?;
+                write_slice(f, body, " ")?;
             }
-            Expr::UnparsedOperator { op, .. } => write!(f, "{}", op.get_str("repr").unwrap())?,
-            Expr::Rule {
-                rule: Rule { name, body },
-                ..
-            } => {
-                write!(f, "{name} =")?;
-                for child in body {
-                    write!(f, "{child}")?;
-                }
-                write!(f, ";")?;
+            Expr::Not { body, .. } => {
+                write!(f, "!")?;
+                write_slice(f, body, " ")?;
             }
+            Expr::Hidden { body, .. } => {
+                write!(f, "<")?;
+                write_slice(f, body, " ")?;
+                write!(f, ">")?;
+            }
+            Expr::Labeled { label, body, .. } => {
+                write!(f, "{label}:")?;
+                write_slice(f, body, " ")?;
+            }
+            Expr::Choice { body, ordered, .. } => {
+                write_slice(f, body, if *ordered { "/" } else { "|" })?;
+            }
+            Expr::UnparsedOperator { op, .. } => write!(f, "{}", op.get_str("repr").unwrap())?,
+            Expr::Rule { rule, .. } => write!(f, "{rule}")?,
         }
         Ok(())
     }
@@ -297,4 +753,108 @@ pub enum Operator {
     Optional,
     #[strum(props(repr = "+"))]
     Repeat,
+    #[strum(props(repr = "&"))]
+    And,
+    #[strum(props(repr = "!"))]
+    Not,
+    #[strum(props(repr = "<"))]
+    OpenedAngle,
+    #[strum(props(repr = ">"))]
+    ClosedAngle,
+    #[strum(props(repr = ":"))]
+    Label,
+    #[strum(props(repr = "/"))]
+    OrderedAlternation,
+    #[strum(props(repr = "%"))]
+    Separator,
 }
+
+impl Operator {
+    /// The bracket closing this one, if this is an opening bracket
+    pub(crate) fn closing_bracket(self) -> Option<Operator> {
+        match self {
+            Operator::OpenedGroup => Some(Operator::ClosedGroup),
+            Operator::OpenedSquare => Some(Operator::ClosedSquare),
+            Operator::OpenedBrace => Some(Operator::ClosedBrace),
+            Operator::OpenedAngle => Some(Operator::ClosedAngle),
+            _ => None,
+        }
+    }
+
+    /// The number standing for this operator in serialized output. See [`NodeKind::stable_code`].
+    pub(crate) const fn stable_code(self) -> u8 {
+        match self {
+            Operator::OpenedGroup => 0,
+            Operator::ClosedGroup => 1,
+            Operator::OpenedSquare => 2,
+            Operator::ClosedSquare => 3,
+            Operator::OpenedBrace => 4,
+            Operator::ClosedBrace => 5,
+            Operator::Terminator => 6,
+            Operator::Equals => 7,
+            Operator::Alternation => 8,
+            Operator::Kleene => 9,
+            Operator::Optional => 10,
+            Operator::Repeat => 11,
+            Operator::And => 12,
+            Operator::Not => 13,
+            Operator::OpenedAngle => 14,
+            Operator::ClosedAngle => 15,
+            Operator::Label => 16,
+            Operator::OrderedAlternation => 17,
+            Operator::Separator => 18,
+        }
+    }
+
+    /// The inverse of [`Operator::stable_code`]
+    pub(crate) const fn from_stable_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Operator::OpenedGroup),
+            1 => Some(Operator::ClosedGroup),
+            2 => Some(Operator::OpenedSquare),
+            3 => Some(Operator::ClosedSquare),
+            4 => Some(Operator::OpenedBrace),
+            5 => Some(Operator::ClosedBrace),
+            6 => Some(Operator::Terminator),
+            7 => Some(Operator::Equals),
+            8 => Some(Operator::Alternation),
+            9 => Some(Operator::Kleene),
+            10 => Some(Operator::Optional),
+            11 => Some(Operator::Repeat),
+            12 => Some(Operator::And),
+            13 => Some(Operator::Not),
+            14 => Some(Operator::OpenedAngle),
+            15 => Some(Operator::ClosedAngle),
+            16 => Some(Operator::Label),
+            17 => Some(Operator::OrderedAlternation),
+            18 => Some(Operator::Separator),
+            _ => None,
+        }
+    }
+}
+
+// Persisted data depends on these codes, so changing any of them is a breaking change. Only new codes should ever be added here.
+const _: () = {
+    macro_rules! check {
+        ($t:ident: $($variant:ident = $code:literal),*) => {
+            $(
+                assert!($t::$variant.stable_code() == $code);
+                assert!(matches!($t::from_stable_code($code), Some($t::$variant)));
+            )*
+        };
+    }
+    check!(NodeKind:
+        Literal = 0, Nonterminal = 1, Choice = 2, Optional = 3, Repetition = 4,
+        Regex = 5, Group = 6, UnparsedOperator = 7, Rule = 8, And = 9, Not = 10,
+        Hidden = 11, Labeled = 12, Empty = 13
+    );
+    check!(Operator:
+        OpenedGroup = 0, ClosedGroup = 1, OpenedSquare = 2, ClosedSquare = 3,
+        OpenedBrace = 4, ClosedBrace = 5, Terminator = 6, Equals = 7,
+        Alternation = 8, Kleene = 9, Optional = 10, Repeat = 11, And = 12, Not = 13,
+        OpenedAngle = 14, ClosedAngle = 15, Label = 16, OrderedAlternation = 17,
+        Separator = 18
+    );
+    assert!(NodeKind::from_stable_code(14).is_none());
+    assert!(Operator::from_stable_code(19).is_none());
+};
@@ -0,0 +1,113 @@
+use alloc::{collections::BTreeSet, vec::Vec};
+
+use crate::{
+    Grammar,
+    lookahead::{Analysis, Terminal},
+};
+
+/// The terminals a rule can begin with, as found by [`Grammar::first_sets`]. This is the rule's FIRST set, split up by kind of terminal so that code generators can tell whether dispatching on the leading literals alone is enough.
+/// ```rust
+/// # use ebnf_toolkit::Grammar;
+/// let g = Grammar::new("stmt = ('let' name) | ('loop' block) | name; name = #'[a-z]+';").unwrap();
+/// let sets = g.first_sets();
+/// let stmt = sets.iter().find(|s| s.rule == "stmt").unwrap();
+/// assert_eq!(stmt.literals, ["let", "loop"]);
+/// assert_eq!(stmt.regexes, ["[a-z]+"]);
+/// assert_eq!(stmt.longest_literal, 4);
+/// assert!(!stmt.is_literal_only());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FirstSet<'a> {
+    /// The name of the rule
+    pub rule: &'a str,
+    /// Whether the rule can match without consuming any input, in which case it may also begin with whatever follows it
+    pub nullable: bool,
+    /// The distinct literals the rule can begin with, in sorted order
+    pub literals: Vec<&'a str>,
+    /// The patterns of the regexes the rule can begin with, in sorted order
+    pub regexes: Vec<&'a str>,
+    /// Rules that aren't defined in the grammar but that this rule can begin with, in sorted order. Nothing is known about what these match.
+    pub undefined: Vec<&'a str>,
+    /// The length in bytes of the longest of `literals`, or 0 if there are none
+    pub longest_literal: usize,
+}
+
+impl FirstSet<'_> {
+    /// Whether every input the rule matches must start with one of `literals`, so that which literal comes next is enough to decide whether the rule applies
+    pub fn is_literal_only(&self) -> bool {
+        !self.nullable && self.regexes.is_empty() && self.undefined.is_empty()
+    }
+}
+
+pub(crate) fn first_sets<'a>(grammar: &'a Grammar<'_>) -> Vec<FirstSet<'a>> {
+    let analysis = Analysis::new(grammar, 1);
+
+    analysis
+        .rules
+        .iter()
+        .map(|(&rule, prefixes)| {
+            let mut literals = BTreeSet::new();
+            let mut regexes = BTreeSet::new();
+            let mut undefined = BTreeSet::new();
+            let mut nullable = false;
+            for prefix in prefixes {
+                match prefix.first() {
+                    None => nullable = true,
+                    Some(Terminal::Literal(str)) => {
+                        literals.insert(*str);
+                    }
+                    Some(Terminal::Regex(pattern)) => {
+                        regexes.insert(*pattern);
+                    }
+                    Some(Terminal::Undefined(name)) => {
+                        undefined.insert(*name);
+                    }
+                }
+            }
+            FirstSet {
+                rule,
+                nullable,
+                longest_literal: literals.iter().map(|l| l.len()).max().unwrap_or(0),
+                literals: literals.into_iter().collect(),
+                regexes: regexes.into_iter().collect(),
+                undefined: undefined.into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::Grammar;
+
+    #[test]
+    fn first_sets() {
+        let g = Grammar::new(
+            "S = A? B 'z'; A = ('a' A) | ''; B = ('b' | 'bb') C; C = #'c+' | D; E = S | 'e';",
+        )
+        .unwrap();
+        let sets = g.first_sets();
+        let summary: Vec<_> = sets
+            .iter()
+            .map(|s| (s.rule, s.nullable, &s.literals[..], s.longest_literal))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("A", true, &["a"][..], 1),
+                ("B", false, &["b", "bb"][..], 2),
+                ("C", false, &[][..], 0),
+                ("E", false, &["a", "b", "bb", "e"][..], 2),
+                ("S", false, &["a", "b", "bb"][..], 2),
+            ]
+        );
+
+        let c = &sets[2];
+        assert_eq!(c.regexes, ["c+"]);
+        assert_eq!(c.undefined, ["D"]);
+        assert!(sets[1].is_literal_only());
+        assert!(!sets[0].is_literal_only());
+    }
+}
@@ -0,0 +1,83 @@
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+
+use crate::{Expr, Grammar};
+
+pub(crate) fn fold_terminal_rules<'a>(
+    grammar: &mut Grammar<'a>,
+    keep_originals: bool,
+) -> Vec<Cow<'a, str>> {
+    let mut folded = BTreeSet::new();
+    // Inlining can leave a rule with nothing but terminals, so this repeats until nothing more can be inlined
+    loop {
+        let terminals: BTreeMap<Cow<'a, str>, Expr<'a>> = grammar
+            .rules
+            .values()
+            .filter_map(|rule| match &rule.body[..] {
+                [node] if !node.contains_nonterminal() => Some((rule.name.clone(), node.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let mut changed = false;
+        for rule in grammar.rules.values_mut() {
+            let mut stack: Vec<_> = rule.body.iter_mut().collect();
+            while let Some(node) = stack.pop() {
+                match node {
+                    &mut Expr::Nonterminal { name, .. } => {
+                        if let Some((original, terminal)) = terminals.get_key_value(name) {
+                            folded.insert(original.clone());
+                            *node = terminal.clone();
+                            changed = true;
+                        }
+                    }
+                    other => stack.extend(other.children_mut()),
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    if !keep_originals {
+        for name in &folded {
+            grammar.rules.remove(name);
+        }
+    }
+    folded.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{Expr, Grammar};
+
+    #[test]
+    fn fold_terminal_rules() {
+        let src = "list = item (SEP item)*; SEP = COMMA SPACE; COMMA = ','; SPACE = ' '+; \
+                   item = #'[a-z]+' | '(' list ')'; unused = 'u';";
+        let mut kept = Grammar::new(src).unwrap();
+        let folded = kept.fold_terminal_rules(true);
+        assert_eq!(folded, ["COMMA", "SPACE"]);
+        assert_eq!(kept.rules.len(), 6);
+        assert_eq!(kept["SEP"].body.len(), 2);
+        assert!(kept["SEP"].nonterminals().is_empty());
+        // SEP has two nodes, so it isn't a single terminal that can be folded
+        assert_eq!(kept["list"].nonterminals(), ["item", "SEP", "item"]);
+
+        let mut removed = Grammar::new(src).unwrap();
+        removed.fold_terminal_rules(false);
+        let names: Vec<_> = removed.rules.keys().map(|k| &**k).collect();
+        assert_eq!(names, ["SEP", "item", "list", "unused"]);
+
+        let mut chain = Grammar::new("A = B; B = C?; C = 'c';").unwrap();
+        assert_eq!(chain.fold_terminal_rules(false), ["B", "C"]);
+        let expected = Expr::new("'c'?").unwrap();
+        assert!(chain["A"].body[0].eq_ignoring_spans(&expected));
+    }
+}
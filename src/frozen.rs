@@ -0,0 +1,238 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{ChoiceSemantics, Grammar, Import, Rule, RuleId, Span, first_set::first_sets};
+
+/// An immutable form of a [`Grammar`], from [`Grammar::freeze`], with indices over its rules built up front so that repeated queries are cheap and can't be invalidated by changes to the rules.
+///
+/// Rules are identified by the same [`RuleId`]s as [`Grammar::rule_ids`] gives, which run from `0` in order of name. Looking up a rule, what it refers to or what refers to it by id takes constant time, while going from a name to an id is a binary search.
+/// ```rust
+/// # use ebnf_toolkit::Grammar;
+/// let g = Grammar::new("expr = term ('+' term)*; term = atom | ('(' expr ')'); atom = digit+;")
+///     .unwrap()
+///     .freeze();
+/// let term = g.id("term").unwrap();
+/// let expr = g.id("expr").unwrap();
+///
+/// assert_eq!(g.dependents(term), [expr]);
+/// assert_eq!(g.references_to(term).len(), 2);
+/// assert_eq!(g.undefined_references()[0].1, "digit");
+/// assert!(!g.is_nullable(expr));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrozenGrammar<'a> {
+    /// Indexed by id, so in order of name
+    rules: Vec<Rule<'a>>,
+    imports: Vec<Import<'a>>,
+    choice_semantics: ChoiceSemantics,
+    name_order: bool,
+    dependencies: Vec<Vec<RuleId>>,
+    dependents: Vec<Vec<RuleId>>,
+    references: Vec<Vec<(RuleId, Span)>>,
+    undefined: Vec<(RuleId, &'a str, Span)>,
+    nullable: Vec<bool>,
+}
+
+impl<'a> FrozenGrammar<'a> {
+    pub(crate) fn new(grammar: Grammar<'a>) -> Self {
+        let nullable = first_sets(&grammar).iter().map(|s| s.nullable).collect();
+        let Grammar {
+            rules,
+            imports,
+            choice_semantics,
+            name_order,
+        } = grammar;
+        let rules: Vec<_> = rules.into_values().collect();
+
+        let id = |name: &str| rules.binary_search_by(|r| (*r.name).cmp(name)).ok();
+        let mut dependencies = vec![vec![]; rules.len()];
+        let mut dependents = vec![vec![]; rules.len()];
+        let mut references = vec![vec![]; rules.len()];
+        let mut undefined = vec![];
+        for (index, rule) in rules.iter().enumerate() {
+            let referrer = RuleId(index);
+            for (name, span) in rule.nonterminal_references() {
+                match id(name) {
+                    Some(target) => {
+                        dependencies[index].push(RuleId(target));
+                        dependents[target].push(referrer);
+                        references[target].push((referrer, span));
+                    }
+                    None => undefined.push((referrer, name, span)),
+                }
+            }
+        }
+        for ids in dependencies.iter_mut().chain(&mut dependents) {
+            ids.sort_unstable();
+            ids.dedup();
+        }
+        for sites in &mut references {
+            sites.sort_by_key(|(_, span)| *span);
+        }
+        undefined.sort_by_key(|(_, _, span)| *span);
+
+        FrozenGrammar {
+            rules,
+            imports,
+            choice_semantics,
+            name_order,
+            dependencies,
+            dependents,
+            references,
+            undefined,
+            nullable,
+        }
+    }
+
+    /// Turns this back into a [`Grammar`] that can be changed
+    pub fn thaw(self) -> Grammar<'a> {
+        let mut grammar: Grammar<'a> = self.rules.into_iter().collect();
+        grammar.imports = self.imports;
+        grammar.choice_semantics = self.choice_semantics;
+        grammar.name_order = self.name_order;
+        grammar
+    }
+
+    /// The number of rules, which is one more than the largest id
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The id of the rule with the given name, if there is one
+    pub fn id(&self, name: &str) -> Option<RuleId> {
+        self.rules
+            .binary_search_by(|r| (*r.name).cmp(name))
+            .ok()
+            .map(RuleId)
+    }
+
+    /// The id of every rule, in order
+    pub fn ids(&self) -> impl Iterator<Item = RuleId> + use<> {
+        (0..self.rules.len()).map(RuleId)
+    }
+
+    /// Finds the rule with the given name
+    pub fn get(&self, name: &str) -> Option<&Rule<'a>> {
+        self.id(name).map(|id| self.rule(id))
+    }
+
+    /// The rule with the given id
+    ///
+    /// # Panics
+    /// If there is no rule with that id
+    pub fn rule(&self, id: RuleId) -> &Rule<'a> {
+        &self.rules[id.0]
+    }
+
+    /// Every rule, in order of id
+    pub fn rules(&self) -> &[Rule<'a>] {
+        &self.rules
+    }
+
+    /// The `%import` directives of the grammar, as for [`Grammar::imports`]
+    pub fn imports(&self) -> &[Import<'a>] {
+        &self.imports
+    }
+
+    /// The ids of the rules that the rule `id` refers to, in order and without repeats
+    ///
+    /// # Panics
+    /// If there is no rule with that id
+    pub fn dependencies(&self, id: RuleId) -> &[RuleId] {
+        &self.dependencies[id.0]
+    }
+
+    /// The ids of the rules that refer to the rule `id`, in order and without repeats
+    ///
+    /// # Panics
+    /// If there is no rule with that id
+    pub fn dependents(&self, id: RuleId) -> &[RuleId] {
+        &self.dependents[id.0]
+    }
+
+    /// Every reference to the rule `id`, as the id of the rule it appears in and where it appears, ordered by where they appear
+    ///
+    /// # Panics
+    /// If there is no rule with that id
+    pub fn references_to(&self, id: RuleId) -> &[(RuleId, Span)] {
+        &self.references[id.0]
+    }
+
+    /// Every reference to a rule that isn't defined, as the id of the rule it appears in, the name it refers to, and where it appears, ordered by where they appear
+    pub fn undefined_references(&self) -> &[(RuleId, &'a str, Span)] {
+        &self.undefined
+    }
+
+    /// Whether the rule `id` can match without consuming any input
+    ///
+    /// # Panics
+    /// If there is no rule with that id
+    pub fn is_nullable(&self, id: RuleId) -> bool {
+        self.nullable[id.0]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{ChoiceSemantics, Grammar};
+
+    #[test]
+    fn frozen_queries() {
+        let src = "%import 'lib.ebnf';\nlist = item (',' list)?; \
+                   item = [word] | ('(' list ')') | missing; loop = loop 'x'; word = #'[a-z]+' other;";
+        let mut g = Grammar::new(src).unwrap();
+        g.set_choice_semantics(ChoiceSemantics::Ordered);
+        g.sort_rules_by_name();
+        let frozen = g.clone().freeze();
+
+        let names: Vec<_> = frozen.ids().map(|id| &*frozen.rule(id).name).collect();
+        assert_eq!(names, ["item", "list", "loop", "word"]);
+        assert_eq!(frozen.len(), 4);
+        let [item, list, looping, word] =
+            ["item", "list", "loop", "word"].map(|n| frozen.id(n).unwrap());
+        assert_eq!(frozen.id("missing"), None);
+        assert_eq!(frozen.get("word"), g.get("word"));
+
+        assert_eq!(frozen.dependencies(item), [list, word]);
+        assert_eq!(frozen.dependencies(list), [item, list]);
+        assert_eq!(frozen.dependencies(looping), [looping]);
+        assert!(frozen.dependencies(word).is_empty());
+        assert_eq!(frozen.dependents(list), [item, list]);
+        assert_eq!(frozen.dependents(word), [item]);
+
+        // Ordered by where the references are, not by the ids of the rules they're in
+        let references: Vec<_> = frozen
+            .references_to(list)
+            .iter()
+            .map(|&(id, span)| (id, &src[span.range()], span.start()))
+            .collect();
+        assert_eq!(references.len(), 2);
+        assert_eq!((references[0].0, references[0].1), (list, "list"));
+        assert_eq!((references[1].0, references[1].1), (item, "list"));
+        assert!(references[0].2 < references[1].2);
+
+        let undefined: Vec<_> = frozen
+            .undefined_references()
+            .iter()
+            .map(|&(id, name, span)| (id, name, &src[span.range()]))
+            .collect();
+        assert_eq!(
+            undefined,
+            [(item, "missing", "missing"), (word, "other", "other")]
+        );
+
+        assert!(frozen.is_nullable(item));
+        assert!(frozen.is_nullable(list));
+        assert!(!frozen.is_nullable(looping));
+        assert!(!frozen.is_nullable(word));
+
+        assert_eq!(frozen.imports(), g.imports());
+        assert_eq!(frozen.thaw(), g);
+    }
+}
@@ -0,0 +1,674 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, btree_map::Entry},
+    rc::Rc,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Debug, Display};
+
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::{ChoiceSemantics, Expr, Grammar, matcher::match_node};
+
+/// Settings for the [`Generator`] made by [`Grammar::generator`]
+/// ```rust
+/// # use ebnf_toolkit::{GenerateOptions, Grammar};
+/// let g = Grammar::new("list = item (',' item)*; item = #'[a-z]{1,3}';").unwrap();
+/// let options = GenerateOptions { seed: 7, max_repetitions: 1, ..GenerateOptions::default() };
+/// let sentence = g.generator(options).generate("list").unwrap();
+/// assert!(sentence.matches(',').count() <= 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GenerateOptions {
+    /// Where the pseudo-random choices start from. The same seed, options and grammar always give the same sentences.
+    pub seed: u64,
+    /// How many rules deep a sentence is expanded freely. Beyond this, each choice takes the alternative with the shallowest way to finish, and repetitions and optional parts are left out where possible, so that recursive rules come to an end.
+    pub max_depth: usize,
+    /// The most times a repetition is repeated beyond the fewest it needs, both for repetitions in the grammar and in its regexes
+    pub max_repetitions: usize,
+    /// How many parts of the grammar a sentence expands freely, counting each literal, regex, rule and operator every time it's expanded. Beyond this, the rest of the sentence is finished as for [`max_depth`](GenerateOptions::max_depth), so that rules like `A = { 'x' % A };`, which can branch out many times before reaching it, still give sentences of a reasonable size.
+    pub max_nodes: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            seed: 0,
+            max_depth: 16,
+            max_repetitions: 3,
+            max_nodes: 10_000,
+        }
+    }
+}
+
+/// The ways [`Generator::generate`] can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GenerateError<'a> {
+    /// The grammar doesn't define the rule called `name` that generation was asked to start from
+    Undefined {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+    },
+    /// Every way of expanding the rule called `name` either refers to an undefined rule or never stops recursing
+    NoFiniteDerivation {
+        #[expect(missing_docs, reason = "Obvious")]
+        name: &'a str,
+    },
+    /// A regex in the grammar could not be parsed
+    Regex {
+        #[expect(missing_docs, reason = "Obvious")]
+        pattern: &'a str,
+        #[expect(missing_docs, reason = "Obvious")]
+        error: Box<regex_syntax::Error>,
+    },
+}
+
+impl Display for GenerateError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GenerateError::Undefined { name } => write!(f, "Rule `{name}` is not defined"),
+            GenerateError::NoFiniteDerivation { name } => {
+                write!(f, "Rule `{name}` has no expansion that finishes")
+            }
+            GenerateError::Regex { pattern, error } => {
+                write!(f, "The regex `{pattern}` is invalid: {error}")
+            }
+        }
+    }
+}
+
+/// How often a [`Generator`] picks each alternative of a rule whose body is a [`Choice`](Expr::Choice), relative to the others. Alternatives are counted from 0 in the order they're written, and any not given a weight have a weight of 1, so by default every alternative is as likely as any other.
+///
+/// An alternative with a weight of 0 is only picked when finishing a sentence needs it, or when every alternative it's picked from has a weight of 0. Choices nested inside a rule's body are always picked from uniformly.
+/// ```rust
+/// # use ebnf_toolkit::{GenerateOptions, Grammar, Weights};
+/// let g = Grammar::new("stmt = 'let' | 'return' | 'break';").unwrap();
+/// let weights = Weights::new().with("stmt", 0, 5).with("stmt", 2, 0);
+/// let mut generator = g.generator(GenerateOptions::default()).with_weights(weights);
+/// let sentences: Vec<_> = (0..20).map(|_| generator.generate("stmt").unwrap()).collect();
+/// assert!(!sentences.contains(&"break".to_string()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Weights {
+    weights: BTreeMap<String, BTreeMap<usize, u32>>,
+}
+
+impl Weights {
+    /// Weights that leave every alternative as likely as any other
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the weight of the alternative numbered `alternative` of the rule called `rule`
+    #[must_use]
+    pub fn with(mut self, rule: &str, alternative: usize, weight: u32) -> Self {
+        self.weights
+            .entry(rule.to_string())
+            .or_default()
+            .insert(alternative, weight);
+        self
+    }
+
+    /// The weight of the alternative numbered `alternative` of the rule called `rule`
+    pub fn get(&self, rule: &str, alternative: usize) -> u32 {
+        self.weights
+            .get(rule)
+            .and_then(|weights| weights.get(&alternative))
+            .copied()
+            .unwrap_or(1)
+    }
+}
+
+/// A callback given to [`Generator::with_terminals`], called with the name of the rule being expanded, the terminal, and a pseudo-random number
+type TerminalHook<'g, 'a> = Rc<dyn Fn(&str, &Expr<'a>, u64) -> Option<String> + 'g>;
+
+/// Produces random sentences matched by the rules of a grammar, from [`Grammar::generator`], such as for building a test corpus.
+///
+/// Successive calls to [`Generator::generate`] give different sentences. Lookahead predicates are left out of the sentences rather than checked, so sentences from a grammar that uses them may not match it.
+///
+/// An [ordered](ChoiceSemantics::Ordered) choice, or one written with `/`, never takes an alternative when an earlier one matches, so an alternative is only kept if no earlier alternative matches the start of the text generated for it, going by [`Rule::match_str`](crate::Rule::match_str), and another is picked otherwise. Only the alternative's own text is looked at, not what comes after it, so an earlier alternative that needs some of what follows to match can still take its place, and a sentence may not match the grammar.
+/// ```rust
+/// # use ebnf_toolkit::{GenerateOptions, Grammar};
+/// let g = Grammar::new("S = A 'c'; A = 'a' / ('a' 'b');").unwrap();
+/// let mut generator = g.generator(GenerateOptions::default());
+/// assert!((0..20).all(|_| generator.generate("S").unwrap() == "ac"));
+/// ```
+#[derive(Clone)]
+pub struct Generator<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    options: GenerateOptions,
+    rng: Rng,
+    /// How many rules deep each rule must expand before it can finish, or `None` if it can't
+    heights: BTreeMap<&'g str, Option<usize>>,
+    regexes: BTreeMap<&'a str, Hir>,
+    weights: Weights,
+    terminals: Option<TerminalHook<'g, 'a>>,
+    /// How many nodes the sentence being generated has expanded so far
+    expanded: usize,
+}
+
+impl Debug for Generator<'_, '_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Generator")
+            .field("grammar", &self.grammar)
+            .field("options", &self.options)
+            .field("rng", &self.rng)
+            .field("heights", &self.heights)
+            .field("regexes", &self.regexes)
+            .field("weights", &self.weights)
+            .field("terminals", &self.terminals.is_some())
+            .field("expanded", &self.expanded)
+            .finish()
+    }
+}
+
+impl<'g, 'a> Generator<'g, 'a> {
+    pub(crate) fn new(grammar: &'g Grammar<'a>, options: GenerateOptions) -> Self {
+        let mut heights: BTreeMap<&'g str, Option<usize>> =
+            grammar.rules.keys().map(|name| (&**name, None)).collect();
+        // Each pass can only lower a height, and one that doesn't lower any has found them all
+        loop {
+            let mut changed = false;
+            for rule in grammar.rules.values() {
+                let height = sequence_height(&rule.body, &heights).map(|h| h + 1);
+                if height != heights[&*rule.name] {
+                    heights.insert(&rule.name, height);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Generator {
+            grammar,
+            options,
+            rng: Rng(options.seed),
+            heights,
+            regexes: BTreeMap::new(),
+            weights: Weights::new(),
+            terminals: None,
+            expanded: 0,
+        }
+    }
+
+    /// Picks the alternatives of rules according to `weights` rather than uniformly
+    #[must_use]
+    pub fn with_weights(mut self, weights: Weights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Lets `hook` write the text for each [`Literal`](Expr::Literal) and [`Regex`](Expr::Regex) in a sentence, such as to give realistic identifiers rather than random characters matching a regex. It's called with the name of the rule the terminal is in, the terminal, and a pseudo-random number following from the [seed](GenerateOptions::seed), and the terminal is generated as usual whenever it returns `None`.
+    ///
+    /// The text `hook` returns is used as it is, so sentences may not match the grammar if it doesn't match the terminal.
+    /// ```rust
+    /// # use ebnf_toolkit::{Expr, GenerateOptions, Grammar};
+    /// let g = Grammar::new("call = name '()'; name = #'[a-z]+';").unwrap();
+    /// let names = ["open", "close", "read"];
+    /// let mut generator = g
+    ///     .generator(GenerateOptions::default())
+    ///     .with_terminals(|rule, node, random| match node {
+    ///         Expr::Regex { .. } if rule == "name" => {
+    ///             Some(names[random as usize % names.len()].to_string())
+    ///         }
+    ///         _ => None,
+    ///     });
+    /// let sentence = generator.generate("call").unwrap();
+    /// assert!(names.iter().any(|name| sentence == format!("{name}()")));
+    /// ```
+    #[must_use]
+    pub fn with_terminals(
+        mut self,
+        hook: impl Fn(&str, &Expr<'a>, u64) -> Option<String> + 'g,
+    ) -> Self {
+        self.terminals = Some(Rc::new(hook));
+        self
+    }
+
+    /// Generates a sentence matched by the rule called `start`
+    ///
+    /// # Errors
+    /// If the rule isn't defined or can't be expanded into a finite sentence, or a regex it uses is invalid
+    pub fn generate<'s>(&mut self, start: &'s str) -> Result<String, GenerateError<'s>>
+    where
+        'g: 's,
+    {
+        let Some((&name, &height)) = self.heights.get_key_value(start) else {
+            return Err(GenerateError::Undefined { name: start });
+        };
+        if height.is_none() {
+            return Err(GenerateError::NoFiniteDerivation { name });
+        }
+        let mut output = String::new();
+        self.expanded = 0;
+        self.rule(name, 1, &mut output)?;
+        Ok(output)
+    }
+
+    fn rule(
+        &mut self,
+        name: &'g str,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), GenerateError<'g>> {
+        let body = &self.grammar.rules[name].body;
+        if let [
+            Expr::Choice {
+                body: alts,
+                ordered,
+                ..
+            },
+        ] = &body[..]
+        {
+            self.choice(name, alts, *ordered, Some(name), depth, output)
+        } else {
+            self.sequence(name, body, depth, output)
+        }
+    }
+
+    fn sequence(
+        &mut self,
+        rule: &'g str,
+        nodes: &'g [Expr<'a>],
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), GenerateError<'g>> {
+        for node in nodes {
+            self.node(rule, node, depth, output)?;
+        }
+        Ok(())
+    }
+
+    /// Expands one of `alts`, weighted by the alternatives of the rule called `weighted` if there is one. If the choice is `ordered`, alternatives whose text an earlier alternative would match instead are picked again, until none are left to pick from.
+    fn choice(
+        &mut self,
+        rule: &'g str,
+        alts: &'g [Expr<'a>],
+        ordered: bool,
+        weighted: Option<&str>,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), GenerateError<'g>> {
+        let free = self.free(depth);
+        let ordered = ordered || self.grammar.choice_semantics == ChoiceSemantics::Ordered;
+        let heights: Vec<_> = alts.iter().map(|alt| self.height(alt)).collect();
+        let Some(lowest) = heights.iter().flatten().min().copied() else {
+            return Ok(());
+        };
+        let mut candidates: Vec<_> = heights
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.is_some_and(|h| free || h == lowest))
+            .map(|(i, _)| i)
+            .collect();
+        loop {
+            let picked = self.pick(&candidates, weighted);
+            let chosen = candidates[picked];
+            if !ordered {
+                return self.node(rule, &alts[chosen], depth, output);
+            }
+            let mut text = String::new();
+            self.node(rule, &alts[chosen], depth, &mut text)?;
+            let shadowed = alts[..chosen]
+                .iter()
+                .any(|earlier| match_node(earlier, self.grammar, &text).is_some());
+            if !shadowed || candidates.len() == 1 {
+                output.push_str(&text);
+                return Ok(());
+            }
+            candidates.remove(picked);
+        }
+    }
+
+    /// The position in `candidates` of the alternative to take, weighted by the alternatives of the rule called `weighted` if there is one
+    fn pick(&mut self, candidates: &[usize], weighted: Option<&str>) -> usize {
+        let weights: Vec<_> = candidates
+            .iter()
+            .map(|&i| weighted.map_or(1, |rule| self.weights.get(rule, i) as usize))
+            .collect();
+        let total = weights
+            .iter()
+            .fold(0, |sum: usize, &w| sum.saturating_add(w));
+        if total == 0 {
+            return self.rng.below(candidates.len());
+        }
+        let mut left = self.rng.below(total);
+        for (n, &weight) in weights.iter().enumerate() {
+            if left < weight {
+                return n;
+            }
+            left -= weight;
+        }
+        0
+    }
+
+    fn node(
+        &mut self,
+        rule: &'g str,
+        node: &'g Expr<'a>,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), GenerateError<'g>> {
+        self.expanded += 1;
+        let free = self.free(depth);
+        if let (Some(hook), Expr::Literal { .. } | Expr::Regex { .. }) = (&self.terminals, node)
+            && let Some(text) = hook(rule, node, self.rng.next())
+        {
+            output.push_str(&text);
+            return Ok(());
+        }
+        match node {
+            Expr::Literal { str, .. } => output.push_str(str),
+            &Expr::Regex { pattern, .. } => {
+                let hir = match self.regexes.entry(pattern) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(regex_syntax::parse(pattern).map_err(|error| {
+                            GenerateError::Regex {
+                                pattern,
+                                error: Box::new(error),
+                            }
+                        })?)
+                    }
+                };
+                self.rng.regex(hir, self.options.max_repetitions, output);
+            }
+            Expr::Nonterminal { name, .. } => {
+                let (name, _) = self.grammar.rules.get_key_value(*name).unwrap();
+                self.rule(name, depth + 1, output)?;
+            }
+            Expr::Choice { body, ordered, .. } => {
+                self.choice(rule, body, *ordered, None, depth, output)?;
+            }
+            Expr::Optional { body, .. } => {
+                if free && sequence_height(body, &self.heights).is_some() && self.rng.below(2) == 1
+                {
+                    self.sequence(rule, body, depth, output)?;
+                }
+            }
+            Expr::Repetition { one_needed, .. } => {
+                let (body, separator) = node.repetition_parts().unwrap();
+                let mut count = usize::from(*one_needed);
+                if free
+                    && sequence_height(body, &self.heights).is_some()
+                    && separator.is_none_or(|s| self.height(s).is_some())
+                {
+                    count += self.rng.below(self.options.max_repetitions + 1);
+                }
+                for n in 0..count {
+                    if let Some(separator) = separator.filter(|_| n > 0) {
+                        self.node(rule, separator, depth, output)?;
+                    }
+                    self.sequence(rule, body, depth, output)?;
+                }
+            }
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                self.sequence(rule, body, depth, output)?;
+            }
+            Expr::And { .. }
+            | Expr::Not { .. }
+            | Expr::Empty { .. }
+            | Expr::UnparsedOperator { .. }
+            | Expr::Rule { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Whether parts at `depth` can still be expanded freely, rather than finished as quickly as possible
+    fn free(&self, depth: usize) -> bool {
+        depth < self.options.max_depth && self.expanded < self.options.max_nodes
+    }
+
+    fn height(&self, node: &Expr<'_>) -> Option<usize> {
+        node_height(node, &self.heights)
+    }
+}
+
+/// The `SplitMix64` pseudo-random sequence, which is small and fast, and good enough for choosing among alternatives
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    /// The next pseudo-random number in the sequence
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A pseudo-random number less than `n`
+    fn below(&mut self, n: usize) -> usize {
+        usize::try_from(self.next() % n as u64).unwrap()
+    }
+
+    /// Appends a string matched by `hir` to `output`
+    fn regex(&mut self, hir: &Hir, max_repetitions: usize, output: &mut String) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => {}
+            HirKind::Literal(lit) => output.push_str(&String::from_utf8_lossy(&lit.0)),
+            HirKind::Class(Class::Unicode(class)) => {
+                if !class.ranges().is_empty() {
+                    let range = class.ranges()[self.below(class.ranges().len())];
+                    let (start, end) = (u32::from(range.start()), u32::from(range.end()));
+                    let offset = self.below((end - start) as usize + 1);
+                    // Ranges can span the surrogates, which aren't characters
+                    let c = u32::try_from(offset)
+                        .ok()
+                        .and_then(|o| char::from_u32(start + o));
+                    output.push(c.unwrap_or(range.start()));
+                }
+            }
+            HirKind::Class(Class::Bytes(class)) => {
+                let ascii: Vec<_> = class
+                    .ranges()
+                    .iter()
+                    .flat_map(|r| r.start()..=r.end())
+                    .filter(u8::is_ascii)
+                    .collect();
+                if !ascii.is_empty() {
+                    output.push(char::from(ascii[self.below(ascii.len())]));
+                }
+            }
+            HirKind::Repetition(rep) => {
+                let min = rep.min as usize;
+                let max = rep.max.map_or(usize::MAX, |max| max as usize);
+                let extra = self.below(max.min(min + max_repetitions) - min + 1);
+                for _ in 0..min + extra {
+                    self.regex(&rep.sub, max_repetitions, output);
+                }
+            }
+            HirKind::Capture(cap) => self.regex(&cap.sub, max_repetitions, output),
+            HirKind::Concat(subs) => {
+                for sub in subs {
+                    self.regex(sub, max_repetitions, output);
+                }
+            }
+            HirKind::Alternation(subs) => {
+                let chosen = &subs[self.below(subs.len())];
+                self.regex(chosen, max_repetitions, output);
+            }
+        }
+    }
+}
+
+/// How many rules deep `nodes` must expand before they can finish, or `None` if they can't, going by the `heights` of each rule found so far
+fn sequence_height(nodes: &[Expr<'_>], heights: &BTreeMap<&str, Option<usize>>) -> Option<usize> {
+    nodes
+        .iter()
+        .try_fold(0, |max, node| Some(max.max(node_height(node, heights)?)))
+}
+
+fn node_height(node: &Expr<'_>, heights: &BTreeMap<&str, Option<usize>>) -> Option<usize> {
+    match node {
+        Expr::Nonterminal { name, .. } => heights.get(name).copied().flatten(),
+        Expr::Choice { body, .. } => body
+            .iter()
+            .filter_map(|alt| node_height(alt, heights))
+            .min(),
+        Expr::Repetition {
+            one_needed: true, ..
+        } => sequence_height(node.repetition_parts().unwrap().0, heights),
+        Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+            sequence_height(body, heights)
+        }
+        _ => Some(0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{collections::BTreeSet, vec::Vec};
+
+    use crate::{ChoiceSemantics, Expr, GenerateError, GenerateOptions, Grammar, Weights};
+
+    #[test]
+    fn sentences_match() {
+        let g = Grammar::new(
+            "call = name '(' [name (', ' name)*] ')'; name = #'[a-z_][a-z0-9_]{0,4}' | 'self';",
+        )
+        .unwrap();
+        let regex = g["call"].compile_to_regex(&g).unwrap();
+        let mut generator = g.generator(GenerateOptions::default());
+        let sentences: Vec<_> = (0..50)
+            .map(|_| generator.generate("call").unwrap())
+            .collect();
+        for sentence in &sentences {
+            assert!(regex.is_match(sentence), "{sentence}");
+        }
+        assert!(sentences.iter().any(|s| s.contains(", ")));
+        assert!(sentences.iter().any(|s| s.ends_with("()")));
+
+        let again: Vec<_> = {
+            let mut generator = g.generator(GenerateOptions::default());
+            (0..50)
+                .map(|_| generator.generate("call").unwrap())
+                .collect()
+        };
+        assert_eq!(sentences, again);
+    }
+
+    #[test]
+    fn recursion_finishes() {
+        let g = Grammar::new("expr = ('(' expr ')') | (expr '+' expr) | 'x'; loop = '[' loop ']';")
+            .unwrap();
+        let options = GenerateOptions {
+            max_depth: 4,
+            ..GenerateOptions::default()
+        };
+        let mut generator = g.generator(options);
+        for _ in 0..50 {
+            let sentence = generator.generate("expr").unwrap();
+            assert_eq!(sentence.matches('(').count(), sentence.matches(')').count());
+            assert!(sentence.len() < 200, "{sentence}");
+        }
+        assert_eq!(
+            generator.generate("loop"),
+            Err(GenerateError::NoFiniteDerivation { name: "loop" })
+        );
+        assert_eq!(
+            generator.generate("missing"),
+            Err(GenerateError::Undefined { name: "missing" })
+        );
+    }
+
+    #[test]
+    fn size_budget() {
+        let g = Grammar::new("A = { 'x' % A }; B = #'x+' % B +;").unwrap();
+        let mut generator = g.generator(GenerateOptions::default());
+        for start in ["A", "B"] {
+            for _ in 0..5 {
+                let sentence = generator.generate(start).unwrap();
+                assert!(sentence.starts_with('x'), "{sentence}");
+                assert!(sentence.len() < 100_000, "{}", sentence.len());
+            }
+        }
+        let options = GenerateOptions {
+            max_nodes: 1,
+            ..GenerateOptions::default()
+        };
+        assert_eq!(g.generator(options).generate("A").unwrap(), "x");
+    }
+
+    #[test]
+    fn ordered_choice() {
+        let g = Grammar::new(
+            "S = A 'c'; A = 'a' / ('a' 'b') / 'b'; \
+             T = ('x' / 'xy' / ('x' 'y' 'z') / 'y') ';'; U = 'q'? / 'r';",
+        )
+        .unwrap();
+        let mut generator = g.generator(GenerateOptions::default());
+        let mut sentences = BTreeSet::new();
+        for _ in 0..100 {
+            for start in ["S", "T", "U"] {
+                let sentence = generator.generate(start).unwrap();
+                assert_eq!(
+                    g[start].match_str(&g, &sentence),
+                    Some(sentence.len()),
+                    "{sentence}"
+                );
+                sentences.insert(sentence);
+            }
+        }
+        assert_eq!(
+            sentences.into_iter().collect::<Vec<_>>(),
+            ["", "ac", "bc", "q", "x;", "y;"]
+        );
+
+        let mut unordered = Grammar::new("A = 'a' | ('a' 'b');").unwrap();
+        let mut generator = unordered.generator(GenerateOptions::default());
+        assert!((0..20).any(|_| generator.generate("A").unwrap() == "ab"));
+        drop(generator);
+        unordered.set_choice_semantics(ChoiceSemantics::Ordered);
+        let mut generator = unordered.generator(GenerateOptions::default());
+        assert!((0..20).all(|_| generator.generate("A").unwrap() == "a"));
+    }
+
+    #[test]
+    fn weights_and_terminals() {
+        let g = Grammar::new(
+            "list = item | (item ',' list); item = #'[0-9]+' | name; name = #'[a-z]+';",
+        )
+        .unwrap();
+        // Never continuing the list, unless past the depth where it has to finish anyway
+        let weights = Weights::new().with("list", 0, 0).with("item", 1, 3);
+        assert_eq!(weights.get("list", 1), 1);
+        let mut generator = g
+            .generator(GenerateOptions::default())
+            .with_weights(weights)
+            .with_terminals(|rule, node, random| match node {
+                Expr::Regex { .. } if rule == "name" => {
+                    Some(if random % 2 == 0 { "x" } else { "y" }.into())
+                }
+                _ => None,
+            });
+        let sentences: Vec<_> = (0..100)
+            .map(|_| generator.generate("list").unwrap())
+            .collect();
+        for sentence in &sentences {
+            assert!(
+                sentence.ends_with(|c: char| c.is_ascii_alphanumeric()),
+                "{sentence}"
+            );
+            let items = sentence.split(',');
+            assert!(items.clone().count() >= 15, "{sentence}");
+            for item in items {
+                assert!(
+                    item == "x" || item == "y" || item.parse::<u64>().is_ok(),
+                    "{item}"
+                );
+            }
+        }
+        let names = sentences
+            .iter()
+            .flat_map(|s| s.split(','))
+            .filter(|item| matches!(*item, "x" | "y"))
+            .count();
+        let total: usize = sentences.iter().map(|s| s.split(',').count()).sum();
+        assert!(names * 2 > total, "{names} of {total}");
+    }
+}
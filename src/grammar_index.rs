@@ -0,0 +1,192 @@
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::ops::Range;
+
+use crate::{Expr, Grammar, Rule, SpanSet};
+
+/// An index over where the rules and nodes of a [`Grammar`] are in its input, from [`Grammar::span_index`], for editor tooling that looks things up by position many times over, such as for hovers and go-to-definition.
+///
+/// The spans are kept in interval trees, so that finding the node at an offset or the rules overlapping a range takes `O(log n)` time, plus the number of spans found, rather than a walk over every rule.
+/// ```rust
+/// # use ebnf_toolkit::{Expr, Grammar};
+/// let src = "list = '[' items ']';\nitems = item (',' item)*;\nitem = #'[0-9]+';";
+/// let g = Grammar::new(src).unwrap();
+/// let index = g.span_index();
+///
+/// let (rule, node) = index.node_at(src.find("(',").unwrap() + 2).unwrap();
+/// assert_eq!(rule.name, "items");
+/// assert!(matches!(node, Expr::Literal { str: ",", .. }));
+///
+/// let names: Vec<_> = index.rules_overlapping(0..30).iter().map(|r| r.name.as_ref()).collect();
+/// assert_eq!(names, ["list", "items"]);
+/// assert_eq!(index.definition_of("item").unwrap().hull().unwrap().start_line(), (3, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct GrammarIndex<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    /// Every definition of every rule
+    definitions: IntervalTree<&'g Rule<'a>>,
+    /// Every node of every rule's body, with the rule it's in and how deeply it's nested
+    nodes: IntervalTree<(&'g Rule<'a>, &'g Expr<'a>, usize)>,
+}
+
+impl<'g, 'a> GrammarIndex<'g, 'a> {
+    pub(crate) fn new(grammar: &'g Grammar<'a>) -> Self {
+        let mut definitions = Vec::new();
+        let mut nodes = Vec::new();
+        for rule in grammar.rules.values() {
+            definitions.extend(rule.definitions.iter().map(|span| (span.range(), rule)));
+            let mut stack: Vec<_> = rule.body.iter().map(|node| (node, 0)).collect();
+            while let Some((node, depth)) = stack.pop() {
+                nodes.push((node.span().range(), (rule, node, depth)));
+                stack.extend(node.children().iter().map(|child| (child, depth + 1)));
+            }
+        }
+        GrammarIndex {
+            grammar,
+            definitions: IntervalTree::new(definitions),
+            nodes: IntervalTree::new(nodes),
+        }
+    }
+
+    /// The innermost node whose span contains the byte `offset`, along with the rule it's in. Returns `None` if the offset isn't inside the body of any rule, such as in a comment or a rule's name.
+    pub fn node_at(&self, offset: usize) -> Option<(&'g Rule<'a>, &'g Expr<'a>)> {
+        let mut found = Vec::new();
+        self.nodes.overlapping(offset..offset + 1, &mut found);
+        found
+            .into_iter()
+            .max_by_key(|(_, _, depth)| *depth)
+            .map(|&(rule, node, _)| (rule, node))
+    }
+
+    /// The rules with a definition overlapping the byte range `range`, each listed once, in order of where the first such definition starts
+    pub fn rules_overlapping(&self, range: Range<usize>) -> Vec<&'g Rule<'a>> {
+        let mut found = Vec::new();
+        self.definitions.overlapping(range, &mut found);
+        let mut seen = BTreeSet::new();
+        found
+            .into_iter()
+            .filter(|rule| seen.insert(&rule.name))
+            .copied()
+            .collect()
+    }
+
+    /// Where the rule called `name` is defined, once for each definition merged into it. Returns `None` if there's no such rule.
+    pub fn definition_of(&self, name: &str) -> Option<&'g SpanSet> {
+        self.grammar.rules.get(name).map(|rule| &rule.definitions)
+    }
+}
+
+/// Byte ranges with a value for each, sorted by where they start and searched as a balanced binary tree, where the range in the middle of each slice is the root of the slice's subtree
+#[derive(Debug, Clone)]
+struct IntervalTree<T> {
+    entries: Vec<(Range<usize>, T)>,
+    /// The furthest end of any range in the subtree rooted at each entry
+    max_ends: Vec<usize>,
+}
+
+impl<T> IntervalTree<T> {
+    fn new(mut entries: Vec<(Range<usize>, T)>) -> Self {
+        entries.sort_by_key(|(range, _)| (range.start, range.end));
+        let mut max_ends = vec![0; entries.len()];
+        fill_max_ends(&entries, &mut max_ends);
+        IntervalTree { entries, max_ends }
+    }
+
+    /// Adds the value of every range overlapping `range` to `found`, in order of where the ranges start
+    fn overlapping<'t>(&'t self, range: Range<usize>, found: &mut Vec<&'t T>) {
+        search(&self.entries, &self.max_ends, &range, found);
+    }
+}
+
+/// Sets each element of `max_ends` to the furthest end of the ranges in its entry's subtree, returning the furthest of all of them
+fn fill_max_ends<T>(entries: &[(Range<usize>, T)], max_ends: &mut [usize]) -> usize {
+    if entries.is_empty() {
+        return 0;
+    }
+    let mid = entries.len() / 2;
+    let (left_max, rest) = max_ends.split_at_mut(mid);
+    let (mid_max, right_max) = rest.split_first_mut().unwrap();
+    let left = fill_max_ends(&entries[..mid], left_max);
+    let right = fill_max_ends(&entries[mid + 1..], right_max);
+    *mid_max = entries[mid].0.end.max(left).max(right);
+    *mid_max
+}
+
+fn search<'t, T>(
+    entries: &'t [(Range<usize>, T)],
+    max_ends: &[usize],
+    range: &Range<usize>,
+    found: &mut Vec<&'t T>,
+) {
+    if entries.is_empty() {
+        return;
+    }
+    let mid = entries.len() / 2;
+    // Nothing in this subtree reaches the range
+    if max_ends[mid] <= range.start {
+        return;
+    }
+    search(&entries[..mid], &max_ends[..mid], range, found);
+    let (entry, value) = &entries[mid];
+    // Everything to the right starts at or after this entry, so after the range too
+    if entry.start >= range.end {
+        return;
+    }
+    if entry.end > range.start {
+        found.push(value);
+    }
+    search(&entries[mid + 1..], &max_ends[mid + 1..], range, found);
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{Expr, Grammar};
+
+    // Every query should agree with a walk over the whole grammar
+    #[test]
+    fn matches_walk() {
+        let src = "// Sums\nsum = term ('+' term)*;\nterm = num | ('(' sum ')');\n\nnum = #'[0-9]+' | ('-' num);\nterm = 'x' ;";
+        let g = Grammar::new(src).unwrap();
+        let index = g.span_index();
+
+        for offset in 0..=src.len() {
+            let mut expected = None;
+            for rule in g.rules() {
+                let mut stack: Vec<(&Expr<'_>, usize)> = rule.body.iter().map(|n| (n, 0)).collect();
+                while let Some((node, depth)) = stack.pop() {
+                    if node.span().range().contains(&offset)
+                        && expected.is_none_or(|(_, _, d)| depth > d)
+                    {
+                        expected = Some((rule.name.as_ref(), node, depth));
+                    }
+                    stack.extend(node.children().iter().map(|c| (c, depth + 1)));
+                }
+            }
+            let found = index.node_at(offset).map(|(r, n)| (r.name.as_ref(), n));
+            assert_eq!(found, expected.map(|(r, n, _)| (r, n)), "{offset}");
+
+            let range = offset..offset + 5;
+            let mut expected: Vec<_> = g
+                .rules()
+                .filter_map(|r| {
+                    let first = r
+                        .definitions
+                        .iter()
+                        .find(|d| d.start() < range.end && range.start < d.end())?;
+                    Some((first.start(), r.name.as_ref()))
+                })
+                .collect();
+            expected.sort_unstable();
+            let found: Vec<_> = index
+                .rules_overlapping(range)
+                .iter()
+                .map(|r| r.name.as_ref())
+                .collect();
+            assert_eq!(found, expected.iter().map(|(_, n)| *n).collect::<Vec<_>>());
+        }
+        assert_eq!(index.definition_of("term").unwrap().len(), 2);
+        assert!(index.definition_of("factor").is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use crate::{Grammar, NamedError, Rule, Span, ValidationError, suggest::closest_name};
+
+/// A grammar split across several named inputs, such as one file per part of a language. Rules in any input may refer to rules defined in any other.
+/// ```rust
+/// # use ebnf_toolkit::GrammarSet;
+/// let mut set = GrammarSet::new();
+/// set.add_file("expr.ebnf", "expr = term ('+' term)*;").unwrap();
+/// set.add_file("term.ebnf", "term = #'[0-9]+' | name;").unwrap();
+///
+/// assert_eq!(set.get("term").unwrap().0, "term.ebnf");
+///
+/// let [(file, error)] = &set.undefined_references()[..] else { panic!() };
+/// assert_eq!(*file, "term.ebnf");
+/// assert!(error.to_string().contains("`name`"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GrammarSet<'a> {
+    files: Vec<(&'a str, Grammar<'a>)>,
+}
+
+impl<'a> GrammarSet<'a> {
+    /// Creates a set with no inputs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `input` as the input called `source_name`, then follows its [`Import`](crate::Import)s, and theirs in turn, using `resolver` to find the text of each. An input imported more than once, including by an import cycle, is only loaded the first time.
+    ///
+    /// # Errors
+    /// Loading stops at the first input that can't be resolved or parsed. Once everything is loaded, any rule defined in more than one input is reported with the locations of both definitions.
+    /// ```rust
+    /// # use ebnf_toolkit::GrammarSet;
+    /// let files = [
+    ///     ("main.ebnf", "%import 'expr.ebnf'; program = expr+;"),
+    ///     ("expr.ebnf", "%import 'main.ebnf'; expr = #'[0-9]+';"),
+    /// ];
+    /// let mut resolver = |_importer: &str, path: &str| {
+    ///     files.into_iter().find(|(name, _)| *name == path).ok_or("no such file")
+    /// };
+    ///
+    /// let set = GrammarSet::load("main.ebnf", files[0].1, &mut resolver).unwrap();
+    /// assert_eq!(set.files().count(), 2);
+    /// assert!(set.undefined_references().is_empty());
+    /// ```
+    pub fn load<R: GrammarResolver<'a>>(
+        source_name: &'a str,
+        input: &'a str,
+        resolver: &mut R,
+    ) -> Result<Self, LoadError<'a, R::Error>> {
+        let mut set = GrammarSet::new();
+        let mut pending = VecDeque::from([(source_name, input)]);
+
+        while let Some((name, input)) = pending.pop_front() {
+            if set.files.iter().any(|(loaded, _)| *loaded == name) {
+                continue;
+            }
+            set.add_file(name, input).map_err(LoadError::Parse)?;
+
+            let imports = set.files.last().map_or(&[][..], |(_, g)| &g.imports);
+            for import in imports {
+                let file =
+                    resolver
+                        .resolve(name, import.path)
+                        .map_err(|error| LoadError::Resolve {
+                            importer: name,
+                            path: import.path,
+                            span: import.span,
+                            error,
+                        })?;
+                pending.push_back(file);
+            }
+        }
+
+        let conflicts = set.conflicting_definitions();
+        if conflicts.is_empty() {
+            Ok(set)
+        } else {
+            Err(LoadError::Conflicts(conflicts))
+        }
+    }
+
+    /// Parses `input` and adds it to the set under the name `source_name`
+    ///
+    /// # Errors
+    /// If the input is ill-formed, the set is left unchanged and the error is returned along with `source_name`.
+    pub fn add_file(&mut self, source_name: &'a str, input: &'a str) -> Result<(), NamedError<'a>> {
+        let grammar = Grammar::new_named(input, source_name)?;
+        self.files.push((source_name, grammar));
+        Ok(())
+    }
+
+    /// Iterates through the name and parsed grammar of each input, in the order they were added
+    pub fn files(&self) -> impl Iterator<Item = (&'a str, &Grammar<'a>)> {
+        self.files.iter().map(|(name, grammar)| (*name, grammar))
+    }
+
+    /// Finds the rule with the given name, along with the name of the input defining it. If more than one input defines the rule, the first one added is used.
+    pub fn get(&self, name: &str) -> Option<(&'a str, &Rule<'a>)> {
+        self.files
+            .iter()
+            .find_map(|(file, grammar)| Some((*file, grammar.rules.get(name)?)))
+    }
+
+    /// Every reference to a rule that none of the inputs define, as [`ValidationError::DanglingReference`]s paired with the name of the input containing the reference. Suggestions are drawn from the rules of every input.
+    pub fn undefined_references(&self) -> Vec<(&'a str, ValidationError<'a>)> {
+        let mut errors = Vec::new();
+        for (file, grammar) in &self.files {
+            for rule in grammar.rules.values() {
+                for (name, span) in rule.nonterminal_references() {
+                    if self.get(name).is_none() {
+                        let defined = self.files.iter().flat_map(|(_, g)| g.rules.values());
+                        let suggestion = closest_name(name, defined.clone().map(|r| &*r.name))
+                            .and_then(|found| defined.clone().find(|r| r.name == found))
+                            .map(|r| r.name.clone());
+                        let error = ValidationError::DanglingReference {
+                            rule: rule.name.clone(),
+                            name,
+                            span,
+                            suggestion,
+                        };
+                        errors.push((*file, error));
+                    }
+                }
+            }
+        }
+        errors
+    }
+
+    /// Every rule that is defined by more than one input, as [`ValidationError::DefinedInSeveralInputs`]s. Each later definition is paired with the first one.
+    pub fn conflicting_definitions(&self) -> Vec<ValidationError<'a>> {
+        let mut first_seen: BTreeMap<&str, (&'a str, &Rule<'a>)> = BTreeMap::new();
+        let mut errors = vec![];
+        for &(file, ref grammar) in &self.files {
+            for rule in grammar.rules.values() {
+                if let Some(&(first_file, first_rule)) = first_seen.get(&*rule.name) {
+                    errors.push(ValidationError::DefinedInSeveralInputs {
+                        name: rule.name.clone(),
+                        first: (first_file, first_rule.definitions.clone()),
+                        second: (file, rule.definitions.clone()),
+                    });
+                } else {
+                    first_seen.insert(&rule.name, (file, rule));
+                }
+            }
+        }
+        errors
+    }
+
+    /// Combines the rules from every input into a single [`Grammar`]. Rules defined in more than one input are merged the same way as duplicates within one input.
+    pub fn to_grammar(&self) -> Grammar<'a> {
+        self.files
+            .iter()
+            .flat_map(|(_, grammar)| grammar.rules.values().cloned())
+            .collect()
+    }
+}
+
+/// Supplies [`GrammarSet::load`] with the text of each imported input, so that loading doesn't depend on any particular way of storing or reading files.
+///
+/// This is implemented for any closure taking the same arguments as [`GrammarResolver::resolve`]. Since the loaded grammars borrow from their inputs, a resolver that reads files will usually need to keep their contents somewhere that outlives the [`GrammarSet`], such as an arena.
+pub trait GrammarResolver<'a> {
+    /// The reason an import could not be resolved
+    type Error;
+
+    /// Finds the input that `path` refers to when it is imported by the input named `importer`. Returns the name to give that input, which is used to recognise repeated imports and to label diagnostics, along with its text.
+    ///
+    /// # Errors
+    /// If there is no input at `path`, or it could not be read.
+    fn resolve(&mut self, importer: &str, path: &'a str)
+    -> Result<(&'a str, &'a str), Self::Error>;
+}
+
+impl<'a, F, E> GrammarResolver<'a> for F
+where
+    F: FnMut(&str, &'a str) -> Result<(&'a str, &'a str), E>,
+{
+    type Error = E;
+
+    fn resolve(&mut self, importer: &str, path: &'a str) -> Result<(&'a str, &'a str), E> {
+        self(importer, path)
+    }
+}
+
+/// The ways [`GrammarSet::load`] can fail, where `E` is the error type of the [`GrammarResolver`] being used
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError<'a, E> {
+    /// One of the inputs could not be parsed
+    Parse(NamedError<'a>),
+    /// The resolver could not find an imported input
+    Resolve {
+        /// The name of the input containing the import
+        importer: &'a str,
+        /// The path as written in the import
+        path: &'a str,
+        /// Where the import appears in `importer`
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        error: E,
+    },
+    /// Some rules are defined by more than one input. Every error is a [`ValidationError::DefinedInSeveralInputs`].
+    Conflicts(Vec<ValidationError<'a>>),
+}
+
+impl<E: Display> Display for LoadError<'_, E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LoadError::Parse(error) => write!(f, "{error}"),
+            LoadError::Resolve {
+                importer,
+                path,
+                span,
+                error,
+            } => write!(
+                f,
+                "Could not import `{path}` from `{importer}` at {span}: {error}"
+            ),
+            LoadError::Conflicts(errors) => {
+                for (n, error) in errors.iter().enumerate() {
+                    if n > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{EbnfError, Grammar, GrammarSet, LoadError, Rule, ValidationError};
+
+    #[test]
+    fn cross_file_references() {
+        let mut set = GrammarSet::new();
+        set.add_file("a.ebnf", "A = B | C;").unwrap();
+        set.add_file("b.ebnf", "B = 'b'; C = A;").unwrap();
+
+        assert!(set.undefined_references().is_empty());
+        assert_eq!(set.get("C").unwrap().0, "b.ebnf");
+
+        let g = set.to_grammar();
+        assert_eq!(g.first_dangling_reference(), None);
+        assert!(g.get("A").is_some());
+
+        set.add_file("c.ebnf", "D = Bb;").unwrap();
+        let [("c.ebnf", ValidationError::DanglingReference { suggestion, .. })] =
+            &set.undefined_references()[..]
+        else {
+            panic!()
+        };
+        assert_eq!(suggestion.as_deref(), Some("B"));
+    }
+
+    #[test]
+    fn error_names_file() {
+        let mut set = GrammarSet::new();
+        set.add_file("a.ebnf", "A = B;").unwrap();
+
+        let err = set.add_file("b.ebnf", "B = (A;").unwrap_err();
+        assert_eq!(err.source_name, "b.ebnf");
+        assert!(err.to_string().contains("b.ebnf"));
+        assert_eq!(set.files().count(), 1);
+    }
+
+    #[test]
+    fn imports() {
+        let files = [
+            (
+                "a.ebnf",
+                "%import \"b.ebnf\";\nA = B | C;\n%import 'c.ebnf';",
+            ),
+            ("b.ebnf", "%import 'a.ebnf'; B = 'b';"),
+            ("c.ebnf", "C = 'c';"),
+        ];
+        let mut resolver = |_: &str, path: &str| {
+            files
+                .into_iter()
+                .find(|(name, _)| *name == path)
+                .ok_or(path.len())
+        };
+
+        let set = GrammarSet::load("a.ebnf", files[0].1, &mut resolver).unwrap();
+        let names: Vec<_> = set.files().map(|(name, _)| name).collect();
+        assert_eq!(names, ["a.ebnf", "b.ebnf", "c.ebnf"]);
+        assert!(set.undefined_references().is_empty());
+
+        let err = GrammarSet::load("x.ebnf", "%import 'y.ebnf';", &mut resolver).unwrap_err();
+        assert!(matches!(
+            err,
+            LoadError::Resolve {
+                importer: "x.ebnf",
+                path: "y.ebnf",
+                error: 6,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn conflicting_imports() {
+        let files = [("b.ebnf", "A = 'b';"), ("c.ebnf", "A = 'c';")];
+        let mut resolver =
+            |_: &str, path: &str| files.into_iter().find(|(name, _)| *name == path).ok_or(());
+
+        let src = "%import 'b.ebnf'; %import 'c.ebnf';";
+        let Err(LoadError::Conflicts(errors)) = GrammarSet::load("a.ebnf", src, &mut resolver)
+        else {
+            panic!()
+        };
+        let [
+            ValidationError::DefinedInSeveralInputs {
+                name,
+                first,
+                second,
+            },
+        ] = &errors[..]
+        else {
+            panic!("{errors:?}")
+        };
+        assert_eq!(name, "A");
+        assert_eq!(first.0, "b.ebnf");
+        assert_eq!(second.0, "c.ebnf");
+        assert_eq!(second.1.iter().next().unwrap().range(), 0..8);
+    }
+
+    #[test]
+    fn misplaced_import() {
+        let err = Grammar::new("A = B %import 'b.ebnf'; C;").unwrap_err();
+        assert!(matches!(err, EbnfError::LexError { offset: 6, .. }));
+
+        let g = Grammar::new("%import 'b.ebnf';").unwrap();
+        assert_eq!(g.imports()[0].path, "b.ebnf");
+        assert!(Rule::new("%import 'b.ebnf'; A = B;").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use core::{
+    hash::{Hash, Hasher},
+    mem::discriminant,
+};
+
+use alloc::vec::Vec;
+
+use crate::{Expr, Grammar, Rule};
+
+/// Wraps an [`Expr`], [`Rule`] or [`Grammar`] (or a reference to one) so that it is compared and hashed by its structure alone, ignoring where in the input each part came from. This lets structurally identical rules be deduplicated, or used as keys in a map, even if they were parsed from different places.
+/// ```rust
+/// # use std::collections::HashSet;
+/// # use ebnf_toolkit::{IgnoringSpans, Rule};
+/// let a = Rule::new("A = 'x' B;").unwrap();
+/// let b = Rule::new("A   =   'x'   B;").unwrap();
+/// assert_ne!(a, b);
+///
+/// let set: HashSet<_> = [&a, &b].into_iter().map(IgnoringSpans).collect();
+/// assert_eq!(set.len(), 1);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct IgnoringSpans<T>(pub T);
+
+impl Expr<'_> {
+    /// Whether two nodes have the same structure and contents, regardless of their [`Span`](crate::Span)s or those of their children
+    pub fn eq_ignoring_spans(&self, other: &Expr<'_>) -> bool {
+        fn all_eq(a: &[Expr<'_>], b: &[Expr<'_>]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignoring_spans(b))
+        }
+        match (self, other) {
+            (Expr::Literal { str: a, .. }, Expr::Literal { str: b, .. })
+            | (Expr::Nonterminal { name: a, .. }, Expr::Nonterminal { name: b, .. })
+            | (Expr::Regex { pattern: a, .. }, Expr::Regex { pattern: b, .. }) => a == b,
+            (
+                Expr::Choice {
+                    body: a,
+                    ordered: x,
+                    ..
+                },
+                Expr::Choice {
+                    body: b,
+                    ordered: y,
+                    ..
+                },
+            )
+            | (
+                Expr::Repetition {
+                    body: a,
+                    one_needed: x,
+                    separated: false,
+                    ..
+                },
+                Expr::Repetition {
+                    body: b,
+                    one_needed: y,
+                    separated: false,
+                    ..
+                },
+            )
+            | (
+                Expr::Repetition {
+                    body: a,
+                    one_needed: x,
+                    separated: true,
+                    ..
+                },
+                Expr::Repetition {
+                    body: b,
+                    one_needed: y,
+                    separated: true,
+                    ..
+                },
+            ) => x == y && all_eq(a, b),
+            (Expr::Optional { body: a, .. }, Expr::Optional { body: b, .. })
+            | (Expr::Group { body: a, .. }, Expr::Group { body: b, .. })
+            | (Expr::And { body: a, .. }, Expr::And { body: b, .. })
+            | (Expr::Not { body: a, .. }, Expr::Not { body: b, .. })
+            | (Expr::Hidden { body: a, .. }, Expr::Hidden { body: b, .. }) => all_eq(a, b),
+            (
+                Expr::Labeled {
+                    label: x, body: a, ..
+                },
+                Expr::Labeled {
+                    label: y, body: b, ..
+                },
+            ) => x == y && all_eq(a, b),
+            (Expr::UnparsedOperator { op: a, .. }, Expr::UnparsedOperator { op: b, .. }) => a == b,
+            (Expr::Empty { .. }, Expr::Empty { .. }) => true,
+            (Expr::Rule { rule: a, .. }, Expr::Rule { rule: b, .. }) => a.eq_ignoring_spans(b),
+            _ => false,
+        }
+    }
+
+    fn hash_ignoring_spans<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Expr::Literal { str: text, .. }
+            | Expr::Nonterminal { name: text, .. }
+            | Expr::Regex { pattern: text, .. } => text.hash(state),
+            Expr::Optional { body, .. }
+            | Expr::Group { body, .. }
+            | Expr::And { body, .. }
+            | Expr::Not { body, .. }
+            | Expr::Hidden { body, .. } => hash_all(body, state),
+            Expr::Labeled { label, body, .. } => {
+                label.hash(state);
+                hash_all(body, state);
+            }
+            Expr::Choice {
+                body,
+                ordered: flag,
+                ..
+            } => {
+                flag.hash(state);
+                hash_all(body, state);
+            }
+            Expr::Repetition {
+                body,
+                one_needed,
+                separated,
+                ..
+            } => {
+                one_needed.hash(state);
+                separated.hash(state);
+                hash_all(body, state);
+            }
+            Expr::UnparsedOperator { op, .. } => op.hash(state),
+            Expr::Empty { .. } => {}
+            Expr::Rule { rule, .. } => rule.hash_ignoring_spans(state),
+        }
+    }
+}
+
+fn hash_all<H: Hasher>(nodes: &[Expr<'_>], state: &mut H) {
+    nodes.len().hash(state);
+    for node in nodes {
+        node.hash_ignoring_spans(state);
+    }
+}
+
+impl Rule<'_> {
+    /// Whether two rules have the same name, body, hiding and annotation names, regardless of the [`Span`](crate::Span)s of the body's nodes and annotations or where the rules were defined
+    pub fn eq_ignoring_spans(&self, other: &Rule<'_>) -> bool {
+        self.name == other.name
+            && self.hidden == other.hidden
+            && self.annotations.len() == other.annotations.len()
+            && self
+                .annotations
+                .iter()
+                .zip(&other.annotations)
+                .all(|(a, b)| a.name == b.name)
+            && self.body.len() == other.body.len()
+            && self
+                .body
+                .iter()
+                .zip(&other.body)
+                .all(|(a, b)| a.eq_ignoring_spans(b))
+    }
+
+    fn hash_ignoring_spans<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.hidden.hash(state);
+        self.annotations.len().hash(state);
+        for annotation in &self.annotations {
+            annotation.name.hash(state);
+        }
+        hash_all(&self.body, state);
+    }
+}
+
+impl Grammar<'_> {
+    /// Whether two grammars have the same rules and imports, as compared by [`Rule::eq_ignoring_spans`] and by the path of each import
+    pub fn eq_ignoring_spans(&self, other: &Grammar<'_>) -> bool {
+        self.rules.len() == other.rules.len()
+            && self
+                .rules
+                .values()
+                .zip(other.rules.values())
+                .all(|(a, b)| a.eq_ignoring_spans(b))
+            && self.imports.len() == other.imports.len()
+            && self
+                .imports
+                .iter()
+                .zip(&other.imports)
+                .all(|(a, b)| a.path == b.path)
+    }
+
+    /// A hash of the grammar's structure alone, which is equal for any two grammars that are equal by [`Grammar::eq_ignoring_spans`], whatever order their rules and imports were written in.
+    ///
+    /// Unlike hashing an [`IgnoringSpans`] with the standard library's randomly seeded hasher, the result is the same every time a program runs, so it can key a cache kept between runs. It isn't guaranteed to stay the same between versions of this crate.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let a = Grammar::new("%import \"x\"; %import \"y\"; A = B; B = 'b';").unwrap();
+    /// let b = Grammar::new("B   =   'b';\n%import \"y\";\nA = B;\n%import \"x\";").unwrap();
+    /// let c = Grammar::new("A = B; B = 'c';").unwrap();
+    /// assert_eq!(a.canonical_hash(), b.canonical_hash());
+    /// assert_ne!(a.canonical_hash(), c.canonical_hash());
+    /// ```
+    pub fn canonical_hash(&self) -> u64 {
+        let mut state = Fnv::default();
+        // Rules are already kept sorted by name, but imports are kept in the order they were written
+        self.rules.len().hash(&mut state);
+        for rule in self.rules.values() {
+            rule.hash_ignoring_spans(&mut state);
+        }
+        let mut paths: Vec<_> = self.imports.iter().map(|import| import.path).collect();
+        paths.sort_unstable();
+        paths.hash(&mut state);
+        state.finish()
+    }
+
+    fn hash_ignoring_spans<H: Hasher>(&self, state: &mut H) {
+        self.rules.len().hash(state);
+        for rule in self.rules.values() {
+            rule.hash_ignoring_spans(state);
+        }
+        self.imports.len().hash(state);
+        for import in &self.imports {
+            import.path.hash(state);
+        }
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `std`'s default hasher is unseeded, and available without `std`
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+macro_rules! ignoring_spans_impls {
+    ($($t:ident),*) => {$(
+        impl PartialEq for IgnoringSpans<$t<'_>> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.eq_ignoring_spans(&other.0)
+            }
+        }
+        impl Eq for IgnoringSpans<$t<'_>> {}
+        impl Hash for IgnoringSpans<$t<'_>> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.hash_ignoring_spans(state);
+            }
+        }
+
+        impl PartialEq for IgnoringSpans<&$t<'_>> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.eq_ignoring_spans(other.0)
+            }
+        }
+        impl Eq for IgnoringSpans<&$t<'_>> {}
+        impl Hash for IgnoringSpans<&$t<'_>> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.hash_ignoring_spans(state);
+            }
+        }
+    )*};
+}
+
+ignoring_spans_impls!(Expr, Rule, Grammar);
+
+#[cfg(test)]
+mod test {
+    use crate::{Expr, Grammar, IgnoringSpans};
+
+    #[test]
+    fn ignoring_spans() {
+        let plain = Expr::new("('a' (B | #'c')* [D])").unwrap();
+        let spaced = Expr::new("( 'a'  ( B|#'c' )*  [ D ] )").unwrap();
+        let repeated = Expr::new("('a' (B | #'c')+ [D])").unwrap();
+        assert_ne!(plain, spaced);
+        assert!(plain.eq_ignoring_spans(&spaced));
+        assert!(!plain.eq_ignoring_spans(&repeated));
+        assert!(IgnoringSpans(&plain) == IgnoringSpans(&spaced));
+
+        let forward = Grammar::new("A = B; B = 'b';").unwrap();
+        let backward = Grammar::new("B = 'b';\nA = B;").unwrap();
+        assert!(forward.eq_ignoring_spans(&backward));
+        assert_eq!(forward.canonical_hash(), backward.canonical_hash());
+        assert!(IgnoringSpans(forward) == IgnoringSpans(backward));
+    }
+}
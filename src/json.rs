@@ -0,0 +1,98 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{Expr, expr::NodeKind};
+
+/// Writes `nodes` as a JSON array of objects with the `kind`, `code` and `span` of each node, plus whatever that kind of node carries, such as its `text` or `children`
+pub(crate) fn write_nodes(out: &mut String, nodes: &[Expr<'_>]) {
+    out.push('[');
+    for (n, node) in nodes.iter().enumerate() {
+        if n > 0 {
+            out.push(',');
+        }
+        write_node(out, node);
+    }
+    out.push(']');
+}
+
+fn write_node(out: &mut String, node: &Expr<'_>) {
+    let kind = NodeKind::from(node);
+    let name: &str = kind.into();
+    let span = node.span();
+    write!(
+        out,
+        r#"{{"kind":"{name}","code":{},"span":[{},{}]"#,
+        kind.stable_code(),
+        span.start(),
+        span.end()
+    )
+    .unwrap();
+
+    match node {
+        Expr::Literal { str: text, .. }
+        | Expr::Nonterminal { name: text, .. }
+        | Expr::Regex { pattern: text, .. } => {
+            out.push_str(r#","text":"#);
+            write_json_str(out, text);
+        }
+        Expr::Empty { .. } => {}
+        Expr::UnparsedOperator { op, .. } => {
+            let code = op.stable_code();
+            let op: &str = op.into();
+            write!(out, r#","text":"{op}","op_code":{code}"#).unwrap();
+        }
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated,
+            ..
+        } => {
+            write!(
+                out,
+                r#","one_needed":{one_needed},"separated":{separated},"children":"#
+            )
+            .unwrap();
+            write_nodes(out, body);
+        }
+        Expr::Choice { body, ordered, .. } => {
+            write!(out, r#","ordered":{ordered},"children":"#).unwrap();
+            write_nodes(out, body);
+        }
+        Expr::Optional { body, .. }
+        | Expr::Group { body, .. }
+        | Expr::And { body, .. }
+        | Expr::Not { body, .. }
+        | Expr::Hidden { body, .. } => {
+            out.push_str(r#","children":"#);
+            write_nodes(out, body);
+        }
+        Expr::Labeled { label, body, .. } => {
+            out.push_str(r#","label":"#);
+            write_json_str(out, label);
+            out.push_str(r#","children":"#);
+            write_nodes(out, body);
+        }
+        Expr::Rule { rule, .. } => {
+            out.push_str(r#","children":"#);
+            write_nodes(out, &rule.body);
+        }
+    }
+    out.push('}');
+}
+
+/// Writes `s` as a quoted JSON string
+pub(crate) fn write_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str(r#"\""#),
+            '\\' => out.push_str(r"\\"),
+            '\n' => out.push_str(r"\n"),
+            '\r' => out.push_str(r"\r"),
+            '\t' => out.push_str(r"\t"),
+            c if c.is_control() => write!(out, r"\u{:04x}", u32::from(c)).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
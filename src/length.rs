@@ -0,0 +1,305 @@
+use alloc::{collections::BTreeMap, collections::BTreeSet, vec, vec::Vec};
+
+use crate::{Expr, Grammar};
+
+/// The length of the shortest and longest inputs a rule matches, in bytes, from [`Grammar::length_bounds`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LengthBounds {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub min: usize,
+    /// `None` if the rule matches inputs of any length, through a repetition or recursion
+    pub max: Option<usize>,
+}
+
+/// The bounds of each rule found so far, or `None` for a rule not yet found to match anything
+type Table<'g> = BTreeMap<&'g str, Option<LengthBounds>>;
+
+pub(crate) fn length_bounds(grammar: &Grammar<'_>, start: &str) -> Option<LengthBounds> {
+    grammar.rules.get(start)?;
+    let mut table: Table = grammar.rules.keys().map(|name| (&**name, None)).collect();
+
+    // The shortest lengths come down from matching nothing until they settle. The longest are meaningless until then.
+    loop {
+        let next = step(grammar, &table);
+        let mins = |t: &Table| t.values().map(|b| b.map(|b| b.min)).collect::<Vec<_>>();
+        if mins(&next) == mins(&table) {
+            break;
+        }
+        table = next;
+    }
+
+    // The longest lengths go up from nothing, which only settles once the rules that can grow forever are known
+    let unbounded = unbounded_rules(grammar, &table);
+    for (name, bounds) in &mut table {
+        if let Some(bounds) = bounds {
+            bounds.max = if unbounded.contains(name) {
+                None
+            } else {
+                Some(0)
+            };
+        }
+    }
+    loop {
+        let next = step(grammar, &table);
+        if next == table {
+            break;
+        }
+        table = next;
+    }
+    table[start]
+}
+
+fn step<'g>(grammar: &'g Grammar<'_>, table: &Table<'g>) -> Table<'g> {
+    grammar
+        .rules
+        .iter()
+        .map(|(name, rule)| (&**name, sequence(&rule.body, table)))
+        .collect()
+}
+
+fn sequence(nodes: &[Expr<'_>], table: &Table<'_>) -> Option<LengthBounds> {
+    nodes.iter().try_fold(
+        LengthBounds {
+            min: 0,
+            max: Some(0),
+        },
+        |total, node| {
+            let bounds = node_bounds(node, table)?;
+            Some(LengthBounds {
+                min: total.min.saturating_add(bounds.min),
+                max: total.max.zip(bounds.max).map(|(a, b)| a.saturating_add(b)),
+            })
+        },
+    )
+}
+
+fn node_bounds(node: &Expr<'_>, table: &Table<'_>) -> Option<LengthBounds> {
+    let exactly = |len| {
+        Some(LengthBounds {
+            min: len,
+            max: Some(len),
+        })
+    };
+    let unknown = Some(LengthBounds { min: 0, max: None });
+    match node {
+        Expr::Literal { str, .. } => exactly(str.len()),
+        // An invalid pattern could be meant to match anything
+        Expr::Regex { pattern, .. } => match regex_syntax::parse(pattern) {
+            Ok(hir) => Some(LengthBounds {
+                min: hir.properties().minimum_len()?,
+                max: hir.properties().maximum_len(),
+            }),
+            Err(_) => unknown,
+        },
+        // A rule that isn't defined could match anything
+        Expr::Nonterminal { name, .. } => table.get(name).copied().unwrap_or(unknown),
+        Expr::Choice { body, .. } => body
+            .iter()
+            .filter_map(|alternative| node_bounds(alternative, table))
+            .reduce(|a, b| LengthBounds {
+                min: a.min.min(b.min),
+                max: a.max.zip(b.max).map(|(a, b)| a.max(b)),
+            }),
+        Expr::Optional { body, .. } => Some(LengthBounds {
+            min: 0,
+            max: sequence(body, table).map_or(Some(0), |b| b.max),
+        }),
+        Expr::Repetition { one_needed, .. } => {
+            let (body, separator) = node.repetition_parts().unwrap();
+            let Some(once) = sequence(body, table) else {
+                return if *one_needed { None } else { exactly(0) };
+            };
+            let min = if *one_needed { once.min } else { 0 };
+            let again = match separator {
+                None => Some(once),
+                Some(separator) => node_bounds(separator, table).map(|s| LengthBounds {
+                    min: s.min.saturating_add(once.min),
+                    max: s.max.zip(once.max).map(|(a, b)| a.saturating_add(b)),
+                }),
+            };
+            // Without a separator that matches, there can only be one repetition
+            let max = match again {
+                None | Some(LengthBounds { max: Some(0), .. }) => once.max,
+                Some(_) => None,
+            };
+            Some(LengthBounds { min, max })
+        }
+        Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+            sequence(body, table)
+        }
+        Expr::Rule { rule, .. } => sequence(&rule.body, table),
+        Expr::Empty { .. }
+        | Expr::And { .. }
+        | Expr::Not { .. }
+        | Expr::UnparsedOperator { .. } => exactly(0),
+    }
+}
+
+/// The rules that reach a loop of references that adds to the input each time around, given `table` with the shortest length of each rule. Rules that only reach a repetition that can grow are found to be unbounded by [`node_bounds`].
+fn unbounded_rules<'g>(grammar: &'g Grammar<'_>, table: &Table<'g>) -> BTreeSet<&'g str> {
+    let mut nonempty = BTreeSet::new();
+    loop {
+        let before = nonempty.len();
+        for (name, rule) in &grammar.rules {
+            if table[&**name].is_some() && rule.body.iter().any(|n| grows(n, table, &nonempty)) {
+                nonempty.insert(&**name);
+            }
+        }
+        if nonempty.len() == before {
+            break;
+        }
+    }
+
+    let mut references = BTreeMap::new();
+    for (name, rule) in &grammar.rules {
+        let mut found = vec![];
+        sequence_references(&rule.body, table, &nonempty, false, &mut found);
+        references.insert(&**name, found);
+    }
+    let reaches = |from: &'g str, to: &str| {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(name) = stack.pop() {
+            if name == to {
+                return true;
+            }
+            if seen.insert(name) {
+                stack.extend(references[name].iter().map(|&(next, _)| next));
+            }
+        }
+        false
+    };
+    let loops: Vec<_> = references
+        .iter()
+        .filter(|(name, found)| {
+            found
+                .iter()
+                .any(|&(next, adds)| adds && reaches(next, name))
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    // Everything that reaches a loop is unbounded too, which keeps the longest lengths from going back down while they settle
+    references
+        .keys()
+        .copied()
+        .filter(|name| loops.iter().any(|to| reaches(name, to)))
+        .collect()
+}
+
+/// Whether `node` can match something that isn't empty, given the rules found to do so far
+fn grows(node: &Expr<'_>, table: &Table<'_>, nonempty: &BTreeSet<&str>) -> bool {
+    let sequence_grows = |nodes: &[Expr<'_>]| {
+        sequence(nodes, table).is_some() && nodes.iter().any(|n| grows(n, table, nonempty))
+    };
+    match node {
+        Expr::Nonterminal { name, .. } => !table.contains_key(name) || nonempty.contains(name),
+        Expr::Choice { body, .. } => body.iter().any(|alternative| {
+            node_bounds(alternative, table).is_some() && grows(alternative, table, nonempty)
+        }),
+        Expr::Optional { body, .. }
+        | Expr::Group { body, .. }
+        | Expr::Hidden { body, .. }
+        | Expr::Labeled { body, .. } => sequence_grows(body),
+        Expr::Repetition { .. } => {
+            let (body, separator) = node.repetition_parts().unwrap();
+            sequence_grows(body)
+                || (sequence(body, table).is_some()
+                    && separator.is_some_and(|s| {
+                        node_bounds(s, table).is_some() && grows(s, table, nonempty)
+                    }))
+        }
+        Expr::Rule { rule, .. } => sequence_grows(&rule.body),
+        _ => node_bounds(node, table).is_some_and(|b| b.max != Some(0)),
+    }
+}
+
+/// Collects the rules `nodes` refer to where they can take part in a match, each with whether the input matched around the reference can be more than empty, so that going around a loop through it makes the input longer
+fn sequence_references<'g>(
+    nodes: &'g [Expr<'_>],
+    table: &Table<'_>,
+    nonempty: &BTreeSet<&str>,
+    adds: bool,
+    found: &mut Vec<(&'g str, bool)>,
+) {
+    if sequence(nodes, table).is_none() {
+        return;
+    }
+    for (n, node) in nodes.iter().enumerate() {
+        let others_grow = nodes
+            .iter()
+            .enumerate()
+            .any(|(m, other)| m != n && grows(other, table, nonempty));
+        references(node, table, nonempty, adds || others_grow, found);
+    }
+}
+
+fn references<'g>(
+    node: &'g Expr<'_>,
+    table: &Table<'_>,
+    nonempty: &BTreeSet<&str>,
+    adds: bool,
+    found: &mut Vec<(&'g str, bool)>,
+) {
+    match node {
+        Expr::Nonterminal { name, .. } if table.contains_key(name) => found.push((name, adds)),
+        Expr::Choice { body, .. } => {
+            for alternative in body {
+                if node_bounds(alternative, table).is_some() {
+                    references(alternative, table, nonempty, adds, found);
+                }
+            }
+        }
+        // Repeating a part that can grow adds to the input each time
+        Expr::Repetition { body, .. } => {
+            let adds = adds || grows(node, table, nonempty);
+            let (repeated, _) = node.repetition_parts().unwrap();
+            sequence_references(repeated, table, nonempty, adds, found);
+            if sequence(repeated, table).is_some() {
+                for separator in &body[repeated.len()..] {
+                    references(separator, table, nonempty, adds, found);
+                }
+            }
+        }
+        Expr::Optional { body, .. }
+        | Expr::Group { body, .. }
+        | Expr::Hidden { body, .. }
+        | Expr::Labeled { body, .. } => sequence_references(body, table, nonempty, adds, found),
+        Expr::Rule { rule, .. } => sequence_references(&rule.body, table, nonempty, adds, found),
+        // Lookaheads don't take part in what is matched
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Grammar, LengthBounds};
+
+    #[test]
+    fn length_bounds() {
+        let g = Grammar::new(
+            "crlf = '\\r'? '\\n'?; digits = #'[0-9]{2,4}' ('.' #'[0-9]')?; list = item % ','; \
+             item = 'a' | ('(' list ')'); silly = (silly) | 'x'; none = 'x' none; \
+             either = none | 'ab'; spaced = ' '* (' ')* % ''; dangling = 'x' missing; \
+             chain = link 'z'; link = (chain) | 'y'; empty = (empty)*;",
+        )
+        .unwrap();
+        let bounds = |name| g.length_bounds(name).map(|b| (b.min, b.max));
+        assert_eq!(bounds("crlf"), Some((0, Some(4))));
+        assert_eq!(bounds("digits"), Some((2, Some(6))));
+        assert_eq!(bounds("list"), Some((1, None)));
+        assert_eq!(bounds("item"), Some((1, None)));
+        // Going around a loop without adding anything doesn't make a rule unbounded
+        assert_eq!(bounds("silly"), Some((1, Some(1))));
+        assert_eq!(bounds("empty"), Some((0, Some(0))));
+        assert_eq!(bounds("none"), None);
+        assert_eq!(bounds("either"), Some((2, Some(2))));
+        assert_eq!(bounds("spaced"), Some((0, None)));
+        assert_eq!(bounds("dangling"), Some((1, None)));
+        assert_eq!(bounds("chain"), Some((2, None)));
+        assert_eq!(g.length_bounds("missing"), None);
+        assert_eq!(
+            g.length_bounds("link"),
+            Some(LengthBounds { min: 1, max: None })
+        );
+    }
+}
@@ -12,10 +12,14 @@
 //! * [`Rule`] - a production rule, associating the name of a nonterminal with a body consisting a sequence of `Expr`.
 //! * [`Grammar`] - a set of `Rule`s. Using [`Grammar::new`] to construct a `Grammar` from a `&str` is likely your first port of call in using this crate.
 //!
-//! Parse failures from any of these types will produce an [`EbnfError`]. The type documentation has a breakdown of possible error conditions, but the value can be passed to `Display` to produce a human-readable report of what went wrong. For instance, attempting to parse `rule = (?;` via [`Rule::new`] will result in:
+//! A grammar spread across several files can be loaded into a [`GrammarSet`], which keeps track of which file each rule came from. A file can name the others it depends on with an `%import "path";` directive, which [`GrammarSet::load`] follows.
+//!
+//! Grammars written inside a Markdown document, such as a language specification, can be collected from its ```` ```ebnf ```` or ```` ```bnf ```` code fences with [`Grammar::from_markdown`].
+//!
+//! Parse failures from any of these types will produce an [`EbnfError`]. The type documentation has a breakdown of possible error conditions, but the value can be passed to `Display` to produce a human-readable report of what went wrong, headed by the stable [`ErrorCode`] of the problem. For instance, attempting to parse `rule = (?;` via [`Rule::new`] will result in:
 //!
 //! ```plain
-//!Error:
+//![E001] Error:
 //!   ╭─[ <input>:1:1 ]
 //!   │
 //! 1 │ rule = (?;
@@ -52,18 +56,40 @@
 //! |[`Nonterminal`](`Expr::Nonterminal`)| One or more letters, numbers or underscores || Yes, `_` and `42` are valid nonterminal names |
 //! |[`Regex`](`Expr::Regex`)| `/regular expression/`| `#'regular expression'` | As defined by [regex](https://docs.rs/regex/latest/regex/), escapes within the regex are processed per that crate|
 //! |[`Optional`](`Expr::Optional`)| `x?` | `[x]` ||
-//! |[`Choice`](`Expr::Choice`)| `x\|y` | `x / y` | Both notations are infix |
+//! |[`Choice`](`Expr::Choice`)| `x\|y` | `x / y` | Both notations are infix. `/` is the PEG notation for ordered choice and sets `ordered`, which `Display` keeps. Mixing the two without brackets groups from the left, so `a \| b / c` is `(a \| b) / c` |
+//! |[`Empty`](`Expr::Empty`)| `ε` | `''` *or* `""` | Matches the empty string. An alternative left out of a choice, as in `x \| ;` or `(\| x)`, is also `Empty` |
 //! |[`Repetition`](`Expr::Repetition`)| `x*` *or* `{x}` | `x+` | Either of the first two notations denotes zero-or-more - `x+` is specifically one-or-more|
+//! |Separated [`Repetition`](`Expr::Repetition`)| `x % s` | `x* % s` | One or more `x` with an `s` between each, or zero or more for the alternative. Takes the single terms either side, so `a b % ','` separates `b` alone. Sets `separated`, see [`Expr::repetition_parts`] and [`Grammar::lower_separated_repetitions`]|
 //! |[`Group`](`Expr::Group`)| `(x...)` | | (This is unlikely to appear directly in output, see below)|
-//! |[`Rule`](`Expr::Rule`)| `name = x...;` | | Any number of nodes may follow the `=` - terminating semicolon is mandatory|
+//! |[`And`](`Expr::And`)| `&x` | | Lookahead - matches without consuming input. Postfix operators bind first, so `&x*` is `&(x*)` |
+//! |[`Not`](`Expr::Not`)| `!x` | | Negative lookahead, as for `And` |
+//! |[`Hidden`](`Expr::Hidden`)| `<x...>` | | Left out of the output tree, as in instaparse |
+//! |[`Labeled`](`Expr::Labeled`)| `name:x` | | Names a single term for code generators. Postfix operators bind first, so `name:x*` is `name:(x*)` |
+//! |[`Rule`](`Expr::Rule`)| `name = x...;` | `<name> = x...;` | Any number of nodes may follow the `=` - terminating semicolon is mandatory. The alternative sets [`Rule::hidden`]|
 //!
 //! Concatenation in the body of a rule or within brackets (including the bracket notations for `Repetition` and `Optional`) may optionally use `,` but no separator is required, i.e. `(xy)` and `(x,y)` are equivalent. The expressions that can be written with brackets can contain any number of child nodes. (e.g. `[xyz]` is equivalent to `(xyz)?`, see below)
 //!
+//! A rule can be preceded by any number of annotations such as `@token` or `@skip`, which are collected in [`Rule::annotations`] for tools generating parsers to interpret. They have no effect on the rule itself.
+//!
 //! ## Reductions
 //!
 //! In order to simplify making the `Display` string representation round-trip correctly, after a syntax tree is produced from the input string, it is then reduced to an equivalent but smaller tree by applying several rules:
-//! * a series of consecutive choices, `a|b|c|d|...` is transformed into a single *n*-ary [`Choice`](`Expr::Choice`) node, `Choice { body: [a,b,c,d, ..], ..}` rather than a binary tree
-//! * A `Group`, `Optional` or `Repetition` node `E` that contains a single `Group` child node is simplified by removing the intermediate node and placing its children as `E`'s direct children.
+//! * a series of consecutive choices with the same operator, `a|b|c|d|...` is transformed into a single *n*-ary [`Choice`](`Expr::Choice`) node, `Choice { body: [a,b,c,d, ..], ..}` rather than a binary tree
+//! * A `Group`, `Optional`, `Repetition`, `And`, `Not` or `Hidden` node `E` that contains a single `Group` child node is simplified by removing the intermediate node and placing its children as `E`'s direct children.
+//!
+//! Setting [`ParseOptions::simplify`] to `false` skips these, keeping the tree exactly as written. The [`simplification`] module has the reductions as separate passes, which [`Grammar::simplify_with`] can apply afterwards alongside passes of your own.
+//!
+//! ## Features
+//!
+//! The lexer, parser and syntax types only need `alloc`, so the crate can be used in `no_std` environments by disabling default features. The optional features are:
+//! * `std` - implements [`DisplayTree`](https://docs.rs/display_tree/latest/display_tree/trait.DisplayTree.html) for syntax trees, and uses `std` in the dependencies that support it
+//! * `report` (default, implies `std`) - the `Display` output of [`EbnfError`] is a full diagnostic report rendered by [ariadne](https://crates.io/crates/ariadne). `EbnfError::render` lays the report out with a `ReportConfig`, which can turn off color codes. Without this feature, `Display` only gives a one-line summary.
+//! * `wasm` (implies `report`) - JavaScript bindings via wasm-bindgen, in the `wasm` module
+//! * `proptest` - adds the [`arbitrary`] module of [proptest](https://docs.rs/proptest) strategies that generate syntax trees and valid grammars, for fuzzing tools that consume EBNF
+//! * `serde` - implements `Serialize` and `Deserialize` for [`StructuredGrammar`] and the types within it
+//! * `cli` (implies `report`) - builds the `ebnf` command-line tool, whose `check`, `fmt`, `graph`, `lint` and `gen` subcommands run the corresponding parts of the library over a grammar file and the files it imports
+//! * `unicode-width` - adds [`Span::display_columns`], which gives the columns a span is drawn at in a terminal, accounting for wide and zero-width characters. Reports from the `report` feature already line up without this.
+#![cfg_attr(not(feature = "std"), no_std)]
 #![forbid(unsafe_code)]
 #![warn(explicit_outlives_requirements)]
 #![warn(missing_debug_implementations)]
@@ -77,35 +103,161 @@
 #![warn(unused)]
 #![allow(clippy::must_use_candidate, reason = "Fires too often")]
 
+extern crate alloc;
+
+mod alternatives;
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod benchmarking;
+mod bytes;
+mod classify;
+mod compile;
+mod compose;
+mod confusables;
+mod convert;
+mod coverage;
+#[cfg(feature = "std")]
 mod debug;
+mod derivations;
+mod diagnostic;
+mod dialect;
 mod error;
+mod expand;
+mod export;
 mod expr;
+mod first_set;
+mod fold;
+mod frozen;
+mod generate;
+mod grammar_index;
+mod grammar_set;
+mod ignoring_spans;
+mod json;
+mod length;
+mod limits;
+pub mod lint;
+mod lookahead;
+mod lossless;
+mod markdown;
+mod matcher;
+mod naming;
+mod node_ids;
+mod overlap;
 mod parser;
+mod pest;
 mod proptesting;
+mod refactor;
+mod reference_graph;
+mod regex_terminal;
+mod reorder;
+#[cfg(feature = "report")]
+mod report;
 mod rule;
-mod simplification;
+mod rule_ids;
+mod sexpr;
+mod shared;
+mod shrink;
+pub mod simplification;
+mod structured;
+mod suggest;
+mod terminal_size;
 mod token_data;
+mod token_diff;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod yacc;
 
 pub use crate::{
-    error::{EbnfError, FailureReason},
-    expr::Expr,
-    rule::{Grammar, Rule},
-    token_data::Span,
+    alternatives::{AlternativeIssue, AlternativeIssueKind, MixedChoice},
+    bytes::BytesError,
+    classify::RuleClass,
+    compile::CompileError,
+    confusables::{CharacterIssue, SuspiciousCharacter},
+    convert::{Approximation, Conversion, ConvertError, convert},
+    coverage::{AlternativeCoverage, Coverage},
+    dialect::{BracketPair, CommentStyle, Dialect, OperatorRole, OperatorSpelling},
+    error::{BracketImbalance, EbnfError, ErrorCode, FailureReason, NamedError, ValidationError},
+    export::{ExportError, ExportOptions, GrmtoolsGrammar},
+    expr::{Expr, NodeKind},
+    first_set::FirstSet,
+    frozen::FrozenGrammar,
+    generate::{GenerateError, GenerateOptions, Generator, Weights},
+    grammar_index::GrammarIndex,
+    grammar_set::{GrammarResolver, GrammarSet, LoadError},
+    ignoring_spans::IgnoringSpans,
+    length::LengthBounds,
+    limits::{ParseLimit, ParseOptions},
+    lookahead::Lookahead,
+    lossless::LosslessGrammar,
+    markdown::MarkdownGrammar,
+    naming::{NameCase, NamingConvention, NamingIssue},
+    node_ids::{NodeId, NodeIds, RuleId},
+    overlap::{LexicalConflict, RegexOverlap, TerminalOverlap},
+    pest::PestError,
+    reference_graph::RecursionCycle,
+    reorder::MovedAlternative,
+    rule::{Annotation, ChoiceSemantics, Grammar, Import, Rule, ValidationLevel},
+    rule_ids::RuleIds,
+    sexpr::SexprError,
+    shared::SharedGrammar,
+    structured::{
+        StructuredAnnotation, StructuredGrammar, StructuredImport, StructuredNode, StructuredRule,
+        StructuredSpan,
+    },
+    terminal_size::{OversizedTerminal, TerminalLimits},
+    token_data::{Span, SpanSet, Token, TokenKind, TokenPayload, lex},
+    token_diff::{ChangedRule, SourceDiff},
+    yacc::YaccError,
 };
 
+#[cfg(feature = "report")]
+pub use crate::report::ReportConfig;
+
+#[cfg(feature = "cli")]
+use clap as _; // Only used by the `ebnf` binary
+
+// Only used by the benchmarks, but dev-dependencies are seen by the library's own tests too
+#[cfg(test)]
+use criterion as _;
+
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    parser::LrStack,
+    limits::check_token_limits,
+    parser::{LrStack, Recognizer},
     simplification::simplify_node,
-    token_data::{Token, TokenPayload},
 };
 
+/// `end_of_input` is the offset reported if the tokens run out part way through a rule, which will be the length of `input` unless only part of it was tokenized
 fn parse_rules_from_tokens<'a>(
     input: &'a str,
+    end_of_input: usize,
     input_tokens: &mut &[Token<'a>],
+    options: ParseOptions,
 ) -> Result<Vec<Rule<'a>>, EbnfError<'a>> {
+    check_token_limits(input, input_tokens, options)?;
+
     let mut outputs = vec![];
     let mut stack = LrStack::new();
 
+    // The tokenizer has already checked that annotations only come before rules, so they can be set aside and given to the next rule to finish
+    let mut annotations = vec![];
+    let tokens: Vec<_> = input_tokens
+        .iter()
+        .filter(|t| match t.payload {
+            TokenPayload::Annotation(name) => {
+                annotations.push(Annotation { name, span: t.span });
+                false
+            }
+            _ => true,
+        })
+        .copied()
+        .collect();
+    let mut annotations = annotations.into_iter().peekable();
+    let input_tokens = &mut &tokens[..];
+
     let num_tokens = input_tokens.len();
 
     let Some(first_token) = input_tokens.split_off_first() else {
@@ -137,10 +289,24 @@ fn parse_rules_from_tokens<'a>(
 
         if let Some(Expr::Rule { .. }) = stack.peek_node() {
             let mut rule_node = stack.pop_node().unwrap();
-            simplify_node(&mut rule_node);
-            let Expr::Rule { rule, .. } = rule_node else {
+            if options.simplify {
+                simplify_node(&mut rule_node);
+            }
+            let Expr::Rule { mut rule, .. } = rule_node else {
                 unreachable!()
             };
+            let start = rule.definitions.hull().map_or(0, |s| s.start());
+            if outputs.len() == options.max_rules {
+                return Err(EbnfError::LimitExceeded {
+                    input,
+                    offset: start,
+                    limit: ParseLimit::Rules,
+                    max: options.max_rules,
+                });
+            }
+            while let Some(annotation) = annotations.next_if(|a| a.span.start() < start) {
+                rule.annotations.push(annotation);
+            }
             outputs.push(rule);
         }
 
@@ -157,18 +323,46 @@ fn parse_rules_from_tokens<'a>(
         Err({
             EbnfError::ParseError {
                 input,
-                offset: input.len(),
+                offset: end_of_input,
                 reason: Some(FailureReason::ExhaustedInput(stack.into_parse_stack())),
             }
         })
     }
 }
 
+/// Runs the same loop as [`parse_rules_from_tokens`] without building any nodes, returning whether every token was consumed by a complete rule
+fn recognize_rules(input_tokens: &[Token<'_>]) -> bool {
+    let mut stack = Recognizer::default();
+    let mut input_tokens = input_tokens
+        .iter()
+        .filter(|t| !matches!(t.payload, TokenPayload::Annotation(_)));
+
+    let Some(first_token) = input_tokens.next() else {
+        return false;
+    };
+    stack.push_token(*first_token);
+
+    let mut end_of_rule_expected = false;
+    loop {
+        let rule_completed = stack.reduce_until_shift_needed();
+        if end_of_rule_expected && !rule_completed {
+            return false;
+        }
+
+        let Some(new_token) = input_tokens.next() else {
+            break;
+        };
+        end_of_rule_expected = TokenPayload::Termination == new_token.payload;
+        stack.push_token(*new_token);
+    }
+    stack.is_empty()
+}
+
 #[cfg(test)]
 mod tests {
     use display_tree::format_tree;
 
-    use crate::{Expr, Rule};
+    use crate::{EbnfError, ErrorCode, Expr, Grammar, Rule, Span};
 
     #[test]
     fn basic_success() {
@@ -177,7 +371,7 @@ mod tests {
 
         let parse = Rule::new(src).unwrap_or_else(|e| panic!("{e}"));
 
-        insta::assert_compact_debug_snapshot!(parse, @r#"Rule { name: "message", body: [Optional { span: Span { start: 19, end: 33, line_offset_start: (1, 19), line_offset_end: (1, 33) }, body: [Literal { span: Span { start: 19, end: 22, line_offset_start: (1, 19), line_offset_end: (1, 22) }, str: "@" }, Nonterminal { span: Span { start: 23, end: 27, line_offset_start: (1, 23), line_offset_end: (1, 27) }, name: "tags" }, Nonterminal { span: Span { start: 28, end: 33, line_offset_start: (1, 28), line_offset_end: (1, 33) }, name: "SPACE" }] }, Optional { span: Span { start: 36, end: 52, line_offset_start: (1, 36), line_offset_end: (1, 52) }, body: [Literal { span: Span { start: 36, end: 39, line_offset_start: (1, 36), line_offset_end: (1, 39) }, str: ":" }, Nonterminal { span: Span { start: 40, end: 46, line_offset_start: (1, 40), line_offset_end: (1, 46) }, name: "source" }, Nonterminal { span: Span { start: 47, end: 52, line_offset_start: (1, 47), line_offset_end: (1, 52) }, name: "SPACE" }] }, Nonterminal { span: Span { start: 55, end: 62, line_offset_start: (1, 55), line_offset_end: (1, 62) }, name: "command" }, Optional { span: Span { start: 64, end: 74, line_offset_start: (1, 64), line_offset_end: (1, 74) }, body: [Nonterminal { span: Span { start: 64, end: 74, line_offset_start: (1, 64), line_offset_end: (1, 74) }, name: "parameters" }] }, Nonterminal { span: Span { start: 76, end: 80, line_offset_start: (1, 76), line_offset_end: (1, 80) }, name: "crlf" }] }"#);
+        insta::assert_compact_debug_snapshot!(parse, @r#"Rule { name: "message", body: [Optional { span: Span { start: 19, end: 33, line_offset_start: (1, 19), line_offset_end: (1, 33) }, body: [Literal { span: Span { start: 19, end: 22, line_offset_start: (1, 19), line_offset_end: (1, 22) }, str: "@" }, Nonterminal { span: Span { start: 23, end: 27, line_offset_start: (1, 23), line_offset_end: (1, 27) }, name: "tags" }, Nonterminal { span: Span { start: 28, end: 33, line_offset_start: (1, 28), line_offset_end: (1, 33) }, name: "SPACE" }] }, Optional { span: Span { start: 36, end: 52, line_offset_start: (1, 36), line_offset_end: (1, 52) }, body: [Literal { span: Span { start: 36, end: 39, line_offset_start: (1, 36), line_offset_end: (1, 39) }, str: ":" }, Nonterminal { span: Span { start: 40, end: 46, line_offset_start: (1, 40), line_offset_end: (1, 46) }, name: "source" }, Nonterminal { span: Span { start: 47, end: 52, line_offset_start: (1, 47), line_offset_end: (1, 52) }, name: "SPACE" }] }, Nonterminal { span: Span { start: 55, end: 62, line_offset_start: (1, 55), line_offset_end: (1, 62) }, name: "command" }, Optional { span: Span { start: 64, end: 74, line_offset_start: (1, 64), line_offset_end: (1, 74) }, body: [Nonterminal { span: Span { start: 64, end: 74, line_offset_start: (1, 64), line_offset_end: (1, 74) }, name: "parameters" }] }, Nonterminal { span: Span { start: 76, end: 80, line_offset_start: (1, 76), line_offset_end: (1, 80) }, name: "crlf" }], definitions: SpanSet([Span { start: 0, end: 81, line_offset_start: (1, 0), line_offset_end: (1, 81) }]), hidden: false, annotations: [] }"#);
     }
 
     #[test]
@@ -214,6 +408,234 @@ mod tests {
         ");
     }
 
+    #[test]
+    fn lookahead_success() {
+        let parse = Expr::new("&'a'*").unwrap_or_else(|e| panic!("{e}"));
+        let Expr::And { span, body } = &parse else {
+            panic!("{parse:?}")
+        };
+        assert_eq!(span.range(), 1..4);
+        assert!(matches!(
+            body[..],
+            [Expr::Repetition {
+                one_needed: false,
+                ..
+            }]
+        ));
+
+        let parse = Expr::new("!'a' | 'b'").unwrap_or_else(|e| panic!("{e}"));
+        assert!(
+            matches!(&parse, Expr::Choice { body, .. } if matches!(body[..], [Expr::Not { .. }, Expr::Literal { .. }]))
+        );
+
+        let rule = Rule::new("A = !B 'c';").unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(
+            rule.body[..],
+            [Expr::Not { .. }, Expr::Literal { .. }]
+        ));
+    }
+
+    #[test]
+    fn hidden_success() {
+        let rule = Rule::new("<ws> = <' '+> comment?;").unwrap_or_else(|e| panic!("{e}"));
+        assert_eq!(rule.name, "ws");
+        assert!(rule.hidden);
+        assert!(matches!(
+            &rule.body[..],
+            [Expr::Hidden { body, .. }, Expr::Optional { .. }] if matches!(body[..], [Expr::Repetition { .. }])
+        ));
+
+        let parse = Expr::new("<A>").unwrap_or_else(|e| panic!("{e}"));
+        assert!(
+            matches!(&parse, Expr::Hidden { body, .. } if matches!(body[..], [Expr::Nonterminal { name: "A", .. }]))
+        );
+
+        let g = Grammar::new("<A> = B <C>; A = 'a'; B = 'b';").unwrap_or_else(|e| panic!("{e}"));
+        assert!(g["A"].hidden);
+        assert!(!g["B"].hidden);
+
+        let rule = Expr::new("<A> = B <C>;").unwrap();
+        let reparsed = rule.to_string();
+        assert!(
+            Expr::new(&reparsed).unwrap().eq_ignoring_spans(&rule),
+            "{reparsed}"
+        );
+    }
+
+    #[test]
+    fn labeled_success() {
+        let rule = Rule::new("pair = key:ident '=' value:(ident | number);")
+            .unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(
+            &rule.body[..],
+            [Expr::Labeled { label: "key", body: key, .. }, Expr::Literal { .. }, Expr::Labeled { label: "value", body: value, .. }]
+                if matches!(key[..], [Expr::Nonterminal { name: "ident", .. }])
+                && matches!(value[..], [Expr::Choice { .. }])
+        ));
+
+        let rule = Expr::new("pair = key:ident '=' value:(ident | number);").unwrap();
+        let reparsed = rule.to_string();
+        assert!(
+            Expr::new(&reparsed).unwrap().eq_ignoring_spans(&rule),
+            "{reparsed}"
+        );
+
+        let parse = Expr::new("items:item*").unwrap_or_else(|e| panic!("{e}"));
+        assert!(
+            matches!(&parse, Expr::Labeled { label: "items", body, .. } if matches!(body[..], [Expr::Repetition { .. }]))
+        );
+
+        let parse = Expr::new("a:A | (!b:B)").unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(
+            &parse,
+            Expr::Choice { body, .. } if matches!(&body[..], [Expr::Labeled { .. }, Expr::Not { body, .. }]
+                if matches!(body[..], [Expr::Labeled { label: "b", .. }]))
+        ));
+
+        assert!(Expr::new("'a':A").is_err());
+    }
+
+    #[test]
+    fn separated_success() {
+        let rule =
+            Rule::new("args = '(' (expr ':' type)* % ',' ')';").unwrap_or_else(|e| panic!("{e}"));
+        let Expr::Repetition {
+            one_needed: false,
+            separated: true,
+            ..
+        } = &rule.body[1]
+        else {
+            panic!("{rule:?}")
+        };
+        let (repeated, separator) = rule.body[1].repetition_parts().unwrap();
+        assert_eq!(repeated.len(), 3);
+        assert!(matches!(separator, Some(Expr::Literal { str: ",", .. })));
+
+        // Only the term before the `%` is repeated
+        let parse = Expr::new("(a b % (',' ws))").unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(
+            &parse,
+            Expr::Group { body, .. } if matches!(&body[..], [Expr::Nonterminal { .. }, Expr::Repetition { one_needed: true, separated: true, body, .. }]
+                if matches!(body[..], [Expr::Nonterminal { name: "b", .. }, Expr::Group { .. }]))
+        ));
+
+        for src in [
+            "args = '(' (expr ':' type)* % ',' ')';",
+            "a = b % (',' ws) c;",
+            "a = (b % ',') % ';';",
+        ] {
+            let rule = Expr::new(src).unwrap();
+            let reparsed = rule.to_string();
+            assert!(
+                Expr::new(&reparsed).unwrap().eq_ignoring_spans(&rule),
+                "{reparsed}"
+            );
+        }
+        assert!(
+            !Expr::new("b % ','")
+                .unwrap()
+                .eq_ignoring_spans(&Expr::new("(b ',')+").unwrap())
+        );
+        assert!(Expr::new("% ','").is_err());
+    }
+
+    #[test]
+    fn ordered_choice() {
+        let parse = Expr::new("a / b / c").unwrap_or_else(|e| panic!("{e}"));
+        assert!(
+            matches!(&parse, Expr::Choice { body, ordered: true, .. } if body.len() == 3),
+            "{parse:?}"
+        );
+        assert_eq!(parse.to_string(), "((a)/(b)/(c))");
+
+        let parse = Expr::new("a | b / c").unwrap_or_else(|e| panic!("{e}"));
+        assert!(matches!(
+            &parse,
+            Expr::Choice { body, ordered: true, .. }
+                if matches!(&body[..], [Expr::Choice { ordered: false, .. }, Expr::Nonterminal { name: "c", .. }])
+        ));
+        let reparsed = parse.to_string();
+        assert!(
+            Expr::new(&reparsed).unwrap().eq_ignoring_spans(&parse),
+            "{reparsed}"
+        );
+        assert!(!parse.eq_ignoring_spans(&Expr::new("a | b | c").unwrap()));
+    }
+
+    #[test]
+    fn empty_production() {
+        let empty = |node: &Expr<'_>| matches!(node, Expr::Empty { .. });
+        for src in [
+            "A = B | ;",
+            "A = | B;",
+            "A = B | ε;",
+            "A = B | '';",
+            "A = (B |);",
+        ] {
+            let rule = Rule::new(src).unwrap_or_else(|e| panic!("{e}"));
+            let Expr::Choice { body, .. } = &rule.body[0] else {
+                panic!("{rule:?}")
+            };
+            assert_eq!(body.iter().filter(|n| empty(n)).count(), 1, "{src}");
+        }
+
+        let rule = Rule::new("A = B | ;").unwrap();
+        let Expr::Choice { body, .. } = &rule.body[0] else {
+            panic!()
+        };
+        assert_eq!(body[1].span().range(), 7..7);
+        assert_eq!(rule.to_string(), "A = ((B)|(ε));");
+        let reparsed = Rule::new("A = ((B)|(ε));").unwrap();
+        assert!(reparsed.eq_ignoring_spans(&rule));
+
+        let g = Grammar::new("A = 'a' | ; B = ε; C = 'c' A;").unwrap();
+        let nullable: Vec<_> = g.first_sets().iter().map(|s| s.nullable).collect();
+        assert_eq!(nullable, [true, true, false]);
+    }
+
+    #[test]
+    fn rule_new_at() {
+        let doc = "Intro\nsee: @token A =\n  'a' | B;";
+        let start = doc.find('@').unwrap();
+        let rule = Rule::new_at(&doc[start..], start, 2, 5).unwrap_or_else(|e| panic!("{e}"));
+
+        assert_eq!(&doc[rule.annotations[0].span.range()], "@token");
+        assert_eq!(rule.annotations[0].span.start_line(), (2, 5));
+        let span = rule.definitions.hull().unwrap();
+        assert_eq!(&doc[span.range()], "A =\n  'a' | B;");
+        assert_eq!(span.start_line(), (2, 12));
+        assert_eq!(span.end_line(), (3, 10));
+        assert_eq!(rule.body[0].span().start_line(), (3, 2));
+    }
+
+    #[test]
+    fn annotations() {
+        let g = Grammar::new("@token @skip\nws = ' '+;\n@token ws = '\\t';\n@keyword <kw> = 'if';")
+            .unwrap_or_else(|e| panic!("{e}"));
+        let names = |rule: &str| -> Vec<_> { g[rule].annotations.iter().map(|a| a.name).collect() };
+        assert_eq!(names("ws"), ["token", "skip"]);
+        assert_eq!(names("kw"), ["keyword"]);
+        assert!(g["kw"].hidden);
+
+        let annotated = Expr::Rule {
+            span: Span::default(),
+            rule: Rule::new("@token A = B;").unwrap(),
+        };
+        assert_eq!(annotated.to_string(), "@token A = B;");
+
+        for misplaced in [
+            "A = @token B;",
+            "A = B; @token",
+            "@token %import \"x\"; A = B;",
+        ] {
+            assert!(
+                matches!(Grammar::new(misplaced), Err(EbnfError::LexError { .. })),
+                "{misplaced}"
+            );
+        }
+        assert!(Expr::new("@token A = B;").is_err());
+    }
+
     #[test]
     fn op_parse_fail() {
         let src = ";";
@@ -221,9 +643,38 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
+    fn check_syntax_agrees() {
+        let srcs = [
+            "A = B;",
+            "A = B | C; B = [x] {y} (z w)*;",
+            "<A> = <B> C; D = <(E F)>;",
+            "<(A)> = B;",
+            "< A > = B;",
+            "@token @skip A = B; @x C = D;",
+            "A = @token B;",
+            "A = B | C = D;",
+            "A = B | C; | D",
+            "A = a:B &c:D e:!F;",
+            "A = a:b:C;",
+            "Foo = (A;",
+            "A = B",
+            "'hi' = A;",
+            "",
+        ];
+        for src in srcs {
+            assert_eq!(
+                Grammar::check_syntax(src),
+                Grammar::new(src).map(|_| ()),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
     fn bracket_parse_fail() {
-        let src = "{}";
-        Expr::new(src).unwrap_or_else(|e| panic!("{e}"));
+        for src in ["{}", "()", "[]", "<>", "A = 'a' ( );", "A = [{}];"] {
+            let err = Expr::new(src).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::EmptyBrackets, "{src}");
+        }
     }
 }
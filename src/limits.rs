@@ -0,0 +1,319 @@
+use alloc::vec::Vec;
+
+use crate::{EbnfError, Token, TokenPayload};
+
+/// Limits on how large an input [`Grammar::new_with_options`](crate::Grammar::new_with_options) will try to parse. Cloning, displaying and dropping a syntax tree recurse once for each level of nesting, so an input nested deeply enough, like ten thousand `(`, would otherwise overflow the stack, and a very large one can take a long time or a lot of memory. Going over a limit gives an [`EbnfError::LimitExceeded`] instead.
+///
+/// The default only limits the nesting depth, to a level that no hand-written grammar comes near, and it applies to every other way of parsing too.
+/// ```rust
+/// # use ebnf_toolkit::{EbnfError, Grammar, ParseLimit, ParseOptions};
+/// let deep = format!("A = {}B{};", "(".repeat(10_000), ")".repeat(10_000));
+/// assert!(matches!(
+///     Grammar::new(&deep),
+///     Err(EbnfError::LimitExceeded { limit: ParseLimit::NestingDepth, max: 256, .. })
+/// ));
+///
+/// let options = ParseOptions { max_rules: 1, ..ParseOptions::default() };
+/// assert!(Grammar::new_with_options("A = 'a';", options).is_ok());
+/// assert!(Grammar::new_with_options("A = 'a'; B = 'b';", options).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    /// How deeply terms may be nested. Each bracket that hasn't been closed counts once, as does each of a run of operators like `!!x` or `x?*` that apply to the same term. A run of alternatives counts once, and once more for each change between `|` and `/`, since `a | b / c | d` nests a choice inside another for each, and each `%` in a run like `a % b % c` counts once. Without [`simplify`](ParseOptions::simplify), each `|` or `/` counts too, as the choices aren't merged.
+    pub max_depth: usize,
+    /// How many tokens the input may contain, not counting whitespace, comments or `%import` directives
+    pub max_tokens: usize,
+    /// How many rules the input may define, counting each definition of a rule defined more than once
+    pub max_rules: usize,
+    /// The longest a literal may be, in bytes without its quotes. [`Grammar::oversized_terminals`](crate::Grammar::oversized_terminals) finds those over a smaller size without rejecting the input.
+    pub max_literal: usize,
+    /// The longest the pattern of a regex may be, in bytes without its `#` and quotes
+    pub max_regex: usize,
+    /// Whether each rule is [reduced](crate#reductions) as it is parsed. Turning this off keeps the tree exactly as written, for tools that need to see every pair of brackets - see the [`simplification`](crate::simplification) module, and [`Rule::new_raw`](crate::Rule::new_raw) and [`Expr::new_raw`](crate::Expr::new_raw) for parsing a single rule or node this way.
+    pub simplify: bool,
+}
+
+impl ParseOptions {
+    /// The default [`max_depth`](ParseOptions::max_depth). Parsing and simplification work on trees of any depth, but the recursive trait implementations of [`Expr`](crate::Expr), like `Clone` and `Display`, are only known to be safe on trees about this deep, which [`Expr::depth`](crate::Expr::depth) can check for trees built by hand.
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+}
+
+impl Default for ParseOptions {
+    /// A depth of [`DEFAULT_MAX_DEPTH`](ParseOptions::DEFAULT_MAX_DEPTH), with no limit on the number of tokens or rules or the size of terminals, and each rule simplified
+    fn default() -> Self {
+        ParseOptions {
+            max_depth: ParseOptions::DEFAULT_MAX_DEPTH,
+            max_tokens: usize::MAX,
+            max_rules: usize::MAX,
+            max_literal: usize::MAX,
+            max_regex: usize::MAX,
+            simplify: true,
+        }
+    }
+}
+
+/// Which of the limits of [`ParseOptions`] an input went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ParseLimit {
+    /// [`ParseOptions::max_depth`]
+    NestingDepth,
+    /// [`ParseOptions::max_tokens`]
+    Tokens,
+    /// [`ParseOptions::max_rules`]
+    Rules,
+    /// [`ParseOptions::max_literal`]
+    LiteralLength,
+    /// [`ParseOptions::max_regex`]
+    RegexLength,
+}
+
+/// The operator last seen in a run of terms joined by `|`, `/` or `%`, and how many levels of nodes the parser builds for the run. It builds `a | b / c` as `(a | b) / c`, and simplifying only merges choices written with the same operator, so each change from one to the other nests the run once more. Nothing merges separated repetitions, so `a % b % c` is one inside another.
+#[derive(Debug, Clone, Copy, Default)]
+struct InfixRun<'a> {
+    last: Option<TokenPayload<'a>>,
+    depth: usize,
+}
+
+impl<'a> InfixRun<'a> {
+    /// Without simplifying, a run like `a | b | c` is also kept as one choice inside another
+    fn push(&mut self, operator: TokenPayload<'a>, simplify: bool) {
+        if !simplify || self.last != Some(operator) || operator == TokenPayload::Separator {
+            self.depth += 1;
+        }
+        self.last = Some(operator);
+    }
+}
+
+/// Checks the limits that can be seen from the tokens alone, before any parsing is done
+pub(crate) fn check_token_limits<'a>(
+    input: &'a str,
+    tokens: &[Token<'a>],
+    options: ParseOptions,
+) -> Result<(), EbnfError<'a>> {
+    let exceeded = |limit, max, token: &Token<'_>| EbnfError::LimitExceeded {
+        input,
+        offset: token.span.start(),
+        limit,
+        max,
+    };
+    if let Some(token) = tokens.get(options.max_tokens) {
+        return Err(exceeded(ParseLimit::Tokens, options.max_tokens, token));
+    }
+
+    // The runs of terms joined by `|`, `/` or `%` within each bracket that hasn't been closed, outermost first
+    let mut enclosing: Vec<InfixRun<'_>> = Vec::new();
+    let mut run = InfixRun::default();
+    let mut outer_runs = 0;
+    let mut ends_term = false;
+    let mut operators = 0;
+    for token in tokens {
+        match token.payload {
+            TokenPayload::String(literal) if literal.len() > options.max_literal => {
+                return Err(exceeded(
+                    ParseLimit::LiteralLength,
+                    options.max_literal,
+                    token,
+                ));
+            }
+            TokenPayload::Regex(pattern) if pattern.len() > options.max_regex => {
+                return Err(exceeded(ParseLimit::RegexLength, options.max_regex, token));
+            }
+            _ => {}
+        }
+        // A term straight after another starts a new run, as `a | b c | d` is built as `(a | b) (c | d)`
+        let starts_term = !matches!(
+            token.payload,
+            TokenPayload::Optional
+                | TokenPayload::Kleene
+                | TokenPayload::Repeat
+                | TokenPayload::Alternation
+                | TokenPayload::OrderedAlternation
+                | TokenPayload::Separator
+                | TokenPayload::Colon
+                | TokenPayload::ClosingGroup
+                | TokenPayload::ClosingSquare
+                | TokenPayload::ClosingBrace
+                | TokenPayload::ClosingAngle
+        );
+        if starts_term && ends_term {
+            run = InfixRun::default();
+        }
+        ends_term = false;
+        match token.payload {
+            TokenPayload::OpeningGroup
+            | TokenPayload::OpeningSquare
+            | TokenPayload::OpeningBrace
+            | TokenPayload::OpeningAngle => {
+                outer_runs += run.depth;
+                enclosing.push(core::mem::take(&mut run));
+                operators = 0;
+            }
+            TokenPayload::ClosingGroup
+            | TokenPayload::ClosingSquare
+            | TokenPayload::ClosingBrace
+            | TokenPayload::ClosingAngle => {
+                run = enclosing.pop().unwrap_or_default();
+                outer_runs -= run.depth;
+                ends_term = true;
+            }
+            TokenPayload::Optional | TokenPayload::Kleene | TokenPayload::Repeat => {
+                operators += 1;
+                ends_term = true;
+            }
+            TokenPayload::And | TokenPayload::Not => operators += 1,
+            TokenPayload::Alternation
+            | TokenPayload::OrderedAlternation
+            | TokenPayload::Separator => {
+                run.push(token.payload, options.simplify);
+                operators = 0;
+            }
+            TokenPayload::Identifier(_)
+            | TokenPayload::String(_)
+            | TokenPayload::Regex(_)
+            | TokenPayload::Epsilon => {
+                operators = 0;
+                ends_term = true;
+            }
+            _ => {
+                run = InfixRun::default();
+                operators = 0;
+            }
+        }
+        if enclosing.len() + outer_runs + run.depth + operators > options.max_depth {
+            return Err(exceeded(ParseLimit::NestingDepth, options.max_depth, token));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+
+    use crate::{EbnfError, ErrorCode, Expr, Grammar, ParseLimit, ParseOptions, Rule};
+
+    #[test]
+    fn limits() {
+        let options = ParseOptions {
+            max_depth: 3,
+            max_tokens: 12,
+            max_rules: 2,
+            max_literal: 3,
+            max_regex: 4,
+            ..ParseOptions::default()
+        };
+        let exceeded = |src| match Grammar::new_with_options(src, options) {
+            Err(EbnfError::LimitExceeded { offset, limit, .. }) => Some((offset, limit)),
+            Ok(_) => None,
+            Err(e) => panic!("{e:?}"),
+        };
+        assert_eq!(
+            exceeded("A = (([('a')]));"),
+            Some((7, ParseLimit::NestingDepth))
+        );
+        assert_eq!(exceeded("A = !!&!B;"), Some((7, ParseLimit::NestingDepth)));
+        assert_eq!(exceeded("A = B?*+?;"), Some((8, ParseLimit::NestingDepth)));
+        assert_eq!(exceeded("A = (!(B)?);"), None);
+        assert_eq!(
+            exceeded("A = B C D E F G H I J K;"),
+            Some((23, ParseLimit::Tokens))
+        );
+        assert_eq!(
+            exceeded("A = B; C = D; E = F;"),
+            Some((14, ParseLimit::Rules))
+        );
+        assert_eq!(exceeded("A = 'abc' #'a+b+';"), None);
+        assert_eq!(
+            exceeded("A = 'abc' 'abcd';"),
+            Some((10, ParseLimit::LiteralLength))
+        );
+        assert_eq!(
+            exceeded("A = \"x\" #'[a-z]';"),
+            Some((8, ParseLimit::RegexLength))
+        );
+        // Rejected before anything is parsed, so a later syntax error isn't reached
+        assert_eq!(
+            exceeded("A = 'abcdef'; B = (;"),
+            Some((4, ParseLimit::LiteralLength))
+        );
+
+        let deep = format!("A = {}B{};", "(".repeat(10_000), ")".repeat(10_000));
+        let err = Grammar::new(&deep).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LimitExceeded);
+        assert_eq!(err.offset(), Some(4 + 256));
+        assert_eq!(Grammar::check_syntax(&deep), Err(err));
+        assert!(Expr::new(&deep[4..deep.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn unsimplified_depth() {
+        let options = ParseOptions {
+            max_depth: 3,
+            simplify: false,
+            ..ParseOptions::default()
+        };
+        assert!(Grammar::new_with_options("A = a | b | c;", options).is_ok());
+        assert!(matches!(
+            Grammar::new_with_options("A = a | b | c | d | e;", options),
+            Err(EbnfError::LimitExceeded {
+                offset: 18,
+                limit: ParseLimit::NestingDepth,
+                ..
+            })
+        ));
+
+        let chain = format!("A = a{};", " | a".repeat(50_000));
+        assert_eq!(
+            Rule::new_raw(&chain).unwrap_err().code(),
+            ErrorCode::LimitExceeded
+        );
+        assert_eq!(
+            Expr::new_raw(&chain[4..chain.len() - 1])
+                .unwrap_err()
+                .code(),
+            ErrorCode::LimitExceeded
+        );
+    }
+
+    #[test]
+    fn infix_depth() {
+        let options = ParseOptions {
+            max_depth: 3,
+            ..ParseOptions::default()
+        };
+        let exceeded = |src| match Grammar::new_with_options(src, options) {
+            Err(EbnfError::LimitExceeded { offset, limit, .. }) => Some((offset, limit)),
+            Ok(_) => None,
+            Err(e) => panic!("{e:?}"),
+        };
+        assert_eq!(
+            exceeded("A = a | b / c | d / e;"),
+            Some((18, ParseLimit::NestingDepth))
+        );
+        assert_eq!(exceeded("A = a | b | c / d / e;"), None);
+        assert_eq!(exceeded("A = (a | b) / (c / d) | e;"), None);
+        assert_eq!(exceeded("A = a | b c / d e | f g / h;"), None);
+
+        // Dropping the chain of choices this would build overflows the stack
+        let mixed = format!("A = a{};", " | a / a".repeat(25_000));
+        let err = Grammar::new(&mixed).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LimitExceeded);
+        assert_eq!(Grammar::check_syntax(&mixed), Err(err));
+
+        assert_eq!(
+            exceeded("A = a % b % c % d % e;"),
+            Some((18, ParseLimit::NestingDepth))
+        );
+        assert_eq!(
+            exceeded("A = a | b % c / d % e;"),
+            Some((18, ParseLimit::NestingDepth))
+        );
+        assert_eq!(exceeded("A = a % b c % d (e % f % g);"), None);
+        let separated = format!("A = a{};", " % a".repeat(50_000));
+        assert_eq!(
+            Grammar::new(&separated).unwrap_err().code(),
+            ErrorCode::LimitExceeded
+        );
+    }
+}
@@ -0,0 +1,527 @@
+//! Style checks for grammars, run by [`Grammar::lint`]. Each check is a [`Lint`], and a [`LintConfig`] chooses which run and how seriously to take what they find. The built-in lints are all run by default, and tools can add their own to enforce the conventions of a project.
+//! ```rust
+//! # use ebnf_toolkit::{Grammar, lint::{Diagnostic, Lint, LintConfig, LintLevel}};
+//! /// Rules matching whitespace should be marked `@skip`
+//! struct SkipWhitespace;
+//!
+//! impl Lint for SkipWhitespace {
+//!     fn name(&self) -> &'static str {
+//!         "skip-whitespace"
+//!     }
+//!
+//!     fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+//!         grammar
+//!             .rules()
+//!             .filter(|rule| rule.name.contains("ws") && !rule.has_annotation("skip"))
+//!             .map(|rule| {
+//!                 let span = rule.definitions.iter().next().unwrap_or_default();
+//!                 Diagnostic::new(&rule.name, span, "Whitespace rules should be marked @skip")
+//!             })
+//!             .collect()
+//!     }
+//! }
+//!
+//! let g = Grammar::new("list = item (ws item)*; item = #'[a-z]+'; ws = ' '+;").unwrap();
+//! let config = LintConfig::empty()
+//!     .with_lint(SkipWhitespace)
+//!     .with_level("skip-whitespace", LintLevel::Error);
+//! let found = g.lint(&config);
+//! assert_eq!(found.len(), 1);
+//! assert_eq!((found[0].lint, found[0].level, found[0].rule), ("skip-whitespace", LintLevel::Error, "ws"));
+//! ```
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Debug, Display};
+
+use crate::{Expr, Grammar, NameCase, Rule, Span};
+
+/// How seriously to take what a [`Lint`] finds, set for each lint by a [`LintConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintLevel {
+    /// The lint doesn't run
+    Allow,
+    /// Worth knowing about, but often deliberate
+    Note,
+    /// Probably a mistake or a matter of style that should be fixed
+    Warning,
+    /// Should stop the grammar being accepted, for tools that enforce a style
+    Error,
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Note => "note",
+            LintLevel::Warning => "warning",
+            LintLevel::Error => "error",
+        })
+    }
+}
+
+/// A problem found by a [`Lint`], from [`Grammar::lint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic<'a> {
+    /// The [`name`](Lint::name) of the lint that found the problem
+    pub lint: &'static str,
+    /// The level the lint was run at, which is never [`LintLevel::Allow`]
+    pub level: LintLevel,
+    /// The name of the rule the problem is in
+    pub rule: &'a str,
+    /// Where the problem is, which for a problem with a whole rule is its first definition
+    pub span: Span,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub message: String,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// A diagnostic for [`Lint::check`] to return. Its `lint` and `level` are filled in by [`Grammar::lint`].
+    pub fn new(rule: &'a str, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            lint: "",
+            level: LintLevel::Warning,
+            rule,
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}[{}] in `{}` at {}: {}",
+            self.level, self.lint, self.rule, self.span, self.message
+        )
+    }
+}
+
+/// A check run over a whole grammar by [`Grammar::lint`]
+pub trait Lint {
+    /// A short name for the lint in kebab-case, such as `unused-rule`, which labels what it finds and picks it out in [`LintConfig::with_level`]
+    fn name(&self) -> &'static str;
+
+    /// The level the lint runs at unless a [`LintConfig`] says otherwise
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Warning
+    }
+
+    /// Every problem the lint finds in `grammar`
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>>;
+}
+
+/// Which lints [`Grammar::lint`] runs, and at what level. The default runs each of the built-in lints in this module at its default level.
+pub struct LintConfig {
+    lints: Vec<Box<dyn Lint>>,
+    levels: BTreeMap<String, LintLevel>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            lints: vec![
+                Box::new(UnusedRule),
+                Box::new(SingleUseRule),
+                Box::new(DeepNesting::default()),
+                Box::new(DuplicateLiteral),
+                Box::new(InconsistentNaming),
+                Box::new(OverlappingRegexes),
+            ],
+            levels: BTreeMap::new(),
+        }
+    }
+}
+
+impl Debug for LintConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LintConfig")
+            .field(
+                "lints",
+                &self.lints.iter().map(|l| l.name()).collect::<Vec<_>>(),
+            )
+            .field("levels", &self.levels)
+            .finish()
+    }
+}
+
+impl LintConfig {
+    /// A configuration with no lints at all, for running only those added with [`LintConfig::with_lint`]
+    pub fn empty() -> Self {
+        LintConfig {
+            lints: Vec::new(),
+            levels: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a lint to run, replacing any already added with the same [`name`](Lint::name). This is also how the settings of a built-in lint are changed, such as with `with_lint(DeepNesting { max_depth: 8 })`.
+    #[must_use]
+    pub fn with_lint(mut self, lint: impl Lint + 'static) -> Self {
+        self.lints.retain(|l| l.name() != lint.name());
+        self.lints.push(Box::new(lint));
+        self
+    }
+
+    /// Runs the lint with the given name at `level` instead of its default. [`LintLevel::Allow`] turns it off.
+    #[must_use]
+    pub fn with_level(mut self, lint: &str, level: LintLevel) -> Self {
+        self.levels.insert(lint.to_string(), level);
+        self
+    }
+
+    fn level(&self, lint: &dyn Lint) -> LintLevel {
+        self.levels
+            .get(lint.name())
+            .copied()
+            .unwrap_or_else(|| lint.default_level())
+    }
+}
+
+pub(crate) fn lint<'a>(grammar: &'a Grammar<'_>, config: &LintConfig) -> Vec<Diagnostic<'a>> {
+    let mut found = Vec::new();
+    for lint in &config.lints {
+        let level = config.level(&**lint);
+        if level == LintLevel::Allow {
+            continue;
+        }
+        found.extend(lint.check(grammar).into_iter().map(|d| Diagnostic {
+            lint: lint.name(),
+            level,
+            ..d
+        }));
+    }
+    found.sort_by_key(|d| d.span.start());
+    found
+}
+
+/// Where a problem with a whole rule is reported
+fn rule_span(rule: &Rule<'_>) -> Span {
+    rule.definitions.iter().next().unwrap_or_default()
+}
+
+/// For each rule, the other rules that refer to it and where, leaving out references a rule makes to itself
+fn references<'a>(grammar: &'a Grammar<'_>) -> BTreeMap<&'a str, Vec<(&'a str, Span)>> {
+    let mut found: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for rule in grammar.rules.values() {
+        for (name, span) in rule.nonterminal_references() {
+            if name != rule.name {
+                found.entry(name).or_default().push((&*rule.name, span));
+            }
+        }
+    }
+    found
+}
+
+/// `unused-rule`: a rule that no other rule refers to. The first rule defined is taken to be where parsing starts, and rules with annotations are assumed to be used by whatever reads the annotation, so neither is reported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnusedRule;
+
+impl Lint for UnusedRule {
+    fn name(&self) -> &'static str {
+        "unused-rule"
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        let references = references(grammar);
+        let start = grammar.rules_in_definition_order().first().map(|r| &r.name);
+        grammar
+            .rules
+            .values()
+            .filter(|rule| {
+                Some(&rule.name) != start
+                    && rule.annotations.is_empty()
+                    && !references.contains_key(&*rule.name)
+            })
+            .map(|rule| {
+                Diagnostic::new(
+                    &rule.name,
+                    rule_span(rule),
+                    format!("Rule `{}` is never used", rule.name),
+                )
+            })
+            .collect()
+    }
+}
+
+/// `single-use-rule`: a rule referred to from only one place, which could be written out there instead. The first rule defined, rules that refer to themselves and annotated rules are left out, and this only runs as a [`Note`](LintLevel::Note) by default, since naming part of a rule is often clearer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SingleUseRule;
+
+impl Lint for SingleUseRule {
+    fn name(&self) -> &'static str {
+        "single-use-rule"
+    }
+
+    fn default_level(&self) -> LintLevel {
+        LintLevel::Note
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        let start = grammar.rules_in_definition_order().first().map(|r| &r.name);
+        references(grammar)
+            .into_iter()
+            .filter_map(|(name, uses)| {
+                let rule = grammar.rules.get(name)?;
+                let [(user, _)] = uses[..] else { return None };
+                (Some(&rule.name) != start && rule.annotations.is_empty() && !rule.is_recursive())
+                    .then(|| {
+                        Diagnostic::new(
+                            &rule.name,
+                            rule_span(rule),
+                            format!("Rule `{name}` is only used once, in `{user}`"),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+/// `deep-nesting`: part of a rule nested inside more than `max_depth` levels of brackets and operators, which may be easier to read as a separate rule
+#[derive(Debug, Clone, Copy)]
+pub struct DeepNesting {
+    /// The most levels of nesting allowed, where a lone literal or nonterminal has none and `(a | b)*` has two
+    pub max_depth: usize,
+}
+
+impl Default for DeepNesting {
+    fn default() -> Self {
+        DeepNesting { max_depth: 4 }
+    }
+}
+
+impl Lint for DeepNesting {
+    fn name(&self) -> &'static str {
+        "deep-nesting"
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        grammar
+            .rules
+            .values()
+            .filter_map(|rule| {
+                // Only the deepest node is reported, so a rule gives at most one diagnostic
+                let (node, nesting) = rule
+                    .body
+                    .iter()
+                    .map(|node| (node, node.depth() - 1))
+                    .max_by_key(|(_, nesting)| *nesting)?;
+                (nesting > self.max_depth).then(|| {
+                    Diagnostic::new(
+                        &rule.name,
+                        node.span(),
+                        format!(
+                            "Nested {nesting} levels deep, more than the limit of {}",
+                            self.max_depth
+                        ),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// `duplicate-literal`: a literal written out in one rule when another rule matches that literal and nothing else, so the other rule could be used instead
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuplicateLiteral;
+
+impl Lint for DuplicateLiteral {
+    fn name(&self) -> &'static str {
+        "duplicate-literal"
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        let mut literal_rules = BTreeMap::new();
+        for rule in grammar.rules.values() {
+            if let [Expr::Literal { str, .. }] = rule.body[..] {
+                literal_rules.entry(str).or_insert(&*rule.name);
+            }
+        }
+
+        let mut found = Vec::new();
+        for rule in grammar.rules.values() {
+            let mut stack: Vec<_> = rule.body.iter().collect();
+            while let Some(node) = stack.pop() {
+                match node {
+                    Expr::Literal { str, span } => {
+                        if let Some(&other) = literal_rules.get(str)
+                            && other != rule.name
+                        {
+                            found.push(Diagnostic::new(
+                                &rule.name,
+                                *span,
+                                format!("'{str}' is the whole of rule `{other}`, which could be used here instead"),
+                            ));
+                        }
+                    }
+                    other => stack.extend(other.children()),
+                }
+            }
+        }
+        found
+    }
+}
+
+/// `inconsistent-naming`: a rule whose name is written in a different case from most of the others, such as `camelCase` among `snake_case`. Names in `SCREAMING_SNAKE_CASE` are always allowed, since they are often used for tokens.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InconsistentNaming;
+
+impl Lint for InconsistentNaming {
+    fn name(&self) -> &'static str {
+        "inconsistent-naming"
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        // Counted in the order each case is first seen, so a tie goes to the case of the earliest rule
+        let mut counts: Vec<(NameCase, usize)> = Vec::new();
+        for rule in grammar.rules_in_definition_order() {
+            let case = NameCase::of(&rule.name);
+            if case == NameCase::ScreamingSnake {
+                continue;
+            }
+            match counts.iter_mut().find(|(c, _)| *c == case) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((case, 1)),
+            }
+        }
+        let Some(usual) = counts
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(case, _)| *case)
+        else {
+            return Vec::new();
+        };
+
+        grammar
+            .rules
+            .values()
+            .filter_map(|rule| {
+                let case = NameCase::of(&rule.name);
+                (case != usual && case != NameCase::ScreamingSnake).then(|| {
+                    Diagnostic::new(
+                        &rule.name,
+                        rule_span(rule),
+                        format!("`{}` is {case}, while most rules are {usual}", rule.name),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// `overlapping-regexes`: two regex alternatives of the same choice that can match the same input, as found by [`Grammar::regex_overlaps`]. A tokenizer seeing such input can't tell which alternative was meant, so the report gives some inputs both can match where it found any.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OverlappingRegexes;
+
+impl Lint for OverlappingRegexes {
+    fn name(&self) -> &'static str {
+        "overlapping-regexes"
+    }
+
+    fn check<'a>(&self, grammar: &'a Grammar<'_>) -> Vec<Diagnostic<'a>> {
+        grammar
+            .regex_overlaps()
+            .into_iter()
+            .map(|overlap| {
+                let pair = format!("{} and {}", overlap.first, overlap.second);
+                let message = if overlap.examples.is_empty() {
+                    format!("{pair} can begin with the same character")
+                } else {
+                    let examples: Vec<_> =
+                        overlap.examples.iter().map(|e| format!("{e:?}")).collect();
+                    format!("{pair} can both match {}", examples.join(", "))
+                };
+                Diagnostic::new(
+                    overlap.rule,
+                    overlap.first.span().join(overlap.second.span()),
+                    message,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{
+        Grammar,
+        lint::{DeepNesting, LintConfig, LintLevel, OverlappingRegexes},
+    };
+
+    #[test]
+    fn built_in_lints() {
+        let src = "expr = term (PLUS term)*; term = factor; factor = '+' | (('(' ((expr))* ')')); \
+                   PLUS = '+'; unused = 'x'; @skip ws = ' '; fooBar = unused2; unused2 = 'y';";
+        let g = Grammar::new(src).unwrap();
+        let found = g.lint(&LintConfig::default());
+        let summary: Vec<_> = found.iter().map(|d| (d.lint, d.level, d.rule)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("single-use-rule", LintLevel::Note, "factor"),
+                ("duplicate-literal", LintLevel::Warning, "factor"),
+                ("single-use-rule", LintLevel::Note, "PLUS"),
+                ("unused-rule", LintLevel::Warning, "unused"),
+                ("unused-rule", LintLevel::Warning, "fooBar"),
+                ("inconsistent-naming", LintLevel::Warning, "fooBar"),
+                ("single-use-rule", LintLevel::Note, "unused2"),
+            ],
+            "{found:#?}"
+        );
+        assert_eq!(&src[found[1].span.range()], "'+'");
+
+        let config = LintConfig::default()
+            .with_level("single-use-rule", LintLevel::Allow)
+            .with_lint(DeepNesting { max_depth: 1 });
+        let found = g.lint(&config);
+        let nesting: Vec<_> = found
+            .iter()
+            .filter(|d| d.lint == "deep-nesting")
+            .map(|d| d.rule)
+            .collect();
+        assert_eq!(nesting, ["factor"]);
+        assert!(found.iter().all(|d| d.lint != "single-use-rule"));
+
+        assert!(g.lint(&LintConfig::empty()).is_empty());
+    }
+
+    #[test]
+    fn overlapping_regexes() {
+        let src = "word = #'[a-z]+' | #'[a-f0-9]+' | 'x'; num = #'[0-9]' | #'0x';";
+        let g = Grammar::new(src).unwrap();
+        let found = g.lint(&LintConfig::empty().with_lint(OverlappingRegexes));
+        let summary: Vec<_> = found
+            .iter()
+            .map(|d| (d.rule, &src[d.span.range()]))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("word", "#'[a-z]+' | #'[a-f0-9]+'"),
+                ("num", "#'[0-9]' | #'0x'"),
+            ],
+            "{found:#?}"
+        );
+        assert_eq!(
+            found[0].message,
+            r#"#'[a-z]+' and #'[a-f0-9]+' can both match "a", "f", "aa""#
+        );
+        assert_eq!(
+            found[1].message,
+            "#'[0-9]' and #'0x' can begin with the same character"
+        );
+        assert!(
+            g.lint(&LintConfig::default())
+                .iter()
+                .any(|d| d.lint == "overlapping-regexes")
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+
+use crate::{ChoiceSemantics, Expr, Grammar, Rule};
+
+/// How many terminals a parser must look ahead to decide on one alternative of a [`Choice`](`Expr::Choice`), as found by [`Grammar::lookahead_requirements`].
+///
+/// This is estimated from the sequences of terminals each alternative can begin with, followed by whatever comes after the `Choice` in the same rule. Terminals are compared by their text, so a literal and a regex that happen to match the same input are treated as different terminals (see [`Grammar::check_regex_overlap`] for finding those). What follows the end of the enclosing rule is not considered, so an alternative that can end while matching the beginning of a sibling is reported as needing more lookahead than the bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lookahead<'a> {
+    /// The name of the rule containing the `Choice`
+    pub rule: &'a str,
+    /// The alternative being chosen
+    pub alternative: &'a Expr<'a>,
+    /// The number of terminals needed to tell this alternative apart from all of its siblings, or only those after it under [ordered](ChoiceSemantics::Ordered) choice, or `None` if that is more than the bound given to [`Grammar::lookahead_requirements`], potentially without any limit.
+    pub terminals: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Terminal<'a> {
+    Literal(&'a str),
+    Regex(&'a str),
+    /// A reference to a rule that isn't defined, which is assumed to stand for a single token
+    Undefined(&'a str),
+}
+
+/// Every sequence of terminals that something can begin with, cut off at the lookahead bound. Sequences shorter than the bound are everything that thing can match.
+pub(crate) type Prefixes<'a> = BTreeSet<Vec<Terminal<'a>>>;
+
+pub(crate) struct Analysis<'a> {
+    max: usize,
+    semantics: ChoiceSemantics,
+    pub(crate) rules: BTreeMap<&'a str, Prefixes<'a>>,
+}
+
+pub(crate) fn lookahead_requirements<'a>(
+    grammar: &'a Grammar<'_>,
+    max: usize,
+) -> Vec<Lookahead<'a>> {
+    let analysis = Analysis::new(grammar, max);
+
+    let mut output = vec![];
+    let end_of_rule = Prefixes::from([vec![]]);
+    for rule in grammar.rules.values() {
+        analysis.visit_sequence(&rule.body, &end_of_rule, &rule.name, &mut output);
+    }
+    output.sort_by_key(|l| l.alternative.span());
+    output
+}
+
+impl<'a> Analysis<'a> {
+    /// Finds the prefixes of up to `max` terminals that each rule of `grammar` can begin with
+    pub(crate) fn new(grammar: &'a Grammar<'_>, max: usize) -> Self {
+        let mut analysis = Analysis {
+            max,
+            semantics: grammar.choice_semantics,
+            rules: grammar
+                .rules
+                .keys()
+                .map(|name| (&**name, Prefixes::new()))
+                .collect(),
+        };
+
+        // Each pass can only add prefixes, and there are finitely many, so this terminates
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in grammar.rules.values() {
+                let prefixes = analysis.sequence(&rule.body);
+                if analysis.rules[&*rule.name] != prefixes {
+                    analysis.rules.insert(&rule.name, prefixes);
+                    changed = true;
+                }
+            }
+        }
+        analysis
+    }
+
+    /// Every way of following a prefix from `first` with one from `second`
+    fn concat(&self, first: &Prefixes<'a>, second: &Prefixes<'a>) -> Prefixes<'a> {
+        let mut output = Prefixes::new();
+        for x in first {
+            if x.len() >= self.max {
+                output.insert(x.clone());
+                continue;
+            }
+            for y in second {
+                let mut joined = x.clone();
+                joined.extend(y.iter().take(self.max - x.len()));
+                output.insert(joined);
+            }
+        }
+        output
+    }
+
+    fn sequence(&self, nodes: &'a [Expr<'a>]) -> Prefixes<'a> {
+        nodes.iter().fold(Prefixes::from([vec![]]), |acc, node| {
+            self.concat(&acc, &self.node(node))
+        })
+    }
+
+    /// What can follow one match of the body of the repetition `node` while it goes on: any number of further matches, each after the separator if there is one
+    fn repeated(&self, node: &'a Expr<'a>) -> Prefixes<'a> {
+        let (body, separator) = node.repetition_parts().unwrap();
+        let mut once = self.sequence(body);
+        if let Some(separator) = separator {
+            once = self.concat(&self.node(separator), &once);
+        }
+        let mut any_number = Prefixes::from([vec![]]);
+        loop {
+            let mut next = self.concat(&once, &any_number);
+            next.insert(vec![]);
+            if next == any_number {
+                break;
+            }
+            any_number = next;
+        }
+        any_number
+    }
+
+    fn node(&self, node: &'a Expr<'a>) -> Prefixes<'a> {
+        match node {
+            // An empty literal or a lookahead predicate matches without consuming anything
+            Expr::Literal { str: "", .. }
+            | Expr::Empty { .. }
+            | Expr::And { .. }
+            | Expr::Not { .. }
+            | Expr::UnparsedOperator { .. } => Prefixes::from([vec![]]),
+            Expr::Literal { str, .. } => Prefixes::from([vec![Terminal::Literal(str)]]),
+            Expr::Regex { pattern, .. } => Prefixes::from([vec![Terminal::Regex(pattern)]]),
+            Expr::Nonterminal { name, .. } => match self.rules.get(*name) {
+                Some(prefixes) => prefixes.clone(),
+                None => Prefixes::from([vec![Terminal::Undefined(name)]]),
+            },
+            Expr::Choice { body, .. } => body.iter().flat_map(|alt| self.node(alt)).collect(),
+            Expr::Optional { body, .. } => {
+                let mut prefixes = self.sequence(body);
+                prefixes.insert(vec![]);
+                prefixes
+            }
+            Expr::Repetition { one_needed, .. } => {
+                let (body, separator) = node.repetition_parts().unwrap();
+                let once = self.sequence(body);
+                let again = self.repeated(node);
+                if separator.is_none() && !one_needed {
+                    return again;
+                }
+                let mut prefixes = self.concat(&once, &again);
+                if !one_needed {
+                    prefixes.insert(vec![]);
+                }
+                prefixes
+            }
+            Expr::Group { body, .. }
+            | Expr::Hidden { body, .. }
+            | Expr::Labeled { body, .. }
+            | Expr::Rule {
+                rule: Rule { body, .. },
+                ..
+            } => self.sequence(body),
+        }
+    }
+
+    /// Looks for choices among `nodes`, where `follow` is everything that can come after the whole sequence
+    fn visit_sequence(
+        &self,
+        nodes: &'a [Expr<'a>],
+        follow: &Prefixes<'a>,
+        rule: &'a str,
+        output: &mut Vec<Lookahead<'a>>,
+    ) {
+        for (n, node) in nodes.iter().enumerate() {
+            let node_follow = self.concat(&self.sequence(&nodes[n + 1..]), follow);
+            self.visit(node, &node_follow, rule, output);
+        }
+    }
+
+    fn visit(
+        &self,
+        node: &'a Expr<'a>,
+        follow: &Prefixes<'a>,
+        rule: &'a str,
+        output: &mut Vec<Lookahead<'a>>,
+    ) {
+        match node {
+            Expr::Choice { body, ordered, .. } => {
+                // A choice written with `/` is ordered whatever the rest of the grammar does
+                let semantics = if *ordered {
+                    ChoiceSemantics::Ordered
+                } else {
+                    self.semantics
+                };
+                let contexts: Vec<_> = body
+                    .iter()
+                    .map(|alt| self.concat(&self.node(alt), follow))
+                    .collect();
+                for (n, alternative) in body.iter().enumerate() {
+                    output.push(Lookahead {
+                        rule,
+                        alternative,
+                        terminals: self.distinguishing_length(n, &contexts, semantics),
+                    });
+                }
+                for alternative in body {
+                    self.visit(alternative, follow, rule, output);
+                }
+            }
+            Expr::Repetition { .. } => {
+                let (body, separator) = node.repetition_parts().unwrap();
+                if let Some(separator) = separator {
+                    let again = self.concat(&self.repeated(node), follow);
+                    self.visit_sequence(body, &again, rule, output);
+                    let after = self.concat(&self.sequence(body), &again);
+                    self.visit(separator, &after, rule, output);
+                } else {
+                    let again = self.concat(&self.node(node), follow);
+                    self.visit_sequence(body, &again, rule, output);
+                }
+            }
+            Expr::Optional { body, .. }
+            | Expr::Group { body, .. }
+            | Expr::And { body, .. }
+            | Expr::Not { body, .. }
+            | Expr::Hidden { body, .. }
+            | Expr::Labeled { body, .. }
+            | Expr::Rule {
+                rule: Rule { body, .. },
+                ..
+            } => self.visit_sequence(body, follow, rule, output),
+            Expr::Literal { .. }
+            | Expr::Nonterminal { .. }
+            | Expr::Regex { .. }
+            | Expr::Empty { .. }
+            | Expr::UnparsedOperator { .. } => {}
+        }
+    }
+
+    /// The fewest terminals that separate every prefix of `contexts[index]` from those of the other alternatives it competes with, which under ordered choice are only the ones after it
+    fn distinguishing_length(
+        &self,
+        index: usize,
+        contexts: &[Prefixes<'a>],
+        semantics: ChoiceSemantics,
+    ) -> Option<usize> {
+        let competing = |n: usize| match semantics {
+            ChoiceSemantics::Unordered => n != index,
+            ChoiceSemantics::Ordered => n > index,
+        };
+        // Nothing is needed to pick an alternative with no competition
+        (0..=self.max).find(|&length| {
+            contexts
+                .iter()
+                .enumerate()
+                .filter(|(n, _)| competing(*n))
+                .all(|(_, other)| {
+                    contexts[index].iter().all(|x| {
+                        other.iter().all(|y| {
+                            let x = &x[..x.len().min(length)];
+                            let y = &y[..y.len().min(length)];
+                            // A shorter prefix means the rule can end there, so the next terminal could be anything
+                            !(x.starts_with(y) || y.starts_with(x))
+                        })
+                    })
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ChoiceSemantics, Grammar};
+
+    #[test]
+    fn alternatives() {
+        let src = "S = ('a' 'b') | ('a' 'c') | 'd'; T = X | (X 'e'); X = 'x';";
+        let g = Grammar::new(src).unwrap();
+        let needed: Vec<_> = g
+            .lookahead_requirements(3)
+            .into_iter()
+            .map(|l| (l.rule, &src[l.alternative.span().range()], l.terminals))
+            .collect();
+
+        insta::assert_debug_snapshot!(needed, @r#"
+        [
+            (
+                "S",
+                "'a' 'b'",
+                Some(
+                    2,
+                ),
+            ),
+            (
+                "S",
+                "'a' 'c'",
+                Some(
+                    2,
+                ),
+            ),
+            (
+                "S",
+                "'d'",
+                Some(
+                    1,
+                ),
+            ),
+            (
+                "T",
+                "X",
+                None,
+            ),
+            (
+                "T",
+                "X 'e'",
+                None,
+            ),
+        ]
+        "#);
+    }
+
+    #[test]
+    fn context_after_choice() {
+        let g = Grammar::new("S = ('a' | ('a' 'b')) 'c';").unwrap();
+        let needed: Vec<_> = g
+            .lookahead_requirements(3)
+            .iter()
+            .map(|l| l.terminals)
+            .collect();
+        assert_eq!(needed, [Some(2), Some(2)]);
+    }
+
+    #[test]
+    fn ordered_choice() {
+        let mut g = Grammar::new("S = ('a' 'b') | ('a' 'c') | 'd';").unwrap();
+        g.set_choice_semantics(ChoiceSemantics::Ordered);
+        let needed: Vec<_> = g
+            .lookahead_requirements(3)
+            .iter()
+            .map(|l| l.terminals)
+            .collect();
+        assert_eq!(needed, [Some(2), Some(1), Some(0)]);
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::{EbnfError, Grammar, Rule, Span};
+
+/// A grammar kept together with the source it was parsed from by [`Grammar::new_lossless`], so that it can be written back out without disturbing the parts that weren't changed.
+///
+/// [`Display`](core::fmt::Display) normalizes the notation of what it writes, so `[x]` comes out as `x?` and `::=` as `=`, and comments are lost. [`LosslessGrammar::to_source`] instead copies each rule that is still as it was parsed straight from the source, along with the comments, whitespace and `%import` directives between rules, so that formatters and refactoring tools only rewrite the rules they touch.
+/// ```rust
+/// # use ebnf_toolkit::Grammar;
+/// let src = "// Comparisons\nop ::= '<' | '<=';\nterm ::= [op] {digit};\n";
+/// let mut lossless = Grammar::new_lossless(src).unwrap();
+/// assert_eq!(lossless.to_source(), src);
+///
+/// lossless.grammar.reorder_choices(|_, _| false);
+/// assert_eq!(
+///     lossless.to_source(),
+///     "// Comparisons\nop = ((\"<=\")|(\"<\"));\nterm ::= [op] {digit};\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LosslessGrammar<'a> {
+    source: &'a str,
+    original: Grammar<'a>,
+    /// The grammar parsed from the source, which can be changed freely
+    pub grammar: Grammar<'a>,
+}
+
+impl<'a> LosslessGrammar<'a> {
+    pub(crate) fn new(source: &'a str) -> Result<LosslessGrammar<'a>, EbnfError<'a>> {
+        let grammar = Grammar::new(source)?;
+        Ok(LosslessGrammar {
+            source,
+            original: grammar.clone(),
+            grammar,
+        })
+    }
+
+    /// The text the grammar was parsed from
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Writes the grammar back out as source text. Each definition of a rule that hasn't changed since parsing is copied as it was written, as is everything between the definitions.
+    ///
+    /// A rule that has changed is written out in the normalized notation of [`Display`](core::fmt::Display) in place of its first definition, and its other definitions are dropped. The definitions of rules that have been removed are dropped, leaving the whitespace around them. Rules that weren't in the source are added at the end, in order of their names.
+    pub fn to_source(&self) -> String {
+        let mut definitions: Vec<(Span, &str)> = self
+            .original
+            .rules
+            .values()
+            .flat_map(|rule| rule.definitions.iter().map(|span| (span, &*rule.name)))
+            .collect();
+        definitions.sort_by_key(|(span, _)| span.start());
+
+        let mut output = String::new();
+        let mut written = BTreeSet::new();
+        let mut copied_up_to = 0;
+        for (span, name) in definitions {
+            output.push_str(&self.source[copied_up_to..span.start()]);
+            copied_up_to = span.end();
+
+            let Some(rule) = self.grammar.rules.get(name) else {
+                continue;
+            };
+            if *rule == self.original.rules[name] {
+                output.push_str(&self.source[span.range()]);
+            } else if written.insert(name) {
+                write_rule(&mut output, rule);
+            }
+        }
+        output.push_str(&self.source[copied_up_to..]);
+
+        for rule in self.grammar.rules.values() {
+            if !self.original.rules.contains_key(&rule.name) {
+                if !output.is_empty() && !output.ends_with('\n') {
+                    output.push('\n');
+                }
+                write_rule(&mut output, rule);
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
+
+/// Writes `rule` without its annotations, which are left in the source between definitions
+fn write_rule(output: &mut String, rule: &Rule<'_>) {
+    if rule.hidden {
+        write!(output, "<{}> =", rule.name).unwrap();
+    } else {
+        write!(output, "{} =", rule.name).unwrap();
+    }
+    for node in &rule.body {
+        write!(output, " {node}").unwrap();
+    }
+    output.push(';');
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use crate::Grammar;
+
+    #[test]
+    fn to_source() {
+        let src = "%import 'lib.ebnf';\n// The start\n@entry <S> ::= A / B;  A = 'a' ;\nB = [A]; A = 'ab';";
+        let lossless = Grammar::new_lossless(src).unwrap();
+        assert_eq!(lossless.to_source(), src);
+
+        let numbers = Grammar::new("n = #'[0-9]+';").unwrap();
+        let mut names = String::new();
+        let mut changed = lossless.clone();
+        changed
+            .grammar
+            .compose("num_", &numbers, &mut names)
+            .unwrap();
+        assert_eq!(
+            changed.to_source(),
+            [src, "\nnum_n = #'[0-9]+';\n"].concat()
+        );
+
+        // A has two definitions, so rewriting it keeps only the first
+        let mut changed = lossless;
+        changed.grammar.reorder_choices(|_, _| false);
+        assert_eq!(
+            changed.to_source(),
+            "%import 'lib.ebnf';\n// The start\n@entry <S> ::= A / B;  A = ((\"ab\")|(\"a\"));\nB = [A]; "
+        );
+    }
+}
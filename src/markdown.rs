@@ -0,0 +1,213 @@
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+use crate::{
+    EbnfError, Grammar, ParseOptions, parse_rules_from_tokens, token_data::tokenize_fragment,
+};
+
+/// The info strings that mark a code fence as holding grammar rules
+const FENCE_TAGS: [&str; 2] = ["ebnf", "bnf"];
+
+/// The rules found in the code fences of a Markdown document by [`Grammar::from_markdown`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownGrammar<'a> {
+    /// The rules of every fence that parsed successfully, merged into one grammar
+    pub grammar: Grammar<'a>,
+    /// The byte range of the contents of each fence, in the order they appear
+    pub fences: Vec<Range<usize>>,
+    /// An error for each fence that could not be parsed, along with the index of that fence in `fences`. These refer to the whole document, so their reports point at the fence in context.
+    pub errors: Vec<(usize, EbnfError<'a>)>,
+}
+
+pub(crate) fn grammar_from_markdown(text: &str) -> MarkdownGrammar<'_> {
+    let fences = fenced_blocks(text, &FENCE_TAGS);
+    let mut rules = vec![];
+    let mut imports = vec![];
+    let mut errors = vec![];
+
+    for (index, range) in fences.iter().enumerate() {
+        match Grammar::from_fragment(text, range.clone(), ParseOptions::default()) {
+            Ok(grammar) => {
+                rules.extend(grammar.rules.into_values());
+                imports.extend(grammar.imports);
+            }
+            // A fence with nothing in it isn't worth reporting
+            Err(EbnfError::EmptyInput) => {}
+            Err(error) => errors.push((index, error)),
+        }
+    }
+
+    let mut grammar: Grammar<'_> = rules.into_iter().collect();
+    grammar.imports = imports;
+    MarkdownGrammar {
+        grammar,
+        fences,
+        errors,
+    }
+}
+
+pub(crate) fn joined_grammar_from_markdown(text: &str) -> Result<Grammar<'_>, EbnfError<'_>> {
+    let fences = fenced_blocks(text, &FENCE_TAGS);
+    let mut tokens = vec![];
+    let mut imports = vec![];
+    for range in &fences {
+        let (fence_tokens, fence_imports) = tokenize_fragment(text, range.clone())?;
+        tokens.extend(fence_tokens);
+        imports.extend(fence_imports);
+    }
+
+    let end = fences.last().map_or(0, |range| range.end);
+    let rules = if tokens.is_empty() && !imports.is_empty() {
+        vec![]
+    } else {
+        parse_rules_from_tokens(text, end, &mut &tokens[..], ParseOptions::default())?
+    };
+    let mut grammar: Grammar<'_> = rules.into_iter().collect();
+    grammar.imports = imports;
+    Ok(grammar)
+}
+
+/// The byte ranges of the contents of every fenced code block whose info string starts with one of `tags`, ignoring ASCII case. Follows `CommonMark` in allowing fences of backticks or tildes, indented by up to three spaces, and in letting an unclosed fence run to the end of the document.
+pub(crate) fn fenced_blocks(text: &str, tags: &[&str]) -> Vec<Range<usize>> {
+    struct OpenFence {
+        marker: char,
+        length: usize,
+        wanted: bool,
+        contents_start: usize,
+    }
+
+    let mut blocks = vec![];
+    let mut open: Option<OpenFence> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        let unindented = line.trim_start_matches(' ');
+        if line.len() - unindented.len() > 3 {
+            continue;
+        }
+        let Some(marker) = unindented
+            .chars()
+            .next()
+            .filter(|c| matches!(*c, '`' | '~'))
+        else {
+            continue;
+        };
+        let after_marker = unindented.trim_start_matches(marker);
+        let length = unindented.len() - after_marker.len();
+        if length < 3 {
+            continue;
+        }
+
+        match open.take() {
+            None => {
+                let info = after_marker.split_whitespace().next().unwrap_or("");
+                open = Some(OpenFence {
+                    marker,
+                    length,
+                    wanted: tags.iter().any(|tag| info.eq_ignore_ascii_case(tag)),
+                    contents_start: offset,
+                });
+            }
+            Some(fence)
+                if fence.marker == marker
+                    && length >= fence.length
+                    && after_marker.trim().is_empty() =>
+            {
+                if fence.wanted {
+                    blocks.push(fence.contents_start..line_start);
+                }
+            }
+            still_open => open = still_open,
+        }
+    }
+
+    if let Some(fence) = open
+        && fence.wanted
+    {
+        blocks.push(fence.contents_start..text.len());
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{EbnfError, Grammar};
+
+    const DOC: &str = "# Spec
+
+Some prose.
+
+```ebnf
+expr = term (op term)*;
+```
+
+~~~ rust
+let x = 1;
+~~~
+
+    ```ebnf
+    indented = 'code block';
+    ```
+
+```EBNF extra words
+term = #'[0-9]+'
+     | '(' expr ')';
+op = '+' | '-';
+```
+
+```ebnf
+broken = (term;
+```
+";
+
+    #[test]
+    fn fences() {
+        let found = Grammar::from_markdown(DOC);
+
+        assert_eq!(found.fences.len(), 3);
+        assert!(found.grammar.first_dangling_reference().is_none());
+
+        let term = found.grammar.get("term").unwrap();
+        let span = term.definitions.hull().unwrap();
+        assert!(DOC[span.range()].starts_with("term = #'[0-9]+'"));
+        assert_eq!(span.start_line(), (18, 0));
+        assert_eq!(span.end_line(), (19, 20));
+
+        let [(2, EbnfError::ParseError { input, offset, .. })] = &found.errors[..] else {
+            panic!("{:?}", found.errors)
+        };
+        assert_eq!(*input, DOC);
+        assert_eq!(&DOC[*offset..=*offset], ";");
+    }
+
+    #[test]
+    fn unclosed_fence() {
+        let found = Grammar::from_markdown("Intro\n```ebnf\nA = 'a';\nB = 'b';");
+        assert!(found.errors.is_empty());
+        assert!(found.grammar.get("A").is_some());
+        assert!(found.grammar.get("B").is_some());
+    }
+
+    #[test]
+    fn joined_fences() {
+        let doc = "```bnf\nlist = item\n```\n\nwhere items are separated by commas:\n\n```bnf\n  (',' item)*;\nitem = 'x';\n```\n";
+
+        let separate = Grammar::from_markdown(doc);
+        assert_eq!(separate.fences.len(), 2);
+        assert_eq!(separate.errors.len(), 2);
+
+        let joined = Grammar::from_markdown_joined(doc).unwrap_or_else(|e| panic!("{e}"));
+        let list = &joined["list"];
+        assert_eq!(list.body.len(), 2);
+        assert_eq!(list.definitions.hull().unwrap().end_line(), (8, 14));
+
+        let doc = "```ebnf\nA = (B;\n```\n\n```ebnf\nC = D;\n```";
+        let err = Grammar::from_markdown_joined(doc).unwrap_err();
+        assert_eq!(err.input(), doc);
+        let offset = err.offset().unwrap();
+        assert_eq!(&doc[offset..=offset], ";");
+    }
+}
@@ -0,0 +1,218 @@
+use alloc::{collections::BTreeMap, format, vec, vec::Vec};
+
+use regex::Regex;
+
+use crate::{ChoiceSemantics, Expr, Grammar, Rule};
+
+/// Why a match was abandoned rather than failing
+struct Unsupported;
+
+/// Something a match went through, recorded so that [`coverage`](crate::Grammar::coverage) can tell what a corpus used
+pub(crate) enum Used<'g, 'a> {
+    /// The rule with this name was matched
+    Rule(&'g str),
+    /// This alternative of a [`Choice`](Expr::Choice) was the one taken
+    Alternative(&'g Expr<'a>),
+}
+
+/// Matches nodes against an input one after another, never going back to try another way once a node has matched
+struct Matcher<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    input: &'g str,
+    /// The rules being matched further out, which would make matching recursive if entered again
+    path: Vec<&'g str>,
+    /// Each pattern, anchored to match only at the start of what it is given, or `None` if it isn't valid
+    regexes: BTreeMap<&'g str, Option<Regex>>,
+    /// What the nodes matched so far went through, leaving out anything that was tried but didn't end up part of the match
+    used: Vec<Used<'g, 'a>>,
+}
+
+pub(crate) fn match_str(rule: &Rule<'_>, grammar: &Grammar<'_>, input: &str) -> Option<usize> {
+    match_str_tracing(rule, grammar, input).map(|(end, _)| end)
+}
+
+/// Matches a single node against the start of `input` as [`match_str`] matches a rule, giving `None` where that would
+pub(crate) fn match_node(node: &Expr<'_>, grammar: &Grammar<'_>, input: &str) -> Option<usize> {
+    let mut matcher = Matcher {
+        grammar,
+        input,
+        path: vec![],
+        regexes: BTreeMap::new(),
+        used: vec![],
+    };
+    matcher.node(node, 0).ok().flatten()
+}
+
+/// Matches as [`match_str`] does, also giving what the match went through
+pub(crate) fn match_str_tracing<'g, 'a>(
+    rule: &'g Rule<'a>,
+    grammar: &'g Grammar<'a>,
+    input: &'g str,
+) -> Option<(usize, Vec<Used<'g, 'a>>)> {
+    let mut matcher = Matcher {
+        grammar,
+        input,
+        path: vec![&*rule.name],
+        regexes: BTreeMap::new(),
+        used: vec![Used::Rule(&rule.name)],
+    };
+    let end = matcher.sequence(&rule.body, 0).ok().flatten()?;
+    Some((end, matcher.used))
+}
+
+impl<'g, 'a> Matcher<'g, 'a> {
+    /// The position after matching `nodes` in order from `start`, or `None` if they don't match there
+    fn sequence(
+        &mut self,
+        nodes: &'g [Expr<'a>],
+        start: usize,
+    ) -> Result<Option<usize>, Unsupported> {
+        let used = self.used.len();
+        let mut position = start;
+        for node in nodes {
+            let Some(end) = self.node(node, position)? else {
+                self.used.truncate(used);
+                return Ok(None);
+            };
+            position = end;
+        }
+        Ok(Some(position))
+    }
+
+    /// Matches `body` once more, after `separator` if there is one, going back to before the separator if `body` doesn't match
+    fn repeat(
+        &mut self,
+        body: &'g [Expr<'a>],
+        separator: Option<&'g Expr<'a>>,
+        start: usize,
+    ) -> Result<Option<usize>, Unsupported> {
+        let used = self.used.len();
+        let start = match separator {
+            Some(separator) => match self.node(separator, start)? {
+                Some(end) => end,
+                None => return Ok(None),
+            },
+            None => start,
+        };
+        let end = self.sequence(body, start)?;
+        if end.is_none() {
+            self.used.truncate(used);
+        }
+        Ok(end)
+    }
+
+    fn node(&mut self, node: &'g Expr<'a>, start: usize) -> Result<Option<usize>, Unsupported> {
+        let rest = &self.input[start..];
+        Ok(match node {
+            Expr::Literal { str, .. } => rest.starts_with(str).then(|| start + str.len()),
+            Expr::Regex { pattern, .. } => {
+                let regex = self
+                    .regexes
+                    .entry(pattern)
+                    .or_insert_with(|| Regex::new(&format!("^(?:{pattern})")).ok())
+                    .as_ref()
+                    .ok_or(Unsupported)?;
+                regex.find(rest).map(|found| start + found.end())
+            }
+            Expr::Empty { .. } => Some(start),
+            Expr::Nonterminal { name, .. } => {
+                let rule = self.grammar.rules.get(*name).ok_or(Unsupported)?;
+                if self.path.contains(name) {
+                    return Err(Unsupported);
+                }
+                self.path.push(name);
+                self.used.push(Used::Rule(name));
+                let end = self.sequence(&rule.body, start)?;
+                self.path.pop();
+                end
+            }
+            Expr::Choice { body, ordered, .. } => {
+                let ordered = *ordered || self.grammar.choice_semantics == ChoiceSemantics::Ordered;
+                let used = self.used.len();
+                let mut longest = None;
+                let mut longest_used = Vec::new();
+                for alternative in body {
+                    self.used.push(Used::Alternative(alternative));
+                    if let Some(end) = self.node(alternative, start)? {
+                        if ordered {
+                            return Ok(Some(end));
+                        }
+                        // As with `max`, a later alternative wins a tie
+                        if longest.is_none_or(|longest| end >= longest) {
+                            longest = Some(end);
+                            longest_used = self.used.split_off(used);
+                        }
+                    }
+                    self.used.truncate(used);
+                }
+                self.used.append(&mut longest_used);
+                longest
+            }
+            Expr::Optional { body, .. } => Some(self.sequence(body, start)?.unwrap_or(start)),
+            Expr::Repetition { one_needed, .. } => {
+                let (body, separator) = node.repetition_parts().unwrap();
+                let mut position = start;
+                let mut count = 0;
+                // A repetition that stops consuming input would go on matching forever
+                while let Some(end) =
+                    self.repeat(body, separator.filter(|_| count > 0), position)?
+                {
+                    count += 1;
+                    if end == position {
+                        break;
+                    }
+                    position = end;
+                }
+                (count > 0 || !one_needed).then_some(position)
+            }
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                self.sequence(body, start)?
+            }
+            Expr::And { body, .. } => self.sequence(body, start)?.map(|_| start),
+            Expr::Not { body, .. } => match self.sequence(body, start)? {
+                Some(_) => None,
+                None => Some(start),
+            },
+            Expr::Rule { rule, .. } => self.sequence(&rule.body, start)?,
+            Expr::UnparsedOperator { .. } => return Err(Unsupported),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ChoiceSemantics, Grammar};
+
+    #[test]
+    fn match_str() {
+        let src = "number = sign? digit+ ('.' digit+)?; sign = '+' | '-'; digit = #'[0-9]'; \
+                   keyword = ('if' | 'iffy') !#'[a-z]'; greedy = 'a'* 'a'; list = '(' list ')' | 'x'; \
+                   dangling = missing; bad = #'[';";
+        let mut g = Grammar::new(src).unwrap();
+        let number = &g["number"];
+        assert_eq!(number.match_str(&g, "-12.5 + 3"), Some(5));
+        assert_eq!(number.match_str(&g, "12."), Some(2));
+        assert_eq!(number.match_str(&g, "x1"), None);
+
+        // Unordered choice takes the longest alternative, ordered choice the first that matches
+        assert_eq!(g["keyword"].match_str(&g, "iffy ="), Some(4));
+        assert_eq!(g["keyword"].match_str(&g, "iffier"), None);
+        // There is no backtracking, so the repetition leaves nothing for the final 'a'
+        assert_eq!(g["greedy"].match_str(&g, "aaa"), None);
+
+        assert_eq!(g["list"].match_str(&g, "x"), None);
+        assert_eq!(g["dangling"].match_str(&g, ""), None);
+        assert_eq!(g["bad"].match_str(&g, "["), None);
+
+        let g2 = Grammar::new("list = #'[0-9]+' % ','; maybe = 'x'* % ';';").unwrap();
+        assert_eq!(g2["list"].match_str(&g2, "1,22,3"), Some(6));
+        assert_eq!(g2["list"].match_str(&g2, "1,22,"), Some(4));
+        assert_eq!(g2["list"].match_str(&g2, ""), None);
+        assert_eq!(g2["maybe"].match_str(&g2, ""), Some(0));
+        assert_eq!(g2["maybe"].match_str(&g2, "x;x;"), Some(3));
+
+        g.set_choice_semantics(ChoiceSemantics::Ordered);
+        assert_eq!(g["keyword"].match_str(&g, "iffy ="), None);
+        assert_eq!(g["keyword"].match_str(&g, "if ("), Some(2));
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::fmt::Display;
+
+use crate::{Expr, Grammar, Rule, RuleClass, Span, classify::classify_rules};
+
+/// A way of joining the words of a rule's name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameCase {
+    /// `like_this`
+    Snake,
+    /// `LIKE_THIS`
+    ScreamingSnake,
+    /// `likeThis`
+    Camel,
+    /// `LikeThis`
+    Pascal,
+}
+
+impl NameCase {
+    /// The case `name` looks to be written in, going by which letters are capitals. A name with no capitals counts as snake case.
+    pub(crate) fn of(name: &str) -> NameCase {
+        let upper = name.chars().any(char::is_uppercase);
+        let lower = name.chars().any(char::is_lowercase);
+        match (upper, lower) {
+            (false, _) => NameCase::Snake,
+            (true, false) => NameCase::ScreamingSnake,
+            _ if name.starts_with(char::is_uppercase) => NameCase::Pascal,
+            _ => NameCase::Camel,
+        }
+    }
+
+    /// `name` rewritten in this case. Words are split at underscores and where the capitals change, so `HTTPRequest` is the words `http` and `request`. Underscores at either end of the name are kept as they are.
+    /// ```rust
+    /// # use ebnf_toolkit::NameCase;
+    /// assert_eq!(NameCase::Snake.apply("parseHTTPRequest2"), "parse_http_request2");
+    /// assert_eq!(NameCase::Pascal.apply("_block_item"), "_BlockItem");
+    /// assert_eq!(NameCase::ScreamingSnake.apply("whiteSpace"), "WHITE_SPACE");
+    /// ```
+    pub fn apply(self, name: &str) -> String {
+        let core = name.trim_matches('_');
+        let leading = name.len() - name.trim_start_matches('_').len();
+        let mut output = String::from(&name[..leading]);
+        for (n, word) in words(core).iter().enumerate() {
+            match self {
+                NameCase::Snake | NameCase::ScreamingSnake if n > 0 => output.push('_'),
+                _ => {}
+            }
+            match self {
+                NameCase::Snake => output.push_str(word),
+                NameCase::ScreamingSnake => {
+                    output.extend(word.chars().flat_map(char::to_uppercase));
+                }
+                NameCase::Camel if n == 0 => output.push_str(word),
+                NameCase::Camel | NameCase::Pascal => {
+                    let mut chars = word.chars();
+                    output.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+                    output.push_str(chars.as_str());
+                }
+            }
+        }
+        output.push_str(&name[leading + core.len()..]);
+        output
+    }
+
+    /// Whether `name` is already written in this case, so that [`NameCase::apply`] leaves it as it is
+    pub fn matches(self, name: &str) -> bool {
+        self.apply(name) == name
+    }
+}
+
+impl Display for NameCase {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            NameCase::Snake => "snake_case",
+            NameCase::Camel => "camelCase",
+            NameCase::Pascal => "PascalCase",
+            NameCase::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+        })
+    }
+}
+
+/// The lowercase words of `name`
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (n, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(core::mem::take(&mut current));
+            }
+            continue;
+        }
+        // A capital starts a word after a lowercase letter or digit, or ends a run of capitals if a lowercase letter follows it
+        let starts_word = c.is_uppercase()
+            && !current.is_empty()
+            && (!chars[n - 1].is_uppercase()
+                || chars.get(n + 1).is_some_and(|next| next.is_lowercase()));
+        if starts_word {
+            words.push(core::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// The cases [`Grammar::check_naming`] expects the names of each class of rule to be written in. The default is snake case for syntactic rules and screaming snake case for lexical ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NamingConvention {
+    /// The case of rules classified as [`RuleClass::Syntactic`]
+    pub syntactic: NameCase,
+    /// The case of rules classified as [`RuleClass::Lexical`]
+    pub lexical: NameCase,
+}
+
+impl Default for NamingConvention {
+    fn default() -> Self {
+        NamingConvention {
+            syntactic: NameCase::Snake,
+            lexical: NameCase::ScreamingSnake,
+        }
+    }
+}
+
+/// A rule whose name doesn't follow a [`NamingConvention`], from [`Grammar::check_naming`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NamingIssue<'a> {
+    /// The name of the rule
+    pub rule: &'a str,
+    /// Which kind of rule it was classified as
+    pub class: RuleClass,
+    /// The case the convention expects for that kind of rule
+    pub expected: NameCase,
+    /// The name rewritten in the expected case
+    pub suggestion: String,
+    /// Where the name is first defined, or the default span if the rule wasn't parsed from any input
+    pub span: Span,
+}
+
+/// Where the name of each definition of `rule` is. A definition's span starts at the name, or at the `<` before it for a hidden rule.
+pub(crate) fn name_spans<'r>(rule: &'r Rule<'_>) -> impl Iterator<Item = Span> + 'r {
+    let prefix = usize::from(rule.hidden);
+    rule.definitions
+        .iter()
+        .map(move |definition| definition.subspan(prefix, "", rule.name.len()))
+}
+
+pub(crate) fn check_naming<'a>(
+    grammar: &'a Grammar<'_>,
+    convention: NamingConvention,
+) -> Vec<NamingIssue<'a>> {
+    classify_rules(grammar)
+        .into_iter()
+        .filter_map(|(rule, class)| {
+            let expected = match class {
+                RuleClass::Lexical => convention.lexical,
+                RuleClass::Syntactic => convention.syntactic,
+            };
+            let suggestion = expected.apply(rule);
+            (suggestion != rule).then(|| NamingIssue {
+                rule,
+                class,
+                expected,
+                suggestion,
+                span: name_spans(&grammar.rules[rule]).next().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn rename_all<'a>(
+    grammar: &mut Grammar<'a>,
+    mut mapper: impl FnMut(&str) -> Option<&'a str>,
+) -> Vec<(Span, String)> {
+    let mut edits = Vec::new();
+    let mut rules = Vec::with_capacity(grammar.rules.len());
+    for mut rule in core::mem::take(&mut grammar.rules).into_values() {
+        if let Some(new) = mapper(&rule.name).filter(|new| *new != rule.name) {
+            edits.extend(name_spans(&rule).map(|span| (span, new.into())));
+            rule.name = Cow::Borrowed(new);
+        }
+        let mut stack: Vec<_> = rule.body.iter_mut().collect();
+        while let Some(node) = stack.pop() {
+            match node {
+                Expr::Nonterminal { span, name } => {
+                    if let Some(new) = mapper(name).filter(|new| new != name) {
+                        edits.push((*span, new.into()));
+                        *name = new;
+                    }
+                }
+                other => stack.extend(other.children_mut()),
+            }
+        }
+        rules.push(rule);
+    }
+    // Rules given the same name are merged, as they would be if they had been written that way
+    grammar.rules = rules.into_iter().collect::<Grammar<'a>>().rules;
+    edits.sort_by_key(|(span, _)| span.start());
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+    use crate::{Grammar, NameCase, NamingConvention, RuleClass};
+
+    #[test]
+    fn name_cases() {
+        let cases = [
+            (
+                "exprList",
+                ["expr_list", "EXPR_LIST", "exprList", "ExprList"],
+            ),
+            (
+                "HTTPRequest",
+                ["http_request", "HTTP_REQUEST", "httpRequest", "HttpRequest"],
+            ),
+            ("__ws2_", ["__ws2_", "__WS2_", "__ws2_", "__Ws2_"]),
+            ("_", ["_", "_", "_", "_"]),
+        ];
+        for (name, expected) in cases {
+            let applied = [
+                NameCase::Snake,
+                NameCase::ScreamingSnake,
+                NameCase::Camel,
+                NameCase::Pascal,
+            ]
+            .map(|case| case.apply(name));
+            assert_eq!(applied, expected, "{name}");
+        }
+        assert!(NameCase::Camel.matches("expr"));
+        assert!(!NameCase::Snake.matches("Expr"));
+    }
+
+    #[test]
+    fn check_and_rename() {
+        let src = "exprList = item (Comma item)*;\n<item> = digit | '(' exprList ')';\nComma = ',';\ndigit = #'[0-9]';";
+        let mut g = Grammar::new(src).unwrap();
+
+        let issues = g.check_naming(NamingConvention::default());
+        let summary: Vec<_> = issues
+            .iter()
+            .map(|i| (i.rule, i.class, &*i.suggestion))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Comma", RuleClass::Lexical, "COMMA"),
+                ("digit", RuleClass::Lexical, "DIGIT"),
+                ("exprList", RuleClass::Syntactic, "expr_list"),
+            ]
+        );
+        assert_eq!(&src[issues[2].span.range()], "exprList");
+
+        let renames: BTreeMap<String, String> = issues
+            .iter()
+            .map(|i| (i.rule.into(), i.suggestion.clone()))
+            .collect();
+        let edits = g.rename_all(|name| renames.get(name).map(String::as_str));
+
+        let mut patched = String::from(src);
+        for (span, new) in edits.iter().rev() {
+            patched.replace_range(span.range(), new);
+        }
+        assert_eq!(
+            patched,
+            "expr_list = item (COMMA item)*;\n<item> = DIGIT | '(' expr_list ')';\nCOMMA = ',';\nDIGIT = #'[0-9]';"
+        );
+        assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+        assert!(g.check_naming(NamingConvention::default()).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{Expr, Grammar, Span};
+
+/// The id of a rule, as given by [`RuleIds`](crate::RuleIds) and used by [`FrozenGrammar`](crate::FrozenGrammar). This is the rule's position among the rules of the grammar in order of name, so it is only meaningful for the set of rules it was taken from: adding or removing a rule renumbers every rule whose name sorts after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleId(pub(crate) usize);
+
+impl RuleId {
+    /// The rule's position in order of name, from `0`, for indexing a table with an entry for each rule
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Identifies a node of a [`Grammar`] by the rule it's in and its position in that rule, for tools that keep data about nodes outside the grammar, such as how often each was used while parsing.
+///
+/// Nodes are numbered from `0` in pre-order: each node comes before its children, and all of them before the next node of the sequence they're in. Ids are positions rather than identities stored in the grammar: an id only depends on the names of the rules and the shape of their bodies, so it is the same for a clone of the grammar, or one rebuilt from its [`StructuredGrammar`](crate::StructuredGrammar), but changing the shape of a rule renumbers the nodes after the change in that rule, and adding or removing a rule renumbers the nodes of every rule after it, as described for [`RuleId`].
+/// ```rust
+/// # use ebnf_toolkit::{Expr, Grammar, NodeId};
+/// let g = Grammar::new("list = item (',' item)*; item = #'[0-9]+';").unwrap();
+/// let list = g.rule_ids().id("list").unwrap();
+///
+/// let comma = NodeId { rule: list, index: 2 };
+/// assert!(matches!(g.node(comma), Some(Expr::Literal { str: ",", .. })));
+///
+/// let structured = g.to_structured();
+/// let rebuilt = Grammar::from_structured(&structured);
+/// assert_eq!(rebuilt.node(comma), g.node(comma));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeId {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub rule: RuleId,
+    /// The node's position in the rule, counting in pre-order
+    pub index: usize,
+}
+
+/// The [`NodeId`] of every node of a [`Grammar`], from [`Grammar::node_ids`], looked up by span. The spans reported by a transform, such as [`Grammar::remove_duplicate_alternatives`], are those of nodes of the grammar before it was changed, so ids taken beforehand turn them into ids.
+///
+/// This only tells nodes apart by their spans, so it relies on the grammar having been parsed from text. Nodes built by hand, or whose spans were removed with [`Grammar::strip_spans`], all have the same empty span, which every one of their ids is then found under.
+/// ```rust
+/// # use ebnf_toolkit::{Grammar, NodeId};
+/// let mut g = Grammar::new("op = '+' | '-' | '+';").unwrap();
+/// let ids = g.node_ids();
+/// let removed: Vec<_> = g
+///     .remove_duplicate_alternatives()
+///     .into_iter()
+///     .flat_map(|span| ids.with_span(span))
+///     .collect();
+/// let op = g.rule_ids().id("op").unwrap();
+/// assert_eq!(removed, [&NodeId { rule: op, index: 3 }]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeIds {
+    /// Keyed by the byte range of the span, with the ids of nodes sharing a span outermost first
+    by_span: BTreeMap<(usize, usize), Vec<NodeId>>,
+    len: usize,
+}
+
+impl NodeIds {
+    pub(crate) fn new(grammar: &Grammar<'_>) -> Self {
+        let mut ids = NodeIds::default();
+        for (rule, body) in grammar.rules.values().map(|r| &r.body).enumerate() {
+            let rule = RuleId(rule);
+            let mut index = 0;
+            preorder(body, &mut |node| {
+                let span = node.span();
+                ids.by_span
+                    .entry((span.start(), span.end()))
+                    .or_default()
+                    .push(NodeId { rule, index });
+                index += 1;
+            });
+            ids.len += index;
+        }
+        ids
+    }
+
+    /// The ids of the nodes with exactly the span `span`, outermost first, which is usually only one for a parsed grammar
+    pub fn with_span(&self, span: Span) -> &[NodeId] {
+        self.by_span
+            .get(&(span.start(), span.end()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The number of nodes in the grammar
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the grammar has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Calls `f` on each of `nodes` and their descendants, in pre-order
+pub(crate) fn preorder<'e, 'a>(nodes: &'e [Expr<'a>], f: &mut impl FnMut(&'e Expr<'a>)) {
+    for node in nodes {
+        f(node);
+        preorder(node.children(), f);
+    }
+}
+
+/// The node of `grammar` with the id `id`, if there is one
+pub(crate) fn node<'g, 'a>(grammar: &'g Grammar<'a>, id: NodeId) -> Option<&'g Expr<'a>> {
+    /// Finds the node `skip` places along in pre-order, counting `skip` down as nodes are passed
+    fn nth<'e, 'a>(nodes: &'e [Expr<'a>], skip: &mut usize) -> Option<&'e Expr<'a>> {
+        for node in nodes {
+            if *skip == 0 {
+                return Some(node);
+            }
+            *skip -= 1;
+            if let Some(found) = nth(node.children(), skip) {
+                return Some(found);
+            }
+        }
+        None
+    }
+    let rule = grammar.rules.values().nth(id.rule.0)?;
+    nth(&rule.body, &mut { id.index })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Expr, Grammar, NodeId, RuleId};
+
+    #[test]
+    fn ids_round_trip() {
+        let g = Grammar::new("a = b ('x' | <c:'y'>)+; b = &'z' #'[a-z]'; c = '';").unwrap();
+        let ids = g.node_ids();
+        let mut count = 0;
+        for (rule, _) in g.rule_ids().iter() {
+            for index in 0.. {
+                let Some(node) = g.node(NodeId { rule, index }) else {
+                    break;
+                };
+                assert!(ids.with_span(node.span()).contains(&NodeId { rule, index }));
+                count += 1;
+            }
+        }
+        assert_eq!(count, ids.len());
+        assert_eq!(
+            g.node(NodeId {
+                rule: RuleId(3),
+                index: 0
+            }),
+            None
+        );
+        let b = g.rule_ids().id("b").unwrap();
+        assert_eq!(b.index(), 1);
+        assert!(matches!(
+            g.node(NodeId { rule: b, index: 1 }),
+            Some(Expr::Literal { str: "z", .. })
+        ));
+        assert_eq!(g.node(NodeId { rule: b, index: 3 }), None);
+    }
+}
@@ -0,0 +1,430 @@
+use alloc::{
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use regex::Regex;
+use regex_syntax::hir::{Class, ClassUnicode, Hir, HirKind};
+
+use crate::{
+    Expr, Grammar, Rule, RuleClass, classify::classify_rules, compile::rule_pattern,
+    regex_terminal::leading_chars,
+};
+
+/// How many strings are generated from each pattern when searching for input both patterns accept
+const SAMPLE_LIMIT: usize = 64;
+/// How many shared inputs are kept for each overlap
+const EXAMPLE_LIMIT: usize = 3;
+
+/// Two regex alternatives of the same [`Choice`](`Expr::Choice`) that can match the same input, which usually means a tokenizer will not behave the way the grammar's author expected. [`Grammar::check_regex_overlap`] also compares alternatives that only begin with a regex, and regexes with literals.
+///
+/// Deciding exactly whether two regular languages intersect is expensive, so this is an approximation built from the patterns' [HIR](regex_syntax::hir::Hir):
+/// * a handful of short strings are generated from each pattern and tested against the other - any that match both are recorded in `examples`
+/// * failing that, the set of characters each pattern can begin with are intersected. If these overlap, the alternatives are still reported, with no examples, as a tokenizer may need unbounded lookahead to pick between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexOverlap<'a> {
+    /// The name of the rule containing the alternatives
+    pub rule: &'a str,
+    /// The alternative appearing first in the [`Choice`](`Expr::Choice`)
+    pub first: &'a Expr<'a>,
+    /// The alternative appearing second in the [`Choice`](`Expr::Choice`)
+    pub second: &'a Expr<'a>,
+    /// Inputs matched in their entirety by both alternatives, shortest first. Empty if the alternatives are only known to share a leading character.
+    pub examples: Vec<String>,
+}
+
+/// Two alternatives of the same [`Choice`](`Expr::Choice`) whose leading terminals can match the same input, where at least one of them is a regex, found by [`Grammar::check_regex_overlap`]. A tokenizer seeing that input can't tell which alternative it starts without looking further, in the same way as for two alternatives sharing a literal in their FIRST sets, which treat a literal and a regex as different terminals.
+///
+/// The leading terminal of an alternative is the literal or regex it must begin with, looking inside groups, labels, hidden parts and repetitions needing at least one match, but not inside other rules. Two regexes are compared as for [`RegexOverlap`], and a literal overlaps a regex that matches all of it, with the literal as the example, or that can begin with the literal's first character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalOverlap<'a> {
+    /// The name of the rule containing the alternatives
+    pub rule: &'a str,
+    /// The leading terminal of the alternative appearing first in the [`Choice`](`Expr::Choice`)
+    pub first: &'a Expr<'a>,
+    /// The leading terminal of the alternative appearing second in the [`Choice`](`Expr::Choice`)
+    pub second: &'a Expr<'a>,
+    /// Inputs matched in their entirety by both terminals, shortest first. Empty if the terminals are only known to share a leading character.
+    pub examples: Vec<String>,
+}
+
+pub(crate) fn rule_regex_overlaps<'a>(rule: &'a Rule<'_>) -> Vec<RegexOverlap<'a>> {
+    let regex = |node: &'a Expr<'a>| matches!(node, Expr::Regex { .. }).then_some(node);
+    choice_overlaps(rule, regex, regex_overlap)
+        .into_iter()
+        .map(|(first, second, examples)| RegexOverlap {
+            rule: &rule.name,
+            first,
+            second,
+            examples,
+        })
+        .collect()
+}
+
+pub(crate) fn rule_terminal_overlaps<'a>(rule: &'a Rule<'_>) -> Vec<TerminalOverlap<'a>> {
+    choice_overlaps(rule, leading_terminal, terminal_overlap)
+        .into_iter()
+        .map(|(first, second, examples)| TerminalOverlap {
+            rule: &rule.name,
+            first,
+            second,
+            examples,
+        })
+        .collect()
+}
+
+/// Compares the nodes `pick` gives for each pair of alternatives of each [`Choice`](`Expr::Choice`) in `rule` with `compare`, giving the pairs it finds overlapping and its examples for them
+fn choice_overlaps<'a>(
+    rule: &'a Rule<'_>,
+    pick: impl Fn(&'a Expr<'a>) -> Option<&'a Expr<'a>>,
+    compare: fn(&Expr<'_>, &Expr<'_>) -> Option<Vec<String>>,
+) -> Vec<(&'a Expr<'a>, &'a Expr<'a>, Vec<String>)> {
+    let mut overlaps = vec![];
+    let mut stack: Vec<_> = rule.body.iter().collect();
+
+    while let Some(node) = stack.pop() {
+        if let Expr::Choice { body, .. } = node {
+            let picked: Vec<_> = body.iter().map(&pick).collect();
+            for (n, first) in picked.iter().enumerate() {
+                for second in &picked[n + 1..] {
+                    let (Some(first), Some(second)) = (*first, *second) else {
+                        continue;
+                    };
+                    if let Some(examples) = compare(first, second) {
+                        overlaps.push((first, second, examples));
+                    }
+                }
+            }
+        }
+        stack.extend(node.children());
+    }
+    overlaps
+}
+
+/// The literal or regex that every match of `node` begins with, if it has one that can be found without looking at other rules
+fn leading_terminal<'e, 'a>(node: &'e Expr<'a>) -> Option<&'e Expr<'a>> {
+    match node {
+        Expr::Literal { str, .. } if !str.is_empty() => Some(node),
+        Expr::Regex { .. } => Some(node),
+        Expr::Group { body, .. }
+        | Expr::Labeled { body, .. }
+        | Expr::Hidden { body, .. }
+        | Expr::Repetition {
+            body,
+            one_needed: true,
+            ..
+        } => leading_terminal(body.first()?),
+        _ => None,
+    }
+}
+
+/// Returns `None` if the terminals aren't a regex and another terminal, or if they were found not to overlap
+fn terminal_overlap(first: &Expr<'_>, second: &Expr<'_>) -> Option<Vec<String>> {
+    match (first, second) {
+        (Expr::Regex { .. }, Expr::Regex { .. }) => regex_overlap(first, second),
+        (Expr::Literal { str: literal, .. }, Expr::Regex { pattern, .. })
+        | (Expr::Regex { pattern, .. }, Expr::Literal { str: literal, .. }) => {
+            let hir = regex_syntax::parse(pattern).ok()?;
+            if anchored(pattern).ok()?.is_match(literal) {
+                return Some(vec![literal.to_string()]);
+            }
+            let mut leading = ClassUnicode::empty();
+            leading_chars(&hir, &mut leading);
+            let c = literal.chars().next()?;
+            leading
+                .ranges()
+                .iter()
+                .any(|range| range.start() <= c && c <= range.end())
+                .then(Vec::new)
+        }
+        _ => None,
+    }
+}
+
+/// Two lexical rules that can match the same input, found by [`Grammar::lexical_conflicts`]. A parser generator that makes each of them a token will have a lexer conflict, settled by whichever rule it tries first, or reported as an error.
+///
+/// Only the rules that would become tokens are compared: the [lexical](RuleClass::Lexical) rules that no other lexical rule refers to, as the others only make up parts of tokens. Each rule is [compiled to a regex](Rule::compile_to_regex), and as for [`RegexOverlap`], a handful of short strings generated from each are tested against the other, so a conflict found this way is certain but one can be missed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexicalConflict<'a> {
+    /// The rule whose name comes first
+    pub first: &'a Rule<'a>,
+    /// The rule whose name comes second
+    pub second: &'a Rule<'a>,
+    /// Inputs matched in their entirety by both rules, shortest first
+    pub examples: Vec<String>,
+}
+
+pub(crate) fn lexical_conflicts<'a>(grammar: &'a Grammar<'_>) -> Vec<LexicalConflict<'a>> {
+    let lexical: Vec<_> = classify_rules(grammar)
+        .into_iter()
+        .filter(|(_, class)| *class == RuleClass::Lexical)
+        .map(|(name, _)| &grammar.rules[name])
+        .collect();
+    let parts: BTreeSet<_> = lexical
+        .iter()
+        .flat_map(|rule| rule.nonterminals())
+        .collect();
+
+    // Rules with a pattern the `regex` crate rejects, or that can't be written as one, can't be compared
+    let tokens: Vec<_> = lexical
+        .into_iter()
+        .filter(|rule| !parts.contains(&*rule.name))
+        .filter_map(|rule| {
+            let pattern = rule_pattern(rule, grammar).ok()?;
+            let hir = regex_syntax::parse(&pattern).ok()?;
+            Some((rule, anchored(&pattern).ok()?, samples(&hir)))
+        })
+        .collect();
+
+    let mut conflicts = vec![];
+    for (n, (first, first_regex, first_samples)) in tokens.iter().enumerate() {
+        for (second, second_regex, second_samples) in &tokens[n + 1..] {
+            let examples =
+                shared_examples(first_samples, second_regex, second_samples, first_regex);
+            if examples.is_empty() {
+                continue;
+            }
+            conflicts.push(LexicalConflict {
+                first,
+                second,
+                examples,
+            });
+        }
+    }
+    conflicts
+}
+
+/// Returns `None` if the nodes aren't both valid regexes, or if they were found not to overlap
+fn regex_overlap(first: &Expr<'_>, second: &Expr<'_>) -> Option<Vec<String>> {
+    let (Expr::Regex { pattern: a, .. }, Expr::Regex { pattern: b, .. }) = (first, second) else {
+        return None;
+    };
+    let (Ok(hir_a), Ok(hir_b)) = (regex_syntax::parse(a), regex_syntax::parse(b)) else {
+        return None;
+    };
+    let (Ok(whole_a), Ok(whole_b)) = (anchored(a), anchored(b)) else {
+        return None;
+    };
+
+    let examples = shared_examples(&samples(&hir_a), &whole_b, &samples(&hir_b), &whole_a);
+
+    let mut shared = ClassUnicode::empty();
+    leading_chars(&hir_a, &mut shared);
+    let mut leading_b = ClassUnicode::empty();
+    leading_chars(&hir_b, &mut leading_b);
+    shared.intersect(&leading_b);
+
+    if examples.is_empty() && shared.ranges().is_empty() {
+        None
+    } else {
+        Some(examples)
+    }
+}
+
+/// The samples of each pattern that the other matches in full, shortest first and without repeats, keeping at most [`EXAMPLE_LIMIT`]
+fn shared_examples(
+    samples_a: &[String],
+    regex_b: &Regex,
+    samples_b: &[String],
+    regex_a: &Regex,
+) -> Vec<String> {
+    let mut examples: Vec<String> = samples_a
+        .iter()
+        .filter(|s| regex_b.is_match(s))
+        .chain(samples_b.iter().filter(|s| regex_a.is_match(s)))
+        .cloned()
+        .collect();
+    examples.sort_by(|x, y| x.len().cmp(&y.len()).then(x.cmp(y)));
+    examples.dedup();
+    examples.truncate(EXAMPLE_LIMIT);
+    examples
+}
+
+fn anchored(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{pattern})$"))
+}
+
+/// A bounded selection of strings matched by `hir`, built from the boundaries of each character class and the smallest repetition counts allowed
+fn samples(hir: &Hir) -> Vec<String> {
+    let mut output: Vec<String> = match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => vec![String::new()],
+        HirKind::Literal(lit) => vec![String::from_utf8_lossy(&lit.0).into_owned()],
+        HirKind::Class(Class::Unicode(c)) => c
+            .ranges()
+            .iter()
+            .flat_map(|r| [r.start(), r.end()])
+            .map(String::from)
+            .collect(),
+        HirKind::Class(Class::Bytes(c)) => c
+            .ranges()
+            .iter()
+            .flat_map(|r| [r.start(), r.end()])
+            .filter(u8::is_ascii)
+            .map(|b| String::from(char::from(b)))
+            .collect(),
+        HirKind::Repetition(rep) => {
+            let inner = samples(&rep.sub);
+            let max = rep.max.unwrap_or(u32::MAX);
+            (rep.min..=max)
+                .take(2)
+                .flat_map(|count| inner.iter().map(move |s| s.repeat(count as usize)))
+                .collect()
+        }
+        HirKind::Capture(cap) => samples(&cap.sub),
+        HirKind::Concat(subs) => subs.iter().fold(vec![String::new()], |prefixes, sub| {
+            let suffixes = samples(sub);
+            prefixes
+                .iter()
+                .flat_map(|p| suffixes.iter().map(move |s| format!("{p}{s}")))
+                .take(SAMPLE_LIMIT)
+                .collect()
+        }),
+        HirKind::Alternation(subs) => subs.iter().flat_map(samples).collect(),
+    };
+    output.dedup();
+    output.truncate(SAMPLE_LIMIT);
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use crate::{Expr, Grammar};
+
+    #[test]
+    fn sibling_regexes() {
+        let src = "A = #'[a-z]+' | #'[a-f0-9]+' | #'[A-Z]'; B = #'x*' | #'y*';";
+        let g = Grammar::new(src).unwrap();
+        let overlaps = g.regex_overlaps();
+
+        assert_eq!(overlaps.len(), 2);
+
+        let [a, b] = &overlaps[..] else {
+            unreachable!()
+        };
+        assert_eq!(a.rule, "A");
+        assert!(matches!(
+            a.first,
+            Expr::Regex {
+                pattern: "[a-z]+",
+                ..
+            }
+        ));
+        assert!(matches!(
+            a.second,
+            Expr::Regex {
+                pattern: "[a-f0-9]+",
+                ..
+            }
+        ));
+        assert_eq!(a.examples, ["a", "f", "aa"]);
+
+        assert_eq!(b.rule, "B");
+        assert_eq!(b.examples, [""]);
+    }
+
+    #[test]
+    fn shared_prefix_only() {
+        let g = Grammar::new("A = #'[a-z]+' | #'[a-z]+[0-9]';").unwrap();
+        let overlaps = g.regex_overlaps();
+
+        assert_eq!(overlaps.len(), 1);
+        assert!(overlaps[0].examples.is_empty());
+    }
+
+    #[test]
+    fn leading_terminals() {
+        let src = "A = #'[a-z]+' | 'abc' | ('if' B) | <x:#'[0-9]'+> | ('9' C) | '_' | ('_' D); \
+                   B = #'x*' | ''; C = [#'q'] | 'q';";
+        let g = Grammar::new(src).unwrap();
+        let overlaps: Vec<_> = g
+            .check_regex_overlap()
+            .iter()
+            .map(|o| {
+                (
+                    &src[o.first.span().range()],
+                    &src[o.second.span().range()],
+                    o.examples.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            overlaps,
+            [
+                ("#'[a-z]+'", "'abc'", vec!["abc".to_string()]),
+                ("#'[a-z]+'", "'if'", vec!["if".to_string()]),
+                ("#'[0-9]'", "'9'", vec!["9".to_string()]),
+            ]
+        );
+
+        let g = Grammar::new("A = #'[a-z]+[0-9]' | ('ab' B);").unwrap();
+        let overlaps = g.check_regex_overlap();
+        assert_eq!(overlaps.len(), 1);
+        assert!(overlaps[0].examples.is_empty());
+    }
+
+    #[test]
+    fn only_regex_alternatives() {
+        // Literals and alternatives that only begin with a regex are left to `check_regex_overlap`
+        let src = "A = #'[a-z]+' | 'abc' | (#'[a-z]' B) | #'[0-9]' | <#'[0-5]'>; B = 'b';";
+        let g = Grammar::new(src).unwrap();
+        let overlaps: Vec<_> = g
+            .regex_overlaps()
+            .iter()
+            .map(|o| (&src[o.first.span().range()], &src[o.second.span().range()]))
+            .collect();
+        assert_eq!(overlaps, []);
+        assert_eq!(g.check_regex_overlap().len(), 4);
+
+        let g = Grammar::new("A = #'[a-z]+' | 'abc' | #'[a-c]';").unwrap();
+        let overlaps = g.regex_overlaps();
+        assert_eq!(overlaps.len(), 1);
+        assert!(matches!(
+            overlaps[0].first,
+            Expr::Regex {
+                pattern: "[a-z]+",
+                ..
+            }
+        ));
+        assert!(matches!(
+            overlaps[0].second,
+            Expr::Regex {
+                pattern: "[a-c]",
+                ..
+            }
+        ));
+        assert_eq!(overlaps[0].examples, ["a", "c"]);
+    }
+
+    #[test]
+    fn lexical_conflicts() {
+        let src = "stmt = (IF expr) | (ident '=' expr); expr = number | ident | (PLUS expr); \
+                   IF = 'if'; ident = letter (letter | digit)*; letter = #'[a-z]'; \
+                   number = digit+; digit = #'[0-9]'; PLUS = '+'; ADD = #'[+]' | 'add'; \
+                   bad = #'[';";
+        let g = Grammar::new(src).unwrap();
+        let conflicts: Vec<_> = g
+            .lexical_conflicts()
+            .iter()
+            .map(|c| {
+                (
+                    c.first.name.as_ref(),
+                    c.second.name.as_ref(),
+                    c.examples.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                ("ADD", "PLUS", vec!["+".to_string()]),
+                ("ADD", "ident", vec!["add".to_string()]),
+                ("IF", "ident", vec!["if".to_string()]),
+            ]
+        );
+    }
+}
@@ -1,10 +1,12 @@
-use std::{ops::Range, slice::SliceIndex, sync::LazyLock};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{ops::Range, slice::SliceIndex};
 
+use once_cell::race::OnceBox;
 use regex::{Match, Regex};
 use strum::VariantNames;
 
 use crate::{
-    expr::{Expr, ExprKind, Operator},
+    expr::{Expr, NodeKind, Operator},
     token_data::{Token, TokenPayload},
 };
 
@@ -12,7 +14,9 @@ use crate::{
 fn decode_rule_regex(pat: &str) -> Regex {
     let mut s = pat.replace(' ', "");
 
-    for name in ExprKind::VARIANTS {
+    s = s.replace("HiddenName", HIDDEN_NAME);
+    s = s.replace("PredicateName", PREDICATE_NAME);
+    for name in NodeKind::VARIANTS {
         s = s.replace(name, &name[..1]);
     }
     s = s.replace("Any", NON_OPERATOR);
@@ -23,103 +27,256 @@ fn decode_rule_regex(pat: &str) -> Regex {
 }
 // Any node, including compound nodes, that is not an operator
 const NON_OPERATOR: &str = "[A-Za-z]";
+/// Stands for a [`Hidden`](Expr::Hidden) node around a lone nonterminal, which can be the name of a rule as well as an ordinary node
+pub(crate) const HIDDEN_NAME: &str = "h";
+/// Stands for an [`And`](Expr::And) or [`Not`](Expr::Not) node around a lone nonterminal, which may turn out to be a label once a `:` follows it
+pub(crate) const PREDICATE_NAME: &str = "p";
 
-/// Regexes over the token types for each reduction rule.
+/// Regexes over the token types for each reduction rule, along with the kind of node the reduction produces.
 /// NB: regex operators will be interpreted as usual, a grammar operator needs escaped
-static REDUCTION_PATTERNS: LazyLock<[(Regex, Reducer); 8]> = LazyLock::new(|| {
-    [
-        (decode_rule_regex(r"Any (\| Any)+"), rules::choice),
-        (decode_rule_regex(r"\[Any+\]"), rules::option),
-        (decode_rule_regex(r"Any\?"), rules::option),
-        (decode_rule_regex(r"Any\*"), rules::repeat),
-        (decode_rule_regex(r"Any\+"), rules::repeat),
-        (decode_rule_regex(r"\{Any\}"), rules::repeat),
-        (decode_rule_regex(r"\(Any+\)"), rules::list),
-        (decode_rule_regex(r"Nonterminal = Any+;"), rules::rule),
-    ]
-});
-
-type Reducer = for<'a> fn(&[Expr<'a>]) -> (Expr<'a>, usize);
+static REDUCTION_PATTERNS: OnceBox<[(Regex, Reducer, NodeKind); 14]> = OnceBox::new();
+
+fn reduction_patterns() -> &'static [(Regex, Reducer, NodeKind); 14] {
+    use NodeKind as K;
+    REDUCTION_PATTERNS.get_or_init(|| {
+        Box::new([
+            (
+                decode_rule_regex(r"Any (\| Any)+"),
+                rules::choice,
+                K::Choice,
+            ),
+            (decode_rule_regex(r"Any (/ Any)+"), rules::choice, K::Choice),
+            (decode_rule_regex(r"\[Any+\]"), rules::option, K::Optional),
+            (decode_rule_regex(r"Any\?"), rules::option, K::Optional),
+            (decode_rule_regex(r"Any\*"), rules::repeat, K::Repetition),
+            (decode_rule_regex(r"Any\+"), rules::repeat, K::Repetition),
+            (decode_rule_regex(r"\{Any\}"), rules::repeat, K::Repetition),
+            (
+                decode_rule_regex(r"Any % Any"),
+                rules::separated,
+                K::Repetition,
+            ),
+            (decode_rule_regex(r"\(Any+\)"), rules::list, K::Group),
+            (decode_rule_regex(r"& Any"), rules::and, K::And),
+            (decode_rule_regex(r"! Any"), rules::not, K::Not),
+            (decode_rule_regex(r"< Any+ >"), rules::hidden, K::Hidden),
+            (
+                decode_rule_regex(r"(?:Nonterminal|PredicateName) : Any"),
+                rules::labeled,
+                K::Labeled,
+            ),
+            (
+                decode_rule_regex(r"(?:Nonterminal|HiddenName) = Any+;"),
+                rules::rule,
+                K::Rule,
+            ),
+        ])
+    })
+}
+
+/// Builds the node for a reduction from the nodes it matched, which have been moved off the stack. The `Vec` is reused as the new node's body where possible, so reducing doesn't copy any subtrees.
+type Reducer = for<'a> fn(Vec<Expr<'a>>) -> Expr<'a>;
 
 mod rules {
-    use std::borrow::Cow;
+    use alloc::{borrow::Cow, vec, vec::Vec};
 
     use crate::{
-        expr::{Expr, ExprKind, Operator},
+        expr::{Expr, NodeKind, Operator},
         rule::Rule,
-        token_data::Span,
+        token_data::{Span, SpanSet},
     };
 
-    fn filter_parsed<'a>(nodes: &[Expr<'a>]) -> (Vec<Expr<'a>>, Span, usize) {
-        let size = nodes.len();
+    fn filter_parsed(mut nodes: Vec<Expr<'_>>) -> (Vec<Expr<'_>>, Span) {
         let span = Span::union(nodes.iter());
-        let new_nodes: Vec<Expr<'_>> = nodes
-            .iter()
-            .filter(|n| ExprKind::UnparsedOperator != ExprKind::from(*n))
-            .cloned()
-            .collect();
+        nodes.retain(|n| NodeKind::UnparsedOperator != NodeKind::from(n));
 
-        debug_assert!(!new_nodes.is_empty());
-        (new_nodes, span, size)
+        debug_assert!(!nodes.is_empty());
+        (nodes, span)
     }
 
-    pub(super) fn choice<'a>(nodes: &[Expr<'a>]) -> (Expr<'a>, usize) {
-        let (body, span, size) = filter_parsed(nodes);
+    /// The patterns for `|` and `/` each only match one of them, so the operators in `nodes` are all the same
+    pub(super) fn choice(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let ordered = matches!(
+            nodes[1],
+            Expr::UnparsedOperator {
+                op: Operator::OrderedAlternation,
+                ..
+            }
+        );
+        let (body, span) = filter_parsed(nodes);
 
-        (Expr::Choice { span, body }, size)
+        Expr::Choice {
+            span,
+            body,
+            ordered,
+        }
     }
 
-    pub(super) fn option<'a>(nodes: &[Expr<'a>]) -> (Expr<'a>, usize) {
-        let (body, span, size) = filter_parsed(nodes);
+    /// Postfix operators bind more tightly than `&`, `!` and labels, but a predicate or label has already been reduced by the time a postfix operator after it is seen. If `nodes` is such a predicate or label and operator, this applies `reducer` to its body instead.
+    fn within_predicate<'a>(
+        mut nodes: Vec<Expr<'a>>,
+        reducer: fn(Vec<Expr<'a>>) -> Expr<'a>,
+    ) -> Result<Expr<'a>, Vec<Expr<'a>>> {
+        if let [
+            Expr::And { .. } | Expr::Not { .. } | Expr::Labeled { .. },
+            Expr::UnparsedOperator { op, .. },
+        ] = &nodes[..]
+            && matches!(op, Operator::Optional | Operator::Kleene | Operator::Repeat)
+        {
+            let op = nodes.pop().unwrap();
+            let span = op.span();
+            let mut predicate = nodes.pop().unwrap();
+            let (Expr::And { span: outer, body }
+            | Expr::Not { span: outer, body }
+            | Expr::Labeled {
+                span: outer, body, ..
+            }) = &mut predicate
+            else {
+                unreachable!()
+            };
+            let mut inner = core::mem::take(body);
+            inner.push(op);
+            body.push(reducer(inner));
+            *outer = outer.join(span);
+            Ok(predicate)
+        } else {
+            Err(nodes)
+        }
+    }
 
-        (Expr::Optional { span, body }, size)
+    pub(super) fn option(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let nodes = match within_predicate(nodes, option) {
+            Ok(predicate) => return predicate,
+            Err(nodes) => nodes,
+        };
+        let (body, span) = filter_parsed(nodes);
+
+        Expr::Optional { span, body }
     }
 
-    pub(super) fn repeat<'a>(nodes: &[Expr<'a>]) -> (Expr<'a>, usize) {
-        let (body, span, size) = filter_parsed(nodes);
+    pub(super) fn and(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let (body, span) = filter_parsed(nodes);
 
-        let node = if let Expr::UnparsedOperator { op, .. } = nodes.last().unwrap() {
-            match op {
-                Operator::Kleene => Expr::Repetition {
-                    span,
-                    body,
-                    one_needed: false,
-                },
-                Operator::ClosedBrace | Operator::Repeat => Expr::Repetition {
-                    span,
+        Expr::And { span, body }
+    }
+
+    pub(super) fn not(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let (body, span) = filter_parsed(nodes);
+
+        Expr::Not { span, body }
+    }
+
+    /// A predicate has already been reduced by the time a `:` after it is seen, so for `!a:b`, this moves the label inside the predicate
+    pub(super) fn labeled(mut nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let span = Span::union(nodes.iter());
+        let body = nodes.split_off(2);
+        let mut head = nodes.swap_remove(0);
+        match &mut head {
+            Expr::Nonterminal { name: label, .. } => Expr::Labeled { span, label, body },
+            Expr::And {
+                span: outer,
+                body: predicate,
+            }
+            | Expr::Not {
+                span: outer,
+                body: predicate,
+            } => {
+                let Some(Expr::Nonterminal { span: start, name }) = predicate.pop() else {
+                    unreachable!("Bug: Label of {predicate:?}")
+                };
+                predicate.push(Expr::Labeled {
+                    span: start.join(span),
+                    label: name,
                     body,
-                    one_needed: true,
-                },
-                t => unreachable!("Encountered {t:?} at the end of a repeat block - this is a bug"),
+                });
+                *outer = outer.join(span);
+                head
             }
-        } else {
+            _ => unreachable!("Bug: Label of {head:?}"),
+        }
+    }
+
+    pub(super) fn hidden(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let (body, span) = filter_parsed(nodes);
+
+        Expr::Hidden { span, body }
+    }
+
+    pub(super) fn repeat(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let nodes = match within_predicate(nodes, repeat) {
+            Ok(predicate) => return predicate,
+            Err(nodes) => nodes,
+        };
+        let Some(Expr::UnparsedOperator { op, .. }) = nodes.last() else {
             unreachable!()
         };
-        (node, size)
+        let one_needed = match op {
+            Operator::Kleene => false,
+            Operator::ClosedBrace | Operator::Repeat => true,
+            t => unreachable!("Encountered {t:?} at the end of a repeat block - this is a bug"),
+        };
+        let (body, span) = filter_parsed(nodes);
+
+        Expr::Repetition {
+            span,
+            body,
+            one_needed,
+            separated: false,
+        }
+    }
+
+    /// `x* % s` and `x+ % s` give the repetition a separator, and any other term before the `%` is repeated one or more times
+    pub(super) fn separated(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let span = Span::union(nodes.iter());
+        let [repeated, _, separator] = <[_; 3]>::try_from(nodes).unwrap();
+        let (mut body, one_needed) = match repeated {
+            Expr::Repetition {
+                body,
+                one_needed,
+                separated: false,
+                ..
+            } => (body, one_needed),
+            other => (vec![other], true),
+        };
+        body.push(separator);
+        Expr::Repetition {
+            span,
+            body,
+            one_needed,
+            separated: true,
+        }
     }
 
-    pub(super) fn list<'a>(nodes: &[Expr<'a>]) -> (Expr<'a>, usize) {
-        let (body, span, size) = filter_parsed(nodes);
+    pub(super) fn list(nodes: Vec<Expr<'_>>) -> Expr<'_> {
+        let (body, span) = filter_parsed(nodes);
 
-        (Expr::Group { span, body }, size)
+        Expr::Group { span, body }
     }
 
-    pub(super) fn rule<'a>(nodes: &[Expr<'a>]) -> (Expr<'a>, usize) {
-        let size = nodes.len();
+    pub(super) fn rule(mut nodes: Vec<Expr<'_>>) -> Expr<'_> {
         let span = Span::union(nodes.iter());
 
-        let [Expr::Nonterminal { name, .. }, _equals, body @ .., _term] = nodes else {
-            unreachable!("Bug: Rule with a name of {:?}", nodes.first())
+        let (name, hidden) = match &nodes[..] {
+            [Expr::Nonterminal { name, .. }, _, .., _] => (*name, false),
+            [Expr::Hidden { body, .. }, _, .., _] => match body[..] {
+                [Expr::Nonterminal { name, .. }] => (name, true),
+                _ => unreachable!("Bug: Hidden rule with a name of {body:?}"),
+            },
+            _ => unreachable!("Bug: Rule with a name of {:?}", nodes.first()),
         };
+        nodes.pop();
+        nodes.drain(..2);
 
-        let rule_node = Expr::Rule {
+        Expr::Rule {
             span,
             rule: Rule {
-                name: Cow::Borrowed(*name),
-                body: body.to_vec(),
+                name: Cow::Borrowed(name),
+                body: nodes,
+                definitions: SpanSet::from(span),
+                hidden,
+                annotations: Vec::new(),
             },
-        };
-        (rule_node, size)
+        }
     }
 }
 
@@ -146,28 +303,15 @@ impl<'a> LrStack<'a> {
     }
 
     pub(crate) fn push_token(&mut self, t: Token<'a>) {
-        use Operator as Op;
-        use TokenPayload as Tp;
-        let Token { payload, span } = t;
-        let op_node = |op| Expr::UnparsedOperator { op, span };
-        let node = match payload {
-            Tp::Alternation => op_node(Op::Alternation),
-            Tp::OpeningBrace => op_node(Op::OpenedBrace),
-            Tp::ClosingBrace => op_node(Op::ClosedBrace),
-            Tp::OpeningSquare => op_node(Op::OpenedSquare),
-            Tp::ClosingSquare => op_node(Op::ClosedSquare),
-            Tp::Equals => op_node(Op::Equals),
-            Tp::Termination => op_node(Op::Terminator),
-            Tp::Kleene => op_node(Op::Kleene),
-            Tp::OpeningGroup => op_node(Op::OpenedGroup),
-            Tp::ClosingGroup => op_node(Op::ClosedGroup),
-            Tp::Optional => op_node(Op::Optional),
-            Tp::Repeat => op_node(Op::Repeat),
-            Tp::String(str) => Expr::Literal { span, str },
-            Tp::Identifier(name) => Expr::Nonterminal { span, name },
-            Tp::Regex(pattern) => Expr::Regex { span, pattern },
-            Tp::Newline => unreachable!(),
-        };
+        let node = token_node(t);
+        if empty_alternative_before(&self.kind_pattern, node.node_pattern_code())
+            && let Some(previous) = self.parse_stack.last()
+        {
+            let span = previous.span().end_point();
+            self.push_node(Expr::Empty { span });
+            // The choice needs reducing before whatever ends it is pushed on top
+            self.reduce_until_shift_needed();
+        }
         self.push_node(node);
     }
 
@@ -199,17 +343,111 @@ impl<'a> LrStack<'a> {
         let mut dirty = true;
         while dirty {
             dirty = false;
-            for (r, f) in &*REDUCTION_PATTERNS {
+            for (r, f, _) in reduction_patterns() {
                 if let Some(range) = self.match_rule(r) {
-                    let nodes = self.get(range).unwrap();
-                    let (replacement, consumed) = f(nodes);
-                    for _ in 0..consumed {
-                        self.pop_node();
-                    }
-                    self.push_node(replacement);
+                    // Patterns are anchored to the top of the stack, so this takes exactly the matched nodes
+                    let nodes = self.parse_stack.split_off(range.start);
+                    self.kind_pattern.truncate(range.start);
+                    self.push_node(f(nodes));
+                    dirty = true;
+                }
+            }
+        }
+    }
+}
+
+/// Whether an alternative has been left out between the top of a stack with pattern `kind_pattern` and a node with code `next`, as in `A = B | ;` or `(| B)`, so that an [`Empty`](Expr::Empty) node belongs between them
+fn empty_alternative_before(kind_pattern: &str, next: &str) -> bool {
+    match kind_pattern.chars().last() {
+        Some('|' | '/') => matches!(next, "|" | "/" | ";" | ")" | "]" | "}" | ">"),
+        Some('=' | '(' | '[' | '{' | '<') => matches!(next, "|" | "/"),
+        _ => false,
+    }
+}
+
+/// The leaf node a token is pushed onto the stack as
+fn token_node(t: Token<'_>) -> Expr<'_> {
+    use Operator as Op;
+    use TokenPayload as Tp;
+    let Token { payload, span } = t;
+    let op_node = |op| Expr::UnparsedOperator { op, span };
+    match payload {
+        Tp::Alternation => op_node(Op::Alternation),
+        Tp::OrderedAlternation => op_node(Op::OrderedAlternation),
+        Tp::OpeningBrace => op_node(Op::OpenedBrace),
+        Tp::ClosingBrace => op_node(Op::ClosedBrace),
+        Tp::OpeningSquare => op_node(Op::OpenedSquare),
+        Tp::ClosingSquare => op_node(Op::ClosedSquare),
+        Tp::Equals => op_node(Op::Equals),
+        Tp::Termination => op_node(Op::Terminator),
+        Tp::Kleene => op_node(Op::Kleene),
+        Tp::OpeningGroup => op_node(Op::OpenedGroup),
+        Tp::ClosingGroup => op_node(Op::ClosedGroup),
+        Tp::Optional => op_node(Op::Optional),
+        Tp::Repeat => op_node(Op::Repeat),
+        Tp::Separator => op_node(Op::Separator),
+        Tp::And => op_node(Op::And),
+        Tp::Not => op_node(Op::Not),
+        Tp::OpeningAngle => op_node(Op::OpenedAngle),
+        Tp::ClosingAngle => op_node(Op::ClosedAngle),
+        Tp::Colon => op_node(Op::Label),
+        Tp::Epsilon | Tp::String("") => Expr::Empty { span },
+        Tp::String(str) => Expr::Literal { span, str },
+        Tp::Identifier(name) => Expr::Nonterminal { span, name },
+        Tp::Regex(pattern) => Expr::Regex { span, pattern },
+        Tp::Newline | Tp::Import(_) | Tp::Annotation(_) => unreachable!(),
+    }
+}
+
+/// A version of [`LrStack`] that only tracks the kind of each node, for checking whether input is well-formed without building the tree
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Recognizer {
+    kind_pattern: String,
+    /// Whether the node on top of the stack is a complete rule
+    rule_on_top: bool,
+}
+
+impl Recognizer {
+    pub(crate) fn push_token(&mut self, t: Token<'_>) {
+        let code = token_node(t).node_pattern_code();
+        if empty_alternative_before(&self.kind_pattern, code) {
+            self.kind_pattern.push_str(NodeKind::Empty.pattern_code());
+            self.reduce_until_shift_needed();
+        }
+        self.kind_pattern.push_str(code);
+        self.rule_on_top = false;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.kind_pattern.is_empty()
+    }
+
+    /// Reduces the same way as [`LrStack::reduce_until_shift_needed`]. If that leaves a rule on top of the stack, it is removed and `true` returned.
+    pub(crate) fn reduce_until_shift_needed(&mut self) -> bool {
+        let mut dirty = true;
+        while dirty {
+            dirty = false;
+            for (r, _, kind) in reduction_patterns() {
+                if let Some(range) = r.find(&self.kind_pattern).as_ref().map(Match::range) {
+                    // Matches what `Expr::node_pattern_code` gives the node the parser would build
+                    let code = match &self.kind_pattern[range.clone()] {
+                        "<N>" => HIDDEN_NAME,
+                        "&N" | "!N" => PREDICATE_NAME,
+                        _ => kind.pattern_code(),
+                    };
+                    self.kind_pattern.truncate(range.start);
+                    self.kind_pattern.push_str(code);
+                    self.rule_on_top = *kind == NodeKind::Rule;
                     dirty = true;
                 }
             }
         }
+        if self.rule_on_top {
+            self.kind_pattern.pop();
+            self.rule_on_top = false;
+            true
+        } else {
+            false
+        }
     }
 }
@@ -0,0 +1,703 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Display, Write};
+
+use crate::{
+    Span, StructuredGrammar, StructuredNode, StructuredRule, StructuredSpan,
+    convert::Approximations,
+};
+
+/// The ways [`Grammar::from_pest`](crate::Grammar::from_pest) can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PestError {
+    /// Something other than what was expected was found at `span`, such as a rule without a `{` before its body
+    Unexpected {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What should have been there instead, such as `"an expression"`
+        expected: &'static str,
+    },
+    /// A string or comment starting at `span` is never closed
+    Unterminated {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+    },
+    /// The expression at `span` has no equivalent in EBNF
+    Unsupported {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What it is about the expression, such as `"the stack"`
+        reason: &'static str,
+    },
+}
+
+impl Display for PestError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PestError::Unexpected { span, expected } => write!(f, "Expected {expected} at {span}"),
+            PestError::Unterminated { span } => write!(f, "The text at {span} is never closed"),
+            PestError::Unsupported { span, reason } => {
+                write!(
+                    f,
+                    "The expression at {span} uses {reason}, which can't be imported"
+                )
+            }
+        }
+    }
+}
+
+/// pest's built-in rules that match a single character, and regexes matching the same
+const BUILTIN_CLASSES: &[(&str, &str)] = &[
+    ("ANY", "(?s)."),
+    ("ASCII", r"[\x00-\x7F]"),
+    ("ASCII_DIGIT", "[0-9]"),
+    ("ASCII_NONZERO_DIGIT", "[1-9]"),
+    ("ASCII_BIN_DIGIT", "[01]"),
+    ("ASCII_OCT_DIGIT", "[0-7]"),
+    ("ASCII_HEX_DIGIT", "[0-9a-fA-F]"),
+    ("ASCII_ALPHA_LOWER", "[a-z]"),
+    ("ASCII_ALPHA_UPPER", "[A-Z]"),
+    ("ASCII_ALPHA", "[a-zA-Z]"),
+    ("ASCII_ALPHANUMERIC", "[a-zA-Z0-9]"),
+    ("NEWLINE", "\n|\r\n|\r"),
+    ("LETTER", r"\p{L}"),
+    ("LOWERCASE_LETTER", r"\p{Ll}"),
+    ("UPPERCASE_LETTER", r"\p{Lu}"),
+    ("NUMBER", r"\p{N}"),
+    ("DECIMAL_NUMBER", r"\p{Nd}"),
+    ("PUNCTUATION", r"\p{P}"),
+    ("SYMBOL", r"\p{S}"),
+    ("SEPARATOR", r"\p{Z}"),
+    ("SPACE_SEPARATOR", r"\p{Zs}"),
+    ("ALPHABETIC", r"\p{Alphabetic}"),
+    ("WHITE_SPACE", r"\p{White_Space}"),
+];
+
+/// pest's built-in rules for its stack, which EBNF has nothing like
+const STACK_BUILTINS: &[&str] = &["PUSH", "POP", "POP_ALL", "PEEK", "PEEK_ALL", "DROP"];
+
+pub(crate) fn from_pest(input: &str) -> Result<StructuredGrammar, PestError> {
+    read_pest(input).map(|(grammar, _)| grammar)
+}
+
+/// Reads a grammar as [`from_pest`] does, along with what of the input it couldn't carry over exactly
+pub(crate) fn read_pest(input: &str) -> Result<(StructuredGrammar, Approximations), PestError> {
+    let mut reader = Reader::new(input);
+    let mut rules = Vec::new();
+    loop {
+        let (token, start) = reader.token()?;
+        let name = match token {
+            Token::End => break,
+            Token::Name(name) => name,
+            _ => return Err(reader.unexpected(start, "the name of a rule")),
+        };
+        reader.expect(&Token::Punct("="), "`=` after the name of a rule")?;
+        let (mut token, mut at) = reader.token()?;
+        // `@`, `$` and `!` only change how pest treats whitespace and builds its tree
+        let hidden = token == Token::Name("_");
+        if hidden || matches!(token, Token::Punct("@" | "$" | "!")) {
+            if !hidden {
+                reader.approximations.add(
+                    "a rule modifier",
+                    "left out",
+                    Some(reader.span_from(at)),
+                );
+            }
+            (token, at) = reader.token()?;
+        }
+        if token != Token::Punct("{") {
+            return Err(reader.unexpected(at, "`{` before the body of a rule"));
+        }
+        let body = match reader.choice()? {
+            Node::Sequence(nodes) => nodes,
+            Node::Single(node) => vec![node],
+        };
+        reader.expect(&Token::Punct("}"), "`}` after the body of a rule")?;
+        if matches!(name, "WHITESPACE" | "COMMENT") {
+            reader.approximations.add(
+                "an implicit whitespace or comment rule",
+                "kept as an ordinary rule, without being matched between the parts of other rules",
+                Some(reader.span_from(start)),
+            );
+        }
+        rules.push(StructuredRule {
+            name: name.to_string(),
+            hidden,
+            annotations: Vec::new(),
+            definitions: vec![reader.span_from(start).into()],
+            body,
+        });
+    }
+    let grammar = StructuredGrammar {
+        rules,
+        imports: Vec::new(),
+        ordered_choice: true,
+    };
+    Ok((grammar, reader.approximations))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Name(&'a str),
+    /// The text of a string, with its escapes replaced, and whether it was marked with `^` to match regardless of case
+    String(String, bool),
+    /// A character in single quotes, as used in ranges like `'a'..'z'`
+    Char(char),
+    Number(u32),
+    Punct(&'static str),
+    End,
+}
+
+/// Punctuation, longest first where one starts with another
+const PUNCTUATION: &[&str] = &[
+    "..", "=", "{", "}", "(", ")", "[", "]", "~", "|", "&", "!", "?", "*", "+", "#", "@", "$", ",",
+    "-",
+];
+
+/// A place in the input, as a byte offset and the line and byte offset within the line it's at
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// A parsed expression, keeping sequences apart from other nodes so that they can be spliced into the node around them
+enum Node {
+    Sequence(Vec<StructuredNode>),
+    Single(StructuredNode),
+}
+
+impl Node {
+    fn into_structured(self) -> StructuredNode {
+        match self {
+            Node::Single(node) => node,
+            Node::Sequence(children) => StructuredNode::Group {
+                span: union(&children),
+                children,
+            },
+        }
+    }
+
+    /// The node as the body of another, with a group's parentheses left out
+    fn into_children(self) -> Vec<StructuredNode> {
+        match self {
+            Node::Sequence(children) | Node::Single(StructuredNode::Group { children, .. }) => {
+                children
+            }
+            Node::Single(node) => vec![node],
+        }
+    }
+}
+
+/// Reads a pest grammar from `input`, keeping track of where it's up to for the spans of what it reads
+struct Reader<'a> {
+    input: &'a str,
+    position: Position,
+    approximations: Approximations,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Reader {
+            input,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 0,
+            },
+            approximations: Approximations::default(),
+        }
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        let end = self.position;
+        Span::new(
+            start.offset..end.offset,
+            (start.line, start.column),
+            (end.line, end.column),
+        )
+    }
+
+    fn unexpected(&self, start: Position, expected: &'static str) -> PestError {
+        PestError::Unexpected {
+            span: self.span_from(start),
+            expected,
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        let position = &mut self.position;
+        for c in self.input[position.offset..position.offset + len].chars() {
+            if c == '\n' {
+                position.line += 1;
+                position.column = 0;
+            } else {
+                position.column += c.len_utf8();
+            }
+        }
+        position.offset += len;
+    }
+
+    /// Skips whitespace and comments, including doc comments
+    fn skip_trivia(&mut self) -> Result<(), PestError> {
+        loop {
+            let rest = &self.input[self.position.offset..];
+            self.advance(rest.len() - rest.trim_start().len());
+            let rest = &self.input[self.position.offset..];
+            if rest.starts_with("//") {
+                self.advance(rest.find('\n').unwrap_or(rest.len()));
+            } else if rest.starts_with("/*") {
+                let start = self.position;
+                let Some(len) = rest.find("*/") else {
+                    self.advance(rest.len());
+                    return Err(PestError::Unterminated {
+                        span: self.span_from(start),
+                    });
+                };
+                self.advance(len + 2);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the next token, along with where it starts
+    fn token(&mut self) -> Result<(Token<'a>, Position), PestError> {
+        self.skip_trivia()?;
+        let start = self.position;
+        let rest = &self.input[start.offset..];
+        let Some(first) = rest.chars().next() else {
+            return Ok((Token::End, start));
+        };
+        let token = match first {
+            '"' | '\'' => self.quoted(start, false)?,
+            '^' if rest[1..].starts_with('"') => {
+                self.advance(1);
+                self.quoted(start, true)?
+            }
+            c if c.is_ascii_digit() => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                self.advance(len);
+                let number = rest[..len]
+                    .parse()
+                    .map_err(|_| self.unexpected(start, "a smaller number"))?;
+                Token::Number(number)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                self.advance(len);
+                Token::Name(&rest[..len])
+            }
+            _ => {
+                let Some(punct) = PUNCTUATION.iter().find(|p| rest.starts_with(**p)) else {
+                    self.advance(first.len_utf8());
+                    return Err(self.unexpected(start, "a name, string or operator"));
+                };
+                self.advance(punct.len());
+                Token::Punct(punct)
+            }
+        };
+        Ok((token, start))
+    }
+
+    /// Reads a string or character from its opening quote, replacing its escapes
+    fn quoted(&mut self, start: Position, insensitive: bool) -> Result<Token<'a>, PestError> {
+        let rest = &self.input[self.position.offset..];
+        let quote = rest.chars().next().unwrap_or('"');
+        let mut text = String::new();
+        let mut chars = rest.char_indices().skip(1);
+        let len = loop {
+            let Some((i, c)) = chars.next() else {
+                self.advance(rest.len());
+                return Err(PestError::Unterminated {
+                    span: self.span_from(start),
+                });
+            };
+            match c {
+                c if c == quote => break i + 1,
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => Some('\n'),
+                        Some('r') => Some('\r'),
+                        Some('t') => Some('\t'),
+                        Some('0') => Some('\0'),
+                        Some(c @ ('"' | '\'' | '\\')) => Some(c),
+                        Some('x') => {
+                            let digits: String = chars.by_ref().take(2).map(|(_, c)| c).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                        }
+                        Some('u') => {
+                            let digits: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .take_while(|&c| c != '}')
+                                .filter(|&c| c != '{')
+                                .collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                        }
+                        _ => None,
+                    };
+                    let Some(escaped) = escaped else {
+                        self.advance(rest.len());
+                        return Err(self.unexpected(start, "a valid escape"));
+                    };
+                    text.push(escaped);
+                }
+                c => text.push(c),
+            }
+        };
+        self.advance(len);
+        if quote == '"' {
+            return Ok(Token::String(text, insensitive));
+        }
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Token::Char(c)),
+            _ => Err(self.unexpected(start, "a single character in single quotes")),
+        }
+    }
+
+    /// Reads the next token, failing unless it's `expected`
+    fn expect(&mut self, expected: &Token<'_>, description: &'static str) -> Result<(), PestError> {
+        let (token, start) = self.token()?;
+        if token == *expected {
+            Ok(())
+        } else {
+            Err(self.unexpected(start, description))
+        }
+    }
+
+    /// Reads the next token without moving past it
+    fn peek(&mut self) -> Result<Token<'a>, PestError> {
+        let before = self.position;
+        let token = self.token()?.0;
+        self.position = before;
+        Ok(token)
+    }
+
+    /// Reads alternatives separated by `|`, which pest tries in order
+    fn choice(&mut self) -> Result<Node, PestError> {
+        if self.peek()? == Token::Punct("|") {
+            self.token()?;
+        }
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek()? == Token::Punct("|") {
+            self.token()?;
+            alternatives.push(self.sequence()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        let children: Vec<_> = alternatives
+            .into_iter()
+            .map(Node::into_structured)
+            .collect();
+        Ok(Node::Single(StructuredNode::Choice {
+            span: union(&children),
+            children,
+            ordered: true,
+        }))
+    }
+
+    /// Reads terms separated by `~`
+    fn sequence(&mut self) -> Result<Node, PestError> {
+        let mut terms = vec![self.term()?];
+        while self.peek()? == Token::Punct("~") {
+            self.token()?;
+            terms.push(self.term()?);
+        }
+        if terms.len() == 1 {
+            return Ok(Node::Single(terms.pop().unwrap()));
+        }
+        Ok(Node::Sequence(terms))
+    }
+
+    /// Reads a primary expression with any prefix and postfix operators, or a tagged one
+    fn term(&mut self) -> Result<StructuredNode, PestError> {
+        let (token, start) = self.token()?;
+        match token {
+            Token::Punct("#") => {
+                let (token, at) = self.token()?;
+                let Token::Name(label) = token else {
+                    return Err(self.unexpected(at, "the name of a tag"));
+                };
+                self.expect(&Token::Punct("="), "`=` after the name of a tag")?;
+                let body = self.term()?;
+                Ok(StructuredNode::Labeled {
+                    span: self.span_from(start).into(),
+                    label: label.to_string(),
+                    children: Node::Single(body).into_children(),
+                })
+            }
+            Token::Punct(prefix @ ("&" | "!")) => {
+                let children = Node::Single(self.term()?).into_children();
+                let span = self.span_from(start).into();
+                Ok(if prefix == "&" {
+                    StructuredNode::And { span, children }
+                } else {
+                    StructuredNode::Not { span, children }
+                })
+            }
+            token => {
+                let mut node = self.primary(token, start)?;
+                loop {
+                    let before = self.position;
+                    let (token, _) = self.token()?;
+                    node = match token {
+                        Token::Punct("?") => StructuredNode::Optional {
+                            span: self.span_from(start).into(),
+                            children: Node::Single(node).into_children(),
+                        },
+                        Token::Punct(op @ ("*" | "+")) => StructuredNode::Repetition {
+                            span: self.span_from(start).into(),
+                            one_needed: op == "+",
+                            separated: false,
+                            children: Node::Single(node).into_children(),
+                        },
+                        Token::Punct("{") => self.bounded(node, start)?,
+                        _ => {
+                            self.position = before;
+                            return Ok(node);
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    /// Reads the bounds of a repetition like `{2, 4}` after its `{`, writing it as copies of `node`, with the optional ones last
+    fn bounded(
+        &mut self,
+        node: StructuredNode,
+        start: Position,
+    ) -> Result<StructuredNode, PestError> {
+        let mut min = None;
+        let mut max = None;
+        let mut comma = false;
+        loop {
+            let (token, at) = self.token()?;
+            match token {
+                Token::Number(n) if comma => max = Some(n),
+                Token::Number(n) => min = Some(n),
+                Token::Punct(",") if !comma => comma = true,
+                Token::Punct("}") => break,
+                _ => return Err(self.unexpected(at, "the bounds of a repetition")),
+            }
+        }
+        let span: StructuredSpan = self.span_from(start).into();
+        let min = min.unwrap_or(0);
+        let mut children = Vec::new();
+        for _ in 0..min {
+            children.push(node.clone());
+        }
+        match (comma, max) {
+            (false, _) => {}
+            (true, None) => children.push(StructuredNode::Repetition {
+                span,
+                one_needed: false,
+                separated: false,
+                children: Node::Single(node).into_children(),
+            }),
+            (true, Some(max)) => {
+                for _ in min..max {
+                    children.push(StructuredNode::Optional {
+                        span,
+                        children: Node::Single(node.clone()).into_children(),
+                    });
+                }
+            }
+        }
+        Ok(match children.len() {
+            0 => StructuredNode::Empty { span },
+            1 => children.pop().unwrap(),
+            _ => StructuredNode::Group { span, children },
+        })
+    }
+
+    fn primary(&mut self, token: Token<'a>, start: Position) -> Result<StructuredNode, PestError> {
+        let node = match token {
+            Token::Punct("(") => {
+                let node = self.choice()?;
+                self.expect(&Token::Punct(")"), "`)` to close the group")?;
+                match node {
+                    Node::Single(node) => node,
+                    Node::Sequence(children) => StructuredNode::Group {
+                        span: self.span_from(start).into(),
+                        children,
+                    },
+                }
+            }
+            Token::String(text, false) => StructuredNode::Literal {
+                span: self.span_from(start).into(),
+                text,
+            },
+            Token::String(text, true) => StructuredNode::Regex {
+                span: self.span_from(start).into(),
+                pattern: format!("(?i){}", regex_syntax::escape(&text)),
+            },
+            Token::Char(low) => {
+                if self.peek()? != Token::Punct("..") {
+                    return Ok(StructuredNode::Literal {
+                        span: self.span_from(start).into(),
+                        text: low.to_string(),
+                    });
+                }
+                self.token()?;
+                let (token, at) = self.token()?;
+                let Token::Char(high) = token else {
+                    return Err(self.unexpected(at, "the end of a character range"));
+                };
+                let mut pattern = String::from("[");
+                for c in [low, high] {
+                    if c.is_alphanumeric() {
+                        pattern.push(c);
+                    } else {
+                        write!(pattern, r"\x{{{:X}}}", u32::from(c)).unwrap();
+                    }
+                    pattern.push('-');
+                }
+                pattern.pop();
+                pattern.push(']');
+                StructuredNode::Regex {
+                    span: self.span_from(start).into(),
+                    pattern,
+                }
+            }
+            Token::Name(name) => {
+                let span: StructuredSpan = self.span_from(start).into();
+                if let Some(&(_, pattern)) = BUILTIN_CLASSES.iter().find(|(n, _)| *n == name) {
+                    StructuredNode::Regex {
+                        span,
+                        pattern: pattern.to_string(),
+                    }
+                } else if name == "EOI" {
+                    StructuredNode::Not {
+                        span,
+                        children: vec![StructuredNode::Regex {
+                            span,
+                            pattern: "(?s).".to_string(),
+                        }],
+                    }
+                } else if name == "SOI" {
+                    // Parsing always starts at the start of the input, which is the only place this is used
+                    self.approximations.add(
+                        "`SOI`",
+                        "left out, as if it were only used at the start of the input",
+                        Some(span.into()),
+                    );
+                    StructuredNode::Empty { span }
+                } else if STACK_BUILTINS.contains(&name) {
+                    return Err(PestError::Unsupported {
+                        span: span.into(),
+                        reason: "the stack",
+                    });
+                } else {
+                    StructuredNode::Nonterminal {
+                        span,
+                        name: name.to_string(),
+                    }
+                }
+            }
+            _ => return Err(self.unexpected(start, "an expression")),
+        };
+        Ok(node)
+    }
+}
+
+/// The span from the start of the first node to the end of the last
+fn union(nodes: &[StructuredNode]) -> StructuredSpan {
+    let span = |node: &StructuredNode| -> Span {
+        match node {
+            StructuredNode::Literal { span, .. }
+            | StructuredNode::Nonterminal { span, .. }
+            | StructuredNode::Regex { span, .. }
+            | StructuredNode::Empty { span }
+            | StructuredNode::Choice { span, .. }
+            | StructuredNode::Optional { span, .. }
+            | StructuredNode::Repetition { span, .. }
+            | StructuredNode::Group { span, .. }
+            | StructuredNode::And { span, .. }
+            | StructuredNode::Not { span, .. }
+            | StructuredNode::Hidden { span, .. }
+            | StructuredNode::Labeled { span, .. } => (*span).into(),
+        }
+    };
+    match (nodes.first(), nodes.last()) {
+        (Some(first), Some(last)) => {
+            let (first, last) = (span(first), span(last));
+            Span::new(
+                first.start()..last.end(),
+                first.start_line(),
+                last.end_line(),
+            )
+            .into()
+        }
+        _ => Span::default().into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ChoiceSemantics, Grammar, PestError};
+
+    #[test]
+    fn from_pest() {
+        let structured = Grammar::from_pest(
+            r#"
+            //! A grammar of strings
+            WHITESPACE = _{ " " | "\t" }
+            /// A quoted string
+            string = ${ "\"" ~ #inner = inner ~ "\"" }
+            inner = @{ (!("\"" | "/") ~ ANY | "/" ~ ("/" | "\u{22}"))* }
+            keyword = { ^"select" ~ &(' '..'~'){2,} ~ 'x'{,1} }
+            "#,
+        )
+        .unwrap();
+        let g = Grammar::from_structured(&structured);
+        assert_eq!(g.choice_semantics(), ChoiceSemantics::Ordered);
+        let ebnf = Grammar::new(
+            r#"
+            <WHITESPACE> = ' ' / '	';
+            string = '"' inner:inner '"';
+            inner = ((!('"' / '/') #'(?s).') / ('/' ('/' / '"')))*;
+            keyword = #'(?i)select' &(#'[\x{20}-\x{7E}]' #'[\x{20}-\x{7E}]' #'[\x{20}-\x{7E}]'*) ['x'];
+            "#,
+        )
+        .unwrap();
+        assert!(g.eq_ignoring_spans(&ebnf));
+        assert_eq!(g["inner"].to_string(), ebnf["inner"].to_string());
+
+        assert!(matches!(
+            Grammar::from_pest("a = { PUSH(\"x\") ~ POP }"),
+            Err(PestError::Unsupported {
+                reason: "the stack",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Grammar::from_pest("a = \"x\" }"),
+            Err(PestError::Unexpected {
+                expected: "`{` before the body of a rule",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Grammar::from_pest("a = { \"x }"),
+            Err(PestError::Unterminated { .. })
+        ));
+    }
+}
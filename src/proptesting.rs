@@ -1,98 +1,16 @@
 #![cfg(test)]
 use crate::Expr;
+use crate::Grammar;
 use crate::Rule;
+use crate::arbitrary::{NAMES, expr_strategy, grammar_source};
 use crate::simplification::simplify_node;
 use crate::token_data::DUMMY_SPAN;
 use display_tree::AsTree;
 use proptest::prelude::*;
-use proptest::prop_oneof;
 use strum::EnumProperty;
 
-const NUM_NAMES: usize = 128;
-
-const NAMES: [&str; NUM_NAMES] = const {
-    const NUM_LENGTH: usize = 4;
-    const DIGITS: [u8; 10] = [b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9'];
-    const PREFIX: &str = "string";
-
-    const BYTE_BLOCKS: [[u8; PREFIX.len() + NUM_LENGTH]; 128] = {
-        let mut orig = [[0; PREFIX.len() + NUM_LENGTH]; 128];
-        let mut name_idx = 0;
-        while name_idx < orig.len() {
-            let leading = orig[name_idx]
-                .first_chunk_mut::<{ PREFIX.len() }>()
-                .unwrap();
-            leading.copy_from_slice(PREFIX.as_bytes());
-            let mut digit = NUM_LENGTH;
-            while digit > 0 {
-                let shift = name_idx / (10usize.pow((digit - 1) as u32));
-
-                orig[name_idx][PREFIX.len() + NUM_LENGTH - digit] = DIGITS[shift % DIGITS.len()];
-
-                digit -= 1;
-            }
-            name_idx += 1;
-        }
-        orig
-    };
-
-    let mut output = [""; NUM_NAMES];
-    let mut a = 0;
-    while a < output.len() {
-        output[a] = match str::from_utf8(&BYTE_BLOCKS[a]) {
-            Ok(s) => s,
-            Err(_) => unreachable!(),
-        };
-        a += 1;
-    }
-
-    output
-};
-
 fn node_strategy() -> impl Strategy<Value = Expr<'static>> {
-    let leaf = (0..NAMES.len() * 3).prop_map(|n| {
-        let typ = n % 3;
-        let n = n / 3;
-        match typ {
-            0 => Expr::Nonterminal {
-                span: DUMMY_SPAN,
-                name: NAMES[n],
-            },
-            1 => Expr::Literal {
-                span: DUMMY_SPAN,
-                str: NAMES[n],
-            },
-            2 => Expr::Regex {
-                span: DUMMY_SPAN,
-                pattern: NAMES[n],
-            },
-            _ => unreachable!(),
-        }
-    });
-
-    leaf.prop_recursive(2, 10, 2, |inner| {
-        prop_oneof![
-            prop::collection::vec(inner.clone(), 2).prop_map(|body| Expr::Choice {
-                span: DUMMY_SPAN,
-                body
-            }),
-            prop::collection::vec(inner.clone(), 2).prop_map(|body| Expr::Optional {
-                span: DUMMY_SPAN,
-                body
-            }),
-            (prop::collection::vec(inner.clone(), 2), any::<bool>()).prop_map(
-                |(body, one_needed)| Expr::Repetition {
-                    span: DUMMY_SPAN,
-                    body,
-                    one_needed,
-                }
-            ),
-            prop::collection::vec(inner.clone(), 2).prop_map(|body| Expr::Group {
-                span: DUMMY_SPAN,
-                body
-            }),
-        ]
-    })
+    expr_strategy(DUMMY_SPAN, &NAMES)
 }
 
 #[test]
@@ -107,7 +25,7 @@ fn test_names() {
 fn expr_parse_rule() {
     let src = "foo = bar;";
     let e = Expr::new(src).unwrap();
-    assert!(matches!(e, Expr::Rule { .. }), "{} not a Rule", e);
+    assert!(matches!(e, Expr::Rule { .. }), "{e} not a Rule");
 }
 
 proptest! {
@@ -129,7 +47,7 @@ proptest! {
             eprintln!();
             eprintln!("Trees\nGot:\n{actual_tree}\nExpected:\n{n_tree}");
             assert_eq!(actual, n);
-        };
+        }
     }
 
     #[test]
@@ -137,7 +55,7 @@ proptest! {
         let string = format!("{n}");
         let mut parsed = Expr::new(&string).unwrap();
         parsed.apply_replacement(&mut |node| {
-            match node {
+            match &node {
                 Expr::Literal { span, str } => {
                 assert_eq!(
                     &string[span.range()],
@@ -146,21 +64,27 @@ proptest! {
                 );
                 }
                 Expr::Nonterminal { span, name } => {
-                    assert_eq!(&string[span.range()], &format!("{name}"), "Range = {:?}", span.range());
+                    assert_eq!(&string[span.range()], *name, "Range = {:?}", span.range());
                 }
                 Expr::UnparsedOperator { span, op } => {
                     assert_eq!(
                         &string[span.range()],
-                        &format!("{}", op.get_str("repr").unwrap()),
+                        op.get_str("repr").unwrap(),
                         "Range = {:?}", span.range()
                     );
                 }
                 _ => {}
             }
-            None
+            node
         });
     }
 
+    #[test]
+    fn check_syntax_accepts(n in node_strategy()) {
+        let rule = format!("rule = {n};");
+        prop_assert_eq!(Grammar::check_syntax(&rule), Ok(()));
+    }
+
     #[test]
     fn display_roundtrip(mut original in node_strategy()) {
         simplify_node(&mut original);
@@ -175,6 +99,12 @@ proptest! {
             eprintln!();
             eprintln!("Trees\nGot:\n{actual_tree}\nExpected:\n{n_tree}");
             assert_eq!(actual, original);
-        };
+        }
+    }
+
+    #[test]
+    fn generated_grammars_parse(source in grammar_source()) {
+        let grammar = Grammar::new(&source).unwrap_or_else(|e| panic!("{e}"));
+        prop_assert_eq!(grammar.first_dangling_reference(), None);
     }
 }
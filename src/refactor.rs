@@ -0,0 +1,204 @@
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::slice;
+
+use crate::{Expr, Grammar, Rule, Span, simplification::simplify_node, token_data::SpanSet};
+
+pub(crate) fn inline_rule(grammar: &mut Grammar<'_>, name: &str) -> Option<Vec<(Span, String)>> {
+    if grammar.rules.get(name)?.is_recursive() {
+        return None;
+    }
+    let rule = grammar.rules.remove(name).unwrap();
+    let replacement = match &rule.body[..] {
+        [node] => node.clone(),
+        _ => rule.body_expr(),
+    };
+    let text = replacement.to_string();
+
+    let mut edits: Vec<_> = rule
+        .definitions
+        .iter()
+        .map(|span| (span, String::new()))
+        .collect();
+    for other in grammar.rules.values_mut() {
+        let inlined = edits.len();
+        let mut stack: Vec<_> = other.body.iter_mut().collect();
+        while let Some(node) = stack.pop() {
+            match node {
+                &mut Expr::Nonterminal { span, name: used } if used == rule.name => {
+                    edits.push((span, text.clone()));
+                    *node = replacement.clone();
+                }
+                other => stack.extend(other.children_mut()),
+            }
+        }
+        // Flattens the inlined groups into the sequences around them, as parsing the edited source would
+        if edits.len() > inlined {
+            other.body.iter_mut().for_each(simplify_node);
+        }
+    }
+    edits.sort_by_key(|(span, _)| span.start());
+    Some(edits)
+}
+
+pub(crate) fn factor_out<'a>(
+    grammar: &mut Grammar<'a>,
+    expr: &Expr<'a>,
+    name: &'a str,
+) -> Option<Vec<(Span, String)>> {
+    // A group stands for its contents, which are looked for as a run of nodes within a sequence
+    let pattern = match expr {
+        Expr::Group { body, .. } => &body[..],
+        other => slice::from_ref(other),
+    };
+    if pattern.is_empty() || grammar.rules.contains_key(name) {
+        return None;
+    }
+
+    let mut edits = vec![];
+    // The definitions of the earliest rule anything was factored out of, which the new rule is written after
+    let mut anchor: Option<Span> = None;
+    let mut changed = false;
+    for rule in grammar.rules.values_mut() {
+        let mut replaced = vec![];
+        let mut exact = true;
+        let mut stack = vec![&mut rule.body];
+        while let Some(body) = stack.pop() {
+            let mut i = 0;
+            while i + pattern.len() <= body.len() {
+                let run = &body[i..i + pattern.len()];
+                if run.iter().zip(pattern).all(|(a, b)| a.eq_ignoring_spans(b)) {
+                    exact &= covers_source(&run[0]) && covers_source(&run[run.len() - 1]);
+                    let span = Span::union(run.iter());
+                    replaced.push(span);
+                    body.splice(i..i + pattern.len(), [Expr::Nonterminal { span, name }]);
+                }
+                i += 1;
+            }
+            stack.extend(
+                body.iter_mut()
+                    .filter_map(|node| Some(node.span_and_body_mut()?.1)),
+            );
+        }
+        if replaced.is_empty() {
+            continue;
+        }
+        changed = true;
+        rule.body.iter_mut().for_each(simplify_node);
+
+        if exact && rule.definitions.len() == 1 {
+            edits.extend(replaced.into_iter().map(|span| (span, name.to_string())));
+        } else {
+            // The spans of the nodes replaced don't cover all of their text, so the whole rule is written out again
+            let mut definitions = rule.definitions.iter();
+            edits.extend(definitions.next().map(|span| (span, rule.to_string())));
+            edits.extend(definitions.map(|span| (span, String::new())));
+        }
+        if let Some(hull) = rule.definitions.hull()
+            && anchor.is_none_or(|earliest| hull.start() < earliest.start())
+        {
+            anchor = Some(hull);
+        }
+    }
+    if !changed {
+        return None;
+    }
+
+    let rule = Rule {
+        name: Cow::Borrowed(name),
+        body: pattern.to_vec(),
+        definitions: SpanSet::default(),
+        hidden: false,
+        annotations: vec![],
+    };
+    if let Some(hull) = anchor {
+        edits.push((hull.end_point(), alloc::format!("\n{rule}")));
+    }
+    grammar.rules.insert(rule.name.clone(), rule);
+    edits.sort_by_key(|(span, _)| span.start());
+    Some(edits)
+}
+
+/// Whether the span of `node` covers all of its text, so that replacing the text of that span replaces the node. Nodes like `x*` leave the operators and brackets that belong to them out of their spans, apart from those of groups and choices, which only group their contents.
+fn covers_source(node: &Expr<'_>) -> bool {
+    match node {
+        Expr::Literal { .. } | Expr::Nonterminal { .. } | Expr::Regex { .. } => true,
+        Expr::Choice { body, .. } | Expr::Group { body, .. } => {
+            body.first().is_some_and(covers_source) && body.last().is_some_and(covers_source)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use crate::{Expr, Grammar, Rule, Span};
+
+    fn apply(src: &str, edits: &[(Span, String)]) -> String {
+        let mut patched = String::from(src);
+        for (span, text) in edits.iter().rev() {
+            patched.replace_range(span.range(), text);
+        }
+        patched
+    }
+
+    #[test]
+    fn inline_rule() {
+        let src = "list = item (SEP item)*; // items\nSEP = ',' ' '*;\nitem = #'[a-z]+';\nnested = '(' list ')';";
+        let mut g = Grammar::new(src).unwrap();
+        assert!(g.inline_rule("missing").is_none());
+        let mut recursive = Grammar::new("A = 'a' A?;").unwrap();
+        assert!(recursive.inline_rule("A").is_none());
+
+        let edits = g.inline_rule("SEP").unwrap();
+        assert_eq!(edits.len(), 2);
+        let patched = apply(src, &edits);
+        assert_eq!(
+            patched,
+            "list = item (((\",\") ((((\" \")))*)) item)*; // items\n\nitem = #'[a-z]+';\nnested = '(' list ')';"
+        );
+        assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+
+        // Spans refer to the source the grammar was parsed from, so further changes start from the patched source
+        let mut g = Grammar::new(&patched).unwrap();
+        let edits = g.inline_rule("list").unwrap();
+        let patched = apply(&patched, &edits);
+        assert!(g.get("list").is_none());
+        assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+    }
+
+    #[test]
+    fn factor_out() {
+        let src = "// first\na = x y z | w;\nb = [x y] 'q';\nc = x y*;\nd = x y; d = 'd';";
+        let mut g = Grammar::new(src).unwrap();
+        let pattern = Rule::new("p = x y;").unwrap().body_expr();
+        assert!(g.clone().factor_out(&pattern, "a").is_none());
+        assert!(
+            g.clone()
+                .factor_out(&Expr::new("v").unwrap(), "e")
+                .is_none()
+        );
+
+        let edits = g.factor_out(&pattern, "xy").unwrap();
+        let patched = apply(src, &edits);
+        assert_eq!(
+            patched,
+            "// first\na = xy z | w;\nxy = x y;\nb = [xy] 'q';\nc = x y*;\nd = ((xy)|(\"d\")); "
+        );
+        assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+
+        let edits = g.factor_out(&Expr::new("y*").unwrap(), "ys").unwrap();
+        assert_eq!(g["c"].nonterminals(), ["x", "ys"]);
+        // The `*` isn't part of the span of `y*`, so the rule is written out again
+        assert_eq!(
+            apply(src, &edits),
+            "// first\na = x y z | w;\nb = [x y] 'q';\nc = x ys;\nys = (((y)))*;\nd = x y; d = 'd';"
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    vec,
+    vec::Vec,
+};
+
+use crate::{Grammar, Rule, Span, ValidationError, suggest::closest_name};
+
+/// A set of rules that refer to each other in a loop, directly or through each other, from [`Grammar::recursion_cycles`]. This is a strongly connected component of the graph of references between rules, so every rule in it can reach every other one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RecursionCycle<'a> {
+    /// The names of the rules in the cycle, in order of name
+    pub rules: Vec<&'a str>,
+    /// Each reference from a rule in the cycle to one in the cycle, as the name of the rule it appears in, the name it refers to, and where it appears, ordered by where they appear
+    pub references: Vec<(&'a str, &'a str, Span)>,
+}
+
+pub(crate) fn slice<'a>(grammar: &Grammar<'a>, starts: &[&str]) -> Grammar<'a> {
+    let mut reached = BTreeSet::new();
+    let mut stack = starts.to_vec();
+    while let Some(name) = stack.pop() {
+        if let Some(rule) = grammar.rules.get(name)
+            && reached.insert(name)
+        {
+            stack.extend(rule.nonterminals());
+        }
+    }
+    Grammar {
+        rules: grammar
+            .rules
+            .iter()
+            .filter(|&(name, _)| reached.contains(&&**name))
+            .map(|(name, rule)| (name.clone(), rule.clone()))
+            .collect(),
+        imports: grammar.imports.clone(),
+        choice_semantics: grammar.choice_semantics,
+        name_order: grammar.name_order,
+    }
+}
+
+pub(crate) fn extract<'a>(
+    grammar: &Grammar<'a>,
+    names: &[&str],
+) -> (Grammar<'a>, Vec<ValidationError<'a>>) {
+    let extracted = slice(grammar, names);
+    let mut dangling = vec![];
+    for rule in extracted.rules.values() {
+        for (name, span) in rule.nonterminal_references() {
+            if !extracted.rules.contains_key(name) {
+                dangling.push(ValidationError::DanglingReference {
+                    rule: rule.name.clone(),
+                    name,
+                    span,
+                    suggestion: closest_name(name, grammar.rules.keys().map(|k| &**k))
+                        .map(|suggestion| grammar.rules[suggestion].name.clone()),
+                });
+            }
+        }
+    }
+    (extracted, dangling)
+}
+
+/// The rules each defined rule refers to, without repeats, all numbered as by [`RuleIds`](crate::RuleIds)
+fn edges(grammar: &Grammar<'_>) -> Vec<Vec<usize>> {
+    let names: Vec<_> = grammar.rules.keys().map(|name| &**name).collect();
+    grammar
+        .rules
+        .values()
+        .map(|rule| {
+            let mut ids: Vec<_> = rule
+                .nonterminals()
+                .into_iter()
+                .filter_map(|name| names.binary_search(&name).ok())
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            ids
+        })
+        .collect()
+}
+
+/// The strongly connected components of the graph of references between defined rules, given by [`edges`], as lists of rules numbered as by [`RuleIds`](crate::RuleIds) in increasing order. Each component comes after every component it refers to.
+fn components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    // Tarjan's algorithm, with an explicit stack so that long chains of rules can't overflow the real one
+    let mut index = vec![None; edges.len()];
+    let mut low = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut next = 0;
+    for root in 0..edges.len() {
+        if index[root].is_some() {
+            continue;
+        }
+        let mut work = vec![(root, 0)];
+        index[root] = Some(next);
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some((rule, edge)) = work.last_mut() {
+            let rule = *rule;
+            if let Some(&target) = edges[rule].get(*edge) {
+                *edge += 1;
+                match index[target] {
+                    None => {
+                        index[target] = Some(next);
+                        low[target] = next;
+                        next += 1;
+                        stack.push(target);
+                        on_stack[target] = true;
+                        work.push((target, 0));
+                    }
+                    Some(target_index) if on_stack[target] => {
+                        low[rule] = low[rule].min(target_index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[rule]);
+            }
+            if Some(low[rule]) == index[rule] {
+                let mut component = vec![];
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == rule {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+pub(crate) fn recursion_cycles<'a>(grammar: &'a Grammar<'_>) -> Vec<RecursionCycle<'a>> {
+    let rules: Vec<_> = grammar.rules.values().collect();
+    let mut cycles: Vec<_> = components(&edges(grammar))
+        .into_iter()
+        .filter_map(|component| {
+            let names: Vec<&str> = component.iter().map(|&id| &*rules[id].name).collect();
+            let mut references: Vec<_> = component
+                .iter()
+                .flat_map(|&id| {
+                    let from = &*rules[id].name;
+                    rules[id]
+                        .nonterminal_references()
+                        .into_iter()
+                        .map(move |(to, span)| (from, to, span))
+                })
+                .filter(|(_, to, _)| names.binary_search(to).is_ok())
+                .collect();
+            // A single rule is only a cycle if it refers to itself
+            if references.is_empty() {
+                return None;
+            }
+            references.sort_unstable_by_key(|&(_, _, span)| span);
+            Some(RecursionCycle {
+                rules: names,
+                references,
+            })
+        })
+        .collect();
+    cycles.sort_unstable_by(|a, b| a.rules.cmp(&b.rules));
+    cycles
+}
+
+pub(crate) fn derives<'a>(grammar: &'a Grammar<'_>, from: &str, to: &str) -> Option<Vec<&'a str>> {
+    let (from, _) = grammar.rules.get_key_value(from)?;
+    if from == to {
+        return Some(vec![from]);
+    }
+
+    // A breadth-first search, so the chain found is one of the shortest
+    let mut parents = BTreeMap::from([(&**from, None)]);
+    let mut queue = VecDeque::from([&**from]);
+    while let Some(name) = queue.pop_front() {
+        let Some(rule) = grammar.rules.get(name) else {
+            continue;
+        };
+        for reference in rule.nonterminals() {
+            if parents.contains_key(reference) {
+                continue;
+            }
+            parents.insert(reference, Some(name));
+            if reference == to {
+                let mut chain = vec![reference];
+                let mut current = name;
+                chain.push(current);
+                while let Some(parent) = parents[current] {
+                    chain.push(parent);
+                    current = parent;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            queue.push_back(reference);
+        }
+    }
+    None
+}
+
+pub(crate) fn topological_order<'g, 'a>(
+    grammar: &'g Grammar<'a>,
+) -> (Vec<&'g Rule<'a>>, Vec<RecursionCycle<'g>>) {
+    let rules: Vec<_> = grammar.rules.values().collect();
+    let mut rank = vec![0; rules.len()];
+    for (position, rule) in grammar.rules_in_definition_order().into_iter().enumerate() {
+        let id = rules
+            .binary_search_by_key(&&rule.name, |r| &r.name)
+            .unwrap();
+        rank[id] = position;
+    }
+
+    let edges = edges(grammar);
+    let mut components = components(&edges);
+    let mut component_of = vec![0; rules.len()];
+    for (index, component) in components.iter_mut().enumerate() {
+        component.sort_unstable_by_key(|&id| rank[id]);
+        for &id in &*component {
+            component_of[id] = index;
+        }
+    }
+    let mut waiting_on = vec![0; components.len()];
+    let mut dependents = vec![vec![]; components.len()];
+    for (index, component) in components.iter().enumerate() {
+        let mut referred: Vec<_> = component
+            .iter()
+            .flat_map(|&id| &edges[id])
+            .map(|&id| component_of[id])
+            .filter(|&other| other != index)
+            .collect();
+        referred.sort_unstable();
+        referred.dedup();
+        waiting_on[index] = referred.len();
+        for other in referred {
+            dependents[other].push(index);
+        }
+    }
+
+    // Of the components whose references have all been placed, the one defined earliest goes next, so an input already in order is left as it is
+    let mut ready: BTreeSet<_> = (0..components.len())
+        .filter(|&index| waiting_on[index] == 0)
+        .map(|index| (rank[components[index][0]], index))
+        .collect();
+    let mut order = Vec::with_capacity(rules.len());
+    while let Some((_, index)) = ready.pop_first() {
+        order.extend(components[index].iter().map(|&id| rules[id]));
+        for &dependent in &dependents[index] {
+            waiting_on[dependent] -= 1;
+            if waiting_on[dependent] == 0 {
+                ready.insert((rank[components[dependent][0]], dependent));
+            }
+        }
+    }
+    (order, recursion_cycles(grammar))
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{string::ToString, vec::Vec};
+
+    use crate::{Grammar, ValidationError};
+
+    #[test]
+    fn derives() {
+        let g = Grammar::new(
+            "file = item*; item = decl | expr; decl = 'let' name '=' expr; expr = term ('+' term)*; term = name | '(' expr ')'; name = #'[a-z]+'; spare = 'x';",
+        )
+        .unwrap();
+        assert_eq!(
+            g.derives("file", "name"),
+            Some(vec!["file", "item", "decl", "name"])
+        );
+        assert_eq!(g.derives("term", "term"), Some(vec!["term"]));
+        assert_eq!(g.derives("expr", "expr"), Some(vec!["expr"]));
+        assert_eq!(g.derives("name", "expr"), None);
+        assert_eq!(g.derives("file", "spare"), None);
+        assert_eq!(g.derives("missing", "name"), None);
+
+        // References to undefined rules can still be reached
+        let g = Grammar::new("a = b; b = c;").unwrap();
+        assert_eq!(g.derives("a", "c"), Some(vec!["a", "b", "c"]));
+    }
+
+    #[test]
+    fn slice() {
+        let g = Grammar::new(
+            "file = stmt*; stmt = expr ';' | 'let' name '=' expr ';'; expr = term ('+' term)*; term = name | num | '(' expr ')'; name = #'[a-z]+'; num = #'[0-9]+'; comment = '#' #'.*';",
+        )
+        .unwrap();
+        let names = |g: &Grammar<'_>| g.rules().map(|r| r.name.to_string()).collect::<Vec<_>>();
+        assert_eq!(names(&g.slice("expr")), ["expr", "name", "num", "term"]);
+        assert_eq!(names(&g.slice("name")), ["name"]);
+        assert_eq!(g.slice("file").rules().count(), 6);
+        assert_eq!(g.slice("missing").rules().count(), 0);
+        assert_eq!(g.slice("expr")["term"], g["term"]);
+    }
+
+    #[test]
+    fn extract() {
+        let src = "program = decl*; decl = fn | struct; fn = 'fn' name '(' args? ')' block; args = name (',' name)*; \
+                   struct = 'struct' name '{' fields '}'; fields = name ':' typ; type = name; name = #'[a-z]+';";
+        let g = Grammar::new(src).unwrap();
+        let (extracted, dangling) = g.extract(&["struct", "args", "missing"]);
+        let names: Vec<_> = extracted.rules().map(|r| &*r.name).collect();
+        assert_eq!(names, ["args", "fields", "name", "struct"]);
+
+        let [
+            ValidationError::DanglingReference {
+                rule,
+                name,
+                span,
+                suggestion,
+            },
+        ] = &dangling[..]
+        else {
+            panic!("{dangling:?}")
+        };
+        assert_eq!(
+            (&**rule, *name, &src[span.range()]),
+            ("fields", "typ", "typ")
+        );
+        assert_eq!(suggestion.as_deref(), Some("type"));
+        // `block` is left out along with `fn`, so only references from extracted rules are reported
+        assert!(
+            g.extract(&["decl"])
+                .1
+                .iter()
+                .any(|e| matches!(e, ValidationError::DanglingReference { name: "block", .. }))
+        );
+    }
+
+    #[test]
+    fn recursion_cycles() {
+        let src = "expr = term ('+' term)*; term = atom+; atom = num | '(' expr ')'; num = #'[0-9]+'; list = '[' items? ']'; items = list (',' list)* | list; digits = digits? #'[0-9]';";
+        let g = Grammar::new(src).unwrap();
+        let cycles = g.recursion_cycles();
+        let rules: Vec<_> = cycles.iter().map(|c| &c.rules[..]).collect();
+        assert_eq!(
+            rules,
+            [
+                &["atom", "expr", "term"][..],
+                &["digits"],
+                &["items", "list"]
+            ]
+        );
+        let references: Vec<_> = cycles[0]
+            .references
+            .iter()
+            .map(|&(from, to, _)| (from, to))
+            .collect();
+        assert_eq!(
+            references,
+            [
+                ("expr", "term"),
+                ("expr", "term"),
+                ("term", "atom"),
+                ("atom", "expr")
+            ]
+        );
+        assert_eq!(cycles[2].references.len(), 4);
+        for (_, to, span) in cycles.iter().flat_map(|c| &c.references) {
+            assert_eq!(&src[span.range()], *to);
+        }
+    }
+
+    #[test]
+    fn topological_order() {
+        let g = Grammar::new("a = b c; c = 'c'; d = a | e; b = d? 'b'; e = 'e'; f = e;").unwrap();
+        let (order, cycles) = g.topological_order();
+        let names: Vec<_> = order.iter().map(|r| &*r.name).collect();
+        assert_eq!(names, ["c", "e", "a", "d", "b", "f"]);
+        assert_eq!(cycles.len(), 1);
+
+        // Rules already in order stay in the order they were written
+        let g = Grammar::new("z = 'z'; y = z; x = y z; w = 'w';").unwrap();
+        let (order, cycles) = g.topological_order();
+        let names: Vec<_> = order.iter().map(|r| &*r.name).collect();
+        assert_eq!(names, ["z", "y", "x", "w"]);
+        assert!(cycles.is_empty());
+    }
+}
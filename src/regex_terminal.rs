@@ -0,0 +1,235 @@
+use alloc::{string::String, vec::Vec};
+use core::ops::RangeInclusive;
+
+use regex::Regex;
+use regex_syntax::hir::{
+    Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind,
+    literal::{ExtractKind, Extractor},
+};
+
+use crate::{Expr, Grammar, Span};
+
+/// Helpers for looking inside [`Regex`](Expr::Regex) nodes, which otherwise only carry their pattern as text. Each returns `None` if called on any other kind of node, or if the pattern isn't a valid regex.
+impl<'a> Expr<'a> {
+    /// Compiles the pattern of a [`Regex`](Expr::Regex) node, exactly as written. The result isn't anchored, so matching it at the start of some input needs [`Regex::find`] checking the match starts at 0, or the pattern wrapping in `^(?:...)` first.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let regex = Expr::new("#'[0-9]+'").unwrap().compile_regex().unwrap().unwrap();
+    /// assert!(regex.is_match("a1"));
+    /// assert!(Expr::new("'x'").unwrap().compile_regex().is_none());
+    /// ```
+    pub fn compile_regex(&self) -> Option<Result<Regex, regex::Error>> {
+        match self {
+            Expr::Regex { pattern, .. } => Some(Regex::new(pattern)),
+            _ => None,
+        }
+    }
+
+    /// The longest text every match of a [`Regex`](Expr::Regex) node begins with, which is empty if matches can begin in more than one way. This is found by [`regex_syntax`]'s literal extraction, so it can be shorter than the true prefix of a complicated pattern, but is never longer.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// assert_eq!(Expr::new("#'0x[0-9a-f]+'").unwrap().regex_prefix().as_deref(), Some("0x"));
+    /// assert_eq!(Expr::new("#'0[xX][0-9a-f]+'").unwrap().regex_prefix().as_deref(), Some("0"));
+    /// assert_eq!(Expr::new("#'[0-9]+'").unwrap().regex_prefix().as_deref(), Some(""));
+    /// ```
+    pub fn regex_prefix(&self) -> Option<String> {
+        let hir = self.regex_hir()?;
+        let prefixes = Extractor::new().kind(ExtractKind::Prefix).extract(&hir);
+        let common = prefixes.longest_common_prefix().unwrap_or_default();
+        // The common bytes may end partway through a character
+        let valid = core::str::from_utf8(common).map_or_else(|e| e.valid_up_to(), str::len);
+        Some(String::from_utf8_lossy(&common[..valid]).into_owned())
+    }
+
+    /// Whether a [`Regex`](Expr::Regex) node can match the empty string, and so match without consuming any input
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// assert_eq!(Expr::new("#'[0-9]*'").unwrap().regex_nullable(), Some(true));
+    /// assert_eq!(Expr::new("#'[0-9]+'").unwrap().regex_nullable(), Some(false));
+    /// ```
+    pub fn regex_nullable(&self) -> Option<bool> {
+        Some(self.regex_hir()?.properties().minimum_len() == Some(0))
+    }
+
+    /// The characters a match of a [`Regex`](Expr::Regex) node can begin with, as sorted ranges that don't touch each other. This is an estimate, as it ignores look-around assertions such as `\b`, so the set may include characters no match actually begins with, but never leaves one out. It is empty if the pattern can only match the empty string.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let first = Expr::new("#'[a-c]?[0-9]|_'").unwrap().regex_first_chars().unwrap();
+    /// assert_eq!(first, ['0'..='9', '_'..='_', 'a'..='c']);
+    /// ```
+    pub fn regex_first_chars(&self) -> Option<Vec<RangeInclusive<char>>> {
+        let mut class = ClassUnicode::empty();
+        leading_chars(&self.regex_hir()?, &mut class);
+        Some(
+            class
+                .ranges()
+                .iter()
+                .map(|range| range.start()..=range.end())
+                .collect(),
+        )
+    }
+
+    /// A pattern matching exactly the text of a [`Literal`](Expr::Literal) node, or `None` for any other kind of node. As a [`Regex`](Expr::Regex) node borrows its pattern rather than owning it, this returns the pattern for the caller to keep and build the node from. Quotes are escaped, so the pattern can be written out between `#'` and `'`.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let literal = Expr::new("'1.5'").unwrap();
+    /// let pattern = literal.literal_to_regex().unwrap();
+    /// assert_eq!(pattern, r"1\.5");
+    /// let regex = Expr::Regex { span: literal.span(), pattern: &pattern };
+    /// assert_eq!(regex.to_string(), r"#'1\.5'");
+    /// ```
+    pub fn literal_to_regex(&self) -> Option<String> {
+        match self {
+            Expr::Literal { str, .. } => Some(regex_syntax::escape(str).replace('\'', r"\x27")),
+            _ => None,
+        }
+    }
+
+    /// Converts a [`Regex`](Expr::Regex) node that only ever matches one string back to a [`Literal`](Expr::Literal) node with the same span, such as `#'let'` to `'let'`. The literal borrows its text from the pattern, so this only succeeds when the string appears in the pattern as written, and not for a pattern such as `#'1\.5'` that escapes some of its characters. Strings containing quotes or backslashes are also left as regexes, as they can't be written as a literal without escaping.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let keyword = Expr::new("#'(let)'").unwrap().regex_to_literal().unwrap();
+    /// assert_eq!(keyword.to_string(), "\"let\"");
+    /// assert!(Expr::new("#'le[t]'").unwrap().regex_to_literal().is_none());
+    /// ```
+    pub fn regex_to_literal(&self) -> Option<Expr<'a>> {
+        let &Expr::Regex { span, pattern } = self else {
+            return None;
+        };
+        let mut hir = &self.regex_hir()?;
+        while let HirKind::Capture(capture) = hir.kind() {
+            hir = &capture.sub;
+        }
+        let HirKind::Literal(literal) = hir.kind() else {
+            return None;
+        };
+        let text = core::str::from_utf8(&literal.0).ok()?;
+        if text.contains(['"', '\'', '\\']) {
+            return None;
+        }
+        let start = pattern.find(text)?;
+        Some(Expr::Literal {
+            span,
+            str: &pattern[start..start + text.len()],
+        })
+    }
+
+    fn regex_hir(&self) -> Option<Hir> {
+        match self {
+            Expr::Regex { pattern, .. } => regex_syntax::parse(pattern).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Adds every character `hir` can begin with to `class`, and returns whether `hir` can match the empty string
+pub(crate) fn leading_chars(hir: &Hir, class: &mut ClassUnicode) -> bool {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => true,
+        HirKind::Literal(lit) => match String::from_utf8_lossy(&lit.0).chars().next() {
+            Some(c) => {
+                class.push(ClassUnicodeRange::new(c, c));
+                false
+            }
+            None => true,
+        },
+        HirKind::Class(Class::Unicode(c)) => {
+            class.union(c);
+            false
+        }
+        HirKind::Class(Class::Bytes(c)) => {
+            if let Some(c) = c.to_unicode_class() {
+                class.union(&c);
+            }
+            false
+        }
+        HirKind::Repetition(rep) => leading_chars(&rep.sub, class) || rep.min == 0,
+        HirKind::Capture(cap) => leading_chars(&cap.sub, class),
+        HirKind::Concat(subs) => subs.iter().all(|sub| leading_chars(sub, class)),
+        HirKind::Alternation(subs) => {
+            let mut nullable = false;
+            for sub in subs {
+                nullable |= leading_chars(sub, class);
+            }
+            nullable
+        }
+    }
+}
+
+pub(crate) fn simplify_trivial_regexes(grammar: &mut Grammar<'_>) -> Vec<Span> {
+    let mut converted = Vec::new();
+    for rule in grammar.rules.values_mut() {
+        let mut stack: Vec<_> = rule.body.iter_mut().collect();
+        while let Some(node) = stack.pop() {
+            if let Some(literal) = node.regex_to_literal() {
+                converted.push(node.span());
+                *node = literal;
+            } else {
+                stack.extend(node.children_mut());
+            }
+        }
+    }
+    converted.sort_unstable();
+    converted
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{Expr, Grammar, Span};
+
+    #[test]
+    fn regex_helpers() {
+        let invalid = Expr::new("#'[0-9'").unwrap();
+        assert!(invalid.compile_regex().unwrap().is_err());
+        assert_eq!(invalid.regex_prefix(), None);
+        assert_eq!(invalid.regex_nullable(), None);
+        assert_eq!(invalid.regex_first_chars(), None);
+
+        let literal = Expr::new("'abc'").unwrap();
+        assert_eq!(literal.regex_prefix(), None);
+        assert_eq!(literal.regex_nullable(), None);
+
+        let keyword = Expr::new("#'(?i)let'").unwrap();
+        assert_eq!(keyword.regex_prefix().as_deref(), Some(""));
+        assert_eq!(keyword.regex_first_chars().unwrap(), ['L'..='L', 'l'..='l']);
+
+        let anchored = Expr::new("#'^$'").unwrap();
+        assert_eq!(anchored.regex_nullable(), Some(true));
+        assert_eq!(anchored.regex_first_chars().unwrap(), []);
+
+        let accented = Expr::new("#'é[ab]|ée'").unwrap();
+        assert_eq!(accented.regex_prefix().as_deref(), Some("é"));
+    }
+
+    #[test]
+    fn simplify_trivial_regexes() {
+        let src = "stmt = #'let' name #'=' expr #';' | #'(?:print)' expr; name = #'[a-z]+'; expr = name (#'\\+' name)* | #'1\\.5' | #'\"' name #'\"';";
+        let mut g = Grammar::new(src).unwrap();
+        let converted = g.simplify_trivial_regexes();
+        let texts: Vec<_> = converted.iter().map(|span| &src[span.range()]).collect();
+        assert_eq!(texts, ["#'let'", "#'='", "#';'", "#'(?:print)'", r"#'\+'"]);
+        assert_eq!(
+            g["stmt"].to_string(),
+            r#"stmt = "let" name "=" expr ((";")|("print")) expr;"#
+        );
+        // `+` appears unescaped within `\+`, but `1.5` and quotes can't be borrowed as the literal's text
+        assert_eq!(
+            g["expr"].to_string(),
+            r#"expr = name ((((("+") (name)))*)|(#'1\.5')|(#'"')) name #'"';"#
+        );
+
+        let literal = Expr::Literal {
+            span: Span::default(),
+            str: "it's (1)",
+        };
+        let pattern = literal.literal_to_regex().unwrap();
+        let regex = Expr::Regex {
+            span: Span::default(),
+            pattern: &pattern,
+        };
+        assert!(regex.compile_regex().unwrap().unwrap().is_match("it's (1)"));
+        assert!(!regex.to_string()[2..regex.to_string().len() - 1].contains('\''));
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use crate::{Expr, Rule, Span};
+
+/// An alternative that [`Grammar::reorder_choices`](crate::Grammar::reorder_choices) moved ahead of one of its siblings, because the sibling's leading literal text was a prefix of its own. Under ordered choice, the sibling would otherwise always be tried first and could succeed without ever giving the longer alternative a chance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovedAlternative<'a> {
+    /// The name of the rule containing the [`Choice`](Expr::Choice)
+    pub rule: Cow<'a, str>,
+    /// The alternative that was moved
+    pub alternative: Span,
+    /// The alternative it now comes directly before
+    pub ahead_of: Span,
+}
+
+pub(crate) fn reorder_rule_choices<'a>(
+    rule: &mut Rule<'a>,
+    keep_order: &mut impl FnMut(&str, &Expr<'_>) -> bool,
+    moved: &mut Vec<MovedAlternative<'a>>,
+) {
+    let mut stack: Vec<_> = rule.body.iter_mut().collect();
+    while let Some(node) = stack.pop() {
+        if matches!(node, Expr::Choice { .. }) && !keep_order(&rule.name, node) {
+            let Expr::Choice { body, .. } = node else {
+                unreachable!()
+            };
+            reorder(&rule.name, body, moved);
+        }
+        stack.extend(node.children_mut());
+    }
+}
+
+/// Moves each alternative directly in front of the first earlier one whose literal prefix is a strict prefix of its own, so that alternatives which don't shadow each other keep their relative order
+#[expect(
+    clippy::ptr_arg,
+    reason = "Cloning the Cow rather than a &str keeps a borrowed name borrowed"
+)]
+fn reorder<'a>(
+    rule: &Cow<'a, str>,
+    body: &mut Vec<Expr<'a>>,
+    moved: &mut Vec<MovedAlternative<'a>>,
+) {
+    let mut placed: Vec<(String, Expr<'a>)> = Vec::with_capacity(body.len());
+    for alternative in body.drain(..) {
+        let mut prefix = String::new();
+        literal_prefix(&alternative, &mut prefix);
+
+        let shadowing = placed.iter().position(|(other, _)| {
+            other.len() < prefix.len() && prefix.starts_with(other.as_str())
+        });
+        match shadowing {
+            Some(index) => {
+                moved.push(MovedAlternative {
+                    rule: rule.clone(),
+                    alternative: alternative.span(),
+                    ahead_of: placed[index].1.span(),
+                });
+                placed.insert(index, (prefix, alternative));
+            }
+            None => placed.push((prefix, alternative)),
+        }
+    }
+    body.extend(placed.into_iter().map(|(_, alternative)| alternative));
+}
+
+/// Appends the literal text `node` must begin with to `prefix`, returning whether `node` consists of nothing else
+pub(crate) fn literal_prefix(node: &Expr<'_>, prefix: &mut String) -> bool {
+    match node {
+        Expr::Literal { str, .. } => {
+            prefix.push_str(str);
+            true
+        }
+        Expr::Empty { .. } => true,
+        Expr::Group { body, .. }
+        | Expr::Rule {
+            rule: Rule { body, .. },
+            ..
+        } => body.iter().all(|n| literal_prefix(n, prefix)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::vec::Vec;
+
+    use crate::{Expr, Grammar};
+
+    #[test]
+    fn longer_prefixes_first() {
+        let src = "S = 'a' | 'ab' | 'b' | ('a' 'bc' X); T = 'c' | 'cd';";
+        let mut g = Grammar::new(src).unwrap();
+        let moved = g.reorder_choices(|rule, _| rule == "T");
+
+        let Expr::Choice { body, .. } = &g["S"].body[0] else {
+            panic!()
+        };
+        let order: Vec<_> = body.iter().map(|a| &src[a.span().range()]).collect();
+        assert_eq!(order, ["'a' 'bc' X", "'ab'", "'a'", "'b'"]);
+
+        let moved: Vec<_> = moved
+            .iter()
+            .map(|m| (&src[m.alternative.range()], &src[m.ahead_of.range()]))
+            .collect();
+        assert_eq!(moved, [("'ab'", "'a'"), ("'a' 'bc' X", "'ab'")]);
+
+        let Expr::Choice { body, .. } = &g["T"].body[0] else {
+            panic!()
+        };
+        assert_eq!(&src[body[0].span().range()], "'c'");
+    }
+}
@@ -0,0 +1,228 @@
+#![allow(clippy::range_plus_one)]
+
+use std::{
+    fmt::{self, Write},
+    io,
+    ops::Range,
+};
+
+use ariadne::{
+    ColorGenerator, Config, IndexType, Label, Report, ReportBuilder, ReportKind, Source,
+};
+use display_tree::Style;
+
+use crate::{EbnfError, Expr, ValidationError, debug::print_vec_tree, diagnostic::diagnostic};
+
+type ReportType<'a> = ReportBuilder<'a, (&'a str, Range<usize>)>;
+
+/// Options controlling how [`EbnfError::render`] lays out a report. The default matches the `Display` implementation of [`EbnfError`]: colored, not compact, and with the source named `<input>`.
+/// ```rust
+/// # use ebnf_toolkit::{ReportConfig, Rule};
+/// let err = Rule::new("A = (B;").unwrap_err();
+/// let config = ReportConfig::default().with_color(false).with_source_name("grammar.ebnf");
+/// let report = err.render(config);
+/// assert!(report.contains("grammar.ebnf"));
+/// assert!(!report.contains('\u{1b}'));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportConfig<'a> {
+    color: bool,
+    compact: bool,
+    source_name: &'a str,
+    first_line: usize,
+}
+
+impl Default for ReportConfig<'_> {
+    fn default() -> Self {
+        ReportConfig {
+            color: true,
+            compact: false,
+            source_name: "<input>",
+            first_line: 1,
+        }
+    }
+}
+
+impl<'a> ReportConfig<'a> {
+    /// Whether the report includes ANSI color codes. Turning this off is useful when the output is going to a log file or snapshot test rather than a terminal.
+    #[must_use]
+    pub fn with_color(self, color: bool) -> Self {
+        ReportConfig { color, ..self }
+    }
+
+    /// Whether the report leaves out blank lines and other spacing between its parts
+    #[must_use]
+    pub fn with_compact(self, compact: bool) -> Self {
+        ReportConfig { compact, ..self }
+    }
+
+    /// The name given to the input in the report's header, such as the path of the file it was read from
+    #[must_use]
+    pub fn with_source_name(self, source_name: &'a str) -> Self {
+        ReportConfig {
+            source_name,
+            ..self
+        }
+    }
+
+    /// The line number given to the first line of the input in the report's header, for an input that was cut out of a larger document such as with [`Rule::new_at`](crate::Rule::new_at). Lines count from 1, which is the default.
+    #[must_use]
+    pub fn with_first_line(self, first_line: usize) -> Self {
+        ReportConfig {
+            first_line: first_line.max(1),
+            ..self
+        }
+    }
+}
+
+/// Renders the full ariadne report for `error` according to `config`
+pub(crate) fn write_report(
+    error: &EbnfError<'_>,
+    f: &mut impl Write,
+    config: ReportConfig<'_>,
+) -> fmt::Result {
+    let mut output = vec![];
+    // Writing to a Vec can't fail
+    let _ = stream_report(error, &mut output, config);
+    write!(f, "{}", String::from_utf8(output).unwrap())
+}
+
+/// Writes the full ariadne report for `error` to `w` as it is rendered, according to `config`
+pub(crate) fn stream_report(
+    error: &EbnfError<'_>,
+    w: &mut impl io::Write,
+    config: ReportConfig<'_>,
+) -> io::Result<()> {
+    let input = match error {
+        EbnfError::LexError { input, .. }
+        | EbnfError::ParseError { input, .. }
+        | EbnfError::LimitExceeded { input, .. } => *input,
+        EbnfError::EmptyInput => return write!(w, "[{}] Input string was empty", error.code()),
+    };
+
+    let s = Source::from(input).with_display_line_offset(config.first_line - 1);
+
+    let name = config.source_name;
+    let mut report = Report::build(ReportKind::Error, (name, 0..input.len()))
+        .with_code(error.code())
+        .with_config(
+            // Spans are byte offsets, and ariadne takes care of how wide each character is drawn
+            Config::default()
+                .with_index_type(IndexType::Byte)
+                .with_color(config.color)
+                .with_compact(config.compact),
+        );
+
+    let diagnostic = diagnostic(error);
+    if let Some(message) = diagnostic.message {
+        report = report.with_message(message);
+    }
+    let mut generator = ColorGenerator::new();
+    let count = diagnostic
+        .labels
+        .iter()
+        .map(|l| l.color + 1)
+        .max()
+        .unwrap_or(0);
+    let colors: Vec<_> = (0..count).map(|_| generator.next()).collect();
+    for label in diagnostic.labels {
+        report = report.with_label(
+            Label::new((name, label.range))
+                .with_message(label.message)
+                .with_color(colors[label.color]),
+        );
+    }
+    for note in diagnostic.notes {
+        report = report.with_note(note);
+    }
+    if let EbnfError::ParseError {
+        reason: Some(reason),
+        ..
+    } = error
+    {
+        report = attach_stack_to_report(report, reason.stack());
+    }
+    for help in diagnostic.helps {
+        report = report.with_help(help);
+    }
+
+    report.finish().write((name, s), w)
+}
+
+/// Renders an ariadne report for `error`, labelling its spans in `input`, according to `config`
+pub(crate) fn write_validation_report(
+    error: &ValidationError<'_>,
+    input: &str,
+    f: &mut impl Write,
+    config: ReportConfig<'_>,
+) -> fmt::Result {
+    let name = config.source_name;
+    let mut report = Report::build(ReportKind::Error, (name, 0..input.len()))
+        .with_code(error.code())
+        .with_config(
+            Config::default()
+                .with_index_type(IndexType::Byte)
+                .with_color(config.color)
+                .with_compact(config.compact),
+        );
+
+    let mut colors = ColorGenerator::new();
+    match error {
+        ValidationError::DuplicateRule {
+            name: rule,
+            definitions,
+        } => {
+            report = report.with_message(format!("Rule `{rule}` is defined more than once"));
+            for span in definitions {
+                report = report.with_label(
+                    Label::new((name, span.range()))
+                        .with_message("Defined here")
+                        .with_color(colors.next()),
+                );
+            }
+        }
+        ValidationError::DanglingReference {
+            rule,
+            name: missing,
+            span,
+            suggestion,
+        } => {
+            report = report
+                .with_message(format!("Rule `{rule}` refers to an undefined rule"))
+                .with_label(
+                    Label::new((name, span.range()))
+                        .with_message(format!("`{missing}` is not defined"))
+                        .with_color(colors.next()),
+                );
+            if let Some(suggestion) = suggestion {
+                report = report.with_help(format!("Did you mean `{suggestion}`?"));
+            }
+        }
+        ValidationError::DefinedInSeveralInputs { .. } => {
+            report = report.with_message(error.to_string());
+        }
+    }
+
+    let mut output = vec![];
+    report
+        .finish()
+        .write(
+            (
+                name,
+                Source::from(input).with_display_line_offset(config.first_line - 1),
+            ),
+            &mut output,
+        )
+        .unwrap();
+    write!(f, "{}", String::from_utf8(output).unwrap())
+}
+
+fn attach_stack_to_report<'a>(report: ReportType<'a>, nodes: &[Expr<'_>]) -> ReportType<'a> {
+    let mut nodes = nodes.to_vec();
+    nodes.reverse();
+    let mut tree_output = String::new();
+    print_vec_tree(&mut tree_output, Style::default(), &nodes).unwrap();
+    report.with_note(format!(
+        "The parse stack looked like this (most recent on top):\n{tree_output}",
+    ))
+}
@@ -1,19 +1,79 @@
-use std::{
+use alloc::{
     borrow::Cow,
-    collections::{HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::{
+    fmt::Display,
     hash::Hash,
-    ops::Index,
+    ops::{Index, Range},
 };
 
-use crate::{Expr, Span, error::EbnfError, parse_rules_from_tokens, token_data::tokenize};
+use regex::Regex;
+
+use crate::{
+    AlternativeIssue, BytesError, CompileError, Coverage, ExportError, ExportOptions, Expr,
+    FirstSet, FrozenGrammar, GenerateOptions, Generator, GrammarIndex, GrmtoolsGrammar,
+    LengthBounds, LexicalConflict, Lookahead, LosslessGrammar, MarkdownGrammar, MixedChoice,
+    MovedAlternative, NamingConvention, NamingIssue, NodeId, NodeIds, OversizedTerminal,
+    ParseOptions, PestError, RecursionCycle, RegexOverlap, RuleIds, SharedGrammar, SourceDiff,
+    Span, SpanSet, StructuredGrammar, SuspiciousCharacter, TerminalLimits, TerminalOverlap,
+    YaccError,
+    alternatives::{remove_rule_duplicates, rule_alternative_issues, rule_mixed_choice},
+    bytes::{from_bytes, to_bytes},
+    classify::{RuleClass, classify_rules},
+    compile::compile_rule,
+    compose::compose,
+    confusables::rule_suspicious_characters,
+    coverage::coverage,
+    derivations::count_derivations,
+    error::{EbnfError, NamedError, ValidationError},
+    expand::expand_rule,
+    export::{to_grmtools, to_lalrpop, to_pest, to_tree_sitter},
+    expr::lower_separated_repetitions,
+    first_set::first_sets,
+    fold::fold_terminal_rules,
+    length::length_bounds,
+    limits::check_token_limits,
+    lint::{Diagnostic, LintConfig, lint},
+    lookahead::lookahead_requirements,
+    markdown::{grammar_from_markdown, joined_grammar_from_markdown},
+    matcher::match_str,
+    naming::{check_naming, rename_all},
+    node_ids::node,
+    overlap::{lexical_conflicts, rule_regex_overlaps, rule_terminal_overlaps},
+    parse_rules_from_tokens,
+    pest::from_pest,
+    recognize_rules,
+    refactor::{factor_out, inline_rule},
+    reference_graph::{derives, extract, recursion_cycles, slice, topological_order},
+    regex_terminal::simplify_trivial_regexes,
+    reorder::reorder_rule_choices,
+    shrink::shrink,
+    simplification::Simplifier,
+    structured::{from_structured, to_structured},
+    suggest::closest_name,
+    terminal_size::rule_oversized_terminals,
+    token_data::{first_rule_end, tokenize, tokenize_fragment, tokenize_with_imports},
+    token_diff::diff_sources,
+    yacc::from_yacc,
+};
 
 /// A single production rule of a grammar. Will generally be an intermediate step on the way to either creating a [`Grammar`] or analysing the rule's `body`, which represents an ordered sequence of [`Expr`].
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Rule<'a> {
     /// The name of this rule - using [`Rule::new`] or [`Grammar::new`] will borrow this from the input data, but an owned String can also be used
     pub name: Cow<'a, str>,
-    /// The sequence of nodes that the name refers to. Semantically equivalent to a [`Expr::Group`]
+    /// The sequence of nodes that the name refers to. Semantically equivalent to a [`Expr::Group`], which [`Rule::body_expr`] builds
     pub body: Vec<Expr<'a>>,
+    /// Where each definition of this rule appears in the input, from its name to the closing terminator. A rule parsed from a single definition has one span here, while rules merged from duplicate definitions by a [`Grammar`] have one for each. Rules built by hand can leave this empty.
+    pub definitions: SpanSet,
+    /// Whether the name was written in angle brackets, `<name> = ...;`, which in instaparse leaves the rule's own node out of the parse tree while keeping its children. A rule merged from several definitions is hidden if any of them are.
+    pub hidden: bool,
+    /// The annotations written before the rule's name, such as `@token` or `@skip`, in the order they appear. A rule merged from several definitions has those of each, leaving out any whose name was already seen.
+    pub annotations: Vec<Annotation<'a>>,
 }
 
 impl<'a> Rule<'a> {
@@ -22,39 +82,213 @@ impl<'a> Rule<'a> {
     /// # Errors
     /// If the input string is ill-formed, an [`EbnfError`] is returned. See that type for possible reasons why.
     pub fn new(input: &str) -> Result<Rule<'_>, EbnfError<'_>> {
+        Rule::parse(input, ParseOptions::default())
+    }
+
+    /// Parses a rule like [`Rule::new`], but without any of the [reductions](crate#reductions), as [`Expr::new_raw`] does. [`ParseOptions::simplify`] does the same for a whole grammar.
+    ///
+    /// # Errors
+    /// As for [`Rule::new`], with the same limit on runs of alternatives as [`Expr::new_raw`]
+    /// ```rust
+    /// # use ebnf_toolkit::{Expr, Rule};
+    /// let rule = Rule::new_raw("list = (item) [(',' list)];").unwrap();
+    /// assert!(matches!(rule.body[0], Expr::Group { .. }));
+    /// assert!(matches!(&rule.body[1], Expr::Optional { body, .. } if matches!(body[0], Expr::Group { .. })));
+    /// ```
+    pub fn new_raw(input: &str) -> Result<Rule<'_>, EbnfError<'_>> {
+        let options = ParseOptions {
+            simplify: false,
+            ..ParseOptions::default()
+        };
+        Rule::parse(input, options)
+    }
+
+    /// Parses the first rule of `input`, up to and including its `;`, and returns it with the rest of the input, which isn't looked at. This lets a rule be read from text that goes on with something other than EBNF, such as directives of the caller's own, without splitting the text up first. The spans of the rule are relative to the start of `input`.
+    ///
+    /// # Errors
+    /// As for [`Rule::new`], for the text up to the first `;` that ends a rule, or the whole input if there is none
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// let input = "digit = #'[0-9]' | ';';\n!include other.ebnf\n";
+    /// let (rule, rest) = Rule::parse_prefix(input).unwrap();
+    /// assert_eq!(rule.name, "digit");
+    /// assert_eq!(rest, "\n!include other.ebnf\n");
+    /// assert!(Rule::parse_prefix("digit = #'[0-9]'\n!include").is_err());
+    /// ```
+    pub fn parse_prefix(input: &str) -> Result<(Rule<'_>, &str), EbnfError<'_>> {
+        let (rule, rest) = input.split_at(first_rule_end(input));
+        Ok((Rule::parse(rule, ParseOptions::default())?, rest))
+    }
+
+    fn parse(input: &str, options: ParseOptions) -> Result<Rule<'_>, EbnfError<'_>> {
         let tokens = tokenize(input)?;
 
         let mut tokens_buffer = &tokens[..];
-        parse_rules_from_tokens(input, &mut tokens_buffer)?
+        parse_rules_from_tokens(input, input.len(), &mut tokens_buffer, options)?
             .into_iter()
             .next()
             .ok_or(EbnfError::EmptyInput)
     }
 
+    /// Parses a rule like [`Rule::new`], where `input` was cut out of a larger document such as a doc comment, starting `byte_offset` bytes into it at byte `column` of `line`. The spans of the rule are positioned within the document rather than `input`. Lines count from 1.
+    ///
+    /// # Errors
+    /// If the input string is ill-formed, an [`EbnfError`] is returned. This still refers to `input`, so only it is needed to show the report, but [`ReportConfig::with_first_line`](crate::ReportConfig::with_first_line) will number the lines as in the document.
+    /// ```rust
+    /// # use ebnf_toolkit::{ReportConfig, Rule};
+    /// let doc = "/// Some prose\n/// digit = #'[0-9]';\nfn f() {}";
+    /// let start = doc.find("digit").unwrap();
+    /// let end = doc[start..].find('\n').unwrap() + start;
+    /// let rule = Rule::new_at(&doc[start..end], start, 2, 4).unwrap();
+    /// assert_eq!(&doc[rule.body[0].span().range()], "#'[0-9]'");
+    /// assert_eq!(rule.body[0].span().start_line(), (2, 12));
+    ///
+    /// let err = Rule::new_at("digit = (#'[0-9]';", start, 2, 4).unwrap_err();
+    /// let report = err.render(ReportConfig::default().with_color(false).with_first_line(2));
+    /// assert!(report.contains("<input>:2:"));
+    /// ```
+    pub fn new_at(
+        input: &str,
+        byte_offset: usize,
+        line: usize,
+        column: usize,
+    ) -> Result<Rule<'_>, EbnfError<'_>> {
+        let mut rule = Rule::new(input)?;
+        let mut relative = |span: Span| span.relative_to(byte_offset, line, column);
+        rule.map_spans_with(&mut relative);
+        for annotation in &mut rule.annotations {
+            annotation.span = relative(annotation.span);
+        }
+        Ok(rule)
+    }
+
     /// Returns a list of all the nonterminal names that appear anywhere within this rule
     pub fn nonterminals(&self) -> Vec<&'a str> {
-        #[allow(clippy::enum_glob_use)]
-        use Expr::*;
+        self.nonterminal_references()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// As [`Rule::nonterminals`], but alongside the span of each reference
+    pub(crate) fn nonterminal_references(&self) -> Vec<(&'a str, Span)> {
         let mut stack: VecDeque<_> = self.body.iter().collect();
         let mut nonterm_names = vec![];
 
         while let Some(node) = stack.pop_front() {
             match node {
-                Regex { .. } | Literal { .. } | UnparsedOperator { .. } => {}
-                Nonterminal { name, .. } => nonterm_names.push(*name),
-                Choice { body, .. }
-                | Optional { body, .. }
-                | Repetition { body, .. }
-                | Rule {
-                    rule: crate::Rule { body, .. }, // Shouldn't be possible in practice but might as well cover it
-                    ..
-                }
-                | Group { body, .. } => stack.extend(body),
+                Expr::Nonterminal { name, span } => nonterm_names.push((*name, *span)),
+                other => stack.extend(other.children()),
             }
         }
         nonterm_names
     }
 
+    /// Replaces the spans of every node in the body, and of each definition, with the result of calling `f` on them. See [`Expr::map_spans`].
+    pub fn map_spans(&mut self, mut f: impl FnMut(Span) -> Span) {
+        self.map_spans_with(&mut f);
+    }
+
+    pub(crate) fn map_spans_with(&mut self, f: &mut impl FnMut(Span) -> Span) {
+        for node in &mut self.body {
+            node.map_spans_with(f);
+        }
+        self.definitions = self.definitions.iter().map(f).collect();
+    }
+
+    /// Sets every span within the rule to [`Span::default`], and forgets where it was defined. See [`Expr::strip_spans`].
+    pub fn strip_spans(&mut self) {
+        self.body.iter_mut().for_each(Expr::strip_spans);
+        self.definitions = SpanSet::default();
+    }
+
+    /// Where the rule appears in the input, from its name to the closing terminator, for mapping it back to a region of the file it was read from. Annotations before the name aren't included. A rule merged from several definitions covers all of them, along with everything in between, so [`definitions`](Rule::definitions) has each separately. Returns `None` for a rule with no recorded definition.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "// Lists\n@skip <ws> = ' '+;\nlist = item (ws item)*;";
+    /// let g = Grammar::new(src).unwrap();
+    /// assert_eq!(&src[g["ws"].span().unwrap().range()], "<ws> = ' '+;");
+    /// assert_eq!(g["list"].span().unwrap().start_line(), (3, 0));
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        self.definitions.hull()
+    }
+
+    /// The definition each alternative of the rule came from, as its span in [`definitions`](Rule::definitions), for pointing at the right one of several definitions that were merged into a [`Choice`](Expr::Choice). The alternatives are those of the choice when the body is a single one, and otherwise the body as a whole is the only alternative. An alternative whose span isn't inside any definition, as in a rule built by hand or with its spans stripped, has `None`.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "value = 'on' | 'off';\nother = value;\nvalue = 'auto';";
+    /// let g = Grammar::new(src).unwrap();
+    /// let origins = g["value"].alternative_origins();
+    /// let lines: Vec<_> = origins.iter().map(|span| span.unwrap().start_line().0).collect();
+    /// assert_eq!(lines, [1, 1, 3]);
+    /// ```
+    pub fn alternative_origins(&self) -> Vec<Option<Span>> {
+        let origin = |span: Span| {
+            self.definitions.iter().find(|definition| {
+                definition.start() <= span.start() && span.end() <= definition.end()
+            })
+        };
+        match &self.body[..] {
+            [Expr::Choice { body, .. }] => body.iter().map(|e| origin(e.span())).collect(),
+            _ => vec![origin(self.body_expr().span())],
+        }
+    }
+
+    /// Whether any of the rule's [`annotations`](Rule::annotations) has the given name, which is written without its `@`
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// let rule = Rule::new("@token @skip ws = #'[ \\t]+';").unwrap();
+    /// assert!(rule.has_annotation("skip"));
+    /// assert!(!rule.has_annotation("keyword"));
+    /// ```
+    pub fn has_annotation(&self, name: &str) -> bool {
+        self.annotations.iter().any(|a| a.name == name)
+    }
+
+    /// The body as a single node, for code that handles every composite node the same way: a [`Group`](Expr::Group) holding a copy of the body, which is the sequence node of this crate. Its span covers the body, or is the hull of [`definitions`](Rule::definitions) if the body is empty.
+    /// ```rust
+    /// # use ebnf_toolkit::{Expr, Rule};
+    /// let mut rule = Rule::new("A = 'a' B;").unwrap();
+    /// let body = rule.body_expr();
+    /// assert!(matches!(&body, Expr::Group { body, .. } if body.len() == 2));
+    /// assert_eq!(body.children(), &rule.body[..]);
+    ///
+    /// rule.set_body_expr(Expr::new("'b'*").unwrap());
+    /// assert!(matches!(&rule.body[..], [Expr::Repetition { .. }]));
+    /// rule.set_body_expr(body);
+    /// assert_eq!(rule.to_string(), "A = \"a\" B;");
+    /// ```
+    pub fn body_expr(&self) -> Expr<'a> {
+        let span = if self.body.is_empty() {
+            self.definitions.hull().unwrap_or_default()
+        } else {
+            Span::union(self.body.iter())
+        };
+        Expr::Group {
+            span,
+            body: self.body.clone(),
+        }
+    }
+
+    /// Replaces the body with `body`, the inverse of [`Rule::body_expr`]: the children of a [`Group`](Expr::Group) become the body, and any other node becomes its only element
+    pub fn set_body_expr(&mut self, body: Expr<'a>) {
+        self.body = match body {
+            Expr::Group { body, .. } => body,
+            other => vec![other],
+        };
+    }
+
+    /// A rough measure of how hard the rule is to follow, in the manner of cyclomatic complexity: one, plus one for each alternative of a [`Choice`](Expr::Choice) beyond the first and for each [`Optional`](Expr::Optional) and [`Repetition`](Expr::Repetition) in its body. This doesn't allocate, so lints can call it on every rule cheaply.
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// assert_eq!(Rule::new("digit = #'[0-9]';").unwrap().complexity(), 1);
+    /// assert_eq!(Rule::new("list = item [(',' | ';') item]*;").unwrap().complexity(), 4);
+    /// ```
+    pub fn complexity(&self) -> usize {
+        1 + self.body.iter().map(Expr::decision_count).sum::<usize>()
+    }
+
     /// Does this rule contain any reference to itself?
     pub fn is_recursive(&self) -> bool {
         self.nonterminals().contains(&&*self.name)
@@ -64,23 +298,843 @@ impl<'a> Rule<'a> {
     pub fn contains_any_nonterminal(&self) -> bool {
         self.body.iter().any(Expr::contains_nonterminal)
     }
+
+    /// Builds a single [`Expr`] for what this rule matches, with each nonterminal replaced by the body of the rule it names from `grammar`, through at most `depth` levels of rules. A nonterminal is left as it is if it names a rule that isn't in `grammar`, or one that is already being expanded further out, so recursive rules don't expand forever.
+    ///
+    /// The nodes keep the spans of wherever they were written, so a node containing parts of several rules can span a lot of unrelated input.
+    /// ```rust
+    /// # use ebnf_toolkit::{Expr, Grammar};
+    /// let g = Grammar::new("list = item (',' item)*; item = digit | ('(' list ')'); digit = #'[0-9]';").unwrap();
+    /// let expanded = g["list"].expand(&g, 2);
+    /// let expected = Expr::new("((#'[0-9]' | ('(' list ')')) (',' (#'[0-9]' | ('(' list ')')))*)").unwrap();
+    /// assert!(expanded.eq_ignoring_spans(&expected));
+    /// ```
+    pub fn expand(&self, grammar: &Grammar<'a>, depth: usize) -> Expr<'a> {
+        expand_rule(self, grammar, depth)
+    }
+
+    /// Matches the rule against the start of `input`, with each nonterminal matched by the rule it names from `grammar`, and returns how many bytes were matched. This is a quick recognizer for small grammars of literals and regexes, not a full parser: each node takes as much input as it can and is never revisited, so `'a'* 'a'` matches nothing, and an unordered [`Choice`](Expr::Choice) takes whichever alternative matches the most input.
+    ///
+    /// Returns `None` if the rule doesn't match, and also if it refers back to itself, refers to a rule `grammar` doesn't define, or contains an invalid regex.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("version = num '.' num ('.' num)?; num = #'[0-9]+';").unwrap();
+    /// assert_eq!(g["version"].match_str(&g, "1.22.3-beta"), Some(6));
+    /// assert_eq!(g["version"].match_str(&g, "v1.2"), None);
+    /// ```
+    pub fn match_str(&self, grammar: &Grammar<'_>, input: &str) -> Option<usize> {
+        match_str(self, grammar, input)
+    }
+
+    /// Builds a single regex matching exactly the input this rule does, with each nonterminal replaced by the rule it names from `grammar`. This only works for rules that don't refer back to themselves and have no lookaheads, but many lexical rules fit, and the regex is much faster than going through a parser. The regex is anchored at both ends, and choices become regex alternations whichever way they were written.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("ident = letter (letter | digit | '_')*; letter = #'[a-z]'; digit = #'[0-9]';").unwrap();
+    /// let ident = g["ident"].compile_to_regex(&g).unwrap();
+    /// assert!(ident.is_match("snake_case_2"));
+    /// assert!(!ident.is_match("2nd"));
+    /// ```
+    ///
+    /// # Errors
+    /// See [`CompileError`](crate::CompileError) for each case.
+    pub fn compile_to_regex(&self, grammar: &Grammar<'a>) -> Result<Regex, CompileError<'a>> {
+        compile_rule(self, grammar)
+    }
 }
 
 /// A set of EBNF rules
+///
+/// Nothing about a grammar depends on hashing, so everything it gives out is the same from one run to the next. The rules are kept in order of name, which is the order they are iterated in and the order every analysis reports them in, while [`Display`](core::fmt::Display) and the exports write them in the order they were defined. [`Grammar::sort_rules_by_name`] makes the latter follow name order as well.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Grammar<'a> {
-    rules: HashMap<Cow<'a, str>, Rule<'a>>,
+    pub(crate) rules: BTreeMap<Cow<'a, str>, Rule<'a>>,
+    pub(crate) imports: Vec<Import<'a>>,
+    pub(crate) choice_semantics: ChoiceSemantics,
+    /// Whether the rules are written out in order of name rather than the order they were defined
+    pub(crate) name_order: bool,
+}
+
+/// An `%import "path";` directive in a grammar, naming another input whose rules it can refer to. The directive can appear anywhere a rule could, and the path can be quoted with either `"` or `'`.
+///
+/// [`Grammar::new`] only records these - see [`GrammarSet::load`](crate::GrammarSet::load) for following them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Import<'a> {
+    /// The path as written, without its quotes
+    pub path: &'a str,
+    /// The whole directive, up to and including its `;`
+    pub span: Span,
+}
+
+/// A marker such as `@token` written before the name of a rule, for tools that generate parsers from the grammar to tell apart kinds of rules, such as lexical rules or those matching whitespace to be skipped. There can be any number before a rule, and they have no meaning to this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Annotation<'a> {
+    /// The name of the annotation, without its `@`
+    pub name: &'a str,
+    /// The annotation, including its `@`
+    pub span: Span,
+}
+
+/// How thoroughly [`Grammar::from_rules`] checks the rules it is given
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ValidationLevel {
+    /// No checks - rules sharing a name are merged into one, as they are by [`Grammar::new`] and [`FromIterator`]
+    #[default]
+    Permissive,
+    /// Rules sharing a name are rejected
+    NoDuplicates,
+    /// Rules sharing a name are rejected, as is any reference to a nonterminal that is not defined
+    Strict,
+}
+
+/// How the alternatives of a [`Choice`](Expr::Choice) are interpreted, which the analyses of a [`Grammar`] take into account. A choice written with `/` is [ordered](Expr::Choice) whatever the grammar's setting, while one written with `|` follows it, so setting [`Ordered`](ChoiceSemantics::Ordered) treats every choice as ordered, as for a grammar converted from pest, and [`Unordered`](ChoiceSemantics::Unordered) leaves only the `/` choices ordered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChoiceSemantics {
+    /// Any alternative that matches can be chosen, as in a context-free grammar
+    #[default]
+    Unordered,
+    /// The alternatives are tried in order and the first that matches is chosen, as in a parsing expression grammar (PEG)
+    Ordered,
+}
+
+impl<'a> Grammar<'a> {
+    /// The rules in the order they were first defined, followed by any with no recorded definition in order of name, or all of them in order of name after [`Grammar::sort_rules_by_name`]
+    pub(crate) fn rules_in_definition_order(&self) -> Vec<&Rule<'a>> {
+        let mut rules: Vec<_> = self.rules.values().collect();
+        if self.name_order {
+            return rules;
+        }
+        rules.sort_by_key(|rule| {
+            rule.definitions
+                .hull()
+                .map_or(usize::MAX, |span| span.range().start)
+        });
+        rules
+    }
+
+    /// Renames rules and the nonterminals referring to them together, replacing each name for which `mapper` returns `Some`. Rules that end up with the same name are merged. Returns the span of each name that was changed, both where it is defined and where it is used, along with its new name, in order, so the same renaming can be made to the source text without losing its comments or layout.
+    ///
+    /// The new names need to live as long as the grammar's input, as nonterminals borrow their names from it.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "list = item (',' item)*; item = #'[a-z]+';";
+    /// let mut g = Grammar::new(src).unwrap();
+    /// let edits = g.rename_all(|name| (name == "item").then_some("word"));
+    /// assert!(g.get("word").is_some());
+    ///
+    /// let mut patched = src.to_string();
+    /// for (span, name) in edits.iter().rev() {
+    ///     patched.replace_range(span.range(), name);
+    /// }
+    /// assert_eq!(patched, "list = word (',' word)*; word = #'[a-z]+';");
+    /// ```
+    pub fn rename_all(
+        &mut self,
+        mapper: impl FnMut(&str) -> Option<&'a str>,
+    ) -> Vec<(Span, String)> {
+        rename_all(self, mapper)
+    }
+
+    /// Replaces every reference to the rule `name` with its body and removes the rule. Returns `None`, leaving the grammar unchanged, if there is no such rule or it refers to itself.
+    ///
+    /// Otherwise the edits that make the same change to the source text are returned, in order: each reference is replaced by the body, written out in the normalized notation of [`Display`](core::fmt::Display), and each definition of the rule is removed, leaving the whitespace around it. The inlined nodes keep the spans of the rule they were copied from.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "// A list\nlist = item (',' item)*;\nitem = #'[a-z]+' | '_';\n";
+    /// let mut g = Grammar::new(src).unwrap();
+    /// let edits = g.inline_rule("item").unwrap();
+    /// assert!(g.get("item").is_none());
+    ///
+    /// let mut patched = src.to_string();
+    /// for (span, text) in edits.iter().rev() {
+    ///     patched.replace_range(span.range(), text);
+    /// }
+    /// assert_eq!(
+    ///     patched,
+    ///     "// A list\nlist = ((#'[a-z]+')|(\"_\")) (',' ((#'[a-z]+')|(\"_\")))*;\n\n"
+    /// );
+    /// assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+    /// ```
+    pub fn inline_rule(&mut self, name: &str) -> Option<Vec<(Span, String)>> {
+        inline_rule(self, name)
+    }
+
+    /// Moves every occurrence of `expr` into a new rule called `name`, replacing each with a reference to it. A [`Group`](Expr::Group), such as one built by [`Rule::body_expr`], is looked for as a run of nodes in a sequence, so that the body of `r = a b;` is found in `a b c`. Nodes are compared ignoring their spans, and the new rule's body keeps those of `expr`. Returns `None`, leaving the grammar unchanged, if `name` is already taken or `expr` doesn't occur anywhere.
+    ///
+    /// Otherwise the edits that make the same change to the source text are returned, in order. Where the text of an occurrence can be told from its span it is replaced by `name`, leaving the rest of the rule as it was written. A rule where that isn't possible, such as one where `x*` was factored out, as the `*` isn't part of the span, is instead written out again in the normalized notation of [`Display`](core::fmt::Display). The new rule is written on a line of its own after the first rule it was factored out of.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, Rule};
+    /// let src = "call = name '(' name (',' name)* ')';\nname = #'[a-z]+';\n";
+    /// let mut g = Grammar::new(src).unwrap();
+    /// let edits = g.factor_out(&Rule::new("p = ',' name;").unwrap().body_expr(), "next_arg").unwrap();
+    /// assert_eq!(g["call"].nonterminals(), ["name", "name", "next_arg"]);
+    ///
+    /// let mut patched = src.to_string();
+    /// for (span, text) in edits.iter().rev() {
+    ///     patched.replace_range(span.range(), text);
+    /// }
+    /// assert_eq!(
+    ///     patched,
+    ///     "call = name '(' name (next_arg)* ')';\nnext_arg = \",\" name;\nname = #'[a-z]+';\n"
+    /// );
+    /// assert!(Grammar::new(&patched).unwrap().eq_ignoring_spans(&g));
+    /// ```
+    pub fn factor_out(&mut self, expr: &Expr<'a>, name: &'a str) -> Option<Vec<(Span, String)>> {
+        factor_out(self, expr, name)
+    }
+
+    /// Builds a grammar from rules that have already been constructed, checking them to the given [`ValidationLevel`].
+    ///
+    /// # Errors
+    /// If any of the checks fail, every problem found is returned.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, Rule, ValidationLevel};
+    /// let rules = [Rule::new("A = B;").unwrap(), Rule::new("B = 'b';").unwrap()];
+    /// assert!(Grammar::from_rules(rules.clone(), ValidationLevel::Strict).is_ok());
+    /// assert!(Grammar::from_rules(rules[..1].to_vec(), ValidationLevel::Strict).is_err());
+    /// ```
+    pub fn from_rules(
+        rules: impl IntoIterator<Item = Rule<'a>>,
+        level: ValidationLevel,
+    ) -> Result<Self, Vec<ValidationError<'a>>> {
+        let rules: Vec<_> = rules.into_iter().collect();
+        let mut errors = vec![];
+
+        let mut duplicates = vec![];
+        if level != ValidationLevel::Permissive {
+            let mut seen = BTreeSet::new();
+            for rule in &rules {
+                if !seen.insert(&rule.name) && !duplicates.contains(&rule.name) {
+                    duplicates.push(rule.name.clone());
+                }
+            }
+        }
+
+        let grammar: Self = rules.into_iter().collect();
+
+        for name in duplicates {
+            let definitions = grammar.rules[&name].definitions.clone();
+            errors.push(ValidationError::DuplicateRule { name, definitions });
+        }
+
+        if level == ValidationLevel::Strict {
+            for rule in grammar.rules.values() {
+                for (name, span) in rule.nonterminal_references() {
+                    if !grammar.rules.contains_key(name) {
+                        errors.push(ValidationError::DanglingReference {
+                            rule: rule.name.clone(),
+                            name,
+                            span,
+                            suggestion: closest_name(name, grammar.rules.keys().map(|k| &**k))
+                                .map(|suggestion| grammar.rules[suggestion].name.clone()),
+                        });
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(grammar)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Adds the rules of `other` to this grammar with `prefix` put before their names, so that `number` added under the prefix `num_` becomes `num_number`, along with the references to them within `other`. This lets a sub-grammar for something common, like numbers or dates, be reused without its names clashing with those already here. References to rules that `other` doesn't define are left alone, so it can rely on this grammar to supply them. The prefix is used as it is, so it should end in whatever separates it from the name, and the names it makes can only be written back out as EBNF if they are made of word characters.
+    ///
+    /// **The grammar can't hold the new names itself**, as nonterminals borrow their names from the input, so they are written into `names` instead, which the added rules borrow from. `names` has to outlive the grammar and stays borrowed for as long as it lives, so each composition needs a `String` of its own, declared before the grammar. Spans in the added rules refer to the input `other` was parsed from.
+    ///
+    /// # Errors
+    /// If any of the new names are already taken, the grammar is left unchanged and a [`DuplicateRule`](ValidationError::DuplicateRule) is returned for each of them.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let numbers = Grammar::new("number = digit+; digit = #'[0-9]';").unwrap();
+    /// let (mut names, mut more_names) = (String::new(), String::new());
+    /// let mut g = Grammar::new("range = num_number '..' num_number | to_number;").unwrap();
+    /// g.compose("num_", &numbers, &mut names).unwrap();
+    /// assert_eq!(g["num_number"].nonterminals(), ["num_digit"]);
+    /// g.compose("to_", &numbers, &mut more_names).unwrap();
+    /// assert_eq!(g["to_number"].nonterminals(), ["to_digit"]);
+    /// assert_eq!(g.first_dangling_reference(), None);
+    /// ```
+    pub fn compose(
+        &mut self,
+        prefix: &str,
+        other: &Grammar<'a>,
+        names: &'a mut String,
+    ) -> Result<(), Vec<ValidationError<'a>>> {
+        compose(self, prefix, other, names)
+    }
+
+    /// Passes each rule through `f`, in order of name, and builds a grammar from what it returns, keeping this grammar's imports and [`ChoiceSemantics`]. Rules that `f` gives the same name are merged, as duplicate definitions are when parsing.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("list = item (',' item)*; item = #'[a-z]+';").unwrap();
+    /// let hidden = g.map_rules(|mut rule| {
+    ///     rule.hidden = rule.name == "item";
+    ///     rule
+    /// });
+    /// assert!(hidden["item"].hidden && !hidden["list"].hidden);
+    /// ```
+    #[must_use]
+    pub fn map_rules(self, f: impl FnMut(Rule<'a>) -> Rule<'a>) -> Grammar<'a> {
+        let Grammar {
+            rules,
+            imports,
+            choice_semantics,
+            name_order,
+        } = self;
+        Grammar {
+            imports,
+            choice_semantics,
+            name_order,
+            ..rules.into_values().map(f).collect()
+        }
+    }
+
+    /// Keeps only the rules for which `predicate` returns `true`, along with all of the grammar's imports. References to the rules removed are left as they are, so may now be dangling.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("list = item (',' item)*; item = #'[a-z]+'; spare = 'x';").unwrap();
+    /// let used = g.retain_rules(|rule| rule.name != "spare");
+    /// assert_eq!(used.rules().count(), 2);
+    /// ```
+    #[must_use]
+    pub fn retain_rules(mut self, mut predicate: impl FnMut(&Rule<'a>) -> bool) -> Grammar<'a> {
+        self.rules.retain(|_, rule| predicate(rule));
+        self
+    }
+
+    /// Splits the grammar in two: the rules for which `predicate` returns `true`, and the rest. Both grammars keep all of the imports and the [`ChoiceSemantics`] of this one.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("sum = NUM (PLUS NUM)*; NUM = #'[0-9]+'; PLUS = '+';").unwrap();
+    /// let (tokens, syntax) = g.partition(|rule| rule.name.chars().all(|c| c.is_ascii_uppercase()));
+    /// assert_eq!(tokens.rules().map(|r| &*r.name).collect::<Vec<_>>(), ["NUM", "PLUS"]);
+    /// assert_eq!(syntax.rules().map(|r| &*r.name).collect::<Vec<_>>(), ["sum"]);
+    /// ```
+    pub fn partition(
+        self,
+        mut predicate: impl FnMut(&Rule<'a>) -> bool,
+    ) -> (Grammar<'a>, Grammar<'a>) {
+        let (matching, rest) = self
+            .rules
+            .into_iter()
+            .partition(|(_, rule)| predicate(rule));
+        let matching = Grammar {
+            rules: matching,
+            imports: self.imports.clone(),
+            choice_semantics: self.choice_semantics,
+            name_order: self.name_order,
+        };
+        let rest = Grammar {
+            rules: rest,
+            imports: self.imports,
+            choice_semantics: self.choice_semantics,
+            name_order: self.name_order,
+        };
+        (matching, rest)
+    }
+
+    /// Describes the grammar in a [`StructuredGrammar`], which owns all its text and, with the `serde` feature, can be serialized to a stable schema for tools that don't read EBNF
+    pub fn to_structured(&self) -> StructuredGrammar {
+        to_structured(self)
+    }
+
+    /// Rebuilds a grammar from a [`StructuredGrammar`], borrowing its text. Rules sharing a name are merged as [`FromIterator`] does, and nothing else is checked.
+    pub fn from_structured(structured: &StructuredGrammar) -> Grammar<'_> {
+        from_structured(structured)
+    }
+
+    /// Encodes the grammar in a compact binary form, which [`Grammar::from_bytes`] reads back much faster than parsing EBNF. This is for grammars that are built into a program: a build script can parse the grammar and write it out, for the program to `include_bytes!` and decode when it starts.
+    ///
+    /// Everything is kept, including spans, annotations and imports. The form can change between versions of this crate, so the bytes should be written by the same version that reads them.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("list = item % ','; item = #'[a-z]+';").unwrap();
+    /// let bytes = g.to_bytes();
+    /// assert_eq!(Grammar::from_bytes(&bytes), Ok(g));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        to_bytes(self)
+    }
+
+    /// Decodes a grammar written by [`Grammar::to_bytes`], borrowing its text from `bytes`. Nothing is checked beyond the data being well-formed, as the grammar was whatever it was when it was encoded, except that nodes may only be nested [`ParseOptions::DEFAULT_MAX_DEPTH`] deep, as for parsed text, so that crafted data can't overflow the stack.
+    ///
+    /// # Errors
+    /// If `bytes` isn't a whole grammar written by a compatible version of `to_bytes`, as described by [`BytesError`]
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Grammar<'a>, BytesError> {
+        from_bytes(bytes)
+    }
+
+    /// Inlines every rule whose body is a single terminal - a literal or regex, possibly with operators like `+` applied, such as `SPACE = ' '+;` - into the rules that refer to it, for exporting to systems that have no concept of named terminal rules. This is repeated until no more can be inlined, so a rule left with nothing but a terminal after inlining is inlined in turn. The inlined nodes keep the spans of the rule they were copied from.
+    ///
+    /// The names of the rules that were inlined anywhere are returned, in order. Unless `keep_originals` is set, those rules are removed from the grammar, while terminal rules that nothing referred to are left in place.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("list = item (COMMA SPACE item)*; COMMA = ','; SPACE = ' '+; item = #'[a-z]+';").unwrap();
+    /// assert_eq!(g.fold_terminal_rules(false), ["COMMA", "SPACE", "item"]);
+    /// assert!(g["list"].nonterminals().is_empty());
+    /// assert!(g.get("SPACE").is_none());
+    /// ```
+    pub fn fold_terminal_rules(&mut self, keep_originals: bool) -> Vec<Cow<'a, str>> {
+        fold_terminal_rules(self, keep_originals)
+    }
+
+    /// How the alternatives of each [`Choice`](Expr::Choice) are interpreted. Grammars are [`Unordered`](ChoiceSemantics::Unordered) unless set otherwise.
+    pub fn choice_semantics(&self) -> ChoiceSemantics {
+        self.choice_semantics
+    }
+
+    /// Sets how the alternatives of each [`Choice`](Expr::Choice) are interpreted, which changes what [`Grammar::alternative_issues`] and [`Grammar::lookahead_requirements`] report
+    /// ```rust
+    /// # use ebnf_toolkit::{ChoiceSemantics, Grammar};
+    /// let mut g = Grammar::new("op = '<' | '<=';").unwrap();
+    /// assert!(g.alternative_issues().is_empty());
+    /// g.set_choice_semantics(ChoiceSemantics::Ordered);
+    /// assert_eq!(g.alternative_issues().len(), 1);
+    /// ```
+    pub fn set_choice_semantics(&mut self, semantics: ChoiceSemantics) {
+        self.choice_semantics = semantics;
+    }
+
+    /// Turns the grammar into a [`FrozenGrammar`], which can't be changed but answers questions about how its rules refer to each other without searching through them each time
+    pub fn freeze(self) -> FrozenGrammar<'a> {
+        FrozenGrammar::new(self)
+    }
+
+    /// Turns the grammar into a [`SharedGrammar`], whose copies share the rules they haven't changed, so that they are cheap to make
+    pub fn share(self) -> SharedGrammar<'a> {
+        SharedGrammar::new(self)
+    }
+
+    /// Builds a [`GrammarIndex`] over where the grammar's rules and nodes are in its input, for looking them up by position without walking every rule each time
+    pub fn span_index(&self) -> GrammarIndex<'_, 'a> {
+        GrammarIndex::new(self)
+    }
+
+    /// Gives every node of the grammar a [`NodeId`], which can be looked up by span. See [`NodeIds`] for details.
+    pub fn node_ids(&self) -> NodeIds {
+        NodeIds::new(self)
+    }
+
+    /// The node with the id `id`, if there is one. See [`NodeId`] for how nodes are numbered. The node is found by stepping past the rules and nodes before it, so this takes time in proportion to how many there are.
+    pub fn node(&self, id: NodeId) -> Option<&Expr<'a>> {
+        node(self, id)
+    }
+
+    /// Matches each of `inputs` against the rule `start` with the recognizer of [`Rule::match_str`], and counts how many of those that match in full use each rule and take each alternative of each [`Choice`](Expr::Choice), so that [`Coverage::unused_alternatives`] shows which branches of the grammar a corpus never exercises. Only what makes up the match is counted, not alternatives tried along the way that didn't end up part of it.
+    ///
+    /// The recognizer's limits apply, so an input that only matches by backtracking, or that makes a rule refer back to itself, doesn't match. Returns `None` if there's no rule called `start`.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("value = number | bool | 'null'; number = #'-?[0-9]+'; bool = 'true' | 'false';").unwrap();
+    /// let coverage = g.coverage(["12", "true", "-3", "maybe"].into_iter(), "value").unwrap();
+    /// assert_eq!(coverage.matched, 3);
+    /// assert_eq!(coverage.unmatched, [3]);
+    /// assert_eq!(coverage.rules["number"], 2);
+    ///
+    /// let unused: Vec<_> = coverage.unused_alternatives().map(|a| g.node(a.id).unwrap().to_string()).collect();
+    /// assert_eq!(unused, ["\"false\"", "\"null\""]);
+    /// ```
+    pub fn coverage<'i>(
+        &self,
+        inputs: impl Iterator<Item = &'i str>,
+        start: &str,
+    ) -> Option<Coverage<'_>> {
+        coverage(self, inputs, start)
+    }
+
+    /// Makes a [`Generator`] of random sentences matched by the rules of this grammar
+    /// ```rust
+    /// # use ebnf_toolkit::{GenerateOptions, Grammar};
+    /// let g = Grammar::new("greeting = ('hello' | 'hi') ', ' name; name = #'[A-Z][a-z]+';").unwrap();
+    /// let mut generator = g.generator(GenerateOptions::default());
+    /// let sentence = generator.generate("greeting").unwrap();
+    /// assert!(sentence.starts_with('h') && sentence.contains(", "));
+    /// ```
+    pub fn generator(&self, options: GenerateOptions) -> Generator<'_, 'a> {
+        Generator::new(self, options)
+    }
+
+    /// Writes the grammar in the notation of [pest](https://pest.rs), with a rule for each of its rules in the order they were defined, for moving on to pest for parsing once the grammar is worked out. [Hidden](Rule::hidden) rules become silent rules, labels become pest's tags, which need its `grammar-extras` feature, and regexes are rewritten as the equivalent pest expressions. Annotations are left out.
+    ///
+    /// pest's grammars are PEGs, so every choice becomes an ordered one, trying each alternative in turn, and repetitions take as much as they can without backtracking. A grammar written with unordered choice may need [`Grammar::reorder_choices`] to mean the same thing in pest.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("pair = key '=' value; <key> = #'[a-z]+'; value = 'on' | 'off';").unwrap();
+    /// assert_eq!(g.to_pest().unwrap(), "\
+    /// pair = { key ~ \"=\" ~ value }
+    /// key = _{ (('a'..'z')+) }
+    /// value = { (\"on\" | \"off\") }
+    /// ");
+    /// ```
+    ///
+    /// # Errors
+    /// If the grammar has an import, a name pest doesn't allow, or a node with no equivalent in pest, such as a [`Hidden`](Expr::Hidden) node or a regex with a lazy repetition or word boundary. Only the first is reported.
+    pub fn to_pest(&self) -> Result<String, ExportError<'_>> {
+        to_pest(self)
+    }
+
+    /// Writes the grammar in LALRPOP's notation, as a recognizer whose rules all have the type `()`, with the first rule defined made `pub` as the one to parse. Literals and regexes are written as LALRPOP terminals, and when there's more than one regex, a `match` block gives them priority in the order they first appear, as LALRPOP refuses regexes that can match the same text otherwise. Note that LALRPOP's lexer skips whitespace between terminals.
+    ///
+    /// LALRPOP has optional parts, repetitions and groups, but not choices inside a sequence, which `options` can lower to extra rules. Ordered choices are written as unordered ones, and hidden and labeled parts as they would be without hiding or a label.
+    /// ```rust
+    /// # use ebnf_toolkit::{ExportOptions, Grammar};
+    /// let g = Grammar::new("list = '[' [#'[0-9]+' (',' #'[0-9]+')*] ']';").unwrap();
+    /// assert_eq!(g.to_lalrpop(ExportOptions::default()).unwrap(), r#"grammar;
+    ///
+    /// pub list: () = {
+    ///     "[" (r"[0-9]+" ("," r"[0-9]+")*)? "]" => (),
+    /// };
+    /// "#);
+    /// ```
+    ///
+    /// # Errors
+    /// If the grammar has an import, a lookahead, a name LALRPOP doesn't allow, or a choice inside a sequence when `options` doesn't lower it. Only the first is reported.
+    pub fn to_lalrpop(&self, options: ExportOptions) -> Result<String, ExportError<'_>> {
+        to_lalrpop(self, options)
+    }
+
+    /// Writes the grammar as the `.y` and `.l` files of grmtools' `lrpar` and `lrlex`, for a parser with no actions that starts at the first rule defined. Each distinct literal and regex becomes a token, named by the literal's text or, for a regex that makes up a whole rule, by the rule. The lexer has no rule to skip whitespace, which can be added to the end of it.
+    ///
+    /// Yacc grammars are plain BNF, so optional parts, repetitions and choices inside a sequence have to be lowered to extra rules by `options` first. Repetitions become left-recursive rules. As with [`to_lalrpop`](Self::to_lalrpop), choices are unordered and nothing is hidden or labeled.
+    /// ```rust
+    /// # use ebnf_toolkit::{ExportOptions, Grammar};
+    /// let g = Grammar::new("sum = num ('+' num)*; num = #'[0-9]+';").unwrap();
+    /// let files = g.to_grmtools(ExportOptions { lower: true }).unwrap();
+    /// assert_eq!(files.grammar, "\
+    /// %start sum
+    /// %%
+    ///
+    /// sum
+    ///     : num sum_rep1
+    ///     ;
+    ///
+    /// sum_rep1
+    ///     :
+    ///     | sum_rep1 '+' num
+    ///     ;
+    ///
+    /// num
+    ///     : 'num'
+    ///     ;
+    /// ");
+    /// assert_eq!(files.lexer, "%%\n\\+ \"+\"\n[0-9]+ \"num\"\n");
+    /// ```
+    ///
+    /// # Errors
+    /// If the grammar has an import, a lookahead, a name yacc doesn't allow, or anything besides a choice of sequences when `options` doesn't lower it. Only the first is reported.
+    pub fn to_grmtools(&self, options: ExportOptions) -> Result<GrmtoolsGrammar, ExportError<'_>> {
+        to_grmtools(self, options)
+    }
+
+    /// Writes the grammar as the skeleton of a tree-sitter `grammar.js`, with a rule for each rule of the grammar, in the order they're defined so that the first is where parsing starts. Regexes are wrapped in `token(...)`, literals are written as strings, which tree-sitter already treats as tokens, and labels become fields. Hidden rules are given the leading `_` that hides them in tree-sitter, but hidden parts of a rule are written as they are, since tree-sitter can only hide whole rules.
+    ///
+    /// Tree-sitter has no lookaheads, so these are written as `blank()` with a comment to mark them, and imports are noted in a comment. The grammar is named `grammar`, and regexes aren't translated from Rust's syntax to JavaScript's besides escaping `/`, so both usually need a look before the file is used.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("list = '[' [num (',' num)*] ']'; <num> = #'[0-9]+';").unwrap();
+    /// assert_eq!(g.to_tree_sitter(), "\
+    /// module.exports = grammar({
+    ///   name: 'grammar',
+    ///
+    ///   rules: {
+    ///     list: $ => seq('[', optional(seq($._num, repeat(seq(',', $._num)))), ']'),
+    ///     _num: $ => token(/[0-9]+/),
+    ///   },
+    /// });
+    /// ");
+    /// ```
+    #[must_use]
+    pub fn to_tree_sitter(&self) -> String {
+        to_tree_sitter(self)
+    }
+
+    /// Finds a smaller sentence derived from the rule `start` for which `fails` still returns `true`, given one that it does, such as an input from a [`Generator`] that a parser under test got wrong. Parts of the sentence are taken away by repeating things fewer times, taking the shortest way through other alternatives or rules, and replacing a rule with a smaller use of the same rule nested inside it, for as long as the sentence keeps failing. Literals and regexes are shrunk to their shortest text the same way, so every sentence tried is derived from the grammar.
+    ///
+    /// Choices are treated as unordered, and lookaheads are checked. Returns `None` if `input` isn't derived from `start`, or a regex in the grammar is invalid.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("list = '[' [item (',' item)*] ']'; item = #'[0-9]+' | list;").unwrap();
+    /// let smaller = g.shrink("list", "[1,[[22],3],[]]", |s| s.contains("[["));
+    /// assert_eq!(smaller.as_deref(), Some("[[]]"));
+    /// ```
+    pub fn shrink(
+        &self,
+        start: &str,
+        input: &str,
+        fails: impl FnMut(&str) -> bool,
+    ) -> Option<String> {
+        shrink(self, start, input, fails)
+    }
+
+    /// Reorders the alternatives of every [`Choice`](`Expr::Choice`) so that each comes before any alternative whose leading literal text is a strict prefix of its own, as an ordered choice (like a PEG's) would otherwise never try it. Alternatives that don't shadow each other this way keep their relative order.
+    ///
+    /// `keep_order` is called with the name of the rule and each `Choice` in turn, and any for which it returns `true` are left alone, for when the order is intentional. Choices nested inside the alternatives are still visited. Every alternative that was moved is returned, in the order they were moved.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("op = '<' | '<=' | '<<';").unwrap();
+    /// let moved = g.reorder_choices(|_, _| false);
+    /// assert_eq!(moved.len(), 2);
+    /// assert_eq!(g["op"].body[0].to_string(), r#"(("<=")|("<<")|("<"))"#);
+    /// ```
+    pub fn reorder_choices(
+        &mut self,
+        mut keep_order: impl FnMut(&str, &Expr<'_>) -> bool,
+    ) -> Vec<MovedAlternative<'a>> {
+        let mut moved = vec![];
+        for rule in self.rules.values_mut() {
+            reorder_rule_choices(rule, &mut keep_order, &mut moved);
+        }
+        moved
+    }
+
+    /// Removes every alternative of a [`Choice`](`Expr::Choice`) that is the same as an earlier alternative of that `Choice`, apart from its span, such as those left by merging duplicate definitions of a rule. A `Choice` left with a single alternative is replaced by it. Returns the spans of the alternatives removed, in the order they were removed.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("digit = '0' | '1'; digit = '1' | '2';").unwrap();
+    /// assert_eq!(g.remove_duplicate_alternatives().len(), 1);
+    /// assert_eq!(g["digit"].body[0].to_string(), r#"(("0")|("1")|("2"))"#);
+    /// ```
+    pub fn remove_duplicate_alternatives(&mut self) -> Vec<Span> {
+        let mut removed = vec![];
+        for rule in self.rules.values_mut() {
+            remove_rule_duplicates(rule, &mut removed);
+        }
+        removed
+    }
+
+    /// Applies the passes of `simplifier` to the body of every rule, such as to a grammar parsed with [`ParseOptions::simplify`] turned off. See the [`simplification`](crate::simplification) module for an example.
+    pub fn simplify_with(&mut self, simplifier: &Simplifier) {
+        for rule in self.rules.values_mut() {
+            rule.body
+                .iter_mut()
+                .for_each(|node| simplifier.simplify(node));
+        }
+    }
+
+    /// Replaces each [`Regex`](Expr::Regex) node that only ever matches one string, such as `#'let'`, with a [`Literal`](Expr::Literal) for that string, as done by [`Expr::regex_to_literal`]. Grammars converted from other tools often write keywords and punctuation this way. Returns the spans of the nodes replaced, in order.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("assign = name #':=' #'[0-9]+';").unwrap();
+    /// assert_eq!(g.simplify_trivial_regexes().len(), 1);
+    /// assert_eq!(g["assign"].to_string(), r#"assign = name ":=" #'[0-9]+';"#);
+    /// ```
+    pub fn simplify_trivial_regexes(&mut self) -> Vec<Span> {
+        simplify_trivial_regexes(self)
+    }
+
+    /// Rewrites every [`Repetition`](Expr::Repetition) with a separator, such as `item % ','`, in plain EBNF, as done by [`Expr::lower_separator`], for exporting to tools that don't have the notation. Repetitions nested inside one another are lowered from the inside out. Returns the spans of the repetitions replaced, in order.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("args = '(' (expr % ',')? ')';").unwrap();
+    /// assert_eq!(g.lower_separated_repetitions().len(), 1);
+    /// assert_eq!(g["args"].to_string(), r#"args = "(" [((((expr) ((((",") (expr)))*))))] ")";"#);
+    /// ```
+    pub fn lower_separated_repetitions(&mut self) -> Vec<Span> {
+        lower_separated_repetitions(self)
+    }
+
+    /// Makes [`Display`](core::fmt::Display) and the exports write the rules in order of name rather than in the order they were written, so that grammars that only differ in the order of their rules give the same text, as snapshot tests and reproducible exports need. Nothing else changes: each rule keeps its [`definitions`](Rule::definitions) and spans, and the order is kept through changes to the rules and copies made with [`Grammar::freeze`] and [`Grammar::share`].
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let mut g = Grammar::new("sum = term ('+' term)*; term = #'[0-9]+';").unwrap();
+    /// let mut swapped = Grammar::new("term = #'[0-9]+'; sum = term ('+' term)*;").unwrap();
+    /// assert_ne!(g.to_string(), swapped.to_string());
+    ///
+    /// g.sort_rules_by_name();
+    /// swapped.sort_rules_by_name();
+    /// assert_eq!(g.to_string(), swapped.to_string());
+    /// assert!(g.to_string().starts_with("sum = "));
+    /// ```
+    pub fn sort_rules_by_name(&mut self) {
+        self.name_order = true;
+    }
 }
 
 impl Grammar<'_> {
-    /// Parses a grammar - a sequence of [`Rule`]s from an input string.
+    /// Parses a grammar - a sequence of [`Rule`]s from an input string. Any [`Import`] directives between the rules are recorded but not followed.
     ///
     /// # Errors
     /// If the input string is ill-formed, an [`EbnfError`] is returned. See that type for possible reasons.
     pub fn new(input: &str) -> Result<Grammar<'_>, EbnfError<'_>> {
-        let tokens = tokenize(input)?;
-        let rules = parse_rules_from_tokens(input, &mut &tokens[..])?;
-        Ok(rules.into_iter().collect())
+        Grammar::from_fragment(input, 0..input.len(), ParseOptions::default())
+    }
+
+    /// Parses a grammar like [`Grammar::new`], but within the limits set by `options` rather than the default ones, for inputs that may have come from somewhere untrusted. See [`ParseOptions`] for an example.
+    ///
+    /// # Errors
+    /// If the input string is ill-formed, an [`EbnfError`] is returned. If it is too large, this is an [`EbnfError::LimitExceeded`].
+    pub fn new_with_options(
+        input: &str,
+        options: ParseOptions,
+    ) -> Result<Grammar<'_>, EbnfError<'_>> {
+        Grammar::from_fragment(input, 0..input.len(), options)
+    }
+
+    /// Parses the part of `document` within `range`, giving spans and errors relative to the whole document
+    pub(crate) fn from_fragment(
+        document: &str,
+        range: Range<usize>,
+        options: ParseOptions,
+    ) -> Result<Grammar<'_>, EbnfError<'_>> {
+        let end = range.end;
+        let (tokens, imports) = tokenize_fragment(document, range)?;
+        let rules = if tokens.is_empty() && !imports.is_empty() {
+            vec![]
+        } else {
+            parse_rules_from_tokens(document, end, &mut &tokens[..], options)?
+        };
+        let mut grammar: Grammar<'_> = rules.into_iter().collect();
+        grammar.imports = imports;
+        Ok(grammar)
+    }
+
+    /// Parses a grammar like [`Grammar::new`], but keeps the source alongside it so that [`LosslessGrammar::to_source`] can write it back out without rewriting the rules that haven't changed.
+    ///
+    /// # Errors
+    /// If the input string is ill-formed, an [`EbnfError`] is returned. See that type for possible reasons.
+    pub fn new_lossless(input: &str) -> Result<LosslessGrammar<'_>, EbnfError<'_>> {
+        LosslessGrammar::new(input)
+    }
+
+    /// Parses a grammar like [`Grammar::new`], but names the input `source_name` so that any error refers to it by that name rather than `<input>`.
+    ///
+    /// # Errors
+    /// If the input string is ill-formed, the [`EbnfError`] is returned along with the name. See that type for possible reasons.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let err = Grammar::new_named("A = (B;", "grammar.ebnf").unwrap_err();
+    /// assert!(err.to_string().contains("grammar.ebnf"));
+    /// ```
+    pub fn new_named<'n>(
+        input: &'n str,
+        source_name: &'n str,
+    ) -> Result<Grammar<'n>, NamedError<'n>> {
+        Grammar::new(input).map_err(|error| NamedError { source_name, error })
+    }
+
+    /// Collects the rules from every code fence tagged `ebnf` or `bnf` in a Markdown document, such as a language specification. Spans and errors refer to positions in the whole document, and a fence that fails to parse doesn't prevent the rules of the others from being used.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let doc = "# Numbers\n\n```ebnf\nnumber = digit+;\n```\n\n```ebnf\ndigit = #'[0-9]';\n```\n";
+    /// let found = Grammar::from_markdown(doc);
+    /// assert!(found.errors.is_empty());
+    /// assert_eq!(found.grammar["digit"].definitions.hull().unwrap().start_line(), (8, 0));
+    /// ```
+    pub fn from_markdown(text: &str) -> MarkdownGrammar<'_> {
+        grammar_from_markdown(text)
+    }
+
+    /// Parses the code fences of a Markdown document like [`Grammar::from_markdown`], but as one continuous grammar, so a rule can be split across fences with prose in between.
+    ///
+    /// # Errors
+    /// If the fences together are ill-formed, an [`EbnfError`] referring to the whole document is returned.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let doc = "```ebnf\nsum = number\n```\n\nfollowed by any number of terms:\n\n```ebnf\n  ('+' number)*;\n```\n";
+    /// let grammar = Grammar::from_markdown_joined(doc).unwrap();
+    /// assert_eq!(grammar["sum"].body.len(), 2);
+    /// ```
+    pub fn from_markdown_joined(text: &str) -> Result<Grammar<'_>, EbnfError<'_>> {
+        joined_grammar_from_markdown(text)
+    }
+
+    /// Reads the rules section of a Yacc or Bison grammar, between its first `%%` and the next, so that grammars written for those tools can be analysed. Each rule's `:`-separated productions become a choice between its `|` alternatives, with `;` ending the rule or, as Bison allows, the next rule's name. Names become nonterminals and quoted characters and strings become literals, while actions, `%prec` and the like are skipped, as are types, named references and comments. An empty alternative, or one marked `%empty`, becomes an [`Empty`](Expr::Empty) node.
+    ///
+    /// Every token declared with `%token`, or a precedence directive like `%left`, and used by a rule becomes a rule of its own, matching a literal of its name, or of the string it's declared as an alias for in Bison. These are defined where the token is first used, so the first rule of the rules section stays the first defined. Nothing from the declarations is kept otherwise, including `%start`.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::from_yacc(r#"
+    /// %{ #include <stdio.h> %}
+    /// %token NUM
+    /// %token POW "**"
+    /// %left '+'
+    /// %%
+    /// expr: expr '+' expr { $$ = $1 + $3; }
+    ///     | expr POW expr
+    ///     | NUM
+    ///     ;
+    /// %%
+    /// int main(void) { return yyparse(); }
+    /// "#).unwrap();
+    /// assert_eq!(g.rules().map(|r| r.name.as_ref()).collect::<Vec<_>>(), ["NUM", "POW", "expr"]);
+    /// assert_eq!(g["POW"].body[0].to_string(), "\"**\"");
+    /// ```
+    ///
+    /// # Errors
+    /// If the input has no `%%` before the rules, or something in it isn't closed or is out of place. See [`YaccError`] for each case.
+    pub fn from_yacc(input: &str) -> Result<Grammar<'_>, YaccError> {
+        from_yacc(input)
+    }
+
+    /// Translates a pest grammar, so that it can be checked against a grammar written in EBNF. Sequences joined with `~` become sequences, `|` becomes an ordered choice, `&` and `!` become [`And`](Expr::And) and [`Not`](Expr::Not) lookaheads, and tags like `#name = ...` become [`Labeled`](Expr::Labeled) nodes. A silent rule, marked with `_`, is [hidden](Rule::hidden), while the other modifiers are left out.
+    ///
+    /// Character ranges and pest's built-in rules for classes of character, like `ASCII_DIGIT`, become regexes, as do case-insensitive strings, and `EOI` becomes a lookahead that nothing follows. `SOI` matches nothing, as parsing always starts at the start of the input. Repetitions with bounds, like `{2, 4}`, are written out as copies of their body, the ones above the minimum being optional. pest inserts `WHITESPACE` and `COMMENT` between the parts of non-atomic rules, but these aren't added, so they keep to the rules they're written in.
+    ///
+    /// The result is a [`StructuredGrammar`], as escapes and ranges in a pest grammar give text that isn't written in it, for a [`Grammar`] to borrow. [`Grammar::from_structured`] then gives the grammar, with [ordered](ChoiceSemantics::Ordered) choice.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let structured = Grammar::from_pest(r#"
+    /// // A comma-separated list
+    /// list = { SOI ~ "[" ~ (item ~ ("," ~ item)*)? ~ "]" ~ EOI }
+    /// item = _{ !"-" ~ 'a'..'z'+ | ASCII_DIGIT{1, 3} }
+    /// "#).unwrap();
+    /// let g = Grammar::from_structured(&structured);
+    /// let ebnf = Grammar::new(r#"
+    /// list = '' '[' [item (',' item)*] ']' !#'(?s).';
+    /// <item> = (!'-' (#'[a-z]'+)) / (#'[0-9]' [#'[0-9]'] [#'[0-9]']);
+    /// "#).unwrap();
+    /// assert!(g.eq_ignoring_spans(&ebnf));
+    /// ```
+    ///
+    /// # Errors
+    /// If the input isn't a valid pest grammar, or uses pest's stack, which has no equivalent. See [`PestError`] for each case.
+    pub fn from_pest(input: &str) -> Result<StructuredGrammar, PestError> {
+        from_pest(input)
+    }
+
+    /// Compares two versions of a grammar's source token by token to find which rules differ between them, without parsing either. Changes to whitespace, comments or the order of the rules are ignored, so the rules that come back are the only ones that need parsing or checking again.
+    ///
+    /// # Errors
+    /// If either source can't be tokenized, the [`LexError`](EbnfError::LexError) for the first of them that fails is returned.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let old = "list = item (',' item)*;\nitem = #'[0-9]+';";
+    /// let new = "item = #'[0-9]+' | list;\nlist = item (',' item)*;";
+    /// let diff = Grammar::diff_sources(old, new).unwrap();
+    /// assert_eq!(diff.rules.len(), 1);
+    /// assert_eq!(diff.rules[0].name, "item");
+    /// assert_eq!(&new[diff.rules[0].new.hull().unwrap().range()], "item = #'[0-9]+' | list;");
+    /// ```
+    pub fn diff_sources<'a>(old: &'a str, new: &'a str) -> Result<SourceDiff<'a>, EbnfError<'a>> {
+        diff_sources(old, new)
+    }
+
+    /// Checks whether `input` is a well-formed grammar without building its syntax tree, which is faster and uses less memory than [`Grammar::new`] on large inputs.
+    ///
+    /// # Errors
+    /// If the input string is ill-formed, the same [`EbnfError`] as [`Grammar::new`] would give is returned. Producing the error involves parsing the input again to build the full report.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// assert!(Grammar::check_syntax("A = B | 'c'; B = #'[a-z]';").is_ok());
+    /// assert!(Grammar::check_syntax("A = (B;").is_err());
+    /// ```
+    pub fn check_syntax(input: &str) -> Result<(), EbnfError<'_>> {
+        let (tokens, imports) = tokenize_with_imports(input)?;
+        let options = ParseOptions::default();
+        check_token_limits(input, &tokens, options)?;
+        if (tokens.is_empty() && !imports.is_empty()) || recognize_rules(&tokens) {
+            Ok(())
+        } else {
+            parse_rules_from_tokens(input, input.len(), &mut &tokens[..], options).map(|_| ())
+        }
+    }
+
+    /// The `%import` directives in the input this grammar was parsed from, in the order they appeared
+    pub fn imports(&self) -> &[Import<'_>] {
+        &self.imports
     }
 
     /// Gets the rule by a given name. The [`Index`] trait is also available to instead panic if the name is not found
@@ -88,6 +1142,34 @@ impl Grammar<'_> {
         self.rules.get(name)
     }
 
+    /// Iterates through the rules in order of name
+    pub fn rules(&self) -> impl Iterator<Item = &Rule<'_>> {
+        self.rules.values()
+    }
+
+    /// Checks the name of each rule against `convention`, with rules told apart into lexical and syntactic ones as by [`Grammar::classify_rules`]. Each rule whose name is written differently is returned, in order of name, with a suggested replacement that can be passed on to [`Grammar::rename_all`].
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, NamingConvention};
+    /// let g = Grammar::new("exprList = digit (',' exprList)?; digit = #'[0-9]';").unwrap();
+    /// let issues = g.check_naming(NamingConvention::default());
+    /// let suggestions: Vec<_> = issues.iter().map(|i| (i.rule, &*i.suggestion)).collect();
+    /// assert_eq!(suggestions, [("digit", "DIGIT"), ("exprList", "expr_list")]);
+    /// ```
+    pub fn check_naming(&self, convention: NamingConvention) -> Vec<NamingIssue<'_>> {
+        check_naming(self, convention)
+    }
+
+    /// Runs the lints chosen by `config` over the grammar, returning what they find in order of where it is in the input. See the [`lint`](crate::lint) module for the built-in lints and how to add others.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, lint::LintConfig};
+    /// let g = Grammar::new("list = item (',' item)*; item = #'[a-z]+'; spare = item;").unwrap();
+    /// let found = g.lint(&LintConfig::default());
+    /// assert_eq!(found[0].to_string(), "warning[unused-rule] in `spare` at [1:43..1:56]: Rule `spare` is never used");
+    /// ```
+    pub fn lint(&self, config: &LintConfig) -> Vec<Diagnostic<'_>> {
+        lint(self, config)
+    }
+
     /// Tests if any of the rules contain a nonterminal name that does not have a corresponding entry in this `Grammar`.
     /// If one exists, returns the name of the rule containing the nonterminal, and the name of the missing rule itself. Else returns `None`.
     /// ```rust
@@ -100,6 +1182,8 @@ impl Grammar<'_> {
     /// let g2 = Grammar::new(recurse).unwrap();
     /// assert_eq!(g2.first_dangling_reference(), None);
     /// ```
+    ///
+    /// [`Grammar::suggest_rule_name`] can then find what the missing name may have been a typo for.
     pub fn first_dangling_reference(&self) -> Option<(&str, &str)> {
         for rule in self.rules.values() {
             let refers = rule.nonterminals();
@@ -111,6 +1195,265 @@ impl Grammar<'_> {
         }
         None
     }
+
+    /// Finds the rule whose name is closest to `name` by edit distance, as a suggestion for what an undefined name was meant to be. Only names within a few characters of `name` are considered, so this returns `None` if nothing is a plausible typo, or if `name` itself is defined.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("message = clinet_prefix command; client_prefix = ':'; command = 'PING';").unwrap();
+    /// let (_, missing) = g.first_dangling_reference().unwrap();
+    /// assert_eq!(g.suggest_rule_name(missing), Some("client_prefix"));
+    /// assert_eq!(g.suggest_rule_name("prefix"), None);
+    /// ```
+    pub fn suggest_rule_name(&self, name: &str) -> Option<&str> {
+        if self.rules.contains_key(name) {
+            return None;
+        }
+        closest_name(name, self.rules.keys().map(|k| &**k))
+    }
+
+    /// Finds how the rule `from` can reach `to` by referring to other rules, as a chain of rule names beginning with `from` and ending with `to` in which each rule refers to the next. The chain found is one of the shortest, and is only `[from]` if `to` is `from` itself.
+    ///
+    /// Returns `None` if `from` isn't defined, or no chain exists. `to` need not be defined, so this can also show where an undefined name is used from.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("stmt = 'print' expr | block; block = '{' stmt* '}'; expr = atom+; atom = #'[0-9]+';").unwrap();
+    /// assert_eq!(g.derives("block", "atom"), Some(vec!["block", "stmt", "expr", "atom"]));
+    /// assert_eq!(g.derives("expr", "stmt"), None);
+    /// ```
+    pub fn derives(&self, from: &str, to: &str) -> Option<Vec<&str>> {
+        derives(self, from, to)
+    }
+
+    /// Builds a grammar of only the rule `start` and the rules it refers to, directly or through each other, such as to test the part of a large language specification that describes one construct on its own. The rules are copied unchanged, along with the imports and [`ChoiceSemantics`] of this grammar. Names that aren't defined here are left dangling, and the grammar is empty if `start` isn't defined.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("stmt = 'print' expr | 'pass'; expr = atom ('+' atom)*; atom = #'[0-9]+'; comment = '#' #'.*';").unwrap();
+    /// let expr = g.slice("expr");
+    /// assert_eq!(expr.rules().map(|r| &*r.name).collect::<Vec<_>>(), ["atom", "expr"]);
+    /// ```
+    #[must_use]
+    pub fn slice(&self, start: &str) -> Self {
+        slice(self, &[start])
+    }
+
+    /// Builds a grammar of the rules in `names` and every rule they refer to, as [`Grammar::slice`] does for a single rule, such as to publish the part of a specification describing one feature on its own. Names that aren't defined are skipped.
+    ///
+    /// Alongside the grammar, each reference within it to a rule that isn't defined, in this grammar or the new one, is returned as a [`ValidationError::DanglingReference`], suggesting a rule defined here that it may have been a typo for.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, ValidationError};
+    /// let g = Grammar::new("stmt = assign | call; assign = name '=' expr; call = name '(' expr ')'; expr = name | lit; name = #'[a-z]+';").unwrap();
+    /// let (calls, dangling) = g.extract(&["call"]);
+    /// assert_eq!(calls.rules().map(|r| &*r.name).collect::<Vec<_>>(), ["call", "expr", "name"]);
+    /// assert!(matches!(&dangling[..], [ValidationError::DanglingReference { name: "lit", .. }]));
+    /// ```
+    pub fn extract(&self, names: &[&str]) -> (Self, Vec<ValidationError<'_>>) {
+        extract(self, names)
+    }
+
+    /// Counts the distinct ways the rule `start` can be derived while expanding rules at most `max_depth` levels deep, counting `start` itself as the first level, to gauge how quickly a grammar's sentences multiply before [generating](Grammar::generator) test inputs from it. Each alternative of a [`Choice`](Expr::Choice) is a separate way, as is whether an [`Optional`](Expr::Optional) is present, and a [`Repetition`](Expr::Repetition) is counted as repeating at most `max_depth` times. Literals, regexes and undefined rules count as a single token each.
+    ///
+    /// The count saturates at [`u128::MAX`] rather than overflowing. Returns `None` if `start` isn't defined.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("expr = num | ('(' expr op expr ')'); op = '+' | '*'; num = '0' | '1';").unwrap();
+    /// assert_eq!(g.count_derivations("expr", 2), Some(2));
+    /// assert_eq!(g.count_derivations("expr", 3), Some(2 + 2 * 2 * 2));
+    /// ```
+    pub fn count_derivations(&self, start: &str, max_depth: usize) -> Option<u128> {
+        count_derivations(self, start, max_depth)
+    }
+
+    /// The length in bytes of the shortest and longest inputs the rule `start` matches, for sizing input buffers and for sanity checks, as a rule for a line ending that can match nothing is likely a mistake. Regexes contribute the lengths of the shortest and longest strings they match, and lookaheads nothing. A reference to an undefined rule, or an invalid regex, is taken to match anything, so leaves no upper bound.
+    ///
+    /// Returns `None` if `start` isn't defined, or if it can't match anything at all, as when it refers to itself without a way out.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, LengthBounds};
+    /// let g = Grammar::new("crlf = '\\r'? '\\n'?; date = #'[0-9]{4}' '-' #'[0-9]{2}'; list = date % ',';").unwrap();
+    /// assert_eq!(g.length_bounds("crlf"), Some(LengthBounds { min: 0, max: Some(4) }));
+    /// assert_eq!(g.length_bounds("date"), Some(LengthBounds { min: 7, max: Some(7) }));
+    /// assert_eq!(g.length_bounds("list"), Some(LengthBounds { min: 7, max: None }));
+    /// ```
+    pub fn length_bounds(&self, start: &str) -> Option<LengthBounds> {
+        length_bounds(self, start)
+    }
+
+    /// Finds every set of rules that refer to each other in a loop, whether a rule refers to itself directly, as checked by [`Rule::is_recursive`], or through other rules. Cycles are in order of their first rule's name. See [`RecursionCycle`] for what each contains.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("value = list | #'[0-9]+'; list = '[' value (',' value)* ']'; id = id '.' #'[a-z]+' | #'[a-z]+';").unwrap();
+    /// let cycles: Vec<_> = g.recursion_cycles().into_iter().map(|c| c.rules).collect();
+    /// assert_eq!(cycles, [vec!["id"], vec!["list", "value"]]);
+    /// ```
+    pub fn recursion_cycles(&self) -> Vec<RecursionCycle<'_>> {
+        recursion_cycles(self)
+    }
+
+    /// Sorts the rules so that each comes after the rules it refers to, for writing out to tools that need rules to be declared before they are used. Where the references allow more than one order, rules are kept in the order they were first defined.
+    ///
+    /// Rules in a [`RecursionCycle`] can't all come before each other, so each cycle is kept together, in the order it was defined, and returned alongside the order. The order is complete when there are no cycles.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("sum = num ('+' num)*; num = digit+; digit = #'[0-9]';").unwrap();
+    /// let (order, cycles) = g.topological_order();
+    /// let names: Vec<_> = order.iter().map(|rule| &*rule.name).collect();
+    /// assert_eq!(names, ["digit", "num", "sum"]);
+    /// assert!(cycles.is_empty());
+    /// ```
+    pub fn topological_order(&self) -> (Vec<&Rule<'_>>, Vec<RecursionCycle<'_>>) {
+        topological_order(self)
+    }
+
+    /// Numbers the rules of the grammar by small integers, in order of name. See [`RuleIds`] for details.
+    pub fn rule_ids(&self) -> RuleIds<'_> {
+        RuleIds::new(self)
+    }
+
+    /// Sorts the rules into those a lexer would match as tokens and those a parser would build from them, in order of rule name. See [`RuleClass`] for how they are told apart.
+    /// ```rust
+    /// # use ebnf_toolkit::{Grammar, RuleClass};
+    /// let g = Grammar::new("expr = number | '(' expr ')'; number = digit+; digit = #'[0-9]';").unwrap();
+    /// assert_eq!(
+    ///     g.classify_rules(),
+    ///     [("digit", RuleClass::Lexical), ("expr", RuleClass::Syntactic), ("number", RuleClass::Lexical)]
+    /// );
+    /// ```
+    pub fn classify_rules(&self) -> Vec<(&str, RuleClass)> {
+        classify_rules(self)
+    }
+
+    /// The rules that [`Grammar::classify_rules`] finds are [lexical](RuleClass::Lexical), in order of name
+    pub fn lexical_rules(&self) -> Vec<&Rule<'_>> {
+        self.rules_of_class(RuleClass::Lexical)
+    }
+
+    /// The rules that [`Grammar::classify_rules`] finds are [syntactic](RuleClass::Syntactic), in order of name
+    pub fn syntactic_rules(&self) -> Vec<&Rule<'_>> {
+        self.rules_of_class(RuleClass::Syntactic)
+    }
+
+    fn rules_of_class(&self, wanted: RuleClass) -> Vec<&Rule<'_>> {
+        classify_rules(self)
+            .into_iter()
+            .filter(|(_, class)| *class == wanted)
+            .map(|(name, _)| &self.rules[name])
+            .collect()
+    }
+
+    /// Finds the terminals each rule can begin with, in order of rule name. See [`FirstSet`] for what is included.
+    pub fn first_sets(&self) -> Vec<FirstSet<'_>> {
+        first_sets(self)
+    }
+
+    /// Estimates how many terminals of lookahead a parser needs to choose between the alternatives of each [`Choice`](`Expr::Choice`), looking no further than `max` terminals. If the grammar has [ordered](ChoiceSemantics::Ordered) choice, the alternatives before each one have already been ruled out, so it only needs telling apart from those after it. The results are ordered by where the alternatives appear - see [`Lookahead`] for how they are found.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("stmt = ('let' name) | ('let' 'mut' name) | expr;").unwrap();
+    /// let needed: Vec<_> = g.lookahead_requirements(3).iter().map(|l| l.terminals).collect();
+    /// assert_eq!(needed, [Some(2), Some(2), Some(1)]);
+    /// ```
+    pub fn lookahead_requirements(&self, max: usize) -> Vec<Lookahead<'_>> {
+        lookahead_requirements(self, max)
+    }
+
+    /// Finds characters in the grammar's literals, regexes and names that can't be seen or are easily mistaken for others, ordered by where they appear. See [`SuspiciousCharacter`] for what is reported.
+    pub fn suspicious_characters(&self) -> Vec<SuspiciousCharacter<'_>> {
+        let mut found: Vec<_> = self
+            .rules
+            .values()
+            .flat_map(rule_suspicious_characters)
+            .collect();
+        found.sort_by_key(|s| s.span);
+        found
+    }
+
+    /// Finds alternatives of a [`Choice`](`Expr::Choice`) that an earlier alternative of the same `Choice` makes redundant, either by being the same or, if the grammar has [ordered](ChoiceSemantics::Ordered) choice, by shadowing it. These are ordered by where the later alternatives appear. See [`AlternativeIssueKind`](crate::AlternativeIssueKind) for each case.
+    /// ```rust
+    /// # use ebnf_toolkit::{AlternativeIssueKind, ChoiceSemantics, Grammar};
+    /// let mut g = Grammar::new("op = '<' | '<=' | '<';").unwrap();
+    /// g.set_choice_semantics(ChoiceSemantics::Ordered);
+    /// let issues = g.alternative_issues();
+    /// assert_eq!(issues[0].kind, AlternativeIssueKind::Prefix);
+    /// assert_eq!(issues[1].kind, AlternativeIssueKind::Duplicate);
+    /// ```
+    pub fn alternative_issues(&self) -> Vec<AlternativeIssue<'_>> {
+        let mut issues: Vec<_> = self
+            .rules
+            .values()
+            .flat_map(|rule| rule_alternative_issues(rule, self.choice_semantics))
+            .collect();
+        issues.sort_by_key(|i| i.later.span());
+        issues
+    }
+
+    /// Finds the rules that write some [`Choice`s](`Expr::Choice`) with `/` and others with `|`, ordered by name. See [`MixedChoice`](crate::MixedChoice).
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("A = 'a' / ('b' | 'c'); B = 'b' / 'c';").unwrap();
+    /// let mixed = g.mixed_choices();
+    /// assert_eq!(mixed.len(), 1);
+    /// assert_eq!(mixed[0].rule, "A");
+    /// assert_eq!(mixed[0].unordered.to_string(), r#"(("b")|("c"))"#);
+    /// ```
+    pub fn mixed_choices(&self) -> Vec<MixedChoice<'_>> {
+        self.rules.values().filter_map(rule_mixed_choice).collect()
+    }
+
+    /// Finds pairs of regex alternatives within the same [`Choice`](`Expr::Choice`) that can match the same input, ordered by where they appear. See [`RegexOverlap`] for how this is determined, and [`Grammar::check_regex_overlap`] for also comparing alternatives that only begin with a regex.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("word = #'[a-z]+' | #'[a-f0-9]+';").unwrap();
+    /// let overlaps = g.regex_overlaps();
+    /// assert_eq!(overlaps[0].examples, ["a", "f", "aa"]);
+    /// ```
+    pub fn regex_overlaps(&self) -> Vec<RegexOverlap<'_>> {
+        let mut overlaps: Vec<_> = self.rules.values().flat_map(rule_regex_overlaps).collect();
+        overlaps.sort_by_key(|o| (o.first.span(), o.second.span()));
+        overlaps
+    }
+
+    /// Checks the alternatives of each [`Choice`](`Expr::Choice`) for pairs whose leading terminals can match the same input, where at least one is a regex, ordered by where they appear. This extends [`Grammar::regex_overlaps`] to alternatives that only begin with a regex, and to regexes that can match a literal, which a FIRST-set comparison treats as different terminals. See [`TerminalOverlap`] for how this is determined.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let src = "stmt = ('if' expr) | (#'[a-z]+' '=' expr);";
+    /// let g = Grammar::new(src).unwrap();
+    /// assert!(g.regex_overlaps().is_empty());
+    /// let overlaps = g.check_regex_overlap();
+    /// assert_eq!(&src[overlaps[0].first.span().range()], "'if'");
+    /// assert_eq!(&src[overlaps[0].second.span().range()], "#'[a-z]+'");
+    /// assert_eq!(overlaps[0].examples, ["if"]);
+    /// ```
+    pub fn check_regex_overlap(&self) -> Vec<TerminalOverlap<'_>> {
+        let mut overlaps: Vec<_> = self
+            .rules
+            .values()
+            .flat_map(rule_terminal_overlaps)
+            .collect();
+        overlaps.sort_by_key(|o| (o.first.span(), o.second.span()));
+        overlaps
+    }
+
+    /// Finds pairs of lexical rules that can match the same input, which become lexer conflicts when the grammar is exported to a parser generator that makes each of them a token, ordered by the names of the rules. See [`LexicalConflict`] for which rules are compared and how.
+    /// ```rust
+    /// # use ebnf_toolkit::Grammar;
+    /// let g = Grammar::new("stmt = (IF expr) | (ident '=' expr); expr = ident | ('(' expr ')'); IF = 'if'; ident = #'[a-z]+';").unwrap();
+    /// let conflicts = g.lexical_conflicts();
+    /// assert_eq!((&*conflicts[0].first.name, &*conflicts[0].second.name), ("IF", "ident"));
+    /// assert_eq!(conflicts[0].examples, ["if"]);
+    /// ```
+    pub fn lexical_conflicts(&self) -> Vec<LexicalConflict<'_>> {
+        lexical_conflicts(self)
+    }
+
+    /// Finds the literals and regexes that are larger than `limits` allows, ordered by where they appear, so that they can be reported as warnings. Inputs with terminals too large to accept at all are better rejected while parsing, with [`ParseOptions::max_literal`] and [`ParseOptions::max_regex`], before any time is spent on them.
+    pub fn oversized_terminals(&self, limits: TerminalLimits) -> Vec<OversizedTerminal<'_>> {
+        let mut found: Vec<_> = self
+            .rules
+            .values()
+            .flat_map(|rule| rule_oversized_terminals(rule, limits))
+            .collect();
+        found.sort_by_key(|o| o.span);
+        found
+    }
 }
 
 impl<'a> Index<&str> for Grammar<'a> {
@@ -120,15 +1463,64 @@ impl<'a> Index<&str> for Grammar<'a> {
     }
 }
 
+impl Display for Rule<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for annotation in &self.annotations {
+            write!(f, "@{} ", annotation.name)?;
+        }
+        if self.hidden {
+            write!(f, "<{}> =", self.name)?;
+        } else {
+            write!(f, "{} =", self.name)?;
+        }
+        for child in &self.body {
+            write!(f, " {child}")?;
+        }
+        write!(f, ";")
+    }
+}
+
+/// Writes the imports, then each rule on a line of its own in the order they were first defined. Rules that weren't parsed from any input come last, in order of name.
+/// ```rust
+/// # use ebnf_toolkit::Grammar;
+/// let g = Grammar::new("%import 'lex';\nsum = term ('+' term)*;\n@token term = #'[0-9]+';").unwrap();
+/// let text = g.to_string();
+/// assert_eq!(text.lines().next(), Some(r#"%import "lex";"#));
+/// assert!(Grammar::new(&text).unwrap().eq_ignoring_spans(&g));
+/// ```
+impl Display for Grammar<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for import in &self.imports {
+            let quote = if import.path.contains('"') { '\'' } else { '"' };
+            writeln!(f, "%import {quote}{}{quote};", import.path)?;
+        }
+        for rule in self.rules_in_definition_order() {
+            writeln!(f, "{rule}")?;
+        }
+        Ok(())
+    }
+}
+
 impl<'a> FromIterator<Rule<'a>> for Grammar<'a> {
     fn from_iter<T: IntoIterator<Item = Rule<'a>>>(iter: T) -> Self {
-        let mut rules: HashMap<Cow<'a, str>, Rule<'a>> = HashMap::new();
+        let mut rules: BTreeMap<Cow<'a, str>, Rule<'a>> = BTreeMap::new();
         for new_rule in iter {
             if let Some(old_rule) = rules.remove(&new_rule.name) {
                 let new_body = merge_duplicate_rule(old_rule.body, new_rule.body);
+                let mut definitions = old_rule.definitions;
+                definitions.extend(new_rule.definitions.iter());
+                let mut annotations = old_rule.annotations;
+                for annotation in new_rule.annotations {
+                    if !annotations.iter().any(|a| a.name == annotation.name) {
+                        annotations.push(annotation);
+                    }
+                }
                 let combined_rule = Rule {
                     name: old_rule.name,
                     body: new_body,
+                    definitions,
+                    hidden: old_rule.hidden || new_rule.hidden,
+                    annotations,
                 };
                 rules.insert(combined_rule.name.clone(), combined_rule);
             } else {
@@ -136,14 +1528,19 @@ impl<'a> FromIterator<Rule<'a>> for Grammar<'a> {
             }
         }
 
-        Grammar { rules }
+        Grammar {
+            rules,
+            imports: vec![],
+            choice_semantics: ChoiceSemantics::default(),
+            name_order: false,
+        }
     }
 }
 
 fn merge_duplicate_rule<'a>(old_rule: Vec<Expr<'a>>, new_rule: Vec<Expr<'a>>) -> Vec<Expr<'a>> {
     fn unwrap_choice_items(mut e: Vec<Expr<'_>>) -> Vec<Expr<'_>> {
         if e.len() == 1
-            && let Some(Expr::Choice { .. }) = e.first()
+            && let Some(Expr::Choice { ordered: false, .. }) = e.first()
         {
             let Some(Expr::Choice { body, .. }) = e.pop() else {
                 unreachable!()
@@ -159,14 +1556,20 @@ fn merge_duplicate_rule<'a>(old_rule: Vec<Expr<'a>>, new_rule: Vec<Expr<'a>>) ->
     let body: Vec<_> = old_body.into_iter().chain(new_body).collect();
     let span = Span::union(body.iter());
 
-    vec![Expr::Choice { span, body }]
+    vec![Expr::Choice {
+        span,
+        body,
+        ordered: false,
+    }]
 }
 
 #[cfg(test)]
 mod test {
     use std::borrow::Cow;
 
-    use crate::{Expr, Grammar, Rule, token_data::DUMMY_SPAN};
+    use crate::{
+        Expr, Grammar, Rule, SpanSet, ValidationError, ValidationLevel, token_data::DUMMY_SPAN,
+    };
     use display_tree::AsTree;
 
     #[test]
@@ -186,6 +1589,9 @@ mod test {
         let nonterms = Rule {
             body,
             name: Cow::Borrowed(""),
+            definitions: SpanSet::default(),
+            hidden: false,
+            annotations: vec![],
         }
         .nonterminals();
         insta::assert_compact_debug_snapshot!(nonterms, @r#"["C", "A", "B"]"#);
@@ -198,6 +1604,33 @@ mod test {
         insta::assert_compact_debug_snapshot!(nonterms, @r#"["A", "B"]"#);
     }
 
+    #[test]
+    fn from_rules_validation() {
+        let rules = || [Rule::new("A = B;").unwrap(), Rule::new("A = C;").unwrap()];
+
+        let g = Grammar::from_rules(rules(), ValidationLevel::Permissive).unwrap();
+        let merged: Grammar<'_> = rules().into_iter().collect();
+        assert_eq!(g, merged);
+
+        let errors = Grammar::from_rules(rules(), ValidationLevel::NoDuplicates).unwrap_err();
+        let [ValidationError::DuplicateRule { name, definitions }] = &errors[..] else {
+            panic!("{errors:?}")
+        };
+        assert_eq!(name, "A");
+        assert_eq!(definitions.len(), 2);
+
+        let errors = Grammar::from_rules(rules(), ValidationLevel::Strict).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(
+            errors[1],
+            ValidationError::DanglingReference { name: "B", .. }
+        ));
+        assert!(matches!(
+            errors[2],
+            ValidationError::DanglingReference { name: "C", .. }
+        ));
+    }
+
     #[test]
     fn duplicate_names() {
         let src = "A = B; A = C; B = A|B; B = C; C = A; C = B|C;  D = C|D; D = A|B;";
@@ -246,5 +1679,79 @@ mod test {
                3: Nonterminal [1:62..1:63]
                   └─ B
         ");
+
+        let definitions: Vec<_> = g.rules["D"].definitions.iter().map(Some).collect();
+        assert_eq!(
+            g.rules["D"].alternative_origins(),
+            [
+                definitions[0],
+                definitions[0],
+                definitions[1],
+                definitions[1]
+            ]
+        );
+        let mut stripped = g.rules["D"].clone();
+        stripped.strip_spans();
+        assert_eq!(stripped.alternative_origins(), [None; 4]);
+    }
+
+    #[test]
+    fn parse_prefix() {
+        let mut rest = "@token a = 'x;';\n%% directive\nb = a+; c = 'y';";
+        let mut names = vec![];
+        while let Ok((rule, after)) = Rule::parse_prefix(rest) {
+            names.push(rule.name.to_string());
+            rest = after.trim_start();
+            if let Some(line) = rest.strip_prefix("%%") {
+                rest = &line[line.find('\n').unwrap()..];
+            }
+        }
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(rest, "");
+
+        let (rule, _) = Rule::parse_prefix("@token a = 'x';").unwrap();
+        assert_eq!(rule.annotations[0].name, "token");
+        assert_eq!(rule.definitions.iter().next().unwrap().range(), 7..15);
+        assert!(Rule::parse_prefix("a = 'x' %% b;").is_err());
+    }
+
+    #[test]
+    fn sort_rules_by_name() {
+        let mut a = Grammar::new(
+            "list = item (',' item)*; item = word | number; word = #'[a-z]+'; number = #'[0-9]+';",
+        )
+        .unwrap();
+        let mut b = Grammar::new(
+            "number = #'[0-9]+'; item = word | number; word = #'[a-z]+'; list = item (',' item)*;",
+        )
+        .unwrap();
+        assert_ne!(a.to_pest().unwrap(), b.to_pest().unwrap());
+
+        a.sort_rules_by_name();
+        b.sort_rules_by_name();
+        assert_eq!(a.to_string(), b.to_string());
+        assert_eq!(a.to_pest().unwrap(), b.to_pest().unwrap());
+        assert_eq!(a.to_tree_sitter(), b.to_tree_sitter());
+        let names: Vec<_> = a
+            .to_string()
+            .lines()
+            .map(|l| l.split(' ').next().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["item", "list", "number", "word"]);
+
+        assert!(a["item"].span().is_some());
+        assert!(a["item"].alternative_origins().iter().all(Option::is_some));
+        let src = "list = item (',' item)*; item = #'[a-z]+';";
+        let mut g = Grammar::new(src).unwrap();
+        g.sort_rules_by_name();
+        let edits = g.clone().inline_rule("item").unwrap();
+        assert!(
+            edits
+                .iter()
+                .any(|(span, text)| span.start() == 25 && text.is_empty())
+        );
+        assert_eq!(g.clone().freeze().thaw(), g);
+        assert_eq!(g.clone().share().unshare(), g);
+        assert_eq!(Grammar::from_bytes(&g.to_bytes()), Ok(g));
     }
 }
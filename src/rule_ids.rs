@@ -0,0 +1,61 @@
+use alloc::vec::Vec;
+
+use crate::{Grammar, RuleId};
+
+/// A numbering of the rules of a [`Grammar`] by small integers, from [`Grammar::rule_ids`], for keying tables such as a packrat parser's memo by rule without hashing names.
+///
+/// Ids run from `0` up to the number of rules, following the order of the rules' names, so the same set of rules is always numbered the same way regardless of the order they were defined in. Adding or removing a rule will renumber those whose names sort after it.
+/// ```rust
+/// # use ebnf_toolkit::Grammar;
+/// let g = Grammar::new("expr = term ('+' term)*; term = atom+; atom = #'[0-9]+';").unwrap();
+/// let ids = g.rule_ids();
+/// let term = ids.id("term").unwrap();
+/// assert_eq!(term.index(), 2);
+/// assert_eq!(ids.name(term), Some("term"));
+/// assert_eq!(ids.id("missing"), None);
+///
+/// let mut memo = vec![None::<usize>; ids.len()];
+/// memo[ids.id("atom").unwrap().index()] = Some(0);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RuleIds<'a> {
+    names: Vec<&'a str>,
+}
+
+impl<'a> RuleIds<'a> {
+    pub(crate) fn new(grammar: &'a Grammar<'_>) -> Self {
+        // The rules are already stored in order of name
+        RuleIds {
+            names: grammar.rules.keys().map(|name| &**name).collect(),
+        }
+    }
+
+    /// The id of the rule with the given name, if there is one
+    pub fn id(&self, name: &str) -> Option<RuleId> {
+        self.names.binary_search(&name).ok().map(RuleId)
+    }
+
+    /// The name of the rule with the given id, if there is one
+    pub fn name(&self, id: RuleId) -> Option<&'a str> {
+        self.names.get(id.0).copied()
+    }
+
+    /// The number of rules, which is one more than the largest id
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether there are no rules to number
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Iterates through the id and name of every rule, in order of id
+    pub fn iter(&self) -> impl Iterator<Item = (RuleId, &'a str)> {
+        self.names
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(id, name)| (RuleId(id), name))
+    }
+}
@@ -0,0 +1,606 @@
+use alloc::{borrow::Cow, string::String, vec::Vec};
+use core::fmt::{Display, Write};
+
+use crate::{Annotation, Expr, Rule, Span, SpanSet};
+
+/// The ways [`Expr::from_sexpr`] and [`Rule::from_sexpr`] can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SexprError<'a> {
+    /// Something other than what was expected was found at `span`, such as a `)` where a node should be, or text left over after the end
+    Unexpected {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What should have been there instead, such as `"a node"`
+        expected: &'static str,
+    },
+    /// A list starts with `head`, which isn't the name of any kind of node
+    UnknownForm {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        #[expect(missing_docs, reason = "Obvious")]
+        head: &'a str,
+    },
+    /// A string starting at `span` is never closed
+    UnterminatedString {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+    },
+}
+
+impl Display for SexprError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SexprError::Unexpected { span, expected } => write!(f, "Expected {expected} at {span}"),
+            SexprError::UnknownForm { span, head } => {
+                write!(f, "`{head}` at {span} is not a kind of node")
+            }
+            SexprError::UnterminatedString { span } => {
+                write!(f, "The string at {span} is never closed")
+            }
+        }
+    }
+}
+
+impl<'a> Expr<'a> {
+    /// Writes the node as a Lisp-style S-expression, which shows its structure without any of the precedence or shorthand of EBNF, for golden tests and for scripts that analyse grammars. Literals are written as strings and nonterminals as symbols, and every other node as a list headed by its kind: `regex`, `empty`, `choice` or `ordered-choice`, `optional`, `zero-or-more` or `one-or-more`, with `-separated` after it if the last child is a [separator](Expr::repetition_parts), `group`, `and`, `not`, `hidden` and `label`, followed by the label.
+    ///
+    /// Strings are written as they are, without escapes. One that contains a `"` is written with enough `#` marks around its quotes that it can't be closed early, as in Rust's raw strings, and a name that isn't made of word characters is written as a string in a `ref` list. Spans are left out. [`Expr::from_sexpr`] reads the S-expression back.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let expr = Expr::new("(item (',' item)* [#'\"[a-z]+\"'])").unwrap();
+    /// assert_eq!(
+    ///     expr.to_sexpr(),
+    ///     r###"(group item (zero-or-more "," item) (optional (regex #""[a-z]+""#)))"###
+    /// );
+    /// ```
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        write_node(&mut out, self);
+        out
+    }
+
+    /// Reads a node from an S-expression in the form written by [`Expr::to_sexpr`], where any amount of whitespace can separate the parts. The spans of the nodes are where they are in `input`.
+    /// ```rust
+    /// # use ebnf_toolkit::Expr;
+    /// let expr = Expr::from_sexpr("(choice \"a\" (one-or-more b))").unwrap();
+    /// assert!(expr.eq_ignoring_spans(&Expr::new("'a' | (b+)").unwrap()));
+    /// ```
+    ///
+    /// # Errors
+    /// If `input` isn't a single node in that form. See [`SexprError`] for why.
+    pub fn from_sexpr(input: &'a str) -> Result<Self, SexprError<'a>> {
+        let mut reader = Reader::new(input);
+        let node = reader.node()?;
+        reader.end()?;
+        Ok(node)
+    }
+}
+
+impl<'a> Rule<'a> {
+    /// Writes the rule as a Lisp-style S-expression, in a list headed by `rule`, or `hidden-rule` for a [hidden](Rule::hidden) rule, followed by its name, its annotations and the nodes of its body written as by [`Expr::to_sexpr`]. [`Rule::from_sexpr`] reads it back.
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// let rule = Rule::new("@token <digits> = #'[0-9]' +;").unwrap();
+    /// assert_eq!(rule.to_sexpr(), r#"(hidden-rule digits @token (one-or-more (regex "[0-9]")))"#);
+    /// ```
+    pub fn to_sexpr(&self) -> String {
+        let mut out = String::new();
+        write_rule(&mut out, self);
+        out
+    }
+
+    /// Reads a rule from an S-expression in the form written by [`Rule::to_sexpr`]. Its [`definitions`](Rule::definitions) are where the S-expression is in `input`.
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// let rule = Rule::from_sexpr("(rule list item (zero-or-more \",\" item))").unwrap();
+    /// assert!(rule.eq_ignoring_spans(&Rule::new("list = item (',' item)*;").unwrap()));
+    /// ```
+    ///
+    /// # Errors
+    /// If `input` isn't a single rule in that form. See [`SexprError`] for why.
+    pub fn from_sexpr(input: &'a str) -> Result<Self, SexprError<'a>> {
+        let mut reader = Reader::new(input);
+        let start = reader.position;
+        let Expr::Rule { rule, .. } = reader.node()? else {
+            return Err(SexprError::Unexpected {
+                span: reader.span_from(start),
+                expected: "a rule",
+            });
+        };
+        reader.end()?;
+        Ok(rule)
+    }
+}
+
+fn write_rule(out: &mut String, rule: &Rule<'_>) {
+    out.push_str(if rule.hidden {
+        "(hidden-rule "
+    } else {
+        "(rule "
+    });
+    write_name(out, &rule.name);
+    for annotation in &rule.annotations {
+        write!(out, " @{}", annotation.name).unwrap();
+    }
+    write_children(out, &rule.body);
+}
+
+/// Writes a space before each of `nodes`, then closes the list they're in
+fn write_children(out: &mut String, nodes: &[Expr<'_>]) {
+    for node in nodes {
+        out.push(' ');
+        write_node(out, node);
+    }
+    out.push(')');
+}
+
+fn write_node(out: &mut String, node: &Expr<'_>) {
+    let (head, body) = match node {
+        Expr::Literal { str, .. } => return write_str(out, str),
+        Expr::Nonterminal { name, .. } => {
+            if is_symbol(name) {
+                out.push_str(name);
+            } else {
+                out.push_str("(ref ");
+                write_str(out, name);
+                out.push(')');
+            }
+            return;
+        }
+        Expr::Regex { pattern, .. } => {
+            out.push_str("(regex ");
+            write_str(out, pattern);
+            out.push(')');
+            return;
+        }
+        Expr::Empty { .. } => return out.push_str("(empty)"),
+        Expr::UnparsedOperator { op, .. } => {
+            let op: &str = op.into();
+            write!(out, "(operator {op})").unwrap();
+            return;
+        }
+        Expr::Rule { rule, .. } => return write_rule(out, rule),
+        Expr::Labeled { label, body, .. } => {
+            out.push_str("(label ");
+            write_name(out, label);
+            return write_children(out, body);
+        }
+        Expr::Choice { body, ordered, .. } => {
+            let head = if *ordered { "ordered-choice" } else { "choice" };
+            (head, body)
+        }
+        Expr::Optional { body, .. } => ("optional", body),
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated,
+            ..
+        } => {
+            let head = match (*one_needed, *separated) {
+                (false, false) => "zero-or-more",
+                (true, false) => "one-or-more",
+                (false, true) => "zero-or-more-separated",
+                (true, true) => "one-or-more-separated",
+            };
+            (head, body)
+        }
+        Expr::Group { body, .. } => ("group", body),
+        Expr::And { body, .. } => ("and", body),
+        Expr::Not { body, .. } => ("not", body),
+        Expr::Hidden { body, .. } => ("hidden", body),
+    };
+    write!(out, "({head}").unwrap();
+    write_children(out, body);
+}
+
+/// Writes the name of a rule or label as a symbol if it can be, and otherwise as a string
+fn write_name(out: &mut String, name: &str) {
+    if is_symbol(name) {
+        out.push_str(name);
+    } else {
+        write_str(out, name);
+    }
+}
+
+fn is_symbol(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Writes `s` in quotes, with as many `#` marks around them as it takes for `s` not to contain the closing quote
+fn write_str(out: &mut String, s: &str) {
+    let mut hashes = 0;
+    if s.contains('"') {
+        hashes = 1;
+        while s.contains(&*closing_quote(hashes)) {
+            hashes += 1;
+        }
+    }
+    let closing = closing_quote(hashes);
+    out.push_str(&closing[1..]);
+    out.push('"');
+    out.push_str(s);
+    out.push_str(&closing);
+}
+
+/// A `"` followed by `hashes` `#` marks
+fn closing_quote(hashes: usize) -> String {
+    let mut closing = String::from('"');
+    closing.extend((0..hashes).map(|_| '#'));
+    closing
+}
+
+enum Token<'a> {
+    Open,
+    Close,
+    Str(&'a str),
+    Atom(&'a str),
+    End,
+}
+
+/// A place in the input, as a byte offset and the line and byte offset within the line it's at
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Reads S-expressions from `input`, keeping track of where it's up to for the spans of what it reads
+struct Reader<'a> {
+    input: &'a str,
+    position: Position,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Reader {
+            input,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 0,
+            },
+        }
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        let end = self.position;
+        Span::new(
+            start.offset..end.offset,
+            (start.line, start.column),
+            (end.line, end.column),
+        )
+    }
+
+    fn advance(&mut self, len: usize) {
+        let position = &mut self.position;
+        for c in self.input[position.offset..position.offset + len].chars() {
+            if c == '\n' {
+                position.line += 1;
+                position.column = 0;
+            } else {
+                position.column += c.len_utf8();
+            }
+        }
+        position.offset += len;
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.position.offset..];
+        self.advance(rest.len() - rest.trim_start().len());
+    }
+
+    /// Reads the next token, along with where it starts
+    fn token(&mut self) -> Result<(Token<'a>, Position), SexprError<'a>> {
+        self.skip_whitespace();
+        let start = self.position;
+        let rest = &self.input[start.offset..];
+        let Some(first) = rest.chars().next() else {
+            return Ok((Token::End, start));
+        };
+        let hashes = rest.len() - rest.trim_start_matches('#').len();
+        let token = match first {
+            '(' => {
+                self.advance(1);
+                Token::Open
+            }
+            ')' => {
+                self.advance(1);
+                Token::Close
+            }
+            '"' | '#' if rest[hashes..].starts_with('"') => {
+                let opened = hashes + 1;
+                let Some(len) = rest[opened..].find(&*closing_quote(hashes)) else {
+                    self.advance(opened);
+                    return Err(SexprError::UnterminatedString {
+                        span: self.span_from(start),
+                    });
+                };
+                self.advance(opened + len + opened);
+                Token::Str(&rest[opened..opened + len])
+            }
+            _ => {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                    .unwrap_or(rest.len());
+                self.advance(len);
+                Token::Atom(&rest[..len])
+            }
+        };
+        Ok((token, start))
+    }
+
+    fn end(&mut self) -> Result<(), SexprError<'a>> {
+        match self.token()? {
+            (Token::End, _) => Ok(()),
+            (_, start) => Err(SexprError::Unexpected {
+                span: self.span_from(start),
+                expected: "the end of the input",
+            }),
+        }
+    }
+
+    fn node(&mut self) -> Result<Expr<'a>, SexprError<'a>> {
+        let (token, start) = self.token()?;
+        match token {
+            Token::Str(str) => Ok(Expr::Literal {
+                span: self.span_from(start),
+                str,
+            }),
+            Token::Atom(name) if is_symbol(name) => Ok(Expr::Nonterminal {
+                span: self.span_from(start),
+                name,
+            }),
+            Token::Open => self.list(start),
+            _ => Err(SexprError::Unexpected {
+                span: self.span_from(start),
+                expected: "a node",
+            }),
+        }
+    }
+
+    /// Reads the rest of a list that was opened at `start`
+    fn list(&mut self, start: Position) -> Result<Expr<'a>, SexprError<'a>> {
+        let (token, head_start) = self.token()?;
+        let Token::Atom(head) = token else {
+            return Err(SexprError::Unexpected {
+                span: self.span_from(head_start),
+                expected: "the kind of node",
+            });
+        };
+        let node = match head {
+            "regex" => {
+                let pattern = self.string()?;
+                self.close()?;
+                Expr::Regex {
+                    span: self.span_from(start),
+                    pattern,
+                }
+            }
+            "ref" => {
+                let name = self.string()?;
+                self.close()?;
+                Expr::Nonterminal {
+                    span: self.span_from(start),
+                    name,
+                }
+            }
+            "empty" => {
+                self.close()?;
+                Expr::Empty {
+                    span: self.span_from(start),
+                }
+            }
+            "label" => {
+                let label = self.name()?;
+                let body = self.children()?;
+                Expr::Labeled {
+                    span: self.span_from(start),
+                    label,
+                    body,
+                }
+            }
+            "rule" => self.rule(start, false)?,
+            "hidden-rule" => self.rule(start, true)?,
+            _ => {
+                let node: fn(Span, Vec<Expr<'a>>) -> Expr<'a> = match head {
+                    "choice" => |span, body| Expr::Choice {
+                        span,
+                        body,
+                        ordered: false,
+                    },
+                    "ordered-choice" => |span, body| Expr::Choice {
+                        span,
+                        body,
+                        ordered: true,
+                    },
+                    "optional" => |span, body| Expr::Optional { span, body },
+                    "zero-or-more" => |span, body| Expr::Repetition {
+                        span,
+                        body,
+                        one_needed: false,
+                        separated: false,
+                    },
+                    "one-or-more" => |span, body| Expr::Repetition {
+                        span,
+                        body,
+                        one_needed: true,
+                        separated: false,
+                    },
+                    "zero-or-more-separated" => |span, body| Expr::Repetition {
+                        span,
+                        body,
+                        one_needed: false,
+                        separated: true,
+                    },
+                    "one-or-more-separated" => |span, body| Expr::Repetition {
+                        span,
+                        body,
+                        one_needed: true,
+                        separated: true,
+                    },
+                    "group" => |span, body| Expr::Group { span, body },
+                    "and" => |span, body| Expr::And { span, body },
+                    "not" => |span, body| Expr::Not { span, body },
+                    "hidden" => |span, body| Expr::Hidden { span, body },
+                    _ => {
+                        return Err(SexprError::UnknownForm {
+                            span: self.span_from(head_start),
+                            head,
+                        });
+                    }
+                };
+                let body = self.children()?;
+                node(self.span_from(start), body)
+            }
+        };
+        Ok(node)
+    }
+
+    /// Reads the rest of a rule opened at `start`, after its head
+    fn rule(&mut self, start: Position, hidden: bool) -> Result<Expr<'a>, SexprError<'a>> {
+        let name = self.name()?;
+        let mut annotations = Vec::new();
+        let body = loop {
+            let before = self.position;
+            match self.token()? {
+                (Token::Atom(atom), at) if atom.starts_with('@') && atom.len() > 1 => {
+                    annotations.push(Annotation {
+                        name: &atom[1..],
+                        span: self.span_from(at),
+                    });
+                }
+                _ => {
+                    self.position = before;
+                    break self.children()?;
+                }
+            }
+        };
+        let span = self.span_from(start);
+        Ok(Expr::Rule {
+            span,
+            rule: Rule {
+                name: Cow::Borrowed(name),
+                body,
+                definitions: SpanSet::from(span),
+                hidden,
+                annotations,
+            },
+        })
+    }
+
+    /// Reads nodes up to the `)` that closes the list they're in
+    fn children(&mut self) -> Result<Vec<Expr<'a>>, SexprError<'a>> {
+        let mut children = Vec::new();
+        loop {
+            let before = self.position;
+            if let (Token::Close, _) = self.token()? {
+                return Ok(children);
+            }
+            self.position = before;
+            children.push(self.node()?);
+        }
+    }
+
+    fn close(&mut self) -> Result<(), SexprError<'a>> {
+        match self.token()? {
+            (Token::Close, _) => Ok(()),
+            (_, start) => Err(SexprError::Unexpected {
+                span: self.span_from(start),
+                expected: "`)`",
+            }),
+        }
+    }
+
+    fn string(&mut self) -> Result<&'a str, SexprError<'a>> {
+        match self.token()? {
+            (Token::Str(str), _) => Ok(str),
+            (_, start) => Err(SexprError::Unexpected {
+                span: self.span_from(start),
+                expected: "a string",
+            }),
+        }
+    }
+
+    /// Reads the name of a rule or label, written as a symbol or a string
+    fn name(&mut self) -> Result<&'a str, SexprError<'a>> {
+        match self.token()? {
+            (Token::Str(name), _) => Ok(name),
+            (Token::Atom(name), _) if is_symbol(name) => Ok(name),
+            (_, start) => Err(SexprError::Unexpected {
+                span: self.span_from(start),
+                expected: "a name",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Expr, Grammar, Rule, SexprError};
+
+    #[test]
+    fn round_trip() {
+        let g = Grammar::new(
+            r#"
+            @token <ws> = #'\s+';
+            call = !keyword name:ident <'('> [args] ')' / ε;
+            args = (expr (',' expr)*) | (&'"' string);
+            string = '"' #'[^"]*' '"';
+            "#,
+        )
+        .unwrap();
+        for rule in g.rules() {
+            let sexpr = rule.to_sexpr();
+            let parsed = Rule::from_sexpr(&sexpr).unwrap();
+            assert!(parsed.eq_ignoring_spans(rule), "{sexpr}");
+            assert_eq!(parsed.to_sexpr(), sexpr);
+        }
+        assert_eq!(
+            g["string"].to_sexpr(),
+            r##"(rule string #"""# (regex #"[^"]*"#) #"""#)"##
+        );
+
+        let expr = Expr::from_sexpr("(group a\n  (ref \"two words\"))").unwrap();
+        let Expr::Group { body, .. } = &expr else {
+            panic!("{expr:?}")
+        };
+        assert_eq!(body[1].span().start_line(), (2, 2));
+        assert_eq!(expr.to_sexpr(), "(group a (ref \"two words\"))");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |input| Expr::from_sexpr(input).unwrap_err();
+        assert!(matches!(
+            error("(choice a"),
+            SexprError::Unexpected { expected: "a node", span } if span.start() == 9
+        ));
+        assert!(matches!(
+            error("(sequence a b)"),
+            SexprError::UnknownForm {
+                head: "sequence",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("(regex #\"a\")"),
+            SexprError::UnterminatedString { .. }
+        ));
+        assert!(matches!(
+            error("a b"),
+            SexprError::Unexpected {
+                expected: "the end of the input",
+                ..
+            }
+        ));
+        assert!(matches!(
+            Rule::from_sexpr("(group a)").unwrap_err(),
+            SexprError::Unexpected {
+                expected: "a rule",
+                ..
+            }
+        ));
+    }
+}
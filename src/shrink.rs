@@ -0,0 +1,946 @@
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
+use core::{ops::Range, slice};
+
+use regex::Regex;
+use regex_automata::{
+    Anchored, Input, MatchKind,
+    hybrid::dfa::{Cache, DFA},
+};
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::{Expr, Grammar, expr::lower_separated_repetitions};
+
+/// How a sentence was derived, with each part mirroring the nodes that made it
+#[derive(Debug, Clone)]
+enum Tree<'g, 'a> {
+    /// The text matched by a literal or a regex
+    Text(String),
+    /// Nodes matched one after another, including those that matched nothing at all, like lookaheads
+    Seq(Vec<Tree<'g, 'a>>),
+    /// The body of the rule called `name`
+    Rule(&'g str, Box<Tree<'g, 'a>>),
+    /// The alternative numbered `index` of a choice between `alts`
+    Alt {
+        alts: &'g [Expr<'a>],
+        index: usize,
+        tree: Box<Tree<'g, 'a>>,
+    },
+    /// Each time the body of an optional part or a repetition was repeated, which needs at least `min`
+    Rep {
+        min: usize,
+        items: Vec<Tree<'g, 'a>>,
+    },
+}
+
+impl Tree<'_, '_> {
+    fn render(&self, output: &mut String) {
+        match self {
+            Tree::Text(text) => output.push_str(text),
+            Tree::Seq(items) | Tree::Rep { items, .. } => {
+                for item in items {
+                    item.render(output);
+                }
+            }
+            Tree::Rule(_, tree) | Tree::Alt { tree, .. } => tree.render(output),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut output = String::new();
+        self.render(&mut output);
+        output
+    }
+
+    fn children(&self) -> &[Self] {
+        match self {
+            Tree::Text(_) => &[],
+            Tree::Seq(items) | Tree::Rep { items, .. } => items,
+            Tree::Rule(_, tree) | Tree::Alt { tree, .. } => slice::from_ref(&**tree),
+        }
+    }
+
+    fn children_mut(&mut self) -> &mut [Self] {
+        match self {
+            Tree::Text(_) => &mut [],
+            Tree::Seq(items) | Tree::Rep { items, .. } => items,
+            Tree::Rule(_, tree) | Tree::Alt { tree, .. } => slice::from_mut(&mut **tree),
+        }
+    }
+
+    /// The node numbered `id` in `layout`, which must be of this tree
+    fn node_mut(&mut self, layout: &Layout, id: usize) -> &mut Self {
+        let mut node = self;
+        let mut at = 0;
+        while at != id {
+            at += 1;
+            let mut index = 0;
+            while id >= at + layout.sizes[at] {
+                at += layout.sizes[at];
+                index += 1;
+            }
+            node = &mut node.children_mut()[index];
+        }
+        node
+    }
+}
+
+/// The nodes of a tree numbered in the order they are met going down it, with where each one's text is in the sentence and how many nodes it has, and for each rule, the expansions of the same rule inside it
+struct Layout {
+    ranges: Vec<Range<usize>>,
+    sizes: Vec<usize>,
+    nested: BTreeMap<usize, Vec<usize>>,
+}
+
+impl Layout {
+    fn of(tree: &Tree<'_, '_>) -> Layout {
+        let mut layout = Layout {
+            ranges: Vec::new(),
+            sizes: Vec::new(),
+            nested: BTreeMap::new(),
+        };
+        layout.add(tree, 0, &mut Vec::new());
+        layout
+    }
+
+    /// Adds `tree`, whose text starts at `start`, returning where it ends. `rules` are the rules `tree` is inside, and their numbers.
+    fn add<'t>(
+        &mut self,
+        tree: &'t Tree<'_, '_>,
+        start: usize,
+        rules: &mut Vec<(&'t str, usize)>,
+    ) -> usize {
+        let id = self.ranges.len();
+        self.ranges.push(start..start);
+        self.sizes.push(0);
+        if let &Tree::Rule(name, _) = tree {
+            for &(outer, outer_id) in &*rules {
+                if outer == name {
+                    self.nested.entry(outer_id).or_default().push(id);
+                }
+            }
+            rules.push((name, id));
+        }
+        let mut end = match tree {
+            Tree::Text(text) => start + text.len(),
+            _ => start,
+        };
+        for child in tree.children() {
+            end = self.add(child, end, rules);
+        }
+        if let Tree::Rule(..) = tree {
+            rules.pop();
+        }
+        self.ranges[id].end = end;
+        self.sizes[id] = self.ranges.len() - id;
+        end
+    }
+}
+
+/// One change to a node of a tree that might make its sentence shorter
+#[derive(Debug, Clone, Copy)]
+enum Edit<'g, 'a> {
+    /// Replacing a rule with the expansion of the same rule numbered this, from inside it
+    Nested(usize),
+    /// Taking the shortest way through a rule with this body
+    Shortest(&'g [Expr<'a>]),
+    /// Taking the shortest way through the alternative numbered this
+    Alternative(usize, &'g Expr<'a>),
+    /// Leaving out the repetition at `index`, which is numbered `id`
+    Remove { index: usize, id: usize },
+}
+
+/// A set of positions in the input, kept as a bit for each, as finding where each node can end takes a great many unions of them
+#[derive(Debug, Clone, Default)]
+struct Positions(Vec<u64>);
+
+impl Positions {
+    fn from_position(position: usize) -> Positions {
+        let mut positions = Positions::default();
+        positions.insert(position);
+        positions
+    }
+
+    fn insert(&mut self, position: usize) {
+        if self.0.len() <= position / 64 {
+            self.0.resize(position / 64 + 1, 0);
+        }
+        self.0[position / 64] |= 1 << (position % 64);
+    }
+
+    fn contains(&self, position: usize) -> bool {
+        self.0
+            .get(position / 64)
+            .is_some_and(|word| word & (1 << (position % 64)) != 0)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    /// Adds every position of `other`, returning those that weren't already here
+    fn union(&mut self, other: &Positions) -> Positions {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let added = self
+            .0
+            .iter_mut()
+            .zip(&other.0)
+            .map(|(word, &new)| {
+                let added = new & !*word;
+                *word |= new;
+                added
+            })
+            .collect();
+        Positions(added)
+    }
+
+    /// The positions in ascending order
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, &word)| {
+            let mut word = word;
+            core::iter::from_fn(move || {
+                let bit = word.trailing_zeros() as usize;
+                word &= word.wrapping_sub(1);
+                (bit < 64).then_some(index * 64 + bit)
+            })
+        })
+    }
+}
+
+impl FromIterator<usize> for Positions {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Positions {
+        let mut positions = Positions::default();
+        for position in iter {
+            positions.insert(position);
+        }
+        positions
+    }
+}
+
+/// A regex terminal compiled for finding where its matches from a given position end
+struct Pattern {
+    /// Anchored to match only the whole of what it is given
+    regex: Regex,
+    /// Reports every match rather than only the leftmost-first, so that one pass over the input finds all the ends. `None` if it couldn't be built, in which case `regex` is tried on each end in turn.
+    dfa: Option<(DFA, Cache)>,
+    hir: Hir,
+}
+
+impl Pattern {
+    fn new(pattern: &str) -> Option<Pattern> {
+        let regex = Regex::new(&format!("^(?:{pattern})$")).ok()?;
+        let hir = regex_syntax::parse(pattern).ok()?;
+        let dfa = DFA::builder()
+            .configure(
+                DFA::config()
+                    .match_kind(MatchKind::All)
+                    .unicode_word_boundary(true),
+            )
+            .build(pattern)
+            .ok()
+            .map(|dfa| {
+                let cache = dfa.create_cache();
+                (dfa, cache)
+            });
+        Some(Pattern { regex, dfa, hir })
+    }
+
+    /// Every position a match from `start` can end at
+    fn ends(&mut self, input: &str, start: usize) -> Positions {
+        self.dfa_ends(input, start).unwrap_or_else(|| {
+            (start..=input.len())
+                .filter(|&end| {
+                    input.is_char_boundary(end) && self.regex.is_match(&input[start..end])
+                })
+                .collect()
+        })
+    }
+
+    /// Feeds the input to the DFA a byte at a time, checking at each position whether the text so far would match if it ended there. `None` if the DFA gives up, as it does on non-ASCII text next to a `\b`.
+    fn dfa_ends(&mut self, input: &str, start: usize) -> Option<Positions> {
+        let (dfa, cache) = self.dfa.as_mut()?;
+        let text = &input[start..];
+        let mut state = dfa
+            .start_state_forward(cache, &Input::new(text).anchored(Anchored::Yes))
+            .ok()?;
+        let mut ends = Positions::default();
+        for (offset, next) in text.bytes().map(Some).chain([None]).enumerate() {
+            let end = dfa.next_eoi_state(cache, state).ok()?;
+            if end.is_quit() {
+                return None;
+            }
+            if end.is_match() && text.is_char_boundary(offset) {
+                ends.insert(start + offset);
+            }
+            let Some(byte) = next else { break };
+            state = dfa.next_state(cache, state, byte).ok()?;
+            if state.is_quit() {
+                return None;
+            }
+            if state.is_dead() {
+                break;
+            }
+        }
+        Some(ends)
+    }
+}
+
+struct Shrinker<'g, 'a> {
+    grammar: &'g Grammar<'a>,
+    input: &'g str,
+    /// The positions each sequence of nodes can end at, keyed by where the sequence is, how many nodes it has, and where it starts. These don't depend on any seed, so are kept from one round to the next.
+    ends: BTreeMap<(usize, usize, usize), Positions>,
+    /// The same for sequences whose ends were found using a seed, which may find more in the next round
+    round_ends: BTreeMap<(usize, usize, usize), Positions>,
+    /// The sequences being matched further out, which would recurse forever if matched again from the same place
+    matching: BTreeSet<(usize, usize, usize)>,
+    /// What a sequence met again while being matched is taken to match, grown from the ends found the round before until they stop changing
+    seeds: BTreeMap<(usize, usize, usize), Positions>,
+    /// Whether the sequence being matched has used a seed so far
+    seeded: bool,
+    /// The rules whose derivations are being built further out, with the text each covers
+    building: Vec<(&'g str, usize, usize)>,
+    /// Each pattern, or `None` if it isn't valid
+    regexes: BTreeMap<&'a str, Option<Pattern>>,
+    /// The length and height of the shortest sentence each rule derives, or `None` if it derives none
+    shortest: BTreeMap<&'g str, Option<(usize, usize)>>,
+    /// The shortest sentence of each sequence of nodes a change might take the shortest way through, keyed by where the sequence is and how many nodes it has
+    shortest_texts: BTreeMap<(usize, usize), Option<String>>,
+    invalid: bool,
+}
+
+pub(crate) fn shrink(
+    grammar: &Grammar<'_>,
+    start: &str,
+    input: &str,
+    mut fails: impl FnMut(&str) -> bool,
+) -> Option<String> {
+    // Derivations are built from plain EBNF, in which a separator is just another node to leave out
+    let mut grammar = grammar.clone();
+    lower_separated_repetitions(&mut grammar);
+    let grammar = &grammar;
+    let rule = grammar.rules.get(start)?;
+    let mut shrinker = Shrinker {
+        grammar,
+        input,
+        ends: BTreeMap::new(),
+        round_ends: BTreeMap::new(),
+        matching: BTreeSet::new(),
+        seeds: BTreeMap::new(),
+        seeded: false,
+        building: Vec::new(),
+        regexes: BTreeMap::new(),
+        shortest: grammar.rules.keys().map(|name| (&**name, None)).collect(),
+        shortest_texts: BTreeMap::new(),
+        invalid: false,
+    };
+    // A left-recursive rule matches more with each round, as each can go around it once more
+    loop {
+        shrinker.round_ends.clear();
+        shrinker.sequence_ends(&rule.body, 0);
+        if shrinker.round_ends.is_empty() {
+            break;
+        }
+        let mut grown = false;
+        for (key, ends) in &shrinker.round_ends {
+            let seed = shrinker.seeds.entry(*key).or_default();
+            grown |= !seed.union(ends).is_empty();
+        }
+        if !grown {
+            break;
+        }
+    }
+    if !shrinker.sequence_ends(&rule.body, 0).contains(input.len()) || shrinker.invalid {
+        return None;
+    }
+    shrinker.find_shortest();
+    shrinker.building.push((&rule.name, 0, input.len()));
+    let mut current = Tree::Rule(
+        &rule.name,
+        Box::new(shrinker.build_sequence(&rule.body, 0, input.len())?),
+    );
+    let mut text = String::from(input);
+    // Each smaller sentence that still fails is taken as soon as it's found, trying the smallest first, until none is left. Sentences of the same length are smaller if they sort first, so terminals are shrunk too. Only the trees for the length being tried are built, as there can be a great many more changes than are ever needed.
+    loop {
+        let layout = Layout::of(&current);
+        let mut edits = Vec::new();
+        shrinker.edits(&current, &layout, &mut 0, &mut edits);
+        edits.retain(|&(length, ..)| length <= text.len());
+        edits.sort_by_key(|&(length, ..)| length);
+        let mut smaller = None;
+        'lengths: for edits in edits.chunk_by(|a, b| a.0 == b.0) {
+            let mut candidates: Vec<_> = edits
+                .iter()
+                .map(|&(_, id, edit)| (shrinker.edited_text(&text, &layout, id, edit), id, edit))
+                .filter(|(candidate, ..)| (candidate.len(), candidate) < (text.len(), &text))
+                .collect();
+            candidates.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+            for (candidate, id, edit) in candidates {
+                if fails(&candidate)
+                    && let Some(tree) = shrinker.apply(&current, &layout, id, edit)
+                {
+                    smaller = Some((candidate, tree));
+                    break 'lengths;
+                }
+            }
+        }
+        let Some((smaller, tree)) = smaller else {
+            return Some(text);
+        };
+        text = smaller;
+        current = tree;
+    }
+}
+
+impl<'g, 'a> Shrinker<'g, 'a> {
+    fn regex(&mut self, pattern: &'a str) -> Option<&mut Pattern> {
+        let compiled = self
+            .regexes
+            .entry(pattern)
+            .or_insert_with(|| Pattern::new(pattern));
+        if compiled.is_none() {
+            self.invalid = true;
+        }
+        compiled.as_mut()
+    }
+
+    /// Every position that `nodes` can end at when matched in order from `start`
+    fn sequence_ends(&mut self, nodes: &'g [Expr<'a>], start: usize) -> Positions {
+        let key = (nodes.as_ptr() as usize, nodes.len(), start);
+        if let Some(ends) = self.ends.get(&key).or_else(|| self.round_ends.get(&key)) {
+            self.seeded |= self.round_ends.contains_key(&key);
+            return ends.clone();
+        }
+        if self.matching.contains(&key) {
+            self.seeded = true;
+            return self.seeds.get(&key).cloned().unwrap_or_default();
+        }
+        self.matching.insert(key);
+        let outer_seeded = core::mem::replace(&mut self.seeded, false);
+        let ends = match nodes.split_first() {
+            None => Positions::from_position(start),
+            Some((first, rest)) => {
+                let mut ends = Positions::default();
+                for middle in self.node_ends(first, start).iter() {
+                    ends.union(&self.sequence_ends(rest, middle));
+                }
+                ends
+            }
+        };
+        self.matching.remove(&key);
+        if self.seeded {
+            self.round_ends.insert(key, ends.clone());
+        } else {
+            self.ends.insert(key, ends.clone());
+        }
+        self.seeded |= outer_seeded;
+        ends
+    }
+
+    fn node_ends(&mut self, node: &'g Expr<'a>, start: usize) -> Positions {
+        let input = self.input;
+        match node {
+            Expr::Literal { str, .. } => input[start..]
+                .starts_with(&**str)
+                .then_some(start + str.len())
+                .into_iter()
+                .collect(),
+            &Expr::Regex { pattern, .. } => self
+                .regex(pattern)
+                .map(|regex| regex.ends(input, start))
+                .unwrap_or_default(),
+            Expr::Nonterminal { name, .. } => match self.grammar.rules.get(*name) {
+                Some(rule) => self.sequence_ends(&rule.body, start),
+                None => Positions::default(),
+            },
+            Expr::Choice { body, .. } => {
+                let mut ends = Positions::default();
+                for alternative in body {
+                    ends.union(&self.node_ends(alternative, start));
+                }
+                ends
+            }
+            Expr::Optional { body, .. } => {
+                let mut ends = self.sequence_ends(body, start);
+                ends.insert(start);
+                ends
+            }
+            Expr::Repetition {
+                body, one_needed, ..
+            } => {
+                let mut ends = Positions::default();
+                let first = self.sequence_ends(body, start);
+                let mut queue: VecDeque<_> = ends.union(&first).iter().collect();
+                while let Some(end) = queue.pop_front() {
+                    let next = self.sequence_ends(body, end);
+                    queue.extend(ends.union(&next).iter());
+                }
+                if !one_needed {
+                    ends.insert(start);
+                }
+                ends
+            }
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                self.sequence_ends(body, start)
+            }
+            Expr::And { body, .. } => {
+                if self.sequence_ends(body, start).is_empty() {
+                    Positions::default()
+                } else {
+                    Positions::from_position(start)
+                }
+            }
+            Expr::Not { body, .. } => {
+                if self.sequence_ends(body, start).is_empty() {
+                    Positions::from_position(start)
+                } else {
+                    Positions::default()
+                }
+            }
+            Expr::Empty { .. } => Positions::from_position(start),
+            Expr::UnparsedOperator { .. } | Expr::Rule { .. } => Positions::default(),
+        }
+    }
+
+    /// Builds how `nodes` match from `start` to `end`, which they must be able to. Ways of matching that expand a rule over the same text it's already being expanded over are passed over, as they would never end.
+    fn build_sequence(
+        &mut self,
+        nodes: &'g [Expr<'a>],
+        start: usize,
+        end: usize,
+    ) -> Option<Tree<'g, 'a>> {
+        let Some((first, rest)) = nodes.split_first() else {
+            return (start == end).then(|| Tree::Seq(vec![]));
+        };
+        for middle in self.node_ends(first, start).iter() {
+            if !self.sequence_ends(rest, middle).contains(end) {
+                continue;
+            }
+            let Some(tree) = self.build_node(first, start, middle) else {
+                continue;
+            };
+            if let Some(Tree::Seq(mut items)) = self.build_sequence(rest, middle, end) {
+                items.insert(0, tree);
+                return Some(Tree::Seq(items));
+            }
+        }
+        None
+    }
+
+    fn build_node(&mut self, node: &'g Expr<'a>, start: usize, end: usize) -> Option<Tree<'g, 'a>> {
+        Some(match node {
+            Expr::Literal { str, .. } => Tree::Text(String::from(&**str)),
+            Expr::Regex { .. } => Tree::Text(String::from(&self.input[start..end])),
+            Expr::Nonterminal { name, .. } => {
+                let (name, rule) = self.grammar.rules.get_key_value(*name)?;
+                if self.building.contains(&(&**name, start, end)) {
+                    return None;
+                }
+                self.building.push((name, start, end));
+                let tree = self.build_sequence(&rule.body, start, end);
+                self.building.pop();
+                Tree::Rule(name, Box::new(tree?))
+            }
+            Expr::Choice { body, .. } => (0..body.len()).find_map(|index| {
+                if !self.node_ends(&body[index], start).contains(end) {
+                    return None;
+                }
+                Some(Tree::Alt {
+                    alts: body,
+                    index,
+                    tree: Box::new(self.build_node(&body[index], start, end)?),
+                })
+            })?,
+            Expr::Optional { body, .. } => Tree::Rep {
+                min: 0,
+                items: if start == end {
+                    vec![]
+                } else {
+                    vec![self.build_sequence(body, start, end)?]
+                },
+            },
+            Expr::Repetition {
+                body, one_needed, ..
+            } => {
+                let min = usize::from(*one_needed);
+                // The fewest repetitions that reach `end`, found by searching outwards from `start`
+                let mut before = BTreeMap::new();
+                let mut queue = VecDeque::from([start]);
+                while let Some(position) = queue.pop_front() {
+                    if position == end && (position != start || min == 0) {
+                        break;
+                    }
+                    for next in self.sequence_ends(body, position).iter() {
+                        if next != start && !before.contains_key(&next) {
+                            before.insert(next, position);
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                let mut path = vec![end];
+                while let Some(&previous) = path.last().and_then(|p| before.get(p)) {
+                    path.push(previous);
+                }
+                if path.last() != Some(&start) {
+                    // Only a repetition that matches nothing can get here, by repeating once
+                    path.push(start);
+                }
+                path.reverse();
+                let items = if start == end && min == 0 {
+                    vec![]
+                } else {
+                    path.windows(2)
+                        .map(|pair| self.build_sequence(body, pair[0], pair[1]))
+                        .collect::<Option<_>>()?
+                };
+                Tree::Rep { min, items }
+            }
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                self.build_sequence(body, start, end)?
+            }
+            Expr::And { .. }
+            | Expr::Not { .. }
+            | Expr::Empty { .. }
+            | Expr::UnparsedOperator { .. }
+            | Expr::Rule { .. } => Tree::Seq(vec![]),
+        })
+    }
+
+    /// Finds the length of the shortest sentence each rule derives, along with how many rules deep it goes. The depth breaks ties in length, so that expanding a rule's shortest sentence always ends.
+    fn find_shortest(&mut self) {
+        // Each pass can only lower a value, and one that doesn't lower any has found them all
+        loop {
+            let mut changed = false;
+            for rule in self.grammar.rules.values() {
+                let value = self
+                    .sequence_shortest(&rule.body)
+                    .map(|(length, height)| (length, height + 1));
+                if value != self.shortest[&*rule.name] {
+                    self.shortest.insert(&rule.name, value);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn sequence_shortest(&mut self, nodes: &'g [Expr<'a>]) -> Option<(usize, usize)> {
+        nodes.iter().try_fold((0, 0), |(length, height), node| {
+            let (l, h) = self.node_shortest(node)?;
+            Some((length + l, height.max(h)))
+        })
+    }
+
+    fn node_shortest(&mut self, node: &'g Expr<'a>) -> Option<(usize, usize)> {
+        match node {
+            Expr::Literal { str, .. } => Some((str.len(), 0)),
+            &Expr::Regex { pattern, .. } => {
+                let hir = &self.regex(pattern)?.hir;
+                Some((hir.properties().minimum_len()?, 0))
+            }
+            Expr::Nonterminal { name, .. } => self.shortest.get(name).copied().flatten(),
+            Expr::Choice { body, .. } => body
+                .iter()
+                .filter_map(|alternative| self.node_shortest(alternative))
+                .min(),
+            Expr::Repetition {
+                body,
+                one_needed: true,
+                ..
+            }
+            | Expr::Group { body, .. }
+            | Expr::Hidden { body, .. }
+            | Expr::Labeled { body, .. } => self.sequence_shortest(body),
+            Expr::UnparsedOperator { .. } | Expr::Rule { .. } => None,
+            _ => Some((0, 0)),
+        }
+    }
+
+    /// The shortest way to derive `nodes`, if there is one
+    fn shortest_sequence(&mut self, nodes: &'g [Expr<'a>]) -> Option<Tree<'g, 'a>> {
+        nodes
+            .iter()
+            .map(|node| self.shortest_node(node))
+            .collect::<Option<_>>()
+            .map(Tree::Seq)
+    }
+
+    fn shortest_node(&mut self, node: &'g Expr<'a>) -> Option<Tree<'g, 'a>> {
+        self.node_shortest(node)?;
+        Some(match node {
+            Expr::Literal { str, .. } => Tree::Text(String::from(&**str)),
+            &Expr::Regex { pattern, .. } => {
+                let mut text = String::new();
+                shortest_regex(&self.regex(pattern)?.hir, &mut text);
+                Tree::Text(text)
+            }
+            Expr::Nonterminal { name, .. } => {
+                let (name, rule) = self.grammar.rules.get_key_value(*name)?;
+                Tree::Rule(name, Box::new(self.shortest_sequence(&rule.body)?))
+            }
+            Expr::Choice { body, .. } => {
+                let index = (0..body.len()).min_by_key(|&i| {
+                    // Alternatives with no sentence at all sort last
+                    self.node_shortest(&body[i])
+                        .map_or((1, 0, 0), |(length, height)| (0, length, height))
+                })?;
+                Tree::Alt {
+                    alts: body,
+                    index,
+                    tree: Box::new(self.shortest_node(&body[index])?),
+                }
+            }
+            Expr::Optional { .. } => Tree::Rep {
+                min: 0,
+                items: vec![],
+            },
+            Expr::Repetition {
+                body, one_needed, ..
+            } => Tree::Rep {
+                min: usize::from(*one_needed),
+                items: if *one_needed {
+                    vec![self.shortest_sequence(body)?]
+                } else {
+                    vec![]
+                },
+            },
+            Expr::Group { body, .. } | Expr::Hidden { body, .. } | Expr::Labeled { body, .. } => {
+                self.shortest_sequence(body)?
+            }
+            _ => Tree::Seq(vec![]),
+        })
+    }
+
+    /// The length of the shortest sentence of `nodes`, as [`Shrinker::shortest_sequence`] builds it, keeping the sentence for [`Shrinker::edited_text`]
+    fn shortest_length(&mut self, nodes: &'g [Expr<'a>]) -> Option<usize> {
+        let key = (nodes.as_ptr() as usize, nodes.len());
+        if let Some(text) = self.shortest_texts.get(&key) {
+            return text.as_ref().map(String::len);
+        }
+        let text = self.shortest_sequence(nodes).map(|tree| tree.text());
+        let length = text.as_ref().map(String::len);
+        self.shortest_texts.insert(key, text);
+        length
+    }
+
+    /// Every change to `tree` that might make its sentence shorter, which is node `id` of `layout`, with the length of the sentence it would give: leaving out a repetition, taking the shortest way through another alternative or a rule, or replacing a rule with an expansion of the same rule from inside it
+    fn edits(
+        &mut self,
+        tree: &Tree<'g, 'a>,
+        layout: &Layout,
+        id: &mut usize,
+        edits: &mut Vec<(usize, usize, Edit<'g, 'a>)>,
+    ) {
+        let this = *id;
+        *id += 1;
+        // The length of the whole sentence without this node's text
+        let rest = layout.ranges[0].len() - layout.ranges[this].len();
+        match tree {
+            Tree::Text(_) | Tree::Seq(_) => {}
+            &Tree::Rule(name, _) => {
+                for &nested in layout.nested.get(&this).into_iter().flatten() {
+                    let length = rest + layout.ranges[nested].len();
+                    edits.push((length, this, Edit::Nested(nested)));
+                }
+                let body = &self.grammar.rules[name].body;
+                if let Some(length) = self.shortest_length(body) {
+                    edits.push((rest + length, this, Edit::Shortest(body)));
+                }
+            }
+            &Tree::Alt { alts, index, .. } => {
+                for (i, alternative) in alts.iter().enumerate() {
+                    if i != index
+                        && let Some(length) = self.shortest_length(slice::from_ref(alternative))
+                    {
+                        edits.push((rest + length, this, Edit::Alternative(i, alternative)));
+                    }
+                }
+            }
+            &Tree::Rep { min, ref items, .. } => {
+                let mut child = *id;
+                for index in 0..items.len() {
+                    if items.len() > min {
+                        let length = layout.ranges[0].len() - layout.ranges[child].len();
+                        edits.push((length, this, Edit::Remove { index, id: child }));
+                    }
+                    child += layout.sizes[child];
+                }
+            }
+        }
+        for child in tree.children() {
+            self.edits(child, layout, id, edits);
+        }
+    }
+
+    /// The sentence `text` of a tree laid out as `layout`, with `edit` made to its node numbered `id`
+    fn edited_text(&self, text: &str, layout: &Layout, id: usize, edit: Edit<'g, 'a>) -> String {
+        let shortest = |nodes: &[Expr<'_>]| {
+            self.shortest_texts[&(nodes.as_ptr() as usize, nodes.len())]
+                .as_deref()
+                .unwrap_or_default()
+        };
+        let (range, replacement) = match edit {
+            Edit::Nested(nested) => (
+                layout.ranges[id].clone(),
+                &text[layout.ranges[nested].clone()],
+            ),
+            Edit::Shortest(body) => (layout.ranges[id].clone(), shortest(body)),
+            Edit::Alternative(_, alternative) => (
+                layout.ranges[id].clone(),
+                shortest(slice::from_ref(alternative)),
+            ),
+            Edit::Remove { id: item, .. } => (layout.ranges[item].clone(), ""),
+        };
+        [&text[..range.start], replacement, &text[range.end..]].concat()
+    }
+
+    /// `tree` with `edit` made to its node numbered `id` in `layout`
+    fn apply(
+        &mut self,
+        tree: &Tree<'g, 'a>,
+        layout: &Layout,
+        id: usize,
+        edit: Edit<'g, 'a>,
+    ) -> Option<Tree<'g, 'a>> {
+        let mut changed = tree.clone();
+        if let Edit::Nested(nested) = edit {
+            let expansion = changed.node_mut(layout, nested).clone();
+            *changed.node_mut(layout, id) = expansion;
+            return Some(changed);
+        }
+        match (edit, changed.node_mut(layout, id)) {
+            (Edit::Shortest(body), Tree::Rule(_, inner)) => {
+                **inner = self.shortest_sequence(body)?;
+            }
+            (Edit::Alternative(i, alternative), Tree::Alt { index, tree, .. }) => {
+                *index = i;
+                **tree = self.shortest_node(alternative)?;
+            }
+            (Edit::Remove { index, .. }, Tree::Rep { items, .. }) => {
+                items.remove(index);
+            }
+            (edit, node) => unreachable!("Bug: {edit:?} of {node:?}"),
+        }
+        Some(changed)
+    }
+}
+
+/// Appends the shortest string matched by `hir` to `output`
+fn shortest_regex(hir: &Hir, output: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(lit) => output.push_str(&String::from_utf8_lossy(&lit.0)),
+        HirKind::Class(Class::Unicode(class)) => {
+            if let Some(range) = class.ranges().first() {
+                output.push(range.start());
+            }
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            if let Some(byte) = class
+                .ranges()
+                .iter()
+                .flat_map(|r| r.start()..=r.end())
+                .find(u8::is_ascii)
+            {
+                output.push(char::from(byte));
+            }
+        }
+        HirKind::Repetition(rep) => {
+            for _ in 0..rep.min {
+                shortest_regex(&rep.sub, output);
+            }
+        }
+        HirKind::Capture(cap) => shortest_regex(&cap.sub, output),
+        HirKind::Concat(subs) => {
+            for sub in subs {
+                shortest_regex(sub, output);
+            }
+        }
+        HirKind::Alternation(subs) => {
+            if let Some(sub) = subs
+                .iter()
+                .min_by_key(|sub| sub.properties().minimum_len().unwrap_or(usize::MAX))
+            {
+                shortest_regex(sub, output);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::string::String;
+
+    use crate::Grammar;
+
+    #[test]
+    fn shrink() {
+        let g = Grammar::new(
+            "expr = term (('+' | '-') term)*; term = num | ('(' expr ')'); num = #'[0-9]+';",
+        )
+        .unwrap();
+        // Any sentence with a `-` fails
+        let input = "(12+(345-6)+7)+(8+9)";
+        assert_eq!(
+            g.shrink("expr", input, |s| s.contains('-')),
+            Some("0-0".into())
+        );
+        // Nested parentheses or parentheses after a `+` fail
+        assert_eq!(
+            g.shrink("expr", input, |s| s.contains("((") || s.contains("+(")),
+            Some("0+(0)".into())
+        );
+        assert_eq!(g.shrink("expr", "1+", |_| true), None);
+        assert_eq!(g.shrink("term", "1+2", |_| true), None);
+        assert_eq!(g.shrink("missing", "1", |_| true), None);
+
+        // The first item can only be shrunk, not left out, and making it `bb` to then leave out the second would mean getting larger first
+        let g = Grammar::new("list = item (',' item)*; item = 'a' | 'bb';").unwrap();
+        assert_eq!(
+            g.shrink("list", "a,bb,a,bb", |s| s.matches("bb").count() == 2),
+            Some("a,bb,bb".into())
+        );
+        // A sentence that can't get smaller is given back as it is
+        assert_eq!(g.shrink("list", "a", |_| true), Some("a".into()));
+
+        let g = Grammar::new("sum = (sum '+' num) | num; num = '1' | '22';").unwrap();
+        assert_eq!(
+            g.shrink("sum", "22+1+22+1", |s| s.matches("22").count() == 2),
+            Some("22+22".into())
+        );
+    }
+
+    #[test]
+    fn long_sentence() {
+        let sentence = |items| {
+            let mut sentence = String::from("a");
+            for i in 0..items {
+                sentence.push('y');
+                sentence.push(if i == items / 2 { 'q' } else { 'b' });
+            }
+            sentence
+        };
+        // Every way of splitting the sentence between the left-recursive rule and the repetition is a derivation
+        let g = Grammar::new("A = lab:A | #'[a-z]' % 'y';").unwrap();
+        let input = sentence(100);
+        assert_eq!(input.len(), 201);
+        assert_eq!(g.shrink("A", &input, |s| s.contains('q')), Some("q".into()));
+
+        let g = Grammar::new("A = #'[a-z]' % 'y';").unwrap();
+        let input = sentence(320);
+        assert_eq!(
+            g.shrink("A", &input, |s| s.contains('q')),
+            Some("ayq".into())
+        );
+    }
+}
@@ -1,93 +1,283 @@
-use crate::{Expr, Span, expr::ExprKind};
+//! The reductions applied to each rule as it is parsed, described in [the root docstring](crate#reductions), and a [`Simplifier`] for choosing which of them to apply to a grammar parsed without them. Parsing with [`ParseOptions::simplify`](crate::ParseOptions::simplify) turned off keeps each tree exactly as written, with a [`Group`](Expr::Group) for every pair of brackets.
+//! ```rust
+//! # use ebnf_toolkit::{Expr, Grammar, ParseOptions, simplification::{FlattenChoices, Simplifier, SimplificationPass}};
+//! /// Replaces an optional repetition, `[x*]`, with the repetition alone
+//! struct CollapseOptionalRepetition;
+//!
+//! impl SimplificationPass for CollapseOptionalRepetition {
+//!     fn name(&self) -> &'static str {
+//!         "collapse-optional-repetition"
+//!     }
+//!
+//!     fn apply<'a>(&self, node: Expr<'a>) -> Expr<'a> {
+//!         match node {
+//!             Expr::Optional { mut body, .. }
+//!                 if matches!(&body[..], [Expr::Repetition { one_needed: false, .. }]) =>
+//!             {
+//!                 body.pop().unwrap()
+//!             }
+//!             other => other,
+//!         }
+//!     }
+//! }
+//!
+//! let options = ParseOptions { simplify: false, ..ParseOptions::default() };
+//! let mut g = Grammar::new_with_options("list = [(item)*] | (a | b);", options).unwrap();
+//! let written = g["list"].body[0].node_count();
+//!
+//! let simplifier = Simplifier::default()
+//!     .without_pass(FlattenChoices.name())
+//!     .with_pass(CollapseOptionalRepetition);
+//! g.simplify_with(&simplifier);
+//! assert_eq!(g["list"].to_string(), "list = (((((item)))*)|(((a)|(b))));");
+//! assert!(g["list"].body[0].node_count() < written);
+//! ```
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::fmt::Debug;
+
+use crate::{Expr, Span, expr::NodeKind};
+
+/// A rewrite that a [`Simplifier`] applies to every node of a tree
+pub trait SimplificationPass {
+    /// A short name for the pass in kebab-case, such as `flatten-groups`, which picks it out in [`Simplifier::without_pass`]
+    fn name(&self) -> &'static str;
+
+    /// Rewrites a single node. Nodes are visited from the bottom up, so the children of `node` have already been rewritten, and the spans of the nodes above it are recalculated from whatever this returns.
+    fn apply<'a>(&self, node: Expr<'a>) -> Expr<'a>;
+}
+
+/// `flatten-groups`: splices the children of a [`Group`](Expr::Group) into the node containing it, and replaces a `Group` of a single node with that node
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlattenGroups;
+
+impl SimplificationPass for FlattenGroups {
+    fn name(&self) -> &'static str {
+        "flatten-groups"
+    }
+
+    fn apply<'a>(&self, node: Expr<'a>) -> Expr<'a> {
+        remove_redundant_layers(node)
+    }
+}
+
+/// `flatten-choices`: merges the alternatives of a [`Choice`](Expr::Choice) into a `Choice` directly containing it, if both were written with the same operator
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlattenChoices;
+
+impl SimplificationPass for FlattenChoices {
+    fn name(&self) -> &'static str {
+        "flatten-choices"
+    }
+
+    fn apply<'a>(&self, node: Expr<'a>) -> Expr<'a> {
+        flatten_choices(node)
+    }
+}
+
+/// Which [`SimplificationPass`]es [`Grammar::simplify_with`](crate::Grammar::simplify_with) applies, in order. Each pass is applied to the whole of a tree before the next begins. The default applies [`FlattenGroups`] and then [`FlattenChoices`], the same as parsing does.
+pub struct Simplifier {
+    passes: Vec<Box<dyn SimplificationPass>>,
+}
+
+impl Default for Simplifier {
+    fn default() -> Self {
+        Simplifier {
+            passes: vec![Box::new(FlattenGroups), Box::new(FlattenChoices)],
+        }
+    }
+}
+
+impl Debug for Simplifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Simplifier")
+            .field(
+                "passes",
+                &self.passes.iter().map(|p| p.name()).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Simplifier {
+    /// A simplifier with no passes at all, which leaves trees as they are unless passes are added with [`Simplifier::with_pass`]
+    pub fn empty() -> Self {
+        Simplifier { passes: Vec::new() }
+    }
+
+    /// Adds a pass to apply after those already added, replacing any already added with the same [`name`](SimplificationPass::name)
+    #[must_use]
+    pub fn with_pass(mut self, pass: impl SimplificationPass + 'static) -> Self {
+        self.passes.retain(|p| p.name() != pass.name());
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    /// Removes the pass with the given name, if there is one
+    #[must_use]
+    pub fn without_pass(mut self, name: &str) -> Self {
+        self.passes.retain(|p| p.name() != name);
+        self
+    }
+
+    /// Applies each pass in turn to `node` and everything within it
+    pub fn simplify(&self, node: &mut Expr<'_>) {
+        for pass in &self.passes {
+            node.apply_replacement(&mut |n| pass.apply(n));
+        }
+    }
+}
 
 pub(crate) fn simplify_node(n: &mut Expr) {
     n.apply_replacement(&mut remove_redundant_layers);
     n.apply_replacement(&mut flatten_choices);
 }
 
-fn remove_redundant_layers<'a>(n: &Expr<'a>) -> Option<Expr<'a>> {
-    fn flatten_groups<'a>(groups: &[Expr<'a>]) -> Vec<Expr<'a>> {
+fn remove_redundant_layers(n: Expr<'_>) -> Expr<'_> {
+    fn has_group(body: &[Expr<'_>]) -> bool {
+        body.iter().any(|e| NodeKind::from(e) == NodeKind::Group)
+    }
+    fn flatten_groups(groups: Vec<Expr<'_>>) -> Vec<Expr<'_>> {
         let mut new_body = vec![];
         for expr in groups {
-            match &expr {
-                Expr::Group { body, .. } => new_body.extend(body.iter().cloned()),
-                &other => new_body.push(other.clone()),
+            match expr {
+                Expr::Group { body, .. } => new_body.extend(body),
+                other => new_body.push(other),
             }
         }
         new_body
     }
     match n {
         Expr::Group { body, .. } => {
-            let new_body = flatten_groups(body);
+            let mut new_body = flatten_groups(body);
             if new_body.len() == 1 {
-                Some(new_body.first().unwrap().clone())
+                new_body.pop().unwrap()
             } else {
-                Some(Expr::Group {
+                Expr::Group {
                     span: Span::union(new_body.iter()),
                     body: new_body,
-                })
+                }
             }
         }
-        Expr::Optional { body, .. } => {
-            if body.iter().any(|e| ExprKind::from(e) == ExprKind::Group) {
-                let new_body = flatten_groups(body);
-                let span = Span::union(new_body.iter());
-
-                Some(Expr::Optional {
-                    span,
-                    body: new_body,
-                })
-            } else {
-                None
+        Expr::Optional { body, .. } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::Optional {
+                span: Span::union(body.iter()),
+                body,
+            }
+        }
+        Expr::And { body, .. } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::And {
+                span: Span::union(body.iter()),
+                body,
+            }
+        }
+        Expr::Not { body, .. } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::Not {
+                span: Span::union(body.iter()),
+                body,
+            }
+        }
+        Expr::Hidden { body, .. } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::Hidden {
+                span: Span::union(body.iter()),
+                body,
             }
         }
+        Expr::Labeled { label, body, .. } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::Labeled {
+                span: Span::union(body.iter()),
+                label,
+                body,
+            }
+        }
+        // A separator stays a single node, even if it's a group
         Expr::Repetition {
-            body, one_needed, ..
-        } => {
-            if body.iter().any(|e| ExprKind::from(e) == ExprKind::Group) {
-                let new_body = flatten_groups(body);
-                let span = Span::union(new_body.iter());
-
-                Some(Expr::Repetition {
-                    span,
-                    body: new_body,
-                    one_needed: *one_needed,
-                })
-            } else {
-                None
+            mut body,
+            one_needed,
+            separated: true,
+            ..
+        } if body.len() > 1 && has_group(&body[..body.len() - 1]) => {
+            let separator = body.pop().unwrap();
+            let mut body = flatten_groups(body);
+            body.push(separator);
+            Expr::Repetition {
+                span: Span::union(body.iter()),
+                body,
+                one_needed,
+                separated: true,
             }
         }
-
-        _ => None,
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated: false,
+            ..
+        } if has_group(&body) => {
+            let body = flatten_groups(body);
+            Expr::Repetition {
+                span: Span::union(body.iter()),
+                body,
+                one_needed,
+                separated: false,
+            }
+        }
+        other => other,
     }
 }
 
-fn flatten_choices<'a>(n: &Expr<'a>) -> Option<Expr<'a>> {
-    if let Expr::Choice { body, .. } = n
-        && body.iter().any(|m| matches!(m, Expr::Choice { .. }))
-    {
-        let mut outputs = vec![];
-        for child in body {
-            match child {
-                Expr::Choice { body, .. } => outputs.extend(body.iter().cloned()),
-                other => outputs.push(other.clone()),
+fn flatten_choices(n: Expr<'_>) -> Expr<'_> {
+    match n {
+        // Choices are only merged with others of the same kind, so `a | b / c` keeps its two levels
+        Expr::Choice { body, ordered, .. }
+            if body
+                .iter()
+                .any(|m| matches!(m, Expr::Choice { ordered: o, .. } if *o == ordered)) =>
+        {
+            let mut outputs = vec![];
+            for child in body {
+                match child {
+                    Expr::Choice {
+                        body, ordered: o, ..
+                    } if o == ordered => outputs.extend(body),
+                    other => outputs.push(other),
+                }
+            }
+            Expr::Choice {
+                span: Span::union(outputs.iter()),
+                body: outputs,
+                ordered,
             }
         }
-        let span = Span::union(outputs.iter());
-        Some(Expr::Choice {
-            span,
-            body: outputs,
-        })
-    } else {
-        None
+        other => other,
     }
 }
 
 #[cfg(test)]
 mod test {
+    use alloc::vec;
+
     use display_tree::AsTree;
 
-    use crate::{simplification::simplify_node, token_data::DUMMY_SPAN};
+    use crate::{
+        Expr, Grammar, ParseOptions,
+        simplification::{FlattenGroups, Simplifier, simplify_node},
+        token_data::DUMMY_SPAN,
+    };
+
+    /// Takes `expr` apart a level at a time, as dropping a very deep tree would recurse
+    fn dismantle(expr: Expr<'_>) {
+        let mut stack = vec![expr];
+        while let Some(mut node) = stack.pop() {
+            if let Some((_, body)) = node.span_and_body_mut() {
+                stack.append(body);
+            }
+        }
+    }
+
     #[test]
     fn flatten_choice1() {
         use crate::Expr::*;
@@ -106,12 +296,14 @@ mod test {
                             name: "nonterminal0028",
                         },
                     ],
+                    ordered: false,
                 },
                 Literal {
                     span: DUMMY_SPAN,
                     str: "literal1",
                 },
             ],
+            ordered: false,
         };
 
         let mut simplified = val.clone();
@@ -127,4 +319,88 @@ mod test {
              └─ 'literal1'
         ");
     }
+
+    #[test]
+    fn simplifier() {
+        let src = "A = ((a | (b | c)) [(d e)]) / (f / g); B = !(x) <(y z)>;";
+        let options = ParseOptions {
+            simplify: false,
+            ..ParseOptions::default()
+        };
+        let simplified = Grammar::new(src).unwrap();
+        let mut raw = Grammar::new_with_options(src, options).unwrap();
+        assert_ne!(raw, simplified);
+        raw.simplify_with(&Simplifier::default());
+        assert_eq!(raw, simplified);
+
+        let mut unchanged = Grammar::new_with_options(src, options).unwrap();
+        unchanged.simplify_with(&Simplifier::empty());
+        assert_eq!(unchanged, Grammar::new_with_options(src, options).unwrap());
+
+        let mut groups_only = Grammar::new_with_options(src, options).unwrap();
+        groups_only.simplify_with(&Simplifier::empty().with_pass(FlattenGroups));
+        let choice = &groups_only["A"].body[0];
+        assert!(matches!(choice, Expr::Choice { body, .. } if body.len() == 2));
+
+        let mut chain = Expr::new_raw("a | b | c").unwrap();
+        assert_eq!(chain.depth(), 3);
+        Simplifier::default().simplify(&mut chain);
+        assert_eq!(chain, Expr::new("a | b | c").unwrap());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        const LEVELS: usize = 100_000;
+        let mut node = Expr::Nonterminal {
+            span: DUMMY_SPAN,
+            name: "x",
+        };
+        for level in 0..LEVELS {
+            let body = vec![node];
+            node = if level % 2 == 0 {
+                Expr::Group {
+                    span: DUMMY_SPAN,
+                    body,
+                }
+            } else {
+                Expr::Optional {
+                    span: DUMMY_SPAN,
+                    body,
+                }
+            };
+        }
+        assert_eq!(node.depth(), LEVELS + 1);
+
+        simplify_node(&mut node);
+        assert_eq!(node.depth(), LEVELS / 2 + 1);
+        let mut innermost = &node;
+        while let [child] = innermost.children() {
+            assert!(!matches!(child, Expr::Group { .. }));
+            innermost = child;
+        }
+        assert!(matches!(innermost, Expr::Nonterminal { name: "x", .. }));
+
+        dismantle(node);
+
+        let mut node = Expr::Nonterminal {
+            span: DUMMY_SPAN,
+            name: "x",
+        };
+        // Each level moves every alternative found below it, so this is kept shorter
+        let alternatives = LEVELS / 10;
+        for _ in 0..alternatives {
+            let alternative = Expr::Literal {
+                span: DUMMY_SPAN,
+                str: "y",
+            };
+            node = Expr::Choice {
+                span: DUMMY_SPAN,
+                body: vec![alternative, node],
+                ordered: false,
+            };
+        }
+        simplify_node(&mut node);
+        assert_eq!(node.depth(), 2);
+        assert_eq!(node.children().len(), alternatives + 1);
+    }
 }
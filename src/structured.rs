@@ -0,0 +1,423 @@
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{Annotation, ChoiceSemantics, Expr, Grammar, Import, Rule, Span};
+
+/// A description of a [`Grammar`] that owns all of its text, for exchanging grammars with tools that don't read EBNF. With the `serde` feature, this and the types it contains can be serialized, and the schema below is kept stable between versions: fields may be added, but none will be renamed or removed.
+///
+/// As JSON, the grammar `<ws> = ' '+;` is
+/// ```json
+/// {
+///   "rules": [
+///     {
+///       "name": "ws",
+///       "hidden": true,
+///       "annotations": [],
+///       "definitions": [{ "start": 0, "end": 12, "start_line": [1, 0], "end_line": [1, 12] }],
+///       "body": [
+///         {
+///           "kind": "Repetition",
+///           "span": { "start": 7, "end": 10, "start_line": [1, 7], "end_line": [1, 10] },
+///           "one_needed": true,
+///           "children": [
+///             {
+///               "kind": "Literal",
+///               "span": { "start": 7, "end": 10, "start_line": [1, 7], "end_line": [1, 10] },
+///               "text": " "
+///             }
+///           ]
+///         }
+///       ]
+///     }
+///   ],
+///   "imports": [],
+///   "ordered_choice": false
+/// }
+/// ```
+/// Rules are listed in the order of their names, and each node has a `kind` naming the variant of [`Expr`] it stands for.
+/// ```rust
+/// # use ebnf_toolkit::{Grammar, StructuredNode};
+/// let g = Grammar::new("greeting = 'hello' (name | 'world');").unwrap();
+/// let structured = g.to_structured();
+/// assert_eq!(structured.rules[0].name, "greeting");
+/// assert!(matches!(&structured.rules[0].body[1], StructuredNode::Choice { children, .. } if children.len() == 2));
+/// assert_eq!(Grammar::from_structured(&structured), g);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredGrammar {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub rules: Vec<StructuredRule>,
+    /// The `%import` directives, in the order they were written
+    pub imports: Vec<StructuredImport>,
+    /// Whether the grammar has [ordered](ChoiceSemantics::Ordered) rather than unordered choice. This was added later than the other fields, so it is taken to be `false` when missing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ordered_choice: bool,
+}
+
+/// A [`Rule`] within a [`StructuredGrammar`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredRule {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub name: String,
+    /// Whether the rule's name was written in angle brackets, as in [`Rule::hidden`]
+    pub hidden: bool,
+    /// The annotations written before the rule
+    pub annotations: Vec<StructuredAnnotation>,
+    /// Each place the rule was defined, as in [`Rule::definitions`]
+    pub definitions: Vec<StructuredSpan>,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub body: Vec<StructuredNode>,
+}
+
+/// A node of a rule's body within a [`StructuredGrammar`], standing for the [`Expr`] variant of the same name
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "kind")
+)]
+#[non_exhaustive]
+#[expect(missing_docs, reason = "Obvious")]
+pub enum StructuredNode {
+    Literal {
+        span: StructuredSpan,
+        text: String,
+    },
+    Nonterminal {
+        span: StructuredSpan,
+        name: String,
+    },
+    Regex {
+        span: StructuredSpan,
+        pattern: String,
+    },
+    Empty {
+        span: StructuredSpan,
+    },
+    Choice {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+        /// Whether the alternatives were separated by `/`. Taken to be `false` when missing.
+        #[cfg_attr(feature = "serde", serde(default))]
+        ordered: bool,
+    },
+    Optional {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+    },
+    Repetition {
+        span: StructuredSpan,
+        one_needed: bool,
+        /// Whether the last of `children` is a separator, as for [`Expr::repetition_parts`]. Taken to be `false` when missing.
+        #[cfg_attr(feature = "serde", serde(default))]
+        separated: bool,
+        children: Vec<StructuredNode>,
+    },
+    Group {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+    },
+    And {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+    },
+    Not {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+    },
+    Hidden {
+        span: StructuredSpan,
+        children: Vec<StructuredNode>,
+    },
+    Labeled {
+        span: StructuredSpan,
+        label: String,
+        children: Vec<StructuredNode>,
+    },
+}
+
+/// A [`Span`] within a [`StructuredGrammar`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredSpan {
+    /// As in [`Span::start`]
+    pub start: usize,
+    /// As in [`Span::end`]
+    pub end: usize,
+    /// As in [`Span::start_line`]
+    pub start_line: (usize, usize),
+    /// As in [`Span::end_line`]
+    pub end_line: (usize, usize),
+}
+
+/// An [`Import`] within a [`StructuredGrammar`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredImport {
+    #[expect(missing_docs, reason = "Obvious")]
+    pub path: String,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub span: StructuredSpan,
+}
+
+/// An [`Annotation`] within a [`StructuredGrammar`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredAnnotation {
+    /// The name of the annotation, without its `@`
+    pub name: String,
+    #[expect(missing_docs, reason = "Obvious")]
+    pub span: StructuredSpan,
+}
+
+impl From<Span> for StructuredSpan {
+    fn from(span: Span) -> Self {
+        StructuredSpan {
+            start: span.start(),
+            end: span.end(),
+            start_line: span.start_line(),
+            end_line: span.end_line(),
+        }
+    }
+}
+
+impl From<StructuredSpan> for Span {
+    fn from(span: StructuredSpan) -> Self {
+        Span::new(span.start..span.end, span.start_line, span.end_line)
+    }
+}
+
+pub(crate) fn to_structured(grammar: &Grammar<'_>) -> StructuredGrammar {
+    StructuredGrammar {
+        rules: grammar.rules.values().map(rule_to_structured).collect(),
+        imports: grammar
+            .imports
+            .iter()
+            .map(|import| StructuredImport {
+                path: import.path.to_string(),
+                span: import.span.into(),
+            })
+            .collect(),
+        ordered_choice: grammar.choice_semantics == ChoiceSemantics::Ordered,
+    }
+}
+
+fn rule_to_structured(rule: &Rule<'_>) -> StructuredRule {
+    StructuredRule {
+        name: rule.name.to_string(),
+        hidden: rule.hidden,
+        annotations: rule
+            .annotations
+            .iter()
+            .map(|annotation| StructuredAnnotation {
+                name: annotation.name.to_string(),
+                span: annotation.span.into(),
+            })
+            .collect(),
+        definitions: rule.definitions.iter().map(StructuredSpan::from).collect(),
+        body: rule.body.iter().map(node_to_structured).collect(),
+    }
+}
+
+fn node_to_structured(node: &Expr<'_>) -> StructuredNode {
+    let span = node.span().into();
+    let children = |body: &[Expr<'_>]| body.iter().map(node_to_structured).collect();
+    match node {
+        Expr::Literal { str, .. } => StructuredNode::Literal {
+            span,
+            text: str.to_string(),
+        },
+        Expr::Nonterminal { name, .. } => StructuredNode::Nonterminal {
+            span,
+            name: name.to_string(),
+        },
+        Expr::Regex { pattern, .. } => StructuredNode::Regex {
+            span,
+            pattern: pattern.to_string(),
+        },
+        Expr::Empty { .. } => StructuredNode::Empty { span },
+        Expr::Choice { body, ordered, .. } => StructuredNode::Choice {
+            span,
+            children: children(body),
+            ordered: *ordered,
+        },
+        Expr::Optional { body, .. } => StructuredNode::Optional {
+            span,
+            children: children(body),
+        },
+        Expr::Repetition {
+            body,
+            one_needed,
+            separated,
+            ..
+        } => StructuredNode::Repetition {
+            span,
+            one_needed: *one_needed,
+            separated: *separated,
+            children: children(body),
+        },
+        Expr::Group { body, .. } => StructuredNode::Group {
+            span,
+            children: children(body),
+        },
+        Expr::And { body, .. } => StructuredNode::And {
+            span,
+            children: children(body),
+        },
+        Expr::Not { body, .. } => StructuredNode::Not {
+            span,
+            children: children(body),
+        },
+        Expr::Hidden { body, .. } => StructuredNode::Hidden {
+            span,
+            children: children(body),
+        },
+        Expr::Labeled { label, body, .. } => StructuredNode::Labeled {
+            span,
+            label: label.to_string(),
+            children: children(body),
+        },
+        Expr::Rule { .. } | Expr::UnparsedOperator { .. } => {
+            unreachable!("{node:?} found inside a grammar")
+        }
+    }
+}
+
+pub(crate) fn from_structured(structured: &StructuredGrammar) -> Grammar<'_> {
+    let mut grammar: Grammar<'_> = structured
+        .rules
+        .iter()
+        .map(|rule| Rule {
+            name: Cow::Borrowed(&rule.name),
+            body: nodes_from_structured(&rule.body),
+            definitions: rule.definitions.iter().map(|s| Span::from(*s)).collect(),
+            hidden: rule.hidden,
+            annotations: rule
+                .annotations
+                .iter()
+                .map(|annotation| Annotation {
+                    name: &annotation.name,
+                    span: annotation.span.into(),
+                })
+                .collect(),
+        })
+        .collect();
+    grammar.imports = structured
+        .imports
+        .iter()
+        .map(|import| Import {
+            path: &import.path,
+            span: import.span.into(),
+        })
+        .collect();
+    if structured.ordered_choice {
+        grammar.choice_semantics = ChoiceSemantics::Ordered;
+    }
+    grammar
+}
+
+fn nodes_from_structured(nodes: &[StructuredNode]) -> Vec<Expr<'_>> {
+    nodes.iter().map(node_from_structured).collect()
+}
+
+fn node_from_structured(node: &StructuredNode) -> Expr<'_> {
+    match node {
+        StructuredNode::Literal { span, text } => Expr::Literal {
+            span: (*span).into(),
+            str: text,
+        },
+        StructuredNode::Nonterminal { span, name } => Expr::Nonterminal {
+            span: (*span).into(),
+            name,
+        },
+        StructuredNode::Regex { span, pattern } => Expr::Regex {
+            span: (*span).into(),
+            pattern,
+        },
+        StructuredNode::Empty { span } => Expr::Empty {
+            span: (*span).into(),
+        },
+        StructuredNode::Choice {
+            span,
+            children,
+            ordered,
+        } => Expr::Choice {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+            ordered: *ordered,
+        },
+        StructuredNode::Optional { span, children } => Expr::Optional {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+        },
+        StructuredNode::Repetition {
+            span,
+            one_needed,
+            separated,
+            children,
+        } => Expr::Repetition {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+            one_needed: *one_needed,
+            separated: *separated,
+        },
+        StructuredNode::Group { span, children } => Expr::Group {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+        },
+        StructuredNode::And { span, children } => Expr::And {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+        },
+        StructuredNode::Not { span, children } => Expr::Not {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+        },
+        StructuredNode::Hidden { span, children } => Expr::Hidden {
+            span: (*span).into(),
+            body: nodes_from_structured(children),
+        },
+        StructuredNode::Labeled {
+            span,
+            label,
+            children,
+        } => Expr::Labeled {
+            span: (*span).into(),
+            label,
+            body: nodes_from_structured(children),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::format;
+
+    use crate::{Grammar, StructuredNode};
+
+    #[test]
+    fn structured_roundtrip() {
+        let src = "%import 'x';\n@token <A> = !B <'c'>* [#'d'] & e;";
+        let g = Grammar::new(src).unwrap();
+        let structured = g.to_structured();
+
+        assert_eq!(structured.imports[0].path, "x");
+        let rule = &structured.rules[0];
+        assert!(rule.hidden);
+        assert_eq!(rule.annotations[0].name, "token");
+        assert_eq!(rule.body.len(), 4);
+        assert!(matches!(
+            &rule.body[1],
+            StructuredNode::Repetition { one_needed: false, children, .. }
+                if matches!(children[..], [StructuredNode::Hidden { .. }])
+        ));
+
+        let rebuilt = Grammar::from_structured(&structured);
+        assert_eq!(format!("{rebuilt:?}"), format!("{g:?}"));
+    }
+}
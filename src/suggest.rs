@@ -0,0 +1,58 @@
+use alloc::vec::Vec;
+
+/// The number of single-character insertions, deletions and substitutions needed to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = Vec::with_capacity(b.len() + 1);
+    for (i, a_char) in a.chars().enumerate() {
+        current.clear();
+        current.push(i + 1);
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            let insertion = current[j] + 1;
+            let deletion = previous[j + 1] + 1;
+            current.push(substitution.min(insertion).min(deletion));
+        }
+        core::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Picks the candidate closest to `name`, as a suggestion for what a misspelt name was meant to be.
+///
+/// Candidates further than a third of the length of `name` away (but always allowing one edit) aren't considered, as they're more likely to be unrelated names than typos, and nor are those that would need every character of `name` changed, so one-letter names don't suggest each other. Ties go to the earliest candidate.
+pub(crate) fn closest_name<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Option<&'c str> {
+    let len = name.chars().count();
+    let limit = (len / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit && *distance < len)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{closest_name, edit_distance};
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("名前", "名"), 1);
+
+        let names = ["client_prefix", "server_prefix", "nick"];
+        assert_eq!(closest_name("clinet_prefix", names), Some("client_prefix"));
+        assert_eq!(closest_name("nik", names), Some("nick"));
+        assert_eq!(closest_name("prefix", names), None);
+        assert_eq!(closest_name("nick", names), None);
+        assert_eq!(closest_name("B", ["A", "Bb"]), None);
+        assert_eq!(closest_name("Bb", ["A", "B"]), Some("B"));
+    }
+}
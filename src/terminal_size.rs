@@ -0,0 +1,103 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Expr, Rule, Span};
+
+/// The largest literals and regexes that [`Grammar::oversized_terminals`](crate::Grammar::oversized_terminals) lets through without a warning, measured in bytes of their contents without quotes. A terminal this large is rarely intended - it is more often a file pasted into the wrong place or generated by a tool gone wrong - and it slows down every analysis that looks at terminals, such as regex overlap checks, without anything to show for it. To refuse such input instead, set [`ParseOptions::max_literal`](crate::ParseOptions::max_literal) and [`ParseOptions::max_regex`](crate::ParseOptions::max_regex), which are checked before the input is parsed.
+///
+/// Storing large terminals behind an `Arc<str>`, so that copying a node doesn't copy its text, was considered and left out: the text of a terminal is already borrowed from the input rather than copied, so sharing it would add an allocation rather than save one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerminalLimits {
+    /// The longest a [`Literal`](Expr::Literal) may be
+    pub max_literal: usize,
+    /// The longest the pattern of a [`Regex`](Expr::Regex) may be
+    pub max_regex: usize,
+}
+
+impl Default for TerminalLimits {
+    /// 1 KiB for both
+    fn default() -> Self {
+        TerminalLimits {
+            max_literal: 1024,
+            max_regex: 1024,
+        }
+    }
+}
+
+/// A literal or regex that is larger than a [`TerminalLimits`] allows
+/// ```rust
+/// # use ebnf_toolkit::{Grammar, TerminalLimits};
+/// let src = format!("blob = '{}' | #'[a-z]+';", "x".repeat(2000));
+/// let g = Grammar::new(&src).unwrap();
+/// let [found] = &g.oversized_terminals(TerminalLimits::default())[..] else { panic!() };
+/// assert_eq!((found.rule, found.len, found.limit), ("blob", 2000, 1024));
+/// assert!(!found.is_regex);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OversizedTerminal<'a> {
+    /// The name of the rule the terminal appears in
+    pub rule: &'a str,
+    /// Where the terminal is, including its quotes
+    pub span: Span,
+    /// Whether the terminal is a regex rather than a literal
+    pub is_regex: bool,
+    /// The size of the terminal's contents in bytes
+    pub len: usize,
+    /// The limit it exceeds
+    pub limit: usize,
+}
+
+pub(crate) fn rule_oversized_terminals<'a>(
+    rule: &'a Rule<'_>,
+    limits: TerminalLimits,
+) -> Vec<OversizedTerminal<'a>> {
+    let mut found = vec![];
+    let mut stack: Vec<_> = rule.body.iter().collect();
+    while let Some(node) = stack.pop() {
+        let (span, len, is_regex, limit) = match node {
+            Expr::Literal { span, str } => (*span, str.len(), false, limits.max_literal),
+            Expr::Regex { span, pattern } => (*span, pattern.len(), true, limits.max_regex),
+            other => {
+                stack.extend(other.children());
+                continue;
+            }
+        };
+        if len > limit {
+            found.push(OversizedTerminal {
+                rule: &rule.name,
+                span,
+                is_regex,
+                len,
+                limit,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{format, vec::Vec};
+
+    use crate::{Grammar, TerminalLimits};
+
+    #[test]
+    fn oversized() {
+        let src = format!("A = 'abc' ('abcd' | #'{}'); B = [#'.'];", ".".repeat(9));
+        let g = Grammar::new(&src).unwrap();
+        let limits = TerminalLimits {
+            max_literal: 3,
+            max_regex: 8,
+        };
+
+        let found: Vec<_> = g
+            .oversized_terminals(limits)
+            .iter()
+            .map(|o| (o.rule, &src[o.span.range()], o.is_regex, o.len))
+            .collect();
+        assert_eq!(
+            found,
+            [("A", "'abcd'", false, 4), ("A", "#'.........'", true, 9)]
+        );
+        assert!(g.oversized_terminals(TerminalLimits::default()).is_empty());
+    }
+}
@@ -1,13 +1,18 @@
-use std::{
+use alloc::{vec, vec::Vec};
+use core::{
     fmt::{Debug, Display},
     hash::Hash,
+    iter::Copied,
     ops::Range,
+    slice,
 };
 
 use logos::{Lexer, Logos, Skip};
 use strum::{Display, EnumDiscriminants, EnumProperty, IntoStaticStr, VariantArray};
+#[cfg(feature = "unicode-width")]
+use unicode_width::UnicodeWidthStr;
 
-use crate::{Expr, error::EbnfError};
+use crate::{Expr, Import, error::EbnfError};
 
 /// Sentinel for testing
 /// Compares equal to any other Span so literal objects don't have to synthesize one to make [`Expr`] compare equal
@@ -19,12 +24,12 @@ pub(crate) const DUMMY_SPAN: Span = Span {
     line_offset_end: (u32::MAX - 1, 2),
 };
 
-/// A half-open range of the input string a `Node` came from
+/// A half-open range of the input string a `Node` came from. The default span is empty and lies at the very start of the input.
 #[allow(
     clippy::derived_hash_with_manual_eq,
     reason = "Overrides PEq for dummy values in test mode, but we don't need to do any hashes"
 )]
-#[derive(Debug, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
 pub struct Span {
     start: usize,
     end: usize,
@@ -35,6 +40,22 @@ pub struct Span {
 }
 
 impl Span {
+    /// Creates a span covering the bytes of `range`, where `start_line` and `end_line` are the line and byte offset within the line of each end, as returned by [`Span::start_line`] and [`Span::end_line`]. Lines count from 1.
+    ///
+    /// This is mostly useful for moving existing spans with [`Expr::map_spans`] after the input has been edited - the positions aren't checked against each other or any input.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "No line will be 2^32 bytes long"
+    )]
+    pub fn new(range: Range<usize>, start_line: (usize, usize), end_line: (usize, usize)) -> Span {
+        Span {
+            start: range.start,
+            end: range.end,
+            line_offset_start: (start_line.0 as u32, start_line.1 as u32),
+            line_offset_end: (end_line.0 as u32, end_line.1 as u32),
+        }
+    }
+
     /// Beginning of the span, as a byte offset across potentially multiple lines of string
     pub fn start(&self) -> usize {
         self.start
@@ -60,25 +81,181 @@ impl Span {
         (line as usize, offset as _)
     }
 
+    /// The columns the start and end of this span are drawn at on their lines in `input`, the text the span came from, as opposed to the byte offsets of [`Span::start_line`] and [`Span::end_line`]. Each character counts for the number of terminal cells it takes up, so CJK ideographs count twice and combining marks not at all, which is where a caret needs to go to line up with the text above it.
+    /// ```rust
+    /// # use ebnf_toolkit::Rule;
+    /// let src = "名前 ::= 'é' x;";
+    /// let rule = Rule::new(src).unwrap();
+    /// let x = rule.body[1].span();
+    /// assert_eq!(x.start_line(), (1, 16));
+    /// assert_eq!(x.display_columns(src), (13, 14));
+    /// ```
+    #[cfg(feature = "unicode-width")]
+    pub fn display_columns(&self, input: &str) -> (usize, usize) {
+        let column = |offset: usize| {
+            let line_start = input[..offset].rfind('\n').map_or(0, |n| n + 1);
+            input[line_start..offset].width()
+        };
+        (column(self.start), column(self.end))
+    }
+
     pub(crate) fn union<'a>(iter: impl Iterator<Item = &'a Expr<'a>>) -> Span {
         iter.map(Expr::span)
-            .reduce(|s, t| {
-                let min = if s.start < t.start { s } else { t };
-                let max = if s.end > t.end { s } else { t };
-
-                Span {
-                    start: min.start,
-                    end: max.end,
-                    line_offset_start: min.line_offset_start,
-                    line_offset_end: max.line_offset_end,
-                }
-            })
+            .reduce(Span::join)
             .expect("Asked for span of empty list")
     }
+
+    /// The `len` bytes of this span that follow the first `prefix` bytes and then the text `skipped`. The part being selected can't contain a newline.
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "No line will be 2^32 bytes long"
+    )]
+    pub(crate) fn subspan(self, prefix: usize, skipped: &str, len: usize) -> Span {
+        let (mut line, mut column) = self.line_offset_start;
+        column += prefix as u32;
+        match skipped.rfind('\n') {
+            Some(last_newline) => {
+                line += skipped.matches('\n').count() as u32;
+                column = (skipped.len() - last_newline - 1) as u32;
+            }
+            None => column += skipped.len() as u32,
+        }
+        let start = self.start + prefix + skipped.len();
+        Span {
+            start,
+            end: start + len,
+            line_offset_start: (line, column),
+            line_offset_end: (line, column + len as u32),
+        }
+    }
+
+    /// This span moved as if its input started `byte_offset` bytes into a larger document, at byte `column` of `line` there
+    #[allow(
+        clippy::cast_possible_truncation,
+        reason = "No line will be 2^32 bytes long"
+    )]
+    pub(crate) fn relative_to(self, byte_offset: usize, line: usize, column: usize) -> Span {
+        // Only the first line of the input shares a line with whatever came before it in the document
+        let shift = |(l, c): (u32, u32)| {
+            if l == 1 {
+                (line as u32, column as u32 + c)
+            } else {
+                (line as u32 + l - 1, c)
+            }
+        };
+        Span {
+            start: byte_offset + self.start,
+            end: byte_offset + self.end,
+            line_offset_start: shift(self.line_offset_start),
+            line_offset_end: shift(self.line_offset_end),
+        }
+    }
+
+    /// The empty span at the end of this one
+    pub(crate) fn end_point(self) -> Span {
+        Span {
+            start: self.end,
+            line_offset_start: self.line_offset_end,
+            ..self
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`
+    pub(crate) fn join(self, other: Span) -> Span {
+        let min = if self.start < other.start {
+            self
+        } else {
+            other
+        };
+        let max = if self.end > other.end { self } else { other };
+
+        Span {
+            start: min.start,
+            end: max.end,
+            line_offset_start: min.line_offset_start,
+            line_offset_end: max.line_offset_end,
+        }
+    }
+}
+
+/// A collection of [`Span`]s, for constructs drawn from several separate regions of the input, such as a [`Rule`](crate::Rule) with more than one definition.
+///
+/// The spans are kept ordered by where they start, and are not merged with each other even if they touch.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SpanSet(Vec<Span>);
+
+impl SpanSet {
+    /// Iterates through the spans in order of where they start
+    pub fn iter(&self) -> Copied<slice::Iter<'_, Span>> {
+        self.0.iter().copied()
+    }
+
+    /// The number of spans in the set
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the set has no spans at all
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds a span to the set, keeping it in order
+    pub fn insert(&mut self, span: Span) {
+        let position = self.0.partition_point(|s| s.start <= span.start);
+        self.0.insert(position, span);
+    }
+
+    /// The smallest single [`Span`] covering every span in the set, or `None` if it is empty. This will usually include parts of the input that none of the spans do.
+    pub fn hull(&self) -> Option<Span> {
+        self.iter().reduce(Span::join)
+    }
+}
+
+impl From<Span> for SpanSet {
+    fn from(span: Span) -> Self {
+        SpanSet(vec![span])
+    }
+}
+
+impl<'a> IntoIterator for &'a SpanSet {
+    type Item = Span;
+    type IntoIter = Copied<slice::Iter<'a, Span>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Span> for SpanSet {
+    fn from_iter<T: IntoIterator<Item = Span>>(iter: T) -> Self {
+        let mut set = SpanSet::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Span> for SpanSet {
+    fn extend<T: IntoIterator<Item = Span>>(&mut self, iter: T) {
+        for span in iter {
+            self.insert(span);
+        }
+    }
+}
+
+impl Display for SpanSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (n, span) in self.iter().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{span}")?;
+        }
+        Ok(())
+    }
 }
 
 impl Display for Span {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         #[cfg(test)]
         if self.start == DUMMY_SPAN.start {
             return write!(f, "[DUMMY]");
@@ -103,79 +280,148 @@ impl PartialEq for Span {
     }
 }
 
+/// One token of a grammar's source, as produced by [`lex`]
 #[derive(Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Token<'a> {
-    pub(crate) span: Span,
-    pub(crate) payload: TokenPayload<'a>,
+pub struct Token<'a> {
+    /// Where the token is, including any quotes or sigils
+    pub span: Span,
+    /// What the token is, and the text it carries if any
+    pub payload: TokenPayload<'a>,
+}
+
+impl Token<'_> {
+    /// What kind of token this is, without its text
+    pub fn kind(&self) -> TokenKind {
+        TokenKind::from(self.payload)
+    }
 }
 
 impl Debug for Token<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         #[allow(clippy::enum_glob_use)]
         use TokenPayload::*;
-        let kind = TokenKind::from(self.payload);
+        let kind = self.kind();
         let span = &self.span;
 
         write!(f, "{kind} {span}")?;
         match &self.payload {
-            Regex(s) | Identifier(s) | String(s) => {
+            Regex(s) | Identifier(s) | String(s) | Import(s) | Annotation(s) => {
                 write!(f, "(\"{}\")", s.escape_debug())
             }
-            Kleene | Repeat | Equals | Termination | Alternation | Optional | OpeningGroup
-            | ClosingGroup | OpeningSquare | ClosingSquare | OpeningBrace | ClosingBrace
-            | Newline => Ok(()),
+            Kleene | Repeat | Separator | And | Not | Equals | Termination | Alternation
+            | OrderedAlternation | Optional | OpeningGroup | ClosingGroup | OpeningSquare
+            | ClosingSquare | OpeningBrace | ClosingBrace | OpeningAngle | ClosingAngle | Colon
+            | Epsilon | Newline => Ok(()),
         }
     }
 }
 
 #[derive(EnumDiscriminants, IntoStaticStr, EnumProperty)]
-#[strum_discriminants(name(TokenKind), derive(VariantArray, Display, PartialOrd, Ord))]
+#[strum_discriminants(
+    name(TokenKind),
+    derive(VariantArray, Display, PartialOrd, Ord),
+    doc = "The kind of a [`Token`], without the text of identifiers, strings and the like"
+)]
 #[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
 #[logos(skip "[[:space:]]")]
 #[logos(skip ",")]
 #[logos(skip "// [^\\n\\r]*")]
 #[logos(extras = (usize, usize))]
+/// The contents of a [`Token`]. Whitespace, commas and comments separate tokens but aren't tokens themselves.
+#[non_exhaustive]
 pub enum TokenPayload<'a> {
+    /// The name of a rule or nonterminal
     #[regex(r"[\w_]*")]
     Identifier(&'a str),
+    /// A literal, without its quotes
     #[regex(r##"'(?:[^'\\]|\\.)*'"##, |l| &l.slice()[1..l.slice().len()-1])]
     #[regex(r##""(?:[^"\\]|\\.)*""##, |l| &l.slice()[1..l.slice().len()-1])]
     String(&'a str),
+    /// A regex pattern, without its `#` and quotes
     #[regex("#\"[^\"]+\"", |l| &l.slice()[2..l.slice().len()-1])]
     #[regex(r"#'[^']+'", |l| &l.slice()[2..l.slice().len()-1])]
     Regex(&'a str),
+    /// `=` or `::=`
     #[token("=")]
     #[token("::=")]
     Equals,
+    /// `;`
     #[token(";")]
     Termination,
+    /// `|`
     #[token("|")]
-    #[token("/")]
     Alternation,
+    /// `/`, for [ordered](crate::ChoiceSemantics::Ordered) choice
+    #[token("/")]
+    OrderedAlternation,
+    /// `ε`, which matches the empty string
+    #[token("ε")]
+    Epsilon,
+    /// `?`
     #[token("?")]
     Optional,
+    /// `*`
     #[token("*")]
     Kleene,
+    /// `+`
     #[token("+")]
     Repeat,
+    /// `%`, between a repeated term and its separator
+    #[token("%")]
+    Separator,
+    /// `&`
+    #[token("&")]
+    And,
+    /// `!`
+    #[token("!")]
+    Not,
+    /// `<`
+    #[token("<")]
+    OpeningAngle,
+    /// `>`
+    #[token(">")]
+    ClosingAngle,
+    /// `:`
+    #[token(":")]
+    Colon,
+    /// `(`
     #[token("(")]
     OpeningGroup,
+    /// `)`
     #[token(")")]
     ClosingGroup,
+    /// `[`
     #[token("[")]
     OpeningSquare,
+    /// `]`
     #[token("]")]
     ClosingSquare,
+    /// `{`
     #[token("{")]
     OpeningBrace,
+    /// `}`
     #[token("}")]
     ClosingBrace,
+    /// A whole `%import` directive, carrying the path without its quotes
+    #[regex(r#"%import[ \t]+(?:"[^"]*"|'[^']*')[ \t]*;"#, import_path)]
+    Import(&'a str),
+    /// An annotation such as `@token`, without its `@`
+    #[regex(r"@[\w_]+", |l| &l.slice()[1..])]
+    Annotation(&'a str),
+    /// Line breaks are skipped like other whitespace, so this is never produced
     #[token("\n", line_counter, priority = 20)]
     #[token("\r", line_counter, priority = 20)]
     #[token("\r\n", line_counter)]
     Newline,
 }
 
+fn import_path<'a>(lex: &mut Lexer<'a, TokenPayload<'a>>) -> &'a str {
+    let slice = lex.slice();
+    let start = slice.find(['"', '\'']).unwrap() + 1;
+    let end = slice.rfind(['"', '\'']).unwrap();
+    &slice[start..end]
+}
+
 fn line_counter<'a>(lex: &mut Lexer<'a, TokenPayload<'a>>) -> Skip {
     #[expect(
         clippy::naive_bytecount,
@@ -192,17 +438,92 @@ fn line_counter<'a>(lex: &mut Lexer<'a, TokenPayload<'a>>) -> Skip {
     Skip
 }
 
+/// Splits a grammar into tokens without parsing it, for tools such as syntax highlighters that only need to know what each part of the input is. Any `%import` directives are included as [`Import`](TokenPayload::Import) tokens in the order they appear.
+/// # Errors
+/// Like [`Grammar::new`](crate::Grammar::new), this fails with a [`LexError`](EbnfError::LexError) on text that isn't a token, an `%import` inside a rule, or an annotation that isn't before a rule.
+/// ```rust
+/// # use ebnf_toolkit::{TokenKind, lex};
+/// let tokens = lex("greeting = 'hello' name?;").unwrap();
+/// let kinds: Vec<_> = tokens.iter().map(|t| t.kind()).collect();
+/// assert_eq!(
+///     kinds,
+///     [
+///         TokenKind::Identifier,
+///         TokenKind::Equals,
+///         TokenKind::String,
+///         TokenKind::Identifier,
+///         TokenKind::Optional,
+///         TokenKind::Termination,
+///     ]
+/// );
+/// assert_eq!(tokens[2].span.range(), 11..18);
+/// ```
+pub fn lex(input: &str) -> Result<Vec<Token<'_>>, EbnfError<'_>> {
+    let (mut tokens, imports) = tokenize_with_imports(input)?;
+    tokens.extend(imports.into_iter().map(|Import { path, span }| Token {
+        span,
+        payload: TokenPayload::Import(path),
+    }));
+    tokens.sort_by_key(|t| t.span.start());
+    Ok(tokens)
+}
+
+/// Tokenizes input that can't contain `%import` directives, such as a single rule or expression
 pub(crate) fn tokenize(input: &str) -> Result<Vec<Token<'_>>, EbnfError<'_>> {
-    let mut lexer = TokenPayload::lexer(input).spanned();
+    let (tokens, imports) = tokenize_with_imports(input)?;
+    if let Some(import) = imports.first() {
+        return Err(EbnfError::LexError {
+            input,
+            offset: import.span.start(),
+        });
+    }
+    Ok(tokens)
+}
+
+/// The offset just after the `;` ending the first rule of `input`, or the length of `input` if no rule ends before the end of the input or a character that can't be tokenized
+pub(crate) fn first_rule_end(input: &str) -> usize {
+    TokenPayload::lexer(input)
+        .spanned()
+        .map_while(|(payload, span)| payload.ok().map(|payload| (payload, span)))
+        .find(|(payload, _)| *payload == TokenPayload::Termination)
+        .map_or(input.len(), |(_, span)| span.end)
+}
+
+/// Tokenizes a whole grammar, separating out any `%import` directives it contains. A directive in the middle of a rule is a [`LexError`](EbnfError::LexError).
+pub(crate) fn tokenize_with_imports(
+    input: &str,
+) -> Result<(Vec<Token<'_>>, Vec<Import<'_>>), EbnfError<'_>> {
+    tokenize_fragment(input, 0..input.len())
+}
+
+/// Tokenizes the part of `document` within `range` as for [`tokenize_with_imports`], giving spans and errors relative to the whole document
+pub(crate) fn tokenize_fragment(
+    document: &str,
+    range: Range<usize>,
+) -> Result<(Vec<Token<'_>>, Vec<Import<'_>>), EbnfError<'_>> {
+    let base = range.start;
+    let preceding = &document[..base];
+    #[expect(
+        clippy::naive_bytecount,
+        reason = "Don't need a whole dependency for doing this once"
+    )]
+    let base_line = preceding.as_bytes().iter().filter(|b| **b == b'\n').count();
+    let base_column = base - preceding.rfind('\n').map_or(0, |n| n + 1);
+
+    let mut lexer = TokenPayload::lexer(&document[range]).spanned();
 
     let mut output = Vec::new();
+    let mut imports = Vec::new();
 
     while let Some((payload, s)) = lexer.next() {
         let (line_count, last_newline_offset) = lexer.extras;
         if let Ok(payload) = payload {
             let Range { start, end } = s;
-            let line_offset_start = start - last_newline_offset;
-            let line_offset_end = end - last_newline_offset;
+            let column = if line_count == 0 { base_column } else { 0 };
+            let line_offset_start = column + start - last_newline_offset;
+            let line_offset_end = column + end - last_newline_offset;
+            let (start, end) = (base + start, base + end);
+            let line = base_line + line_count;
             #[allow(
                 clippy::cast_possible_truncation,
                 reason = "No line will be 2^32 bytes long"
@@ -210,26 +531,64 @@ pub(crate) fn tokenize(input: &str) -> Result<Vec<Token<'_>>, EbnfError<'_>> {
             let span = Span {
                 start,
                 end,
-                line_offset_start: (1 + line_count as u32, line_offset_start as u32),
-                line_offset_end: (1 + line_count as u32, line_offset_end as u32),
+                line_offset_start: (1 + line as u32, line_offset_start as u32),
+                line_offset_end: (1 + line as u32, line_offset_end as u32),
             };
 
-            output.push(Token { span, payload });
+            if let TokenPayload::Import(path) = payload {
+                let between_rules = output
+                    .last()
+                    .is_none_or(|t: &Token<'_>| t.payload == TokenPayload::Termination);
+                if !between_rules {
+                    return Err(EbnfError::LexError {
+                        input: document,
+                        offset: start,
+                    });
+                }
+                imports.push(Import { path, span });
+            } else {
+                if let TokenPayload::Annotation(_) = payload {
+                    let before_rule = output.last().is_none_or(|t: &Token<'_>| {
+                        matches!(
+                            t.payload,
+                            TokenPayload::Termination | TokenPayload::Annotation(_)
+                        )
+                    });
+                    if !before_rule {
+                        return Err(EbnfError::LexError {
+                            input: document,
+                            offset: start,
+                        });
+                    }
+                }
+                output.push(Token { span, payload });
+            }
         } else {
             return Err(EbnfError::LexError {
-                input,
-                offset: s.start,
+                input: document,
+                offset: base + s.start,
             });
         }
     }
-    Ok(output)
+    if let Some(Token {
+        payload: TokenPayload::Annotation(_),
+        span,
+    }) = output.last()
+    {
+        // Nothing for the annotation to apply to
+        return Err(EbnfError::LexError {
+            input: document,
+            offset: span.start(),
+        });
+    }
+    Ok((output, imports))
 }
 
 #[cfg(test)]
 mod test {
     use insta::assert_compact_debug_snapshot;
 
-    use crate::{parse_rules_from_tokens, token_data::tokenize};
+    use crate::{ParseOptions, lex, parse_rules_from_tokens, token_data::tokenize};
 
     #[test]
     fn basic_token_test() {
@@ -278,12 +637,26 @@ mod test {
         assert_compact_debug_snapshot!(err, @r#"LexError { input: " A ? ££££", offset: 5 }"#);
     }
 
+    #[test]
+    fn lex_keeps_imports() {
+        let input = "A = B; %import 'other.ebnf'; @token B = 'b';";
+        let tokens = lex(input).unwrap();
+
+        assert_compact_debug_snapshot!(tokens, @r#"[Identifier [1:0..1:1]("A"), Equals [1:2..1:3], Identifier [1:4..1:5]("B"), Termination [1:5..1:6], Import [1:7..1:28]("other.ebnf"), Annotation [1:29..1:35]("token"), Identifier [1:36..1:37]("B"), Equals [1:38..1:39], String [1:40..1:43]("b"), Termination [1:43..1:44]]"#);
+    }
+
     #[test]
     fn lines_test() {
         let input = "foo = bar;\nbaz = baxx;";
         let tokens = tokenize(input).unwrap();
 
-        let rules = parse_rules_from_tokens(input, &mut &tokens[..]).unwrap();
+        let rules = parse_rules_from_tokens(
+            input,
+            input.len(),
+            &mut &tokens[..],
+            ParseOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(rules.len(), 2);
         assert_ne!(rules[0], rules[1]);
@@ -0,0 +1,146 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use crate::{
+    EbnfError, Import, Span, SpanSet,
+    token_data::{Token, TokenPayload, tokenize_with_imports},
+};
+
+/// How two versions of a grammar's source differ, as found by [`Grammar::diff_sources`](crate::Grammar::diff_sources)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDiff<'a> {
+    /// The rules whose definitions differ, in order of name
+    pub rules: Vec<ChangedRule<'a>>,
+    /// Whether the `%import` directives differ, either in their paths or the order they're listed
+    pub imports_changed: bool,
+}
+
+impl SourceDiff<'_> {
+    /// Whether the two sources contain the same rules and imports
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && !self.imports_changed
+    }
+}
+
+/// A rule whose tokens differ between two versions of a grammar's source. A rule that is only in one of them has an empty [`SpanSet`] for the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedRule<'a> {
+    /// The name of the rule. A definition that doesn't start with a name, which won't parse, is counted under the empty name.
+    pub name: &'a str,
+    /// Where each definition of the rule was in the old source, from its name, or any annotations before that, to its terminating `;`
+    pub old: SpanSet,
+    /// Where each definition of the rule is in the new source
+    pub new: SpanSet,
+}
+
+/// The tokens of each definition in a source, grouped by the name being defined
+type Definitions<'t, 'a> = BTreeMap<&'a str, Vec<&'t [Token<'a>]>>;
+
+pub(crate) fn diff_sources<'a>(
+    old: &'a str,
+    new: &'a str,
+) -> Result<SourceDiff<'a>, EbnfError<'a>> {
+    let (old_tokens, old_imports) = tokenize_with_imports(old)?;
+    let (new_tokens, new_imports) = tokenize_with_imports(new)?;
+    let mut old_definitions = definitions(&old_tokens);
+    let mut new_definitions = definitions(&new_tokens);
+
+    let mut names: Vec<_> = old_definitions
+        .keys()
+        .chain(new_definitions.keys())
+        .copied()
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut rules = vec![];
+    for name in names {
+        let before = old_definitions.remove(name).unwrap_or_default();
+        let after = new_definitions.remove(name).unwrap_or_default();
+        let same = before.len() == after.len()
+            && before.iter().zip(&after).all(|(a, b)| same_tokens(a, b));
+        if !same {
+            rules.push(ChangedRule {
+                name,
+                old: before.iter().map(|d| span_of(d)).collect(),
+                new: after.iter().map(|d| span_of(d)).collect(),
+            });
+        }
+    }
+
+    let paths = |imports: &[Import<'a>]| imports.iter().map(|i| i.path).collect::<Vec<_>>();
+    Ok(SourceDiff {
+        rules,
+        imports_changed: paths(&old_imports) != paths(&new_imports),
+    })
+}
+
+/// Splits the tokens after each terminator, keeping any unterminated tokens at the end as a definition of their own
+fn definitions<'t, 'a>(tokens: &'t [Token<'a>]) -> Definitions<'t, 'a> {
+    let mut definitions = Definitions::new();
+    for definition in tokens.split_inclusive(|t| t.payload == TokenPayload::Termination) {
+        let mut tokens = definition
+            .iter()
+            .skip_while(|t| matches!(t.payload, TokenPayload::Annotation(_)));
+        let name = match (
+            tokens.next().map(|t| t.payload),
+            tokens.next().map(|t| t.payload),
+        ) {
+            (Some(TokenPayload::Identifier(name)), _)
+            | (Some(TokenPayload::OpeningAngle), Some(TokenPayload::Identifier(name))) => name,
+            _ => "",
+        };
+        definitions.entry(name).or_default().push(definition);
+    }
+    definitions
+}
+
+/// Compares tokens by their contents alone, so that moving a definition or changing the whitespace or comments within it doesn't count
+fn same_tokens(a: &[Token<'_>], b: &[Token<'_>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.payload == b.payload)
+}
+
+fn span_of(definition: &[Token<'_>]) -> Span {
+    definition[0]
+        .span
+        .join(definition[definition.len() - 1].span)
+}
+
+#[cfg(test)]
+mod test {
+    use alloc::{vec, vec::Vec};
+
+    use crate::Grammar;
+
+    #[test]
+    fn changed_rules() {
+        let old =
+            "%import \"lex.ebnf\";\nA = B 'a';\nB = 'b'; // comment\nC = 'c';\nD = 'd';\nD = 'dd';";
+        let new =
+            "%import \"lex.ebnf\";\nB = 'b';\nA =   B\n  'a';\nC = 'c' 'c';\nD = 'dd';\nE = 'e';";
+        let diff = Grammar::diff_sources(old, new).unwrap();
+        assert!(!diff.imports_changed);
+
+        let changed: Vec<_> = diff
+            .rules
+            .iter()
+            .map(|r| {
+                let old: Vec<_> = r.old.iter().map(|s| &old[s.range()]).collect();
+                let new: Vec<_> = r.new.iter().map(|s| &new[s.range()]).collect();
+                (r.name, old, new)
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                ("C", vec!["C = 'c';"], vec!["C = 'c' 'c';"]),
+                ("D", vec!["D = 'd';", "D = 'dd';"], vec!["D = 'dd';"]),
+                ("E", vec![], vec!["E = 'e';"]),
+            ]
+        );
+
+        let diff =
+            Grammar::diff_sources(old, "A = B 'a'; B = 'b'; C = 'c'; D = 'd'; D = 'dd';").unwrap();
+        assert!(diff.imports_changed);
+        assert!(diff.rules.is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Bindings for using the crate from JavaScript through [wasm-bindgen](https://docs.rs/wasm-bindgen), for instance to power a web-based grammar playground. Only available with the `wasm` feature.
+//!
+//! The exported functions are included in any `cdylib` that depends on this crate with the feature enabled, so they can be built with `wasm-pack` from a small wrapper crate.
+
+use std::fmt::Write;
+
+use wasm_bindgen::{JsValue, UnwrapThrowExt, prelude::wasm_bindgen};
+
+use crate::{
+    EbnfError, Grammar, ReportConfig,
+    json::{write_json_str, write_nodes},
+};
+
+/// Parses `src` as a [`Grammar`], returning an object of the form
+/// ```json
+/// {
+///     "rules": [{ "name": "rule", "body": [...] }],
+///     "diagnostics": [{ "severity": "error", "code": "E001", "message": "...", "span": [0, 1] }]
+/// }
+/// ```
+/// Each node of a rule's `body` is an object with the `kind` of node (the name of the [`Expr`] variant), a numeric `code` for the kind that stays the same across versions of this crate, and its `span` as byte offsets. Leaf nodes carry their `text`, other nodes their `children`, repetitions also have booleans `one_needed` and `separated`, the latter meaning the last child is a separator, and choices a boolean `ordered`.
+///
+/// If the grammar could not be parsed, `rules` is empty and there will be a single error diagnostic, whose `code` is the [`ErrorCode`](crate::ErrorCode) of the error and whose `message` is the full report rendered without ANSI color codes. If the parser got far enough to say why it failed, the diagnostic also has the `stack` of nodes it was left with, most recent last, as in [`FailureReason::stack`](crate::FailureReason::stack). Otherwise, there is a warning for each reference to an undefined rule, which also has a `suggestion` of the defined rule it may have been a typo for, or `null` if there isn't a likely one.
+#[wasm_bindgen(js_name = parseGrammar)]
+pub fn parse_grammar(src: &str) -> JsValue {
+    let json = grammar_json(src);
+    js_sys::JSON::parse(&json).unwrap_throw()
+}
+
+fn grammar_json(src: &str) -> String {
+    let mut out = String::new();
+    let mut diagnostics = vec![];
+
+    out.push_str(r#"{"rules":["#);
+    match Grammar::new(src) {
+        Ok(grammar) => {
+            for (n, rule) in grammar.rules.values().enumerate() {
+                if n > 0 {
+                    out.push(',');
+                }
+                out.push_str(r#"{"name":"#);
+                write_json_str(&mut out, &rule.name);
+                out.push_str(r#","body":"#);
+                write_nodes(&mut out, &rule.body);
+                out.push('}');
+
+                for (name, span) in rule.nonterminal_references() {
+                    if grammar.get(name).is_none() {
+                        let mut message =
+                            format!("`{}` refers to undefined rule `{name}`", rule.name);
+                        let suggestion = grammar.suggest_rule_name(name).map(str::to_owned);
+                        if let Some(suggestion) = &suggestion {
+                            write!(message, ", did you mean `{suggestion}`?").unwrap();
+                        }
+                        let span = Some((span.start(), span.end()));
+                        diagnostics.push(("warning", None, message, span, Some(suggestion), None));
+                    }
+                }
+            }
+        }
+        Err(error) => {
+            let report = error.render(ReportConfig::default().with_color(false));
+            let span = error.offset().map(|offset| (offset, offset));
+            let stack = match &error {
+                EbnfError::ParseError {
+                    reason: Some(reason),
+                    ..
+                } => {
+                    let mut stack = String::new();
+                    write_nodes(&mut stack, reason.stack());
+                    Some(stack)
+                }
+                _ => None,
+            };
+            diagnostics.push(("error", Some(error.code()), report, span, None, stack));
+        }
+    }
+
+    out.push_str(r#"],"diagnostics":["#);
+    for (n, (severity, code, message, span, suggestion, stack)) in
+        diagnostics.into_iter().enumerate()
+    {
+        if n > 0 {
+            out.push(',');
+        }
+        write!(out, r#"{{"severity":"{severity}","#).unwrap();
+        if let Some(code) = code {
+            write!(out, r#""code":"{code}","#).unwrap();
+        }
+        out.push_str(r#""message":"#);
+        write_json_str(&mut out, &message);
+        match span {
+            Some((start, end)) => write!(out, r#","span":[{start},{end}]"#).unwrap(),
+            None => out.push_str(r#","span":null"#),
+        }
+        match suggestion {
+            Some(Some(suggestion)) => {
+                out.push_str(r#","suggestion":"#);
+                write_json_str(&mut out, &suggestion);
+            }
+            Some(None) => out.push_str(r#","suggestion":null"#),
+            None => {}
+        }
+        if let Some(stack) = stack {
+            out.push_str(r#","stack":"#);
+            out.push_str(&stack);
+        }
+        out.push('}');
+    }
+    out.push_str("]}");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::grammar_json;
+
+    #[test]
+    fn json_output() {
+        let json = grammar_json("A = 'a\"' B*;");
+        assert_eq!(
+            json,
+            r#"{"rules":[{"name":"A","body":[{"kind":"Literal","code":0,"span":[4,8],"text":"a\""},{"kind":"Repetition","code":4,"span":[9,10],"one_needed":false,"separated":false,"children":[{"kind":"Nonterminal","code":1,"span":[9,10],"text":"B"}]}]}],"diagnostics":[{"severity":"warning","message":"`A` refers to undefined rule `B`","span":[9,10],"suggestion":null}]}"#
+        );
+    }
+
+    #[test]
+    fn json_suggestion() {
+        let json = grammar_json("nick = 'n'; user = nik;");
+        assert!(json.ends_with(
+            r#""message":"`user` refers to undefined rule `nik`, did you mean `nick`?","span":[19,22],"suggestion":"nick"}]}"#
+        ));
+    }
+
+    #[test]
+    fn json_error() {
+        let json = grammar_json("A = (B;");
+        assert!(json.starts_with(r#"{"rules":[],"diagnostics":[{"severity":"error","code":"E001","message":"[E001] Error:"#));
+        assert!(!json.contains(r"\u001b"));
+        assert!(json.ends_with(
+            r#""stack":[{"kind":"Nonterminal","code":1,"span":[0,1],"text":"A"},{"kind":"UnparsedOperator","code":7,"span":[2,3],"text":"Equals","op_code":7},{"kind":"UnparsedOperator","code":7,"span":[4,5],"text":"OpenedGroup","op_code":0},{"kind":"Nonterminal","code":1,"span":[5,6],"text":"B"},{"kind":"UnparsedOperator","code":7,"span":[6,7],"text":"Terminator","op_code":6}]}]}"#
+        ));
+    }
+}
@@ -0,0 +1,592 @@
+use alloc::{borrow::Cow, vec, vec::Vec};
+use core::fmt::Display;
+
+use crate::{Expr, Grammar, Rule, Span, SpanSet, convert::Approximations};
+
+/// The ways [`Grammar::from_yacc`] can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum YaccError {
+    /// Something other than what was expected was found at `span`, such as a rule name without a `:` after it, or the end of the input before the `%%` that starts the rules
+    Unexpected {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What should have been there instead, such as `"the name of a rule"`
+        expected: &'static str,
+    },
+    /// Something starting at `span` is never closed
+    Unterminated {
+        #[expect(missing_docs, reason = "Obvious")]
+        span: Span,
+        /// What it is, such as `"action"` or `"comment"`
+        what: &'static str,
+    },
+}
+
+impl Display for YaccError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            YaccError::Unexpected { span, expected } => write!(f, "Expected {expected} at {span}"),
+            YaccError::Unterminated { span, what } => {
+                write!(f, "The {what} at {span} is never closed")
+            }
+        }
+    }
+}
+
+/// Directives whose names declare tokens
+const TOKEN_DIRECTIVES: &[&str] = &["token", "left", "right", "nonassoc", "precedence"];
+
+/// Directives that set the precedence and associativity of tokens
+const PRECEDENCE_DIRECTIVES: &[&str] = &["left", "right", "nonassoc", "precedence"];
+
+pub(crate) fn from_yacc(input: &str) -> Result<Grammar<'_>, YaccError> {
+    read_yacc(input).map(|(grammar, _)| grammar)
+}
+
+/// Reads a grammar as [`from_yacc`] does, along with what of the input it couldn't carry over exactly
+pub(crate) fn read_yacc(input: &str) -> Result<(Grammar<'_>, Approximations), YaccError> {
+    let mut reader = Reader::new(input);
+    let tokens = reader.declarations()?;
+    let mut rules = reader.rules()?;
+    // A token becomes a rule where it's first used, rather than where it's declared, so that the first rule of the rules section is still the first one defined
+    let mut uses = Vec::new();
+    for rule in &rules {
+        for (name, span) in rule.nonterminal_references() {
+            if !uses.iter().any(|&(used, _)| used == name) {
+                uses.push((name, span));
+            }
+        }
+    }
+    for (name, alias) in tokens {
+        if rules.iter().any(|rule| rule.name == name) {
+            continue;
+        }
+        if let Some(&(_, span)) = uses.iter().find(|&&(used, _)| used == name) {
+            reader.approximations.add(
+                "a token",
+                "made a rule matching a literal of its name, or of its alias",
+                Some(span),
+            );
+            rules.push(Rule {
+                name: Cow::Borrowed(name),
+                body: vec![Expr::Literal {
+                    span,
+                    str: alias.unwrap_or(name),
+                }],
+                definitions: SpanSet::from(span),
+                hidden: false,
+                annotations: Vec::new(),
+            });
+        }
+    }
+    Ok((rules.into_iter().collect(), reader.approximations))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    Name(&'a str),
+    /// The text between the quotes of a character literal like `'+'`
+    Char(&'a str),
+    /// The text between the quotes of a string like `"<="`
+    String(&'a str),
+    Colon,
+    Bar,
+    Semicolon,
+    /// The `%%` between sections
+    Separator,
+    /// A directive such as `%token`, without its `%`
+    Directive(&'a str),
+    /// Code in braces, such as an action
+    Code,
+    /// A type like `<int>`, a number or a named reference like `[left]`, none of which matter to the grammar
+    Ignored,
+    End,
+}
+
+/// A place in the input, as a byte offset and the line and byte offset within the line it's at
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Reads the sections of a Yacc file from `input`, keeping track of where it's up to for the spans of what it reads
+struct Reader<'a> {
+    input: &'a str,
+    position: Position,
+    approximations: Approximations,
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a str) -> Self {
+        Reader {
+            input,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 0,
+            },
+            approximations: Approximations::default(),
+        }
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        let end = self.position;
+        Span::new(
+            start.offset..end.offset,
+            (start.line, start.column),
+            (end.line, end.column),
+        )
+    }
+
+    fn advance(&mut self, len: usize) {
+        let position = &mut self.position;
+        for c in self.input[position.offset..position.offset + len].chars() {
+            if c == '\n' {
+                position.line += 1;
+                position.column = 0;
+            } else {
+                position.column += c.len_utf8();
+            }
+        }
+        position.offset += len;
+    }
+
+    /// Skips past a part of the input that ends with `end`, or fails with what it is
+    fn skip_until(
+        &mut self,
+        start: Position,
+        end: &str,
+        what: &'static str,
+    ) -> Result<(), YaccError> {
+        let rest = &self.input[self.position.offset..];
+        let Some(len) = rest.find(end) else {
+            self.advance(rest.len());
+            return Err(YaccError::Unterminated {
+                span: self.span_from(start),
+                what,
+            });
+        };
+        self.advance(len + end.len());
+        Ok(())
+    }
+
+    /// Skips whitespace and comments
+    fn skip_trivia(&mut self) -> Result<(), YaccError> {
+        loop {
+            let rest = &self.input[self.position.offset..];
+            self.advance(rest.len() - rest.trim_start().len());
+            let rest = &self.input[self.position.offset..];
+            let start = self.position;
+            if rest.starts_with("//") {
+                self.advance(rest.find('\n').unwrap_or(rest.len()));
+            } else if rest.starts_with("/*") {
+                self.advance(2);
+                self.skip_until(start, "*/", "comment")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads the next token, along with where it starts
+    fn token(&mut self) -> Result<(Token<'a>, Position), YaccError> {
+        self.skip_trivia()?;
+        let start = self.position;
+        let rest = &self.input[start.offset..];
+        let Some(first) = rest.chars().next() else {
+            return Ok((Token::End, start));
+        };
+        let token = match first {
+            ':' | '|' | ';' => {
+                self.advance(1);
+                match first {
+                    ':' => Token::Colon,
+                    '|' => Token::Bar,
+                    _ => Token::Semicolon,
+                }
+            }
+            '%' if rest.starts_with("%%") => {
+                self.advance(2);
+                Token::Separator
+            }
+            '%' if rest.starts_with("%{") => {
+                self.advance(2);
+                self.skip_until(start, "%}", "prologue")?;
+                Token::Ignored
+            }
+            '%' => {
+                let len = rest[1..]
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-')))
+                    .unwrap_or(rest.len() - 1);
+                self.advance(1 + len);
+                Token::Directive(&rest[1..=len])
+            }
+            '\'' | '"' => {
+                let Some(len) = quoted_len(rest) else {
+                    self.advance(rest.find('\n').unwrap_or(rest.len()));
+                    return Err(YaccError::Unterminated {
+                        span: self.span_from(start),
+                        what: "literal",
+                    });
+                };
+                self.advance(len);
+                let text = &rest[1..len - 1];
+                if first == '\'' {
+                    Token::Char(text)
+                } else {
+                    Token::String(text)
+                }
+            }
+            '{' => {
+                let Some(len) = code_len(rest) else {
+                    self.advance(rest.len());
+                    return Err(YaccError::Unterminated {
+                        span: self.span_from(start),
+                        what: "action",
+                    });
+                };
+                self.advance(len);
+                Token::Code
+            }
+            '<' | '[' => {
+                self.advance(1);
+                let (end, what) = if first == '<' {
+                    (">", "type")
+                } else {
+                    ("]", "named reference")
+                };
+                self.skip_until(start, end, what)?;
+                Token::Ignored
+            }
+            c if c.is_ascii_digit() => {
+                self.advance(
+                    rest.find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(rest.len()),
+                );
+                Token::Ignored
+            }
+            c if c.is_alphabetic() || matches!(c, '_' | '.') => {
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '-')))
+                    .unwrap_or(rest.len());
+                self.advance(len);
+                Token::Name(&rest[..len])
+            }
+            c => {
+                self.advance(c.len_utf8());
+                return Err(YaccError::Unexpected {
+                    span: self.span_from(start),
+                    expected: "a name, literal or punctuation",
+                });
+            }
+        };
+        Ok((token, start))
+    }
+
+    /// Reads the declarations section up to the `%%` that ends it, returning each token declared, along with the string it's an alias for in Bison, if there is one
+    fn declarations(&mut self) -> Result<Vec<(&'a str, Option<&'a str>)>, YaccError> {
+        let mut tokens: Vec<(&'a str, Option<&'a str>)> = Vec::new();
+        let mut declaring = false;
+        // Whether the last token declared can still be given an alias
+        let mut aliasable = false;
+        loop {
+            match self.token()? {
+                (Token::Separator, _) => return Ok(tokens),
+                (Token::End, start) => {
+                    return Err(YaccError::Unexpected {
+                        span: self.span_from(start),
+                        expected: "`%%` before the rules",
+                    });
+                }
+                (Token::Directive(directive), start) => {
+                    declaring = TOKEN_DIRECTIVES.contains(&directive);
+                    aliasable = false;
+                    let span = Some(self.span_from(start));
+                    if PRECEDENCE_DIRECTIVES.contains(&directive) {
+                        self.approximations.add(
+                            "a precedence declaration",
+                            "left out, so the conflicts it settles are left ambiguous",
+                            span,
+                        );
+                    } else if directive == "start" {
+                        self.approximations.add(
+                            "a `%start` declaration",
+                            "left out, so parsing starts at the first rule",
+                            span,
+                        );
+                    }
+                }
+                (Token::Name(name), _) if declaring => {
+                    if !tokens.iter().any(|&(declared, _)| declared == name) {
+                        tokens.push((name, None));
+                    }
+                    aliasable = true;
+                }
+                (Token::String(alias), _) if aliasable => {
+                    if let Some(token) = tokens.last_mut() {
+                        token.1 = Some(alias);
+                    }
+                    aliasable = false;
+                }
+                (Token::Code | Token::Ignored, _) => {}
+                _ => aliasable = false,
+            }
+        }
+    }
+
+    /// Reads the rules section, up to the `%%` before the epilogue or the end of the input
+    fn rules(&mut self) -> Result<Vec<Rule<'a>>, YaccError> {
+        let mut rules = Vec::new();
+        loop {
+            let (token, start) = self.token()?;
+            let name = match token {
+                Token::Name(name) => name,
+                Token::Separator | Token::End => return Ok(rules),
+                Token::Semicolon => continue,
+                _ => {
+                    return Err(YaccError::Unexpected {
+                        span: self.span_from(start),
+                        expected: "the name of a rule",
+                    });
+                }
+            };
+            let (mut token, mut at) = self.token()?;
+            while token == Token::Ignored {
+                (token, at) = self.token()?;
+            }
+            if token != Token::Colon {
+                return Err(YaccError::Unexpected {
+                    span: self.span_from(at),
+                    expected: "`:` after the name of a rule",
+                });
+            }
+            let alternatives = self.alternatives()?;
+            let span = self.span_from(start);
+            let body = match <[_; 1]>::try_from(alternatives) {
+                Ok([alternative]) => alternative,
+                Err(alternatives) => vec![Expr::Choice {
+                    span: Span::union(alternatives.iter().flatten()),
+                    body: alternatives
+                        .into_iter()
+                        .map(|mut alternative| {
+                            if alternative.len() == 1 {
+                                alternative.pop().unwrap()
+                            } else {
+                                Expr::Group {
+                                    span: Span::union(alternative.iter()),
+                                    body: alternative,
+                                }
+                            }
+                        })
+                        .collect(),
+                    ordered: false,
+                }],
+            };
+            rules.push(Rule {
+                name: Cow::Borrowed(name),
+                body,
+                definitions: SpanSet::from(span),
+                hidden: false,
+                annotations: Vec::new(),
+            });
+        }
+    }
+
+    /// Whether the next token, not counting a named reference like `[left]`, is a `:`, without moving past it
+    fn colon_follows(&mut self) -> Result<bool, YaccError> {
+        let before = self.position;
+        let mut token = self.token()?.0;
+        while token == Token::Ignored {
+            token = self.token()?.0;
+        }
+        self.position = before;
+        Ok(token == Token::Colon)
+    }
+
+    /// Reads the alternatives of a rule after its `:`, up to the `;` that ends it, or the name of the next rule when that's left out as Bison allows. An empty alternative matches nothing.
+    fn alternatives(&mut self) -> Result<Vec<Vec<Expr<'a>>>, YaccError> {
+        let mut alternatives = Vec::new();
+        let mut alternative = Vec::new();
+        let mut alternative_start = self.position;
+        loop {
+            let before = self.position;
+            let (token, start) = self.token()?;
+            match token {
+                Token::Name(name) => {
+                    let span = self.span_from(start);
+                    if self.colon_follows()? {
+                        self.position = before;
+                        break;
+                    }
+                    alternative.push(Expr::Nonterminal { span, name });
+                }
+                Token::Char(str) | Token::String(str) => alternative.push(Expr::Literal {
+                    span: self.span_from(start),
+                    str,
+                }),
+                Token::Bar => {
+                    alternatives.push(finish(alternative, alternative_start, before));
+                    alternative = Vec::new();
+                    alternative_start = self.position;
+                }
+                Token::Semicolon => break,
+                Token::Separator | Token::End => {
+                    self.position = before;
+                    break;
+                }
+                // The token after `%prec` only sets the precedence of the alternative
+                Token::Directive("prec") => {
+                    self.token()?;
+                    self.approximations.add(
+                        "a `%prec` marker",
+                        "left out",
+                        Some(self.span_from(start)),
+                    );
+                }
+                Token::Code => {
+                    self.approximations
+                        .add("an action", "left out", Some(self.span_from(start)));
+                }
+                Token::Directive(_) | Token::Ignored => {}
+                Token::Colon => {
+                    return Err(YaccError::Unexpected {
+                        span: self.span_from(start),
+                        expected: "a symbol",
+                    });
+                }
+            }
+        }
+        let end = self.position;
+        alternatives.push(finish(alternative, alternative_start, end));
+        Ok(alternatives)
+    }
+}
+
+/// Gives an alternative with no symbols an [`Empty`](Expr::Empty) node, spanning from `start` to `end`
+fn finish(mut alternative: Vec<Expr<'_>>, start: Position, end: Position) -> Vec<Expr<'_>> {
+    if alternative.is_empty() {
+        alternative.push(Expr::Empty {
+            span: Span::new(
+                start.offset..end.offset,
+                (start.line, start.column),
+                (end.line, end.column),
+            ),
+        });
+    }
+    alternative
+}
+
+/// The length of the quoted literal `s` starts with, including its quotes, if it's closed on the same line. A backslash escapes the character after it.
+fn quoted_len(s: &str) -> Option<usize> {
+    let quote = s.chars().next()?;
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            '\n' => return None,
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(i + 1),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// The length of the braced code `s` starts with, including its braces, skipping over any braces in strings, character literals and comments in it
+fn code_len(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        let c = rest.chars().next()?;
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            '\'' | '"' => {
+                // An apostrophe that doesn't start a closed literal is left alone
+                if let Some(len) = quoted_len(rest) {
+                    i += len;
+                    continue;
+                }
+            }
+            '/' if rest.starts_with("//") => {
+                i += rest.find('\n')?;
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                i += rest.find("*/")? + 2;
+                continue;
+            }
+            _ => {}
+        }
+        i += c.len_utf8();
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Grammar, YaccError};
+
+    #[test]
+    fn from_yacc() {
+        let g = Grammar::from_yacc(
+            r#"
+%{
+#include "calc.h"
+%}
+%union { int value; }
+%token <value> NUM 300 "number"
+%token UNUSED
+%right '^'
+%%
+input: %empty | input line ;
+line: '\n' | exp[e] '\n' { printf("}%d\n", $e); }
+exp: NUM
+   | exp '+' exp
+   | '-' exp %prec '^'   /* unary minus */
+   | '(' exp ')'
+statement : 'x' ';'
+%%
+int main(void) { return yyparse(); }
+"#,
+        )
+        .unwrap();
+        let expected = Grammar::new(
+            r"
+            input = '' | (input line);
+            line = '\n' | (exp '\n');
+            exp = NUM | (exp '+' exp) | ('-' exp) | ('(' exp ')');
+            statement = 'x' ';';
+            NUM = 'number';
+            ",
+        )
+        .unwrap();
+        assert!(g.eq_ignoring_spans(&expected));
+        assert_eq!(g["NUM"].definitions.hull().unwrap().start_line(), (12, 5));
+
+        assert!(matches!(
+            Grammar::from_yacc("%token A\n"),
+            Err(YaccError::Unexpected {
+                expected: "`%%` before the rules",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Grammar::from_yacc("%%\na: b { c;\n"),
+            Err(YaccError::Unterminated { what: "action", .. })
+        ));
+        let Err(YaccError::Unexpected { span, expected }) = Grammar::from_yacc("%%\na b;") else {
+            panic!("a rule without a `:` was read");
+        };
+        assert_eq!(expected, "`:` after the name of a rule");
+        assert_eq!(span.range(), 5..6);
+    }
+}
@@ -0,0 +1,82 @@
+use std::{fs, path::PathBuf, process::Command};
+
+/// Writes the given files into a fresh directory for the test called `name`
+fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ebnf-cli-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, text) in files {
+        fs::write(dir.join(file), text).unwrap();
+    }
+    dir
+}
+
+fn run(args: &[&str], file: &PathBuf) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ebnf"))
+        .args(args)
+        .arg(file)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn subcommands() {
+    let dir = write_files(
+        "subcommands",
+        &[
+            (
+                "main.ebnf",
+                "%import 'lex.ebnf';\nsum = term ('+' term)*;\nterm = NUMBER | ('(' sum ')');\n",
+            ),
+            ("lex.ebnf", "NUMBER = #'[0-9]{1,3}';\n"),
+            ("broken.ebnf", "sum = term ('+' term;\n"),
+            ("dangling.ebnf", "sum = term ('+' term)*;\n"),
+        ],
+    );
+    let main = dir.join("main.ebnf");
+
+    assert!(run(&["check"], &main).0);
+    let (ok, _, stderr) = run(&["check"], &dir.join("broken.ebnf"));
+    assert!(!ok);
+    assert!(stderr.contains("This bracket is never closed"), "{stderr}");
+    let (ok, _, stderr) = run(&["check"], &dir.join("dangling.ebnf"));
+    assert!(!ok);
+    assert!(stderr.contains("`term` is not defined"), "{stderr}");
+
+    let (ok, formatted, _) = run(&["fmt"], &main);
+    assert!(ok);
+    assert!(!run(&["fmt", "--check"], &main).0);
+    fs::write(&main, &formatted).unwrap();
+    assert!(run(&["fmt", "--check"], &main).0);
+
+    let (_, graph, _) = run(&["graph"], &main);
+    assert_eq!(graph, "NUMBER:\nsum: term\nterm: NUMBER sum\n");
+    let (_, dot, _) = run(&["graph", "--dot"], &main);
+    assert!(dot.starts_with("digraph grammar {\n"));
+    assert!(dot.contains("    \"term\" -> \"NUMBER\";\n"));
+
+    let (ok, sentences, _) = run(&["gen", "--count", "10", "--max-depth", "3"], &main);
+    assert!(ok);
+    assert_eq!(sentences.lines().count(), 10);
+    assert!(
+        sentences
+            .lines()
+            .all(|s| s.chars().all(|c| "0123456789+()".contains(c)))
+    );
+    let (_, numbers, _) = run(&["gen", "--start", "NUMBER", "--seed", "1"], &main);
+    assert!(numbers.trim().parse::<u32>().unwrap() < 1000);
+
+    let (ok, pest, stderr) = run(&["convert", "--to", "pest"], &dir.join("lex.ebnf"));
+    assert!(ok);
+    assert_eq!(pest, "NUMBER = { (('0'..'9'){1, 3}) }\n");
+    assert_eq!(stderr, "");
+    let (ok, _, stderr) = run(&["convert", "--to", "yacc"], &main);
+    assert!(!ok);
+    assert!(stderr.contains("can't be exported"), "{stderr}");
+
+    fs::remove_dir_all(dir).unwrap();
+}
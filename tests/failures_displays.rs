@@ -1,10 +1,12 @@
 use ebnf_toolkit::EbnfError;
+use ebnf_toolkit::ErrorCode;
+use ebnf_toolkit::ReportConfig;
 use ebnf_toolkit::Rule;
 use insta::assert_compact_debug_snapshot;
 
 #[test]
 fn incomplete_rule() {
-    let err = Rule::new("Foo = A|").unwrap_err();
+    let err = Rule::new("Foo = A &").unwrap_err();
 
     println!("{err}");
     insta::assert_snapshot!(err);
@@ -17,7 +19,7 @@ fn incomplete_rule() {
 
 #[test]
 fn invalid_syntax_rule() {
-    let srcs = ["Foo = A|;", "Foo = (A;", "Rule = (?;"];
+    let srcs = ["Foo = A &;", "Foo = (A;", "Rule = (?;"];
     for src in srcs {
         let err = Rule::new(src).unwrap_err();
 
@@ -46,6 +48,30 @@ fn invalid_start() {
     }
 }
 
+#[test]
+fn unclosed_brackets() {
+    let err = Rule::new("Foo = (A [B | C;").unwrap_err();
+    let report = err.render(ReportConfig::default().with_color(false));
+    assert_eq!(report.matches("This bracket is never closed").count(), 2);
+    assert!(report.contains("Expected `])` here"), "{report}");
+    assert!(
+        report.contains("1 `[` but no `]` before the end of the rule"),
+        "{report}"
+    );
+
+    let err = Rule::new("Foo = (A & ) B;").unwrap_err();
+    let report = err.render(ReportConfig::default().with_color(false));
+    assert!(!report.contains("never closed"), "{report}");
+}
+
+#[test]
+fn empty_brackets() {
+    let err = Rule::new("Foo = A { } B;").unwrap_err();
+    let report = err.render(ReportConfig::default().with_color(false));
+    assert_eq!(report.matches("Empty brackets are not allowed").count(), 1);
+    assert!(!report.contains("Contents of these brackets"), "{report}");
+}
+
 #[test]
 fn empty_input() {
     let src = "";
@@ -65,3 +91,72 @@ fn unclosed_string() {
     assert_eq!(err, err);
     assert_compact_debug_snapshot!(err);
 }
+
+#[test]
+fn render_config() {
+    let err = Rule::new("Foo = (A;").unwrap_err();
+
+    let plain = err.render(ReportConfig::default().with_color(false));
+    assert!(!plain.contains('\u{1b}'));
+    assert!(plain.contains("<input>:1:1"));
+    assert_eq!(err.render(ReportConfig::default()), err.to_string());
+
+    let named = err.render(
+        ReportConfig::default()
+            .with_color(false)
+            .with_source_name("grammar.ebnf"),
+    );
+    assert!(named.contains("grammar.ebnf:1:1"));
+    assert!(!named.contains("<input>"));
+
+    let compact = err.render(ReportConfig::default().with_color(false).with_compact(true));
+    assert!(compact.lines().count() < plain.lines().count());
+}
+
+#[test]
+fn stray_backslash() {
+    let err = Rule::new(r"Foo = A \n B;").unwrap_err();
+
+    assert!(matches!(err, EbnfError::LexError { offset: 8, .. }));
+    let report = err.render(ReportConfig::default().with_color(false));
+    assert!(report.contains("Backslash escapes are only valid inside quoted literals or regexes"));
+}
+
+#[test]
+fn wide_characters() {
+    let err = Rule::new("名前 = \\").unwrap_err();
+
+    assert!(matches!(err, EbnfError::LexError { offset: 9, .. }));
+    let report = err.render(ReportConfig::default().with_color(false));
+    let caret_line = report.lines().find(|l| l.contains('┬')).unwrap();
+    let (_, caret) = caret_line.split_once("│ ").unwrap();
+    // Each of the first two characters takes up two columns
+    assert_eq!(caret.find('┬'), Some(7));
+}
+
+#[test]
+fn error_codes() {
+    let cases = [
+        ("Foo = (A;", ErrorCode::UnclosedBracket),
+        ("Foo = A", ErrorCode::MissingSemicolon),
+        ("Foo = A &", ErrorCode::UnexpectedEnd),
+        ("Foo = A &;", ErrorCode::InvalidRule),
+        ("Foo = (A &);", ErrorCode::InvalidRule),
+        ("'Hello' = A;", ErrorCode::InvalidRuleName),
+        ("Foo = A $;", ErrorCode::UnrecognizedToken),
+        ("'Hello", ErrorCode::UnclosedString),
+        (r"Foo = A \n B;", ErrorCode::StrayBackslash),
+        ("", ErrorCode::EmptyInput),
+        ("Foo = A {};", ErrorCode::EmptyBrackets),
+    ];
+    for (src, code) in cases {
+        let err = Rule::new(src).unwrap_err();
+        assert_eq!(err.code(), code, "{src}");
+
+        let report = err.render(ReportConfig::default().with_color(false));
+        assert!(
+            report.starts_with(&format!("[{}]", code.as_str())),
+            "{report}"
+        );
+    }
+}
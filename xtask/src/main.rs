@@ -1,6 +1,6 @@
 #![warn(clippy::pedantic)]
 #![warn(unused_crate_dependencies)]
-/// This file has no public functionality - it runs the code coverage analysis
+/// This file has no public functionality - it runs the code coverage analysis and benchmarks
 use std::env::set_current_dir;
 use std::path::{Path, PathBuf};
 
@@ -30,6 +30,14 @@ enum Commands {
         #[arg(short, long)]
         open_report: bool,
     },
+    /// Run the benchmarks, comparing against the previous run
+    Bench {
+        /// Only run benchmarks whose names contain this, such as `synthetic` or `parse`
+        filter: Option<String>,
+        /// Save the results under this name to compare later runs against, rather than against the previous run
+        #[arg(short, long)]
+        save_baseline: Option<String>,
+    },
 }
 
 fn main() -> Result<(), Error> {
@@ -40,9 +48,27 @@ fn main() -> Result<(), Error> {
             readable,
             open_report,
         } => coverage(readable, open_report),
+        Commands::Bench {
+            filter,
+            save_baseline,
+        } => bench(filter.as_deref(), save_baseline.as_deref()),
     }
 }
 
+fn bench(filter: Option<&str>, save_baseline: Option<&str>) -> Result<(), Error> {
+    set_current_dir(root_crate_dir())?;
+
+    let mut args = vec!["bench", "--features", "bench", "--bench", "phases", "--"];
+    if let Some(filter) = filter {
+        args.push(filter);
+    }
+    if let Some(baseline) = save_baseline {
+        args.extend(["--save-baseline", baseline]);
+    }
+    cmd("cargo", args).run()?;
+    Ok(())
+}
+
 fn coverage(readable: bool, open_report: bool) -> Result<(), Error> {
     set_current_dir(root_crate_dir())?;
 